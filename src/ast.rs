@@ -1,9 +1,13 @@
 pub use sqlparser::ast::{
-    helpers::attached_token::AttachedToken, AlterColumnOperation, AlterTable, AlterTableOperation,
-    AlterType, AlterTypeAddValue, AlterTypeAddValuePosition, AlterTypeOperation,
-    AlterTypeRenameValue, ColumnDef, ColumnOption, ColumnOptionDef, CreateDomain, CreateExtension,
-    CreateIndex, CreateTable, DropDomain, DropExtension, GeneratedAs, ObjectName, ObjectNamePart,
-    ObjectType, ReferentialAction, RenameTableNameKind, Statement, UserDefinedTypeRepresentation,
+    helpers::attached_token::AttachedToken, AlterColumnOperation, AlterTable, AlterTableAlgorithm,
+    AlterTableLock, AlterTableOperation, AlterType, AlterTypeAddValue, AlterTypeAddValuePosition,
+    AlterTypeOperation, AlterTypeRenameValue, CheckConstraint, ColumnDef, ColumnOption,
+    ColumnOptionDef, CreateDomain, CreateExtension, CreateFunction, CreateIndex, CreateTable,
+    CreateTrigger, CreateView, DataType, DropDomain, DropExtension, DropFunction, DropTrigger,
+    Expr, ForeignKeyConstraint, GeneratedAs, Ident, IndexColumn, ObjectName, ObjectNamePart,
+    ObjectType, PrimaryKeyConstraint, Query, ReferentialAction, RenameTableNameKind, SetExpr,
+    Statement, TableConstraint, TableFactor, TableWithJoins, UniqueConstraint,
+    UserDefinedTypeRepresentation,
 };
 
 /// This is a copy of [`Statement::CreateType`].
@@ -0,0 +1,692 @@
+/*!
+Convert a schema to and from [Atlas](https://atlasgo.io)'s HCL schema format, so a schema can be
+round-tripped between the two tools and existing HCL definitions can be diffed like SQL.
+
+Tables (with their columns, primary keys, foreign keys, unique and check constraints, and indexes)
+and enum types are converted. Views, domains, extensions, and other types have no equivalent here,
+so they're left out and reported. Tables in the `public` schema are written unqualified, data types
+Atlas doesn't know by name are written as `sql("...")`, and constraints and indexes Atlas needs a
+name for are given PostgreSQL's default one (which is left out again when converting back).
+*/
+
+use hcl::{
+    expr::{FuncCall, Traversal, TraversalOperator, Variable},
+    format::{Format, Formatter},
+    Attribute, Block, BlockLabel, Body, Expression, Identifier, Number,
+};
+use thiserror::Error;
+
+use crate::{
+    changes::{Change, ObjectKind, Operation},
+    dialect::PostgreSQL,
+    diff::{StatementDiffer, Unnamed},
+    schema::{Column, Constraint, ConstraintKind, Index, Schema, Table, TypeKind},
+    Parse, ParseError, SyntaxTree,
+};
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum AtlasError {
+    #[error("Oops, we couldn't read the HCL: {0}")]
+    Hcl(#[from] hcl::Error),
+    #[error("Oops, we don't know what to make of {0} in the HCL")]
+    Unsupported(String),
+    #[error("Oops, we couldn't parse the SQL the HCL converts to")]
+    Parse(#[from] ParseError),
+}
+
+pub type Result<T, E = AtlasError> = std::result::Result<T, E>;
+
+/// the schema unqualified names belong to
+pub const DEFAULT_SCHEMA: &str = "public";
+
+/// the Atlas HCL for `tree`, along with the statements that were left out of it
+pub fn to_hcl<Dialect>(tree: &SyntaxTree<Dialect>) -> Result<(String, Vec<Change>)> {
+    let schema = tree.schema();
+    let is_enum = |name: &str| {
+        schema
+            .types
+            .iter()
+            .any(|ty| ty.name == name && matches!(ty.kind, TypeKind::Enum { .. }))
+    };
+    let left_out = tree
+        .changes()
+        .into_iter()
+        .filter(|change| {
+            change.operation != Operation::Create
+                || !match change.object {
+                    ObjectKind::Table | ObjectKind::Index => true,
+                    ObjectKind::Type => is_enum(&change.name),
+                    _ => false,
+                }
+        })
+        .collect();
+
+    let mut schemas = Vec::new();
+    let mut blocks = Vec::new();
+    for ty in &schema.types {
+        let TypeKind::Enum { labels } = &ty.kind else {
+            continue;
+        };
+        let (schema_name, name) = split_name(&ty.name);
+        let values = labels.iter().cloned().map(Expression::String);
+        blocks.push(
+            Block::builder("enum")
+                .add_label(name)
+                .add_attribute(("schema", reference(["schema", schema_name])))
+                .add_attribute(("values", Expression::Array(values.collect())))
+                .build(),
+        );
+        schemas.push(schema_name);
+    }
+    for table in &schema.tables {
+        let (schema_name, _) = split_name(&table.name);
+        blocks.push(table_block(&schema, table));
+        schemas.push(schema_name);
+    }
+    let mut body = Body::builder();
+    let mut seen = Vec::new();
+    for name in schemas {
+        if !seen.contains(&name) {
+            body = body.add_block(Block::builder("schema").add_label(name).build());
+            seen.push(name);
+        }
+    }
+    let body = body.add_blocks(blocks).build();
+    let mut formatter = Formatter::builder().compact_arrays(true).build_vec();
+    Ok((body.format_string(&mut formatter)?, left_out))
+}
+
+fn table_block(schema: &Schema, table: &Table) -> Block {
+    let (schema_name, name) = split_name(&table.name);
+    let mut block = Block::builder("table")
+        .add_label(name)
+        .add_attribute(("schema", reference(["schema", schema_name])));
+    for column in &table.columns {
+        block = block.add_block(column_block(schema, column));
+    }
+    for constraint in &table.constraints {
+        block = block.add_block(constraint_block(name, constraint));
+    }
+    for index in schema.indexes.iter().filter(|i| i.table == table.name) {
+        block = block.add_block(index_block(name, index));
+    }
+    block.build()
+}
+
+fn column_block(schema: &Schema, column: &Column) -> Block {
+    let mut block = Block::builder("column")
+        .add_label(column.name.as_str())
+        .add_attribute(("null", column.nullable))
+        .add_attribute(("type", type_expr(schema, &column.data_type)));
+    if let Some(default) = &column.default {
+        block = block.add_attribute(("default", default_expr(default)));
+    }
+    block.build()
+}
+
+fn constraint_block(table: &str, constraint: &Constraint) -> Block {
+    let columns = |columns: &[String]| {
+        let columns = columns.iter().map(|c| reference(["column", c]));
+        Attribute::new("columns", Expression::Array(columns.collect()))
+    };
+    let name = |columns: &[String], kind| {
+        constraint
+            .name
+            .clone()
+            .unwrap_or_else(|| label_name(table, columns, kind))
+    };
+    match &constraint.kind {
+        ConstraintKind::PrimaryKey { columns: cols } => Block::builder("primary_key")
+            .add_attribute(columns(cols))
+            .build(),
+        ConstraintKind::Unique { columns: cols } => Block::builder("unique")
+            .add_label(name(cols, Unnamed::Unique))
+            .add_attribute(columns(cols))
+            .build(),
+        ConstraintKind::ForeignKey {
+            columns: cols,
+            foreign_table,
+            referred_columns,
+            on_delete,
+            on_update,
+        } => {
+            let (_, foreign_table) = split_name(foreign_table);
+            let referred = referred_columns
+                .iter()
+                .map(|c| reference(["table", foreign_table, "column", c]));
+            let mut block = Block::builder("foreign_key")
+                .add_label(name(cols, Unnamed::ForeignKey))
+                .add_attribute(columns(cols))
+                .add_attribute(("ref_columns", Expression::Array(referred.collect())));
+            for (key, action) in [("on_delete", on_delete), ("on_update", on_update)] {
+                if let Some(action) = action {
+                    let action = action.replace(' ', "_").to_uppercase();
+                    block = block.add_attribute((key, Variable::unchecked(action)));
+                }
+            }
+            block.build()
+        }
+        ConstraintKind::Check { expr } => {
+            let block = Block::builder("check");
+            let block = match &constraint.name {
+                Some(name) => block.add_label(name.as_str()),
+                None => block,
+            };
+            block.add_attribute(("expr", expr.as_str())).build()
+        }
+    }
+}
+
+fn index_block(table: &str, index: &Index) -> Block {
+    let name = index
+        .name
+        .clone()
+        .unwrap_or_else(|| label_name(table, &index.columns, Unnamed::Index));
+    let mut block = Block::builder("index").add_label(split_name(&name).1);
+    if index.unique {
+        block = block.add_attribute(("unique", true));
+    }
+    // indexes on expressions list each part in an `on` block
+    if index.columns.iter().all(|c| is_ident(c)) {
+        let columns = index.columns.iter().map(|c| reference(["column", c]));
+        block = block.add_attribute(("columns", Expression::Array(columns.collect())));
+    } else {
+        for part in &index.columns {
+            let on = match is_ident(part) {
+                true => Attribute::new("column", reference(["column", part])),
+                false => Attribute::new("expr", part.as_str()),
+            };
+            block = block.add_block(Block::builder("on").add_attribute(on).build());
+        }
+    }
+    block.build()
+}
+
+/// a column type Atlas knows by name (e.g. `int` or `varchar(255)`), a reference to an enum, or
+/// the type as SQL
+fn type_expr(schema: &Schema, data_type: &str) -> Expression {
+    let is_enum = schema
+        .types
+        .iter()
+        .any(|ty| ty.name == data_type && matches!(ty.kind, TypeKind::Enum { .. }));
+    if is_enum {
+        return reference(["enum", split_name(data_type).1]);
+    }
+    if is_ident(data_type) {
+        return Variable::unchecked(data_type.to_lowercase()).into();
+    }
+    let args = data_type
+        .strip_suffix(')')
+        .and_then(|rest| rest.split_once('('))
+        .filter(|(name, _)| is_ident(name))
+        .and_then(|(name, args)| {
+            let args = args
+                .split(',')
+                .map(|arg| arg.trim().parse::<u64>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            Some((name, args))
+        });
+    match args {
+        Some((name, args)) => {
+            let mut call = FuncCall::builder(Identifier::unchecked(name.to_lowercase()));
+            for arg in args {
+                call = call.arg(arg);
+            }
+            call.build().into()
+        }
+        None => sql(data_type),
+    }
+}
+
+/// a default that's a string, number, or boolean literal as one, or any other expression as SQL
+fn default_expr(default: &str) -> Expression {
+    if let Some(s) = default
+        .strip_prefix('\'')
+        .and_then(|s| s.strip_suffix('\''))
+        .filter(|s| !s.replace("''", "").contains('\''))
+    {
+        return Expression::String(s.replace("''", "'"));
+    }
+    if let Ok(n) = default.parse::<i64>() {
+        return Expression::Number(Number::from(n));
+    }
+    match default.to_uppercase().as_str() {
+        "TRUE" => Expression::Bool(true),
+        "FALSE" => Expression::Bool(false),
+        _ => sql(default),
+    }
+}
+
+fn sql(sql: &str) -> Expression {
+    FuncCall::builder("sql").arg(sql).build().into()
+}
+
+/// a reference to another block, e.g. `column.id`
+fn reference<'a>(path: impl IntoIterator<Item = &'a str>) -> Expression {
+    let mut path = path.into_iter();
+    let root = Variable::unchecked(path.next().unwrap_or_default());
+    let operators = path.map(|name| TraversalOperator::GetAttr(Identifier::unchecked(name)));
+    Traversal::new(root, operators.collect::<Vec<_>>()).into()
+}
+
+/// the schema and unqualified name of `name`
+fn split_name(name: &str) -> (&str, &str) {
+    name.rsplit_once('.').unwrap_or((DEFAULT_SCHEMA, name))
+}
+
+/// the label of an unnamed constraint or index Atlas needs a name for: PostgreSQL's name for it
+/// (e.g. `posts_author_id_fkey`), leaving out the columns when PostgreSQL would shorten it
+fn label_name(table: &str, columns: &[String], kind: Unnamed) -> String {
+    PostgreSQL
+        .default_name(table, columns, kind)
+        .or_else(|| PostgreSQL.default_name(table, &[], kind))
+        .unwrap_or_else(|| table.to_owned())
+}
+
+/// whether `name` is the one PostgreSQL gives an unnamed constraint or index
+fn is_default_name(name: &str, table: &str, columns: &[String], kind: Unnamed) -> bool {
+    PostgreSQL.default_name(table, columns, kind).as_deref() == Some(name)
+}
+
+fn is_ident(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// parses Atlas HCL into a schema: the enum types it defines, then its tables, then their indexes
+pub fn from_hcl<Dialect: Parse>(dialect: Dialect, hcl: &str) -> Result<SyntaxTree<Dialect>> {
+    let body = hcl::parse(hcl)?;
+    let mut types = Vec::new();
+    let mut tables = Vec::new();
+    let mut indexes = Vec::new();
+    for block in body.blocks() {
+        match block.identifier() {
+            "enum" => {
+                let values = match attribute(block, "values") {
+                    Some(Expression::Array(values)) => values
+                        .iter()
+                        .map(|value| match value {
+                            Expression::String(s) => Ok(quote(s)),
+                            value => Err(unsupported("enum value", value)),
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                    _ => Vec::new(),
+                };
+                types.push(format!(
+                    "CREATE TYPE {} AS ENUM ({});",
+                    qualified_name(block)?,
+                    values.join(", ")
+                ));
+            }
+            "table" => {
+                let (table, table_indexes) = create_table(block)?;
+                tables.push(table);
+                indexes.extend(table_indexes);
+            }
+            // schemas are referred to by the objects in them
+            "schema" => {}
+            other => return Err(AtlasError::Unsupported(format!("a {other} block"))),
+        }
+    }
+    let sql = [types, tables, indexes].concat().join("\n");
+    Ok(SyntaxTree::parse(dialect, sql.as_str())?)
+}
+
+/// the CREATE TABLE statement for a `table` block, along with the CREATE INDEX statements for its
+/// indexes
+fn create_table(block: &Block) -> Result<(String, Vec<String>)> {
+    let table = qualified_name(block)?;
+    let table_name = label(block)?;
+    let mut defs = Vec::new();
+    let mut indexes = Vec::new();
+    for inner in block.body().blocks() {
+        let constraint = |kind| -> Result<String> {
+            let columns = columns(inner, "columns")?;
+            Ok(match inner.labels().first().map(BlockLabel::as_str) {
+                Some(name) if !is_default_name(name, table_name, &columns, kind) => {
+                    format!("CONSTRAINT {} ", ident(name))
+                }
+                _ => String::new(),
+            })
+        };
+        match inner.identifier() {
+            "column" => {
+                let mut def = format!("{} {}", ident(label(inner)?), column_type(inner)?);
+                if matches!(attribute(inner, "null"), Some(Expression::Bool(false))) {
+                    def.push_str(" NOT NULL");
+                }
+                if let Some(default) = attribute(inner, "default") {
+                    def.push_str(" DEFAULT ");
+                    def.push_str(&default_sql(default)?);
+                }
+                defs.push(def);
+            }
+            "primary_key" => {
+                let columns = columns(inner, "columns")?;
+                defs.push(format!("PRIMARY KEY ({})", idents(&columns)));
+            }
+            "unique" => {
+                let columns = columns(inner, "columns")?;
+                defs.push(format!(
+                    "{}UNIQUE ({})",
+                    constraint(Unnamed::Unique)?,
+                    idents(&columns)
+                ));
+            }
+            "foreign_key" => {
+                let columns = columns(inner, "columns")?;
+                let referred = references(inner, "ref_columns")?;
+                let foreign_table = referred
+                    .first()
+                    .and_then(|path| path.iter().position(|p| p == "column").map(|i| &path[..i]))
+                    .and_then(|path| path.last())
+                    .ok_or_else(|| {
+                        AtlasError::Unsupported("a foreign key without ref_columns".to_owned())
+                    })?;
+                let referred = referred
+                    .iter()
+                    .filter_map(|path| path.last().cloned())
+                    .collect::<Vec<_>>();
+                let mut def = format!(
+                    "{}FOREIGN KEY ({}) REFERENCES {} ({})",
+                    constraint(Unnamed::ForeignKey)?,
+                    idents(&columns),
+                    ident(foreign_table),
+                    idents(&referred)
+                );
+                for (key, clause) in [("on_delete", "ON DELETE"), ("on_update", "ON UPDATE")] {
+                    match attribute(inner, key) {
+                        Some(Expression::Variable(action)) => {
+                            def.push_str(&format!(" {clause} {}", action.replace('_', " ")));
+                        }
+                        Some(other) => return Err(unsupported(key, other)),
+                        None => {}
+                    }
+                }
+                defs.push(def);
+            }
+            "check" => {
+                let expr = match attribute(inner, "expr") {
+                    Some(Expression::String(expr)) => expr,
+                    other => {
+                        return Err(unsupported(
+                            "check expr",
+                            other.unwrap_or(&Expression::Null),
+                        ))
+                    }
+                };
+                let name = match inner.labels().first() {
+                    Some(name) => format!("CONSTRAINT {} ", ident(name.as_str())),
+                    None => String::new(),
+                };
+                defs.push(format!("{name}CHECK ({expr})"));
+            }
+            "index" => indexes.push(create_index(&table, table_name, inner)?),
+            other => {
+                return Err(AtlasError::Unsupported(format!(
+                    "a {other} block in {table}"
+                )))
+            }
+        }
+    }
+    Ok((
+        format!("CREATE TABLE {table} ({});", defs.join(", ")),
+        indexes,
+    ))
+}
+
+fn create_index(table: &str, table_name: &str, block: &Block) -> Result<String> {
+    let mut parts = columns(block, "columns")?;
+    for on in block.body().blocks().filter(|b| b.identifier() == "on") {
+        match (attribute(on, "expr"), references(on, "column")) {
+            (Some(Expression::String(expr)), _) => parts.push(expr.clone()),
+            (_, Ok(columns)) if !columns.is_empty() => {
+                parts.extend(columns.iter().filter_map(|path| path.last().cloned()))
+            }
+            _ => return Err(AtlasError::Unsupported(format!("an index part in {table}"))),
+        }
+    }
+    let unique = match attribute(block, "unique") {
+        Some(Expression::Bool(true)) => "UNIQUE ",
+        _ => "",
+    };
+    let name = match block.labels().first().map(BlockLabel::as_str) {
+        Some(name) if !is_default_name(name, table_name, &parts, Unnamed::Index) => {
+            format!("{} ", ident(name))
+        }
+        _ => String::new(),
+    };
+    let parts = parts
+        .iter()
+        .map(|part| match is_ident(part) {
+            true => ident(part),
+            false => part.clone(),
+        })
+        .collect::<Vec<_>>();
+    Ok(format!(
+        "CREATE {unique}INDEX {name}ON {table} ({});",
+        parts.join(", ")
+    ))
+}
+
+/// the SQL type of a `column` block
+fn column_type(block: &Block) -> Result<String> {
+    match attribute(block, "type") {
+        Some(Expression::Variable(name)) => Ok(name.to_uppercase()),
+        Some(Expression::Traversal(traversal)) => Ok(traversal_path(traversal)
+            .and_then(|path| path.last().cloned())
+            .map(|name| ident(&name))
+            .ok_or_else(|| {
+                unsupported("a column type", &Expression::Traversal(traversal.clone()))
+            })?),
+        Some(Expression::FuncCall(call)) => func_sql(call),
+        other => Err(unsupported(
+            "a column type",
+            other.unwrap_or(&Expression::Null),
+        )),
+    }
+}
+
+fn default_sql(default: &Expression) -> Result<String> {
+    match default {
+        Expression::String(s) => Ok(quote(s)),
+        Expression::Number(n) => Ok(n.to_string()),
+        Expression::Bool(b) => Ok(b.to_string().to_uppercase()),
+        Expression::Null => Ok("NULL".to_owned()),
+        Expression::FuncCall(call) => func_sql(call),
+        other => Err(unsupported("a default", other)),
+    }
+}
+
+/// the SQL of `sql("...")`, or of a type with parameters (e.g. `varchar(255)`)
+fn func_sql(call: &FuncCall) -> Result<String> {
+    let name = call.name.name.as_str();
+    match (name, call.args.as_slice()) {
+        ("sql", [Expression::String(sql)]) => Ok(sql.clone()),
+        _ => {
+            let args = call
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    Expression::Number(n) => Ok(n.to_string()),
+                    arg => Err(unsupported(name, arg)),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(format!("{}({})", name.to_uppercase(), args.join(", ")))
+        }
+    }
+}
+
+fn attribute<'a>(block: &'a Block, key: &str) -> Option<&'a Expression> {
+    block
+        .body()
+        .attributes()
+        .find(|attr| attr.key.as_str() == key)
+        .map(|attr| &attr.expr)
+}
+
+/// the first label of `block`, i.e. the name of the object it defines
+fn label(block: &Block) -> Result<&str> {
+    block
+        .labels()
+        .first()
+        .map(BlockLabel::as_str)
+        .ok_or_else(|| {
+            AtlasError::Unsupported(format!("a {} block without a name", block.identifier()))
+        })
+}
+
+/// the name of a table or enum, qualified with its schema unless it's the default one
+fn qualified_name(block: &Block) -> Result<String> {
+    let name = ident(label(block)?);
+    let schema = match attribute(block, "schema") {
+        Some(Expression::Traversal(traversal)) => traversal_path(traversal)
+            .and_then(|path| path.last().cloned())
+            .filter(|schema| schema != DEFAULT_SCHEMA),
+        _ => None,
+    };
+    Ok(match schema {
+        Some(schema) => format!("{}.{name}", ident(&schema)),
+        None => name,
+    })
+}
+
+/// the names of the columns referred to by `key` (e.g. `columns = [column.id]`)
+fn columns(block: &Block, key: &str) -> Result<Vec<String>> {
+    Ok(references(block, key)?
+        .into_iter()
+        .filter_map(|mut path| path.pop())
+        .collect())
+}
+
+/// the paths of the references in `key`, e.g. `[table.users.column.id]`
+fn references(block: &Block, key: &str) -> Result<Vec<Vec<String>>> {
+    let exprs = match attribute(block, key) {
+        Some(Expression::Array(exprs)) => exprs.as_slice(),
+        Some(expr) => std::slice::from_ref(expr),
+        None => &[],
+    };
+    exprs
+        .iter()
+        .map(|expr| match expr {
+            Expression::Traversal(traversal) => {
+                traversal_path(traversal).ok_or_else(|| unsupported(key, expr))
+            }
+            expr => Err(unsupported(key, expr)),
+        })
+        .collect()
+}
+
+fn traversal_path(traversal: &Traversal) -> Option<Vec<String>> {
+    let Expression::Variable(root) = &traversal.expr else {
+        return None;
+    };
+    let mut path = vec![root.to_string()];
+    for operator in &traversal.operators {
+        match operator {
+            TraversalOperator::GetAttr(name) => path.push(name.to_string()),
+            TraversalOperator::Index(Expression::String(name)) => path.push(name.clone()),
+            _ => return None,
+        }
+    }
+    Some(path)
+}
+
+fn unsupported(what: &str, expr: &Expression) -> AtlasError {
+    AtlasError::Unsupported(format!("{what} `{expr}`"))
+}
+
+fn ident(name: &str) -> String {
+    match is_ident(name) {
+        true => name.to_owned(),
+        false => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+fn idents(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| ident(name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::PostgreSQL;
+
+    #[test]
+    fn round_trip() {
+        let sql = "CREATE TYPE status AS ENUM ('active', 'banned');\n\
+            CREATE TABLE users (id INT NOT NULL, email VARCHAR(255) NOT NULL, \
+            status status DEFAULT 'active', PRIMARY KEY (id), UNIQUE (email));\n\
+            CREATE TABLE posts (id BIGINT NOT NULL, author_id INT, title TEXT NOT NULL, \
+            created_at TIMESTAMP WITH TIME ZONE DEFAULT now(), PRIMARY KEY (id), \
+            CONSTRAINT posts_author FOREIGN KEY (author_id) REFERENCES users (id) ON DELETE CASCADE, \
+            CHECK (length(title) > 0));\n\
+            CREATE INDEX ON posts (author_id);\n\
+            CREATE UNIQUE INDEX posts_title ON posts (lower(title));\n\
+            CREATE EXTENSION pgcrypto;";
+        let tree = SyntaxTree::parse(PostgreSQL, sql).unwrap();
+        let (hcl, left_out) = to_hcl(&tree).unwrap();
+        assert_eq!(
+            left_out.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+            ["pgcrypto"]
+        );
+        assert!(hcl.contains("type = varchar(255)"), "{hcl}");
+        assert!(hcl.contains("type = enum.status"), "{hcl}");
+        assert!(
+            hcl.contains("type = sql(\"TIMESTAMP WITH TIME ZONE\")"),
+            "{hcl}"
+        );
+        assert!(
+            hcl.contains("ref_columns = [table.users.column.id]"),
+            "{hcl}"
+        );
+        assert!(hcl.contains("on_delete = CASCADE"), "{hcl}");
+
+        let converted = from_hcl(PostgreSQL, &hcl).unwrap();
+        let expected = SyntaxTree::parse(PostgreSQL, sql.rsplit_once('\n').unwrap().0).unwrap();
+        assert_eq!(converted.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn from_atlas() {
+        let hcl = r#"
+            schema "app" {}
+            table "accounts" {
+              schema = schema.app
+              column "id" {
+                null = false
+                type = bigint
+              }
+              column "active" {
+                null = false
+                type = boolean
+                default = true
+              }
+              primary_key {
+                columns = [column.id]
+              }
+            }
+        "#;
+        let tree = from_hcl(PostgreSQL, hcl).unwrap();
+        let expected = SyntaxTree::parse(
+            PostgreSQL,
+            "CREATE TABLE app.accounts \
+            (id BIGINT NOT NULL, active BOOLEAN NOT NULL DEFAULT TRUE, PRIMARY KEY (id));",
+        )
+        .unwrap();
+        assert_eq!(tree.to_string(), expected.to_string());
+        assert!(matches!(
+            from_hcl(PostgreSQL, "view \"active\" {}"),
+            Err(AtlasError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+use std::{any, fs};
+
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sql_schema::{
+    load::LoadError, migrations, sqlparser::ast::Statement, Parse, Provenance, SyntaxTree,
+    TreeDiffer, TreeMigrator,
+};
+use tracing::{debug, warn};
+
+pub const DEFAULT_CACHE_DIR: &str = ".sql-schema/cache";
+
+/// the schema each migrations dir adds up to, along with a hash of the migrations it's made from
+/// so later runs only parse and apply the migrations added since
+pub struct SnapshotCache {
+    dir: Utf8PathBuf,
+}
+
+impl Default for SnapshotCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_DIR)
+    }
+}
+
+impl SnapshotCache {
+    pub fn new(dir: impl Into<Utf8PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// builds a [SyntaxTree] by applying each migration in `dir` in order, starting from the
+    /// snapshot when it was taken of the same migrations (or the first of them)
+    pub fn migrate<Dialect>(
+        &self,
+        dialect: Dialect,
+        dir: &Utf8Path,
+        paths: &[&Utf8Path],
+    ) -> anyhow::Result<SyntaxTree<Dialect>>
+    where
+        Dialect: TreeDiffer + TreeMigrator + Parse + Send + Sync,
+    {
+        let files = paths
+            .par_iter()
+            .map(|path| fs::read_to_string(path).with_context(|| format!("reading {path}")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let keys = snapshot_keys::<Dialect>(paths, &files);
+        let entry = self.entry(dir);
+
+        let (start, schema) = read(&entry)
+            .and_then(|snapshot| {
+                let i = keys.iter().position(|key| *key == snapshot.key)?;
+                let schema =
+                    SyntaxTree::from(snapshot.statements).with_provenance(snapshot.provenance);
+                Some((i + 1, schema))
+            })
+            .unwrap_or_else(|| (0, SyntaxTree::empty()));
+        // what applying the migrations (and diffing against them) glosses over
+        let schema = schema.on_warning(|w| warn!("{w}"));
+        if start > 0 {
+            debug!("starting from the snapshot of {}", paths[start - 1]);
+        }
+
+        // parsing each file doesn't depend on the others, applying them does
+        let parsed = paths[start..]
+            .par_iter()
+            .zip(&files[start..])
+            .map(|(path, sql)| {
+                debug!("parsing {path}");
+                // only the up section of a migration holding both is applied
+                let (up, _) = migrations::split_sections(sql);
+                SyntaxTree::parse(dialect.clone(), up)
+                    .map(|tree| tree.with_path(path))
+                    .map_err(|source| LoadError::Parse {
+                        path: path.to_path_buf(),
+                        source: source.with_path(path),
+                    })
+            })
+            .collect::<Vec<_>>();
+        // apply the migrations up to the first that couldn't be parsed, so an error applying an
+        // earlier one is reported first
+        let mut parse_error = None;
+        let migrations = parsed
+            .into_iter()
+            .map_while(|migration| migration.map_err(|err| parse_error = Some(err)).ok());
+        let mut schema = schema;
+        schema.apply_all(migrations)?;
+        if let Some(err) = parse_error {
+            return Err(err.into());
+        }
+
+        if let Some(key) = keys.last().filter(|_| start < keys.len()) {
+            if let Err(err) = self.write(&entry, key, &schema) {
+                debug!("couldn't write the snapshot of {dir}: {err:#}");
+            }
+        }
+        Ok(schema)
+    }
+
+    /// where the snapshot of `dir` is stored
+    fn entry(&self, dir: &Utf8Path) -> Utf8PathBuf {
+        let dir = dir.canonicalize_utf8().unwrap_or_else(|_| dir.to_owned());
+        self.dir
+            .join(format!("{:x}.json", Sha256::digest(dir.as_str())))
+    }
+
+    fn write<Dialect>(
+        &self,
+        entry: &Utf8Path,
+        key: &str,
+        schema: &SyntaxTree<Dialect>,
+    ) -> anyhow::Result<()> {
+        if !self.dir.exists() {
+            fs::create_dir_all(&self.dir)?;
+            // keep the cache out of version control
+            if let Some(parent) = self.dir.parent().filter(|p| !p.as_str().is_empty()) {
+                let gitignore = parent.join(".gitignore");
+                if !gitignore.exists() {
+                    fs::write(gitignore, "*\n")?;
+                }
+            }
+        }
+        let statements = schema.statements();
+        let provenance = (0..statements.len())
+            .map(|i| schema.provenance(i))
+            .collect::<Vec<_>>();
+        let snapshot = Snapshot {
+            key,
+            statements,
+            provenance,
+        };
+        fs::write(entry, serde_json::to_vec(&snapshot)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot<K, S, P> {
+    key: K,
+    statements: S,
+    /// where each statement was first parsed from, for pointing errors at the migrations
+    #[serde(default)]
+    provenance: P,
+}
+
+fn read(entry: &Utf8Path) -> Option<Snapshot<String, Vec<Statement>, Vec<Option<Provenance>>>> {
+    let json = fs::read(entry).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+/// a hash of each prefix of the migrations (what they parse to also depends on the dialect and the
+/// version of sql-schema)
+fn snapshot_keys<Dialect>(paths: &[&Utf8Path], files: &[String]) -> Vec<String> {
+    let mut hash = Sha256::new();
+    update(&mut hash, env!("CARGO_PKG_VERSION"));
+    update(&mut hash, any::type_name::<Dialect>());
+    paths
+        .iter()
+        .zip(files)
+        .map(|(path, sql)| {
+            update(&mut hash, path.as_str());
+            update(&mut hash, sql);
+            format!("{:x}", hash.clone().finalize())
+        })
+        .collect()
+}
+
+fn update(hash: &mut Sha256, part: &str) {
+    hash.update(part.len().to_le_bytes());
+    hash.update(part);
+}
@@ -0,0 +1,349 @@
+use std::{collections::HashMap, fmt, fs};
+
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
+use chrono::Datelike;
+use serde::Deserialize;
+use sql_schema::{
+    path_template::{DateBounds, PathTemplate},
+    FormatOptions, Indent,
+};
+
+pub const DEFAULT_CONFIG_PATH: &str = "sql-schema.toml";
+
+/// written by `sql-schema init --write-config`
+pub const TEMPLATE: &str = r#"[migration]
+# template inserted at the top of every generated migration
+# (supports {timestamp}, {version}, {name}, and {schema_hash} placeholders)
+# header = """
+# -- {name}: generated by sql-schema {version}
+# """
+
+# or read the template from a file (relative to this one)
+# header_file = "schema/header.sql"
+
+# name new migrations with this template instead of following the most recent migration
+# (supports {timestamp}, {counter}, {name}, {updown}, {branch}, and {author} placeholders)
+# path_template = "{timestamp}_{author}_{name}.{updown}.sql"
+
+# write migration names as given instead of slugifying them (e.g. `Add users & teams` becomes
+# `add_users_teams` by default)
+# verbatim_name = true
+
+# fail when the most recent migration's name can't be made out instead of naming new migrations
+# after the default template
+# strict_naming = true
+
+# file extensions of migrations (default is sql, pgsql, and ddl); new migrations follow the most
+# recent one, or use the first extension when there are none
+# extensions = ["psql"]
+
+# write new migrations as a dir holding up.sql and down.sql (dir) or as a file each (flat), instead
+# of following the most recent migration
+# layout = "dir"
+
+# the years a date or timestamp in a migration name can be in (default is 2000 through 2100), e.g.
+# to read 19991231_init.sql as a date
+# min_year = 1990
+# max_year = 2100
+
+# lay out migrations the way another migration tool expects them (diesel, refinery,
+# golang-migrate, or dbmate)
+# compat = "diesel"
+
+# write every CREATE, DROP, and ALTER statement of generated migrations with IF EXISTS and
+# IF NOT EXISTS (always) or without them (never), instead of the way the diff writes them
+# if_exists = "always"
+
+# values of any other placeholders in the path template, e.g. {team} (`--var team=payments` takes
+# precedence)
+# [migration.vars]
+# team = "payments"
+
+[format]
+# spaces per indentation level
+# indent = 2
+# indent with tabs instead of spaces
+# tabs = false
+# uppercase (true) or lowercase (false) reserved keywords
+# uppercase = true
+# blank lines between statements
+# lines_between = 1
+
+[hooks]
+# run after a migration is written, once per file with {path} or once for all of them with {paths}
+# post_migration = "pg_format -i {path}"
+"#;
+
+/// settings read from `sql-schema.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// the directory containing the config file (paths in the config are relative to it)
+    #[serde(skip)]
+    pub dir: Utf8PathBuf,
+    pub migration: MigrationConfig,
+    pub format: FormatConfig,
+    pub hooks: HooksConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MigrationConfig {
+    /// template inserted at the top of every generated migration
+    pub header: Option<String>,
+    /// file containing the header template
+    pub header_file: Option<String>,
+    /// naming convention for new migrations (default is to follow the most recent migration;
+    /// `--path-template` takes precedence)
+    pub path_template: Option<String>,
+    /// write migration names as given instead of slugifying them (also set by `--verbatim-name`)
+    pub verbatim_name: bool,
+    /// fail when the most recent migration's name can't be made out instead of naming new
+    /// migrations after the default template (also set by `--strict-naming`)
+    pub strict_naming: bool,
+    /// file extensions of migrations, without the `.` (`--extension` takes precedence)
+    pub extensions: Option<Vec<String>>,
+    /// how new migrations are laid out (default is to follow the most recent migration; `--layout`
+    /// takes precedence)
+    pub layout: Option<Layout>,
+    /// the migration tool to stay compatible with (`--compat` takes precedence)
+    pub compat: Option<Compat>,
+    /// whether generated statements are written with `IF EXISTS` and `IF NOT EXISTS` (default is
+    /// the way the diff writes them; `--if-exists` takes precedence)
+    pub if_exists: Option<IfExists>,
+    /// values of the variables in the path template (e.g. `team` for `{team}`; `--var` takes
+    /// precedence)
+    pub vars: HashMap<String, String>,
+    /// the earliest year a timestamp in a migration name can be in (default is 2000)
+    pub min_year: Option<i32>,
+    /// the latest year a timestamp in a migration name can be in (default is 2100)
+    pub max_year: Option<i32>,
+}
+
+impl MigrationConfig {
+    /// the dates a timestamp in a migration name plausibly falls between
+    pub fn date_bounds(&self) -> anyhow::Result<DateBounds> {
+        let default = DateBounds::default();
+        let (min, max) = (
+            self.min_year.unwrap_or(default.min.year()),
+            self.max_year.unwrap_or(default.max.year()),
+        );
+        match DateBounds::years(min, max) {
+            Some(bounds) if min <= max => Ok(bounds),
+            _ => Err(anyhow::anyhow!(
+                "min_year ({min}) and max_year ({max}) aren't a range of years"
+            )),
+        }
+    }
+}
+
+/// how the files of a migration are laid out
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    /// a file per migration, e.g. `<version>_<name>.up.sql` and `<version>_<name>.down.sql`
+    Flat,
+    /// a dir per migration, e.g. `<version>_<name>/up.sql` and `<version>_<name>/down.sql`
+    Dir,
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flat => write!(f, "flat"),
+            Self::Dir => write!(f, "dir"),
+        }
+    }
+}
+
+/// whether the statements of generated migrations are written with `IF EXISTS` and `IF NOT EXISTS`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum IfExists {
+    /// on every statement that can have them
+    Always,
+    /// on none of them
+    Never,
+    /// the way the diff writes them
+    Preserve,
+}
+
+impl fmt::Display for IfExists {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Always => write!(f, "always"),
+            Self::Never => write!(f, "never"),
+            Self::Preserve => write!(f, "preserve"),
+        }
+    }
+}
+
+impl From<IfExists> for sql_schema::IfExists {
+    fn from(policy: IfExists) -> Self {
+        match policy {
+            IfExists::Always => Self::Always,
+            IfExists::Never => Self::Never,
+            IfExists::Preserve => Self::Preserve,
+        }
+    }
+}
+
+/// another migration tool whose layout migrations follow
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compat {
+    /// `migrations/<timestamp>_<name>/{up,down}.sql` in the dir set in `diesel.toml`
+    Diesel,
+    /// `migrations/V<version>__<name>.sql` without down migrations
+    Refinery,
+    /// `migrations/<version>_<name>.{up,down}.sql`, numbered sequentially or by timestamp
+    GolangMigrate,
+    /// `db/migrations/<timestamp>_<name>.sql` with `-- migrate:up` and `-- migrate:down` sections
+    Dbmate,
+}
+
+impl fmt::Display for Compat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Diesel => write!(f, "diesel"),
+            Self::Refinery => write!(f, "refinery"),
+            Self::GolangMigrate => write!(f, "golang-migrate"),
+            Self::Dbmate => write!(f, "dbmate"),
+        }
+    }
+}
+
+impl Compat {
+    /// how the tool names migrations, continuing the numbering of `detected` (the template of the
+    /// most recent migration)
+    pub fn path_template(self, detected: &PathTemplate) -> PathTemplate {
+        let sequential = detected
+            .template_data()
+            .is_ok_and(|data| data.counter.is_some());
+        match self {
+            Self::Diesel => PathTemplate::parse("2000-01-01-000000_name/up.sql"),
+            Self::Refinery => PathTemplate::compile("V{counter}__{name}.sql"),
+            // `migrate create -seq` numbers migrations, otherwise they're timestamped
+            Self::GolangMigrate if sequential => PathTemplate::parse("000000_name.up.sql"),
+            Self::GolangMigrate => PathTemplate::compile("{timestamp}_{name}.{updown}.sql"),
+            Self::Dbmate => PathTemplate::parse("20000101000000_name.sql"),
+        }
+        .expect("valid path template")
+        .continue_from(detected)
+    }
+
+    /// whether the tool needs (true) or doesn't support (false) down migrations
+    pub fn down_migrations(self) -> bool {
+        match self {
+            Self::Diesel => true,
+            Self::Refinery => false,
+            Self::GolangMigrate => true,
+            Self::Dbmate => true,
+        }
+    }
+
+    /// whether the tool keeps the down migration in the same file as the up migration
+    pub fn single_file(self) -> bool {
+        matches!(self, Self::Dbmate)
+    }
+
+    /// the migrations dir the tool is configured with
+    pub fn migrations_dir(self) -> anyhow::Result<Utf8PathBuf> {
+        match self {
+            Self::Diesel => diesel_migrations_dir(),
+            Self::Refinery | Self::GolangMigrate => Ok(Utf8PathBuf::from("migrations")),
+            Self::Dbmate => Ok(Utf8PathBuf::from("db/migrations")),
+        }
+    }
+}
+
+/// the `[migrations_directory]` of `diesel.toml` (relative to it), or `migrations` by default
+fn diesel_migrations_dir() -> anyhow::Result<Utf8PathBuf> {
+    #[derive(Default, Deserialize)]
+    #[serde(default)]
+    struct DieselConfig {
+        migrations_directory: Option<MigrationsDirectory>,
+    }
+
+    #[derive(Deserialize)]
+    struct MigrationsDirectory {
+        dir: Utf8PathBuf,
+    }
+
+    let path = Utf8Path::new("diesel.toml");
+    let config = match fs::read_to_string(path) {
+        Ok(data) => toml::from_str(&data).context(format!("path: {path}"))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => DieselConfig::default(),
+        Err(err) => return Err(err).context(format!("path: {path}")),
+    };
+    Ok(config
+        .migrations_directory
+        .map_or_else(|| Utf8PathBuf::from("migrations"), |m| m.dir))
+}
+
+/// shell commands run after sql-schema writes files
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// run after a migration is written (e.g. `pg_format -i {path}`)
+    pub post_migration: Option<String>,
+}
+
+/// how generated and formatted SQL is laid out
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatConfig {
+    /// spaces per indentation level (default is 2)
+    pub indent: Option<u8>,
+    /// indent with tabs instead of spaces
+    pub tabs: bool,
+    /// uppercase (true) or lowercase (false) reserved keywords
+    pub uppercase: Option<bool>,
+    /// blank lines between statements (default is 1)
+    pub lines_between: Option<u8>,
+}
+
+impl FormatConfig {
+    pub fn options(&self) -> FormatOptions {
+        let indent = if self.tabs {
+            Indent::Tabs
+        } else {
+            Indent::Spaces(self.indent.unwrap_or(2))
+        };
+        FormatOptions::new(indent, self.uppercase)
+            .with_lines_between(self.lines_between.unwrap_or(1))
+    }
+}
+
+impl Config {
+    /// loads the config at `path`, or `sql-schema.toml` if it exists when no path is given
+    pub fn load(path: Option<&Utf8Path>) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None if Utf8Path::new(DEFAULT_CONFIG_PATH).is_file() => {
+                Utf8Path::new(DEFAULT_CONFIG_PATH)
+            }
+            None => return Ok(Self::default()),
+        };
+        let data = fs::read_to_string(path).context(format!("path: {path}"))?;
+        let mut config: Self = toml::from_str(&data).context(format!("path: {path}"))?;
+        config.dir = path.parent().map(Utf8Path::to_owned).unwrap_or_default();
+        Ok(config)
+    }
+
+    /// the header template for generated migrations, with `header_file` taking precedence
+    pub fn header(&self, header_file: Option<&Utf8Path>) -> anyhow::Result<Option<String>> {
+        let header_file = match (header_file, &self.migration.header_file) {
+            (Some(path), _) => Some(path.to_owned()),
+            (None, Some(path)) => Some(self.dir.join(path)),
+            (None, None) => None,
+        };
+        match header_file {
+            Some(path) => Ok(Some(
+                fs::read_to_string(&path).context(format!("path: {path}"))?,
+            )),
+            None => Ok(self.migration.header.clone()),
+        }
+    }
+}
@@ -0,0 +1,3669 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, IsTerminal, Write},
+    process::{self},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        OnceLock,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Context};
+use cache::SnapshotCache;
+use camino::{Utf8Path, Utf8PathBuf};
+use chrono::{DateTime, FixedOffset, Utc};
+use clap::{CommandFactory, Parser, Subcommand};
+use config::{Compat, Config, IfExists, Layout};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+#[cfg(feature = "atlas")]
+use sql_schema::atlas;
+#[cfg(feature = "codegen")]
+use sql_schema::codegen::{self, RustOptions};
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+use sql_schema::tracking::{self, Runner};
+use sql_schema::{
+    changes::{Change, IrreversibleChange, ObjectKind, Operation, Order},
+    dialect::Dialect,
+    load,
+    migrations::{
+        self, detect_path_template, find_migrations, MigrationFile, DOWN_MARKER, SEEDS_DIR_NAME,
+        UP_MARKER,
+    },
+    name_gen,
+    path_template::{PathTemplate, TemplateData, UpDown},
+    pg_dump,
+    tracking::{AppliedMigration, TrackingTable},
+    transpile::{Transpile, Untranslatable},
+    Backfill, FormatOptions, LockImpact, LockLevel, Render, SyntaxTree, TreeDiffer, TreeMigrator,
+    ZeroDowntime,
+};
+use tracing::{debug, error, info, warn, Level};
+use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
+
+mod cache;
+mod config;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Commands,
+    /// print more detail (-v for debug output such as per-statement diffs, -vv for everything)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    /// only print warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// disable colored output (also respects NO_COLOR)
+    #[arg(long, global = true)]
+    no_color: bool,
+    /// path to config file (default is `sql-schema.toml` if it exists)
+    #[arg(long, global = true, env = "SQL_SCHEMA_CONFIG")]
+    config: Option<Utf8PathBuf>,
+    /// lay out migrations the way another migration tool expects them (naming new migrations
+    /// after its convention and defaulting to its migrations dir)
+    #[arg(long, global = true, env = "SQL_SCHEMA_COMPAT")]
+    compat: Option<Compat>,
+    /// name new migrations with this template (e.g. `{timestamp}_{name}.{updown}.sql`) instead of
+    /// following the most recent migration
+    #[arg(long, global = true, env = "SQL_SCHEMA_PATH_TEMPLATE")]
+    path_template: Option<String>,
+    /// write migration names given with --name as they are instead of slugifying them (e.g.
+    /// `Add users & teams` becomes `add_users_teams` by default)
+    #[arg(long, global = true)]
+    verbatim_name: bool,
+    /// file extension of migrations (e.g. `pgsql`), repeated for more than one (default is sql,
+    /// pgsql, and ddl)
+    #[arg(long = "extension", global = true, value_name = "EXT")]
+    extensions: Vec<String>,
+    /// write new migrations as a dir holding `up.sql` and `down.sql` (dir) or as a file each
+    /// (flat) instead of following the most recent migration
+    #[arg(long, global = true, env = "SQL_SCHEMA_LAYOUT")]
+    layout: Option<Layout>,
+    /// write every CREATE, DROP, and ALTER statement of generated migrations with `IF EXISTS` and
+    /// `IF NOT EXISTS` (always) or without them (never) instead of the way the diff writes them
+    #[arg(long, global = true, env = "SQL_SCHEMA_IF_EXISTS")]
+    if_exists: Option<IfExists>,
+    /// value of a variable in the path template (e.g. `--var team=payments` for `{team}`),
+    /// repeated for more than one
+    #[arg(long = "var", global = true, value_name = "KEY=VALUE", value_parser = parse_var)]
+    vars: Vec<(String, String)>,
+    /// fail when the most recent migration's name can't be made out instead of naming new
+    /// migrations after the default template
+    #[arg(long, global = true)]
+    strict_naming: bool,
+}
+
+/// parses a `key=value` pair of `--var`
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => Err(format!("expected KEY=VALUE, got `{s}`")),
+    }
+}
+
+/// whether a migration name that can't be made out is an error, set from the config before running
+/// a command
+static STRICT_NAMING: AtomicBool = AtomicBool::new(false);
+
+/// how migrations dirs are read, set from the config before running a command
+static FIND_OPTIONS: OnceLock<migrations::Options> = OnceLock::new();
+
+/// the options to find migrations with (see [FIND_OPTIONS])
+fn find_options() -> &'static migrations::Options {
+    FIND_OPTIONS.get_or_init(Default::default)
+}
+
+const DEFAULT_MIGRATIONS_DIR: &str = "./schema/migrations";
+const DEFAULT_SCHEMA_PATH: &str = "./schema/schema.sql";
+const DEFAULT_SEEDS_DIR: &str = "./schema/seeds";
+/// the name of a migration that isn't named for what it changes
+const GENERIC_NAME: &str = "generated_migration";
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// create a baseline migration from an existing schema file or database
+    Init(InitCommand),
+    /// generate a new schema
+    Schema(SchemaCommand),
+    /// generate a new migration
+    Migration(MigrationCommand),
+    /// create a new empty migration
+    New(NewCommand),
+    /// print the migration from one schema to another
+    Diff(DiffCommand),
+    /// verify the schema file matches the migrations
+    Check(CheckCommand),
+    /// consolidate older migrations into a single baseline migration
+    Squash(SquashCommand),
+    /// write the schema of a live database to the schema file
+    Introspect(IntrospectCommand),
+    /// print the statements needed to bring a live database in line with the schema file
+    Drift(DriftCommand),
+    /// list which migrations have been applied to a live database
+    Status(StatusCommand),
+    /// apply the pending migrations to a live database, recording them in its tracking table
+    Apply(ApplyCommand),
+    /// run the down migrations of the most recently applied migrations against a live database
+    #[command(alias = "down")]
+    Rollback(RollbackCommand),
+    /// reformat the schema and migration files
+    Fmt(FmtCommand),
+    /// check the migrations dir for duplicate versions, gaps, missing down migrations, and
+    /// inconsistent file names
+    Validate(ValidateCommand),
+    /// print the dependency graph of the schema (foreign keys, type usage, and view references)
+    Graph(GraphCommand),
+    /// print model definitions for the tables and enum types of the schema
+    #[cfg(feature = "codegen")]
+    Codegen(CodegenCommand),
+    /// print the schema in Atlas's HCL format (schema files ending in `.hcl` are read as Atlas HCL
+    /// by every command)
+    #[cfg(feature = "atlas")]
+    Atlas(AtlasCommand),
+    /// check that the seed data files parse and don't change the schema
+    Seed(SeedCommand),
+    /// convert a schema from one dialect to another
+    Transpile(TranspileCommand),
+    /// combine schema files into one, failing when an object is created in more than one
+    Merge(MergeCommand),
+    /// print a shell completion script
+    Completions(CompletionsCommand),
+}
+
+#[derive(Parser, Debug)]
+struct InitCommand {
+    /// path to schema file (or directory of schema files)
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_SCHEMA_PATH), env = "SQL_SCHEMA_SCHEMA_PATH")]
+    schema_path: Utf8PathBuf,
+    /// path to migrations directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// dialect of SQL to use (default is generic, or inferred from --database-url)
+    #[arg(short, long, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Option<Dialect>,
+    /// introspect this database into the schema file first (e.g. postgres://localhost/app)
+    #[arg(long, env = "SQL_SCHEMA_DATABASE_URL")]
+    database_url: Option<String>,
+    /// database schema to introspect (postgres only)
+    #[arg(long, default_value = "public", env = "SQL_SCHEMA_DB_SCHEMA")]
+    db_schema: String,
+    /// name of the baseline migration
+    #[arg(short, long, default_value = "initial_schema")]
+    name: String,
+    /// creates both an up and down migration when true
+    #[arg(long)]
+    include_down: Option<bool>,
+    /// number migrations sequentially (e.g. `0001_initial_schema`) instead of by timestamp
+    #[arg(long)]
+    sequential: bool,
+    /// also write a `sql-schema.toml` with the available settings
+    #[arg(long)]
+    write_config: bool,
+    #[command(flatten)]
+    timestamp: TimestampArgs,
+}
+
+#[derive(Parser, Debug)]
+struct SchemaCommand {
+    /// path to schema file (or directory of schema files), or `-` to print the schema to stdout
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_SCHEMA_PATH), env = "SQL_SCHEMA_SCHEMA_PATH")]
+    schema_path: Utf8PathBuf,
+    /// path to migrations directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+    /// write one file per object (e.g. `tables/users.sql`) into the schema dir
+    #[arg(long)]
+    split_by: Option<SplitBy>,
+    /// the order to write the statements in
+    #[arg(long, default_value_t = SchemaOrder::Migration)]
+    order: SchemaOrder,
+    /// group the statements by kind (extensions, types, domains, tables, views, indexes, then
+    /// everything else), each under a header comment (e.g. `-- tables`) when writing a single file
+    #[arg(long)]
+    group_by_kind: bool,
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct FilterArgs {
+    /// only manage tables matching this glob pattern (can be repeated)
+    #[arg(long = "include-table", value_name = "PATTERN")]
+    include_tables: Vec<String>,
+    /// ignore tables matching this glob pattern (can be repeated)
+    #[arg(long = "exclude-table", value_name = "PATTERN")]
+    exclude_tables: Vec<String>,
+    /// only manage objects in database schemas matching this glob pattern (can be repeated)
+    #[arg(long = "include-schema", value_name = "PATTERN")]
+    include_schemas: Vec<String>,
+    /// ignore objects in database schemas matching this glob pattern (can be repeated)
+    #[arg(long = "exclude-schema", value_name = "PATTERN")]
+    exclude_schemas: Vec<String>,
+}
+
+impl FilterArgs {
+    fn build(&self) -> anyhow::Result<ObjectFilter> {
+        fn glob_set(patterns: &[String]) -> anyhow::Result<Option<GlobSet>> {
+            if patterns.is_empty() {
+                return Ok(None);
+            }
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                builder.add(Glob::new(pattern).context(format!("pattern: {pattern}"))?);
+            }
+            Ok(Some(builder.build()?))
+        }
+
+        Ok(ObjectFilter {
+            include_tables: glob_set(&self.include_tables)?,
+            exclude_tables: glob_set(&self.exclude_tables)?,
+            include_schemas: glob_set(&self.include_schemas)?,
+            exclude_schemas: glob_set(&self.exclude_schemas)?,
+        })
+    }
+}
+
+/// decides which objects are managed by sql-schema
+#[derive(Debug)]
+struct ObjectFilter {
+    include_tables: Option<GlobSet>,
+    exclude_tables: Option<GlobSet>,
+    include_schemas: Option<GlobSet>,
+    exclude_schemas: Option<GlobSet>,
+}
+
+impl ObjectFilter {
+    fn matches(&self, change: &Change) -> bool {
+        // indexes belong to the table they're on
+        let name = match change.object {
+            ObjectKind::Index => change.table.as_deref().unwrap_or(&change.name),
+            _ => change.name.as_str(),
+        };
+        let name = name.replace('"', "");
+        let (schema, name) = match name.rsplit_once('.') {
+            Some((schema, name)) => (Some(schema), name),
+            None => (None, name.as_str()),
+        };
+
+        // unqualified objects aren't subject to the schema filters
+        if let Some(schema) = schema {
+            if self
+                .include_schemas
+                .as_ref()
+                .is_some_and(|g| !g.is_match(schema))
+                || self
+                    .exclude_schemas
+                    .as_ref()
+                    .is_some_and(|g| g.is_match(schema))
+            {
+                return false;
+            }
+        }
+        let is_table = matches!(change.object, ObjectKind::Table | ObjectKind::Index);
+        !(is_table
+            && (self
+                .include_tables
+                .as_ref()
+                .is_some_and(|g| !g.is_match(name))
+                || self
+                    .exclude_tables
+                    .as_ref()
+                    .is_some_and(|g| g.is_match(name))))
+    }
+
+    fn apply<Dialect>(&self, tree: &mut SyntaxTree<Dialect>) {
+        tree.retain(|change| self.matches(change));
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+enum SplitBy {
+    /// every object gets its own file (e.g. `indexes/users_email_idx.sql`)
+    Object,
+    /// like object, but indexes are written to the file of the table they're on
+    Table,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum)]
+enum SchemaOrder {
+    /// the order the migrations create things in
+    #[default]
+    Migration,
+    /// by name, with indexes right after the table they're on
+    Alphabetical,
+    /// extensions, types, and domains first, then tables and views after the ones they depend on,
+    /// then indexes and everything else
+    Dependency,
+}
+
+impl SchemaOrder {
+    fn order(self) -> Order {
+        match self {
+            Self::Migration => Order::Migration,
+            Self::Alphabetical => Order::Alphabetical,
+            Self::Dependency => Order::Dependency,
+        }
+    }
+}
+
+impl fmt::Display for SchemaOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Migration => write!(f, "migration"),
+            Self::Alphabetical => write!(f, "alphabetical"),
+            Self::Dependency => write!(f, "dependency"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum)]
+enum Format {
+    /// SQL
+    #[default]
+    Text,
+    /// a JSON description of each change
+    Json,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum)]
+enum TimeZone {
+    /// coordinated universal time
+    #[default]
+    Utc,
+    /// the time zone of this machine
+    Local,
+}
+
+impl fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Utc => write!(f, "utc"),
+            Self::Local => write!(f, "local"),
+        }
+    }
+}
+
+#[derive(clap::Args, Debug, Default, Clone)]
+struct TimestampArgs {
+    /// write dates and times in migration names in UTC or local time (epoch timestamps are the
+    /// same either way)
+    #[arg(long = "timestamp", default_value_t = TimeZone::Utc)]
+    time_zone: TimeZone,
+    /// name the migration for this time instead of now (RFC 3339, e.g. 2025-01-31T09:00:00+01:00)
+    #[arg(long, value_name = "RFC3339")]
+    at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum)]
+enum GraphFormat {
+    /// Graphviz (e.g. `| dot -Tsvg > schema.svg`)
+    #[default]
+    Dot,
+    /// a Mermaid flowchart
+    Mermaid,
+}
+
+impl fmt::Display for GraphFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dot => write!(f, "dot"),
+            Self::Mermaid => write!(f, "mermaid"),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+struct MigrationCommand {
+    /// path to schema file (or directory of schema files), or `-` to read it from stdin and print
+    /// the migration to stdout
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_SCHEMA_PATH), env = "SQL_SCHEMA_SCHEMA_PATH")]
+    schema_path: Utf8PathBuf,
+    /// path to migrations directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+    /// name of migration (default is named for what it changes, e.g. `create_users`)
+    #[arg(short, long)]
+    name: Option<String>,
+    /// name migrations without --name `generated_migration` instead of for what they change
+    #[arg(long, conflicts_with = "name")]
+    generic_name: bool,
+    /// creates both an up and down migration when true
+    ///
+    /// default is to match the pattern in the migrations dir
+    #[arg(long)]
+    include_down: Option<bool>,
+    /// watch the schema file and print the pending migration whenever it changes
+    ///
+    /// press enter to write the pending migration
+    #[arg(long)]
+    watch: bool,
+    /// don't write anything, just print the pending migration and exit with 2 if there is one
+    /// (0 when the schema and migrations match, 1 on errors)
+    #[arg(long, conflicts_with = "watch")]
+    check: bool,
+    /// open the generated files in $VISUAL (or $EDITOR) after writing them
+    #[arg(long, conflicts_with = "check")]
+    edit: bool,
+    /// write each statement to its own migration (for runners that run each file in its own
+    /// transaction)
+    ///
+    /// each file gets the next version after the one before it (a second later for timestamps)
+    /// so they're applied in order
+    #[arg(long)]
+    split_statements: bool,
+    /// rewrite the migration into steps that don't lock tables in use (e.g. CREATE INDEX
+    /// CONCURRENTLY, and constraints added as NOT VALID then validated), as far as the dialect
+    /// supports it
+    ///
+    /// with mysql, ALTER TABLE statements get `ALGORITHM = INSTANT` or `ALGORITHM = INPLACE, LOCK =
+    /// NONE` instead, so they fail rather than block writes, and those that can only copy the
+    /// table are warned about
+    ///
+    /// the steps that can't run inside a transaction (e.g. CREATE INDEX CONCURRENTLY) are written
+    /// to migrations of their own, marked to run without one (`-- no-transaction` on their first
+    /// line, or the way the --compat tool expects)
+    #[arg(long)]
+    zero_downtime: bool,
+    /// build indexes on existing tables with CREATE INDEX CONCURRENTLY (postgres), so they don't
+    /// block writes while they're built
+    ///
+    /// since it can't run inside a transaction, each one is written to a migration of its own,
+    /// marked to run without one
+    #[arg(long)]
+    concurrent_indexes: bool,
+    /// add NOT NULL columns without a default as nullable, followed by a commented template for
+    /// backfilling existing rows and setting NOT NULL (adding them as-is fails on tables with rows)
+    #[arg(long)]
+    backfill_stubs: bool,
+    /// rebuild tables whose changes the dialect can't make with ALTER TABLE (e.g. changing a
+    /// column or its constraints with SQLite) by copying them into a new table that replaces them
+    ///
+    /// foreign keys are turned off around the rebuilds (and checked before they're committed),
+    /// since dropping the old table deletes its rows otherwise, so the migration runs in a
+    /// transaction of its own and is marked to run without one
+    #[arg(long, conflicts_with = "split_statements")]
+    rebuild_tables: bool,
+    /// print a description of the migration to stdout
+    #[arg(long, default_value_t = Format::Text, conflicts_with = "watch")]
+    format: Format,
+    /// write migrations that drop tables, columns, etc. without asking first
+    #[arg(short, long)]
+    yes: bool,
+    /// file containing a header to insert at the top of generated migrations
+    ///
+    /// supports {timestamp}, {version}, {name}, and {schema_hash} placeholders
+    #[arg(long)]
+    header_file: Option<Utf8PathBuf>,
+    /// leave statements in the schema that can't be parsed or diffed (e.g. CREATE FUNCTION) out of
+    /// the comparison, with a warning for each, instead of failing
+    #[arg(long)]
+    lenient: bool,
+    #[command(flatten)]
+    filter: FilterArgs,
+    #[command(flatten)]
+    timestamp: TimestampArgs,
+}
+
+#[derive(Parser, Debug)]
+struct NewCommand {
+    /// path to migrations directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// name of migration
+    #[arg(short, long)]
+    name: String,
+    /// creates both an up and down migration when true
+    ///
+    /// default is to match the pattern in the migrations dir
+    #[arg(long)]
+    include_down: Option<bool>,
+    #[command(flatten)]
+    timestamp: TimestampArgs,
+}
+
+#[derive(Parser, Debug)]
+struct DiffCommand {
+    /// schema file, migrations directory, or `-` for stdin
+    a: Utf8PathBuf,
+    /// schema file, migrations directory, or `-` for stdin
+    b: Utf8PathBuf,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+    /// output format
+    #[arg(long, default_value_t = Format::Text)]
+    format: Format,
+    /// leave statements in the schema that can't be parsed or diffed (e.g. CREATE FUNCTION) out of
+    /// the comparison, with a warning for each, instead of failing
+    #[arg(long)]
+    lenient: bool,
+}
+
+#[derive(Parser, Debug)]
+struct CheckCommand {
+    /// path to schema file (or directory of schema files), or `-` for stdin
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_SCHEMA_PATH), env = "SQL_SCHEMA_SCHEMA_PATH")]
+    schema_path: Utf8PathBuf,
+    /// path to migrations directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+    /// output format
+    #[arg(long, default_value_t = Format::Text)]
+    format: Format,
+    /// leave statements in the schema that can't be parsed or diffed (e.g. CREATE FUNCTION) out of
+    /// the comparison, with a warning for each, instead of failing
+    #[arg(long)]
+    lenient: bool,
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+#[derive(Parser, Debug)]
+struct SquashCommand {
+    /// path to migrations directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+    /// squash every migration before this version
+    #[arg(long)]
+    before: String,
+    /// name of the baseline migration
+    #[arg(short, long, default_value = "squashed_migrations")]
+    name: String,
+    /// delete the squashed migrations after writing the baseline
+    #[arg(long)]
+    delete: bool,
+}
+
+#[derive(Parser, Debug)]
+struct IntrospectCommand {
+    /// path to schema file, or `-` for stdout
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_SCHEMA_PATH), env = "SQL_SCHEMA_SCHEMA_PATH")]
+    schema_path: Utf8PathBuf,
+    /// url of the database to introspect (e.g. postgres://localhost/app or sqlite://app.db)
+    #[arg(long, env = "SQL_SCHEMA_DATABASE_URL")]
+    database_url: String,
+    /// database schema to introspect (postgres only)
+    #[arg(long, default_value = "public", env = "SQL_SCHEMA_DB_SCHEMA")]
+    db_schema: String,
+}
+
+#[derive(Parser, Debug)]
+struct DriftCommand {
+    /// path to schema file (or directory of schema files), or `-` for stdin
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_SCHEMA_PATH), env = "SQL_SCHEMA_SCHEMA_PATH")]
+    schema_path: Utf8PathBuf,
+    /// migrations directory to skip when it's inside the schema directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// url of the database to compare (e.g. postgres://localhost/app or sqlite://app.db)
+    #[arg(long, env = "SQL_SCHEMA_DATABASE_URL")]
+    database_url: String,
+    /// database schema to compare (postgres only)
+    #[arg(long, default_value = "public", env = "SQL_SCHEMA_DB_SCHEMA")]
+    db_schema: String,
+    /// exit with an error if the database has drifted from the schema file
+    #[arg(long)]
+    check: bool,
+}
+
+#[derive(Parser, Debug)]
+struct StatusCommand {
+    /// path to migrations directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// url of the database to check (e.g. postgres://localhost/app or sqlite://app.db)
+    #[arg(long, env = "SQL_SCHEMA_DATABASE_URL")]
+    database_url: String,
+    /// table applied migrations are recorded in, optionally schema-qualified
+    ///
+    /// tables created by other tools (e.g. sqlx's `_sqlx_migrations` or Diesel's
+    /// `__diesel_schema_migrations`) are read as-is, finding the version column by name, and
+    /// golang-migrate's `schema_migrations` (which only records the most recent migration) counts
+    /// every migration up to it as applied
+    #[arg(long, default_value_t = TrackingTable::default(), env = "SQL_SCHEMA_MIGRATIONS_TABLE")]
+    migrations_table: TrackingTable,
+}
+
+#[derive(Parser, Debug)]
+struct ApplyCommand {
+    /// path to migrations directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// url of the database to migrate (e.g. postgres://localhost/app or sqlite://app.db)
+    #[arg(long, env = "SQL_SCHEMA_DATABASE_URL")]
+    database_url: String,
+    /// table applied migrations are recorded in, optionally schema-qualified (see `status`); it's
+    /// created when it doesn't exist yet
+    #[arg(long, default_value_t = TrackingTable::default(), env = "SQL_SCHEMA_MIGRATIONS_TABLE")]
+    migrations_table: TrackingTable,
+    /// run the seed files (in path order, in one transaction) after migrating; they aren't recorded,
+    /// so they run every time and should be safe to run again
+    #[arg(long)]
+    seed: bool,
+    /// path to seeds directory
+    #[arg(long, default_value_t = Utf8PathBuf::from(DEFAULT_SEEDS_DIR), env = "SQL_SCHEMA_SEEDS_DIR")]
+    seeds_dir: Utf8PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct RollbackCommand {
+    /// path to migrations directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// number of migrations to roll back, starting from the most recent
+    #[arg(long, conflicts_with = "to")]
+    steps: Option<usize>,
+    /// roll back every migration after this version
+    #[arg(long)]
+    to: Option<String>,
+    /// url of the database to roll back (e.g. postgres://localhost/app or sqlite://app.db)
+    #[arg(
+        long,
+        env = "SQL_SCHEMA_DATABASE_URL",
+        required_unless_present = "dry_run"
+    )]
+    database_url: Option<String>,
+    /// table applied migrations are recorded in, optionally schema-qualified (see `status`)
+    #[arg(long, default_value_t = TrackingTable::default(), env = "SQL_SCHEMA_MIGRATIONS_TABLE")]
+    migrations_table: TrackingTable,
+    /// print the down migrations (most recent first) instead of running them, e.g. to pipe into a
+    /// database client
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+struct FmtCommand {
+    /// files or directories to format (default is the schema path and migrations dir)
+    paths: Vec<Utf8PathBuf>,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+    /// list files that aren't formatted and exit with an error instead of formatting them
+    #[arg(long)]
+    check: bool,
+    /// spaces per indentation level (overrides the config)
+    #[arg(long, conflicts_with = "tabs")]
+    indent: Option<u8>,
+    /// indent with tabs instead of spaces
+    #[arg(long)]
+    tabs: bool,
+    /// uppercase reserved keywords
+    #[arg(long, conflicts_with = "lowercase")]
+    uppercase: bool,
+    /// lowercase reserved keywords
+    #[arg(long)]
+    lowercase: bool,
+    /// blank lines between statements (overrides the config)
+    #[arg(long)]
+    lines_between: Option<u8>,
+}
+
+#[derive(Parser, Debug)]
+struct ValidateCommand {
+    /// path to migrations directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct SeedCommand {
+    /// path to seeds directory
+    #[arg(long, default_value_t = Utf8PathBuf::from(DEFAULT_SEEDS_DIR), env = "SQL_SCHEMA_SEEDS_DIR")]
+    seeds_dir: Utf8PathBuf,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+}
+
+#[derive(Parser, Debug)]
+struct GraphCommand {
+    /// path to schema file (or directory of schema files), or `-` for stdin
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_SCHEMA_PATH), env = "SQL_SCHEMA_SCHEMA_PATH")]
+    schema_path: Utf8PathBuf,
+    /// migrations directory to skip when it's inside the schema directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+    /// output format
+    #[arg(long, default_value_t = GraphFormat::Dot)]
+    format: GraphFormat,
+    /// print an entity-relationship diagram of the tables (their columns and the foreign keys
+    /// between them) instead
+    #[arg(long)]
+    erd: bool,
+}
+
+#[cfg(feature = "codegen")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum)]
+enum CodegenLang {
+    /// a struct for each table and an enum for each enum type
+    #[default]
+    Rust,
+}
+
+#[cfg(feature = "codegen")]
+impl fmt::Display for CodegenLang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rust => write!(f, "rust"),
+        }
+    }
+}
+
+#[cfg(feature = "codegen")]
+#[derive(Parser, Debug)]
+struct CodegenCommand {
+    /// path to schema file (or directory of schema files), or `-` for stdin
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_SCHEMA_PATH), env = "SQL_SCHEMA_SCHEMA_PATH")]
+    schema_path: Utf8PathBuf,
+    /// migrations directory to skip when it's inside the schema directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+    /// language to generate
+    #[arg(long, default_value_t = CodegenLang::Rust)]
+    lang: CodegenLang,
+    /// also derive this for each type (e.g. `serde::Serialize`), can be given more than once
+    #[arg(long = "derive", value_name = "PATH")]
+    derives: Vec<String>,
+}
+
+#[cfg(feature = "atlas")]
+#[derive(Parser, Debug)]
+struct AtlasCommand {
+    /// path to schema file (or directory of schema files), or `-` for stdin
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_SCHEMA_PATH), env = "SQL_SCHEMA_SCHEMA_PATH")]
+    schema_path: Utf8PathBuf,
+    /// migrations directory to skip when it's inside the schema directory
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+}
+
+#[derive(Parser, Debug)]
+struct TranspileCommand {
+    /// schema file (or directory of schema files), or `-` for stdin
+    path: Utf8PathBuf,
+    /// dialect the schema is written in
+    #[arg(long)]
+    from: Dialect,
+    /// dialect to convert it to
+    #[arg(long)]
+    to: Dialect,
+}
+
+#[derive(Parser, Debug)]
+struct MergeCommand {
+    /// schema files or directories of schema files (migrations and seeds dirs are skipped)
+    #[arg(required = true)]
+    paths: Vec<Utf8PathBuf>,
+    /// where to write the merged schema (default is stdout)
+    #[arg(short, long, default_value = "-")]
+    output: Utf8PathBuf,
+    /// migrations directory to skip when it's inside one of the directories
+    #[arg(short, long, default_value_t = Utf8PathBuf::from(DEFAULT_MIGRATIONS_DIR), env = "SQL_SCHEMA_MIGRATIONS_DIR")]
+    migrations_dir: Utf8PathBuf,
+    /// dialect of SQL to use
+    #[arg(short, long, default_value_t = Dialect::Generic, env = "SQL_SCHEMA_DIALECT")]
+    dialect: Dialect,
+}
+
+#[derive(Parser, Debug)]
+struct CompletionsCommand {
+    /// shell to generate completions for
+    shell: clap_complete::Shell,
+}
+
+#[derive(Debug, Default, Clone)]
+struct MigrationOptions {
+    path_template: PathTemplate,
+    include_down: bool,
+    num_migrations: usize,
+    timestamp: TimestampArgs,
+    /// the migration tool whose naming is followed
+    compat: Option<Compat>,
+    /// whether the down migration goes in the same file as the up migration (in `-- migrate:up`
+    /// and `-- migrate:down` sections)
+    single_file: bool,
+    /// whether the name is written as given instead of slugified
+    verbatim_name: bool,
+    /// the layout new migrations are written in instead of following the detected one
+    layout: Option<Layout>,
+    /// values of the variables in the path template
+    vars: HashMap<String, String>,
+}
+
+impl MigrationOptions {
+    /// detects the naming convention from the most recent migration
+    fn detect(dir: &Utf8Path, migrations: &[MigrationFile]) -> anyhow::Result<Self> {
+        let mut path_template = match detect_path_template(dir, migrations, find_options()) {
+            Ok(path_template) => path_template,
+            Err(err) if !STRICT_NAMING.load(Ordering::Relaxed) => {
+                warn!(
+                    "{err}, so new migrations follow the default naming \
+                     ({{timestamp}}_{{name}}.up.sql) instead (--strict-naming makes this an error)"
+                );
+                PathTemplate::default()
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if migrations.iter().all(MigrationFile::is_repeatable) {
+            // there's no extension to follow
+            if let Some(ext) = find_options().extensions.first() {
+                path_template = path_template.with_extension(ext);
+            }
+        }
+        let single_file = match migrations.iter().rev().find(|m| !m.is_repeatable()) {
+            Some(migration) if migration.down.is_none() => {
+                migrations::has_sections(&fs::read_to_string(&migration.up)?)
+            }
+            _ => false,
+        };
+        Ok(Self {
+            include_down: single_file || path_template.includes_up_down(),
+            path_template,
+            num_migrations: migrations.len(),
+            timestamp: TimestampArgs::default(),
+            compat: None,
+            single_file,
+            verbatim_name: false,
+            layout: None,
+            vars: HashMap::new(),
+        })
+    }
+
+    /// uses the configured path template (or that of the compat profile) instead of the detected
+    /// one, continuing the numbering of the existing migrations
+    fn with_config(mut self, config: &Config) -> anyhow::Result<Self> {
+        self.verbatim_name = config.migration.verbatim_name;
+        self.layout = config.migration.layout;
+        self.vars = config.migration.vars.clone();
+        let path_template = match (&config.migration.path_template, config.migration.compat) {
+            (Some(template), _) => PathTemplate::compile(template)
+                .with_context(|| format!("path template: {template}"))?
+                .continue_from(&self.path_template),
+            (None, Some(compat)) => return Ok(self.with_compat(compat)),
+            (None, None) => return Ok(self),
+        };
+        Ok(Self {
+            include_down: path_template.includes_up_down(),
+            path_template,
+            ..self
+        })
+    }
+
+    /// names migrations the way `compat` expects, continuing the numbering of the existing ones
+    fn with_compat(self, compat: Compat) -> Self {
+        Self {
+            path_template: compat.path_template(&self.path_template),
+            include_down: compat.down_migrations(),
+            compat: Some(compat),
+            single_file: compat.single_file(),
+            ..self
+        }
+    }
+
+    /// names migrations for the given time and time zone
+    fn with_timestamp(self, timestamp: &TimestampArgs) -> Self {
+        Self {
+            timestamp: timestamp.clone(),
+            ..self
+        }
+    }
+
+    fn reconcile(self, include_down: Option<bool>) -> Self {
+        let include_down = match (include_down, self.compat) {
+            (Some(include_down), Some(compat)) if include_down != compat.down_migrations() => {
+                warn!("ignoring --include-down={include_down}, which {compat} doesn't support");
+                self.include_down
+            }
+            (Some(include_down), _) => include_down,
+            (None, _) => self.include_down,
+        };
+        let (path_template, single_file) = match (self.layout, self.compat) {
+            (Some(layout), Some(compat)) => {
+                warn!("ignoring --layout={layout}, as {compat} lays out migrations its own way");
+                (self.path_template, self.single_file)
+            }
+            // up and down migrations are separate files in a migration's dir
+            (Some(Layout::Dir), None) => (self.path_template.with_dir_layout(), false),
+            (Some(Layout::Flat), None) => (self.path_template.with_flat_layout(), self.single_file),
+            (None, _) => (self.path_template, self.single_file),
+        };
+        Self {
+            include_down,
+            path_template,
+            single_file,
+            ..self
+        }
+    }
+
+    /// resolves the up path (and down path when `include_down` is set) for a new migration
+    fn resolve_paths(
+        &self,
+        dir: &Utf8Path,
+        name: String,
+    ) -> anyhow::Result<(Utf8PathBuf, Option<Utf8PathBuf>)> {
+        let (_, up_path, down_path) = self.resolve_unused_paths(dir, name, 0)?;
+        Ok((up_path, down_path))
+    }
+
+    /// resolves the paths for the `n`th of several migrations written at once, moving on to the
+    /// next version while the paths (or their version) are already taken in `dir`
+    ///
+    /// returns the `n` that was used, so the next migration can start after it
+    fn resolve_unused_paths(
+        &self,
+        dir: &Utf8Path,
+        name: String,
+        mut n: usize,
+    ) -> anyhow::Result<(usize, Utf8PathBuf, Option<Utf8PathBuf>)> {
+        let versions = if dir.is_dir() {
+            find_migrations(dir, find_options())?
+                .iter()
+                .filter_map(|migration| version_number(&migration.up).map(str::to_owned))
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        let mut prev_path = None;
+        loop {
+            let (up_path, down_path) = self.resolve_nth_paths(dir, name.clone(), n)?;
+            let taken = [Some(&up_path), down_path.as_ref()]
+                .into_iter()
+                .flatten()
+                .any(|path| path.exists())
+                || version_number(&up_path).is_some_and(|v| versions.contains(v));
+            if !taken {
+                return Ok((n, up_path, down_path));
+            }
+            // the template has no timestamp or counter to move on to
+            if prev_path.as_ref() == Some(&up_path) {
+                return Err(anyhow!("{up_path} already exists"));
+            }
+            debug!("{up_path} is taken, trying the next version");
+            prev_path = Some(up_path);
+            n += 1;
+        }
+    }
+
+    /// resolves the paths for the `n`th of several migrations written at once, bumping the
+    /// timestamp (or counter) so they sort in order
+    fn resolve_nth_paths(
+        &self,
+        dir: &Utf8Path,
+        name: String,
+        n: usize,
+    ) -> anyhow::Result<(Utf8PathBuf, Option<Utf8PathBuf>)> {
+        if let Some(var) = self
+            .path_template
+            .vars()
+            .find(|var| !self.vars.contains_key(*var))
+        {
+            return Err(anyhow!(
+                "the path template uses {{{var}}}, which isn't set (pass --var {var}=<value> or \
+                 set it under [migration.vars] in the config)"
+            ));
+        }
+        let (branch, author) = if self.path_template.includes_git() {
+            let branch = git(&["branch", "--show-current"])
+                .and_then(|branch| {
+                    (!branch.is_empty())
+                        .then_some(branch)
+                        .ok_or_else(|| anyhow!("HEAD is detached"))
+                })
+                .context("git branch")?;
+            let author = git(&["config", "user.name"]).context("git author")?;
+            (Some(branch), Some(author))
+        } else {
+            (None, None)
+        };
+        let counter = match n {
+            0 => None,
+            n => self
+                .path_template
+                .template_data()?
+                .counter
+                .map(|c| c + 1 + n),
+        };
+        let timestamp = self.timestamp(n);
+        let offset = match self.timestamp.time_zone {
+            TimeZone::Utc => None,
+            TimeZone::Local => Some(*timestamp.offset()),
+        };
+        // a migration holding both up and down is named without an up/down suffix
+        let separate_down = self.include_down && !self.single_file;
+        let path_data = TemplateData {
+            timestamp: timestamp.to_utc(),
+            offset,
+            name,
+            counter,
+            // a migration's dir always holds an `up.sql`
+            up_down: if separate_down || self.path_template.is_dir_layout() {
+                Some(UpDown::Up)
+            } else {
+                None
+            },
+            branch,
+            author,
+            verbatim_name: self.verbatim_name,
+            vars: self.vars.clone(),
+            ..Default::default()
+        };
+
+        let path_template = if separate_down {
+            // ensure template includes an UpDown token
+            self.path_template.clone().with_up_down()
+        } else {
+            self.path_template.clone()
+        };
+
+        let up_path = dir.join(path_template.resolve(&path_data));
+        let down_path = separate_down.then(|| {
+            let path_data = TemplateData {
+                up_down: Some(UpDown::Down),
+                ..path_data
+            };
+            dir.join(path_template.resolve(&path_data))
+        });
+
+        Ok((up_path, down_path))
+    }
+
+    /// when the `n`th of several migrations written at once is dated (a second after the one before
+    /// it), in the time zone its name is written in
+    fn timestamp(&self, n: usize) -> DateTime<FixedOffset> {
+        let timestamp = match self.timestamp.at {
+            Some(at) => at.to_utc(),
+            None => DateTime::<Utc>::from(SystemTime::now()),
+        } + chrono::TimeDelta::seconds(n as i64);
+        match self.timestamp.time_zone {
+            TimeZone::Utc => timestamp.fixed_offset(),
+            TimeZone::Local => timestamp.with_timezone(&chrono::Local).fixed_offset(),
+        }
+    }
+
+    /// the SQL to write to the up path and the down path, joining them into sections of the up
+    /// migration when they share a file
+    fn sections(&self, up: String, down: Option<String>) -> (String, Option<String>) {
+        if self.single_file {
+            let down = down.as_deref().unwrap_or_default();
+            return (migrations::join_sections(&up, down), None);
+        }
+        (up, down)
+    }
+
+    /// marks a migration (or its `section`, when it shares a file) as one that can't run inside a
+    /// transaction, the way the tool that runs it expects, along with its header
+    fn no_transaction(
+        &self,
+        sql: String,
+        path: &Utf8Path,
+        header: Option<String>,
+        section: &str,
+    ) -> anyhow::Result<(String, Option<String>)> {
+        match self.compat {
+            // golang-migrate doesn't run migrations inside a transaction
+            Some(Compat::GolangMigrate) => Ok((sql, header)),
+            // diesel reads it from the `metadata.toml` next to the migration
+            Some(Compat::Diesel) => {
+                let path = path.with_file_name("metadata.toml");
+                if let Some(parent) = path.parent() {
+                    ensure_migration_dir(parent)?;
+                }
+                info!("writing {path}");
+                fs::write(&path, "run_in_transaction = false\n")
+                    .context(format!("path: {path}"))?;
+                Ok((sql, header))
+            }
+            Some(Compat::Refinery) => {
+                warn!(
+                    "{path} can't run inside a transaction, which refinery runs it in, so it has to be run by hand"
+                );
+                Ok((sql, header))
+            }
+            // dbmate takes it as an option of the section
+            _ if self.single_file => {
+                let marker = format!("{section} {}", migrations::DBMATE_NO_TRANSACTION);
+                Ok((sql.replacen(section, &marker, 1), header))
+            }
+            _ => {
+                let header = match header {
+                    Some(header) => format!("{}\n{header}", migrations::NO_TRANSACTION_MARKER),
+                    None => migrations::NO_TRANSACTION_MARKER.to_owned(),
+                };
+                Ok((sql, Some(header)))
+            }
+        }
+    }
+}
+
+/// points the command at the migrations dir of the `compat` tool, unless it was given one
+fn use_compat_migrations_dir(compat: Compat, command: &mut Commands) -> anyhow::Result<()> {
+    let dir = match command {
+        Commands::Init(command) => &mut command.migrations_dir,
+        Commands::Schema(command) => &mut command.migrations_dir,
+        Commands::Migration(command) => &mut command.migrations_dir,
+        Commands::New(command) => &mut command.migrations_dir,
+        Commands::Check(command) => &mut command.migrations_dir,
+        Commands::Squash(command) => &mut command.migrations_dir,
+        Commands::Status(command) => &mut command.migrations_dir,
+        Commands::Apply(command) => &mut command.migrations_dir,
+        Commands::Rollback(command) => &mut command.migrations_dir,
+        Commands::Validate(command) => &mut command.migrations_dir,
+        Commands::Merge(command) => &mut command.migrations_dir,
+        #[cfg(feature = "atlas")]
+        Commands::Atlas(command) => &mut command.migrations_dir,
+        #[cfg(feature = "codegen")]
+        Commands::Codegen(command) => &mut command.migrations_dir,
+        Commands::Graph(command) => &mut command.migrations_dir,
+        Commands::Drift(command) => &mut command.migrations_dir,
+        _ => return Ok(()),
+    };
+    if dir == DEFAULT_MIGRATIONS_DIR {
+        *dir = compat.migrations_dir()?;
+        debug!("using {dir} as the migrations dir");
+    }
+    Ok(())
+}
+
+/// runs git and returns its trimmed output
+fn git(args: &[&str]) -> anyhow::Result<String> {
+    let output = process::Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if args.no_color {
+        anstream::ColorChoice::Never.write_global();
+    }
+    let ansi = anstream::AutoStream::choice(&io::stderr()) != anstream::ColorChoice::Never;
+
+    // -v only turns up our own output, -vv includes dependencies (e.g. the SQL parser)
+    let filter = match (args.quiet, args.verbose) {
+        (true, _) => Targets::new().with_default(Level::WARN),
+        (false, 0) => Targets::new().with_default(Level::INFO),
+        (false, 1) => Targets::new()
+            .with_target("sql_schema", Level::DEBUG)
+            .with_default(Level::WARN),
+        (false, _) => Targets::new().with_default(Level::TRACE),
+    };
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(io::stderr)
+                .with_ansi(ansi)
+                .with_target(false)
+                .without_time(),
+        )
+        .with(filter)
+        .init();
+
+    let mut command = args.command;
+    let config = Config::load(args.config.as_deref())
+        .context("config")
+        .and_then(|mut config| {
+            config.migration.compat = args.compat.or(config.migration.compat);
+            config.migration.path_template = args.path_template.or(config.migration.path_template);
+            config.migration.verbatim_name |= args.verbatim_name;
+            config.migration.layout = args.layout.or(config.migration.layout);
+            config.migration.if_exists = args.if_exists.or(config.migration.if_exists);
+            config.migration.vars.extend(args.vars);
+            config.migration.strict_naming |= args.strict_naming;
+            STRICT_NAMING.store(config.migration.strict_naming, Ordering::Relaxed);
+            if !args.extensions.is_empty() {
+                config.migration.extensions = Some(args.extensions);
+            }
+            let mut options = migrations::Options::default();
+            if let Some(extensions) = &config.migration.extensions {
+                options.extensions = extensions
+                    .iter()
+                    .map(|ext| ext.trim_start_matches('.').to_owned())
+                    .collect();
+            }
+            options.date_bounds = config.migration.date_bounds().context("config")?;
+            FIND_OPTIONS.get_or_init(|| options);
+            if let Some(compat) = config.migration.compat {
+                use_compat_migrations_dir(compat, &mut command).context("compat")?;
+            }
+            Ok(config)
+        });
+    if let Err(err) = config.and_then(|config| match command {
+        Commands::Init(command) => run_init(command, &config).context("init"),
+        Commands::Schema(command) => run_schema(command, &config).context("schema"),
+        Commands::Migration(command) => run_migration(command, &config).context("migration"),
+        Commands::New(command) => run_new(command, &config).context("new"),
+        Commands::Diff(command) => run_diff(command).context("diff"),
+        Commands::Check(command) => run_check(command).context("check"),
+        Commands::Squash(command) => run_squash(command, &config).context("squash"),
+        Commands::Introspect(command) => run_introspect(command).context("introspect"),
+        Commands::Drift(command) => run_drift(command).context("drift"),
+        Commands::Status(command) => run_status(command).context("status"),
+        Commands::Apply(command) => run_apply(command, &config).context("apply"),
+        Commands::Rollback(command) => run_rollback(command).context("rollback"),
+        Commands::Fmt(command) => run_fmt(command, &config).context("fmt"),
+        Commands::Validate(command) => run_validate(command, &config).context("validate"),
+        Commands::Graph(command) => run_graph(command).context("graph"),
+        #[cfg(feature = "codegen")]
+        Commands::Codegen(command) => run_codegen(command).context("codegen"),
+        #[cfg(feature = "atlas")]
+        Commands::Atlas(command) => run_atlas(command).context("atlas"),
+        Commands::Seed(command) => run_seed(command).context("seed"),
+        Commands::Transpile(command) => run_transpile(command).context("transpile"),
+        Commands::Merge(command) => run_merge(command, &config).context("merge"),
+        Commands::Completions(command) => run_completions(command).context("completions"),
+    }) {
+        if err.downcast_ref::<PendingMigration>().is_some() {
+            info!("{err:#}");
+            process::exit(2);
+        }
+        #[cfg(feature = "miette")]
+        if let Some(report) = render_diagnostic(&err, ansi) {
+            eprintln!("Error: {err}\n\n{report}");
+            process::exit(1);
+        }
+        eprintln!("Error: {err:?}");
+        process::exit(1);
+    }
+}
+
+/// renders the first error in the chain that's a [miette::Diagnostic]
+#[cfg(feature = "miette")]
+fn render_diagnostic(err: &anyhow::Error, ansi: bool) -> Option<String> {
+    use miette::{Diagnostic, GraphicalReportHandler, GraphicalTheme};
+
+    let diagnostic = err.chain().find_map(|err| -> Option<&dyn Diagnostic> {
+        if let Some(err) = err.downcast_ref::<load::LoadError>() {
+            return Some(err);
+        }
+        if let Some(err) = err.downcast_ref::<sql_schema::ParseError>() {
+            return Some(err);
+        }
+        if let Some(err) = err.downcast_ref::<sql_schema::DiffError>() {
+            return Some(err);
+        }
+        if let Some(err) = err.downcast_ref::<sql_schema::ApplyError>() {
+            return Some(err);
+        }
+        if let Some(err) = err.downcast_ref::<sql_schema::MigrateError>() {
+            return Some(err);
+        }
+        err.downcast_ref::<sql_schema::path_template::ParseError>()
+            .map(|err| err as &dyn Diagnostic)
+    })?;
+    let theme = if ansi {
+        GraphicalTheme::unicode()
+    } else {
+        GraphicalTheme::unicode_nocolor()
+    };
+    let mut report = String::new();
+    GraphicalReportHandler::new_themed(theme)
+        .render_report(&mut report, diagnostic)
+        .ok()?;
+    Some(report)
+}
+
+/// returned by `migration --check` when a migration would be generated, so it exits with 2
+#[derive(Debug, thiserror::Error)]
+#[error("the schema file has changes that aren't in a migration yet")]
+struct PendingMigration;
+
+macro_rules! match_dialect {
+    ( $dialect:expr, $expr:expr ) => {
+        match $dialect {
+            Dialect::Generic => {
+                let dialect = sql_schema::dialect::Generic::default();
+                $expr(dialect)
+            }
+            Dialect::PostgreSql => {
+                let dialect = sql_schema::dialect::PostgreSQL::default();
+                $expr(dialect)
+            }
+            Dialect::SQLite => {
+                let dialect = sql_schema::dialect::SQLite::default();
+                $expr(dialect)
+            }
+            Dialect::MySql => {
+                let dialect = sql_schema::dialect::MySQL::default();
+                $expr(dialect)
+            }
+            Dialect::MsSql => {
+                let dialect = sql_schema::dialect::MsSQL::default();
+                $expr(dialect)
+            }
+            dialect => unreachable!("the {dialect} dialect has no implementation in sql-schema"),
+        }
+    };
+}
+
+/// connects to the database at `$url` and evaluates `$expr` with `$conn` bound to the connection (a
+/// [tracking::Connection]), creating a SQLite database that doesn't exist yet when `$create` is true
+macro_rules! with_connection {
+    ( $url:expr, $create:expr, |$conn:ident| $expr:expr ) => {
+        match Dialect::from_database_url($url)? {
+            Dialect::PostgreSql => {
+                #[cfg(feature = "postgres")]
+                {
+                    let mut client = postgres::Client::connect($url, postgres::NoTls)?;
+                    let $conn = &mut client;
+                    $expr
+                }
+                #[cfg(not(feature = "postgres"))]
+                {
+                    Err(anyhow!(
+                        "sql-schema was built without postgres support (enable the `postgres` feature)"
+                    ))
+                }
+            }
+            Dialect::SQLite => {
+                let path = sqlite_path($url);
+                #[cfg(feature = "sqlite")]
+                {
+                    let mut flags = rusqlite::OpenFlags::default();
+                    if !$create {
+                        flags.remove(rusqlite::OpenFlags::SQLITE_OPEN_CREATE);
+                    }
+                    let mut conn = rusqlite::Connection::open_with_flags(path, flags)?;
+                    let $conn = &mut conn;
+                    $expr
+                }
+                #[cfg(not(feature = "sqlite"))]
+                {
+                    let _ = path;
+                    Err(anyhow!(
+                        "sql-schema was built without sqlite support (enable the `sqlite` feature)"
+                    ))
+                }
+            }
+            // database urls have a specific dialect
+            dialect => Err(anyhow!("Oops, we can't connect to {dialect} databases")),
+        }
+    };
+}
+
+/// baseline an existing project with a migration that creates the current schema
+fn run_init(command: InitCommand, config: &Config) -> anyhow::Result<()> {
+    if command.migrations_dir.exists()
+        && !find_migrations(&command.migrations_dir, find_options())?.is_empty()
+    {
+        return Err(anyhow!(
+            "{} already contains migrations",
+            command.migrations_dir
+        ));
+    }
+    let dialect = match &command.database_url {
+        Some(url) => {
+            let dialect = Dialect::from_database_url(url)?;
+            if let Some(other) = command.dialect.filter(|other| *other != dialect) {
+                return Err(anyhow!(
+                    "--dialect {other} doesn't match the {dialect} database at --database-url"
+                ));
+            }
+            if command.schema_path.exists() {
+                return Err(anyhow!(
+                    "{} already exists (remove it to introspect the database)",
+                    command.schema_path
+                ));
+            }
+            let (_, schema) = introspect_database(url, &command.db_schema)?;
+            write_schema(&schema, &command.schema_path)?;
+            dialect
+        }
+        None => {
+            if !command.schema_path.exists() {
+                return Err(anyhow!(
+                    "{} doesn't exist (pass --database-url to introspect a database)",
+                    command.schema_path
+                ));
+            }
+            command.dialect.unwrap_or_default()
+        }
+    };
+    if command.write_config {
+        write_config()?;
+    }
+    match_dialect!(&dialect, |dialect| run_init_inner(dialect, command, config))
+}
+
+fn run_init_inner<D>(dialect: D, command: InitCommand, config: &Config) -> anyhow::Result<()>
+where
+    D: TreeDiffer + TreeMigrator + sql_schema::Parse + Render,
+{
+    let schema = parse_schema_file(
+        dialect,
+        &command.schema_path,
+        Some(&command.migrations_dir),
+        false,
+    )?
+    .with_format(config.format.options());
+    let rendered = schema.to_sql();
+    if rendered.is_empty() {
+        return Err(anyhow!("{} is empty", command.schema_path));
+    }
+    let mut opts = MigrationOptions::detect(&command.migrations_dir, &[])?.with_config(config)?;
+    if command.sequential {
+        // resolving a counter increments it, so this yields 0001
+        opts.path_template = PathTemplate::parse("0000_generated_migration.up.sql")?
+            .with_extension(opts.path_template.extension());
+    }
+    let opts = opts
+        .with_timestamp(&command.timestamp)
+        .reconcile(command.include_down);
+    let (n, up_path, down_path) =
+        opts.resolve_unused_paths(&command.migrations_dir, command.name.clone(), 0)?;
+    let header = config
+        .header(None)?
+        .map(|template| render_header(&template, &command.name, &rendered, opts.timestamp(n)));
+    let down_migration = match opts.include_down {
+        true => Some(
+            schema
+                .diff(&SyntaxTree::empty())?
+                .unwrap_or_else(SyntaxTree::empty)
+                .to_sql(),
+        ),
+        false => None,
+    };
+    let (up_migration, down_migration) = opts.sections(rendered, down_migration);
+    write_migration(up_migration, &up_path, header.as_deref())?;
+    if let (Some(down_migration), Some(down_path)) = (down_migration, &down_path) {
+        write_migration(down_migration, down_path, header.as_deref())?;
+    }
+    run_post_migration_hook(config, &up_path, down_path.as_deref())
+}
+
+/// writes a commented-out `sql-schema.toml` unless one already exists
+fn write_config() -> anyhow::Result<()> {
+    let path = Utf8Path::new(config::DEFAULT_CONFIG_PATH);
+    if path.exists() {
+        warn!("{path} already exists");
+        return Ok(());
+    }
+    info!("writing {path}");
+    fs::write(path, config::TEMPLATE)?;
+    Ok(())
+}
+
+/// create or update schema file from migrations
+fn run_schema(command: SchemaCommand, config: &Config) -> anyhow::Result<()> {
+    if command.split_by.is_some() {
+        if command.schema_path == "-" || command.schema_path.is_file() {
+            return Err(anyhow!(
+                "--split-by requires the schema path to be a directory"
+            ));
+        }
+        fs::create_dir_all(&command.schema_path)?;
+    } else if command.schema_path != "-" {
+        ensure_schema_file(&command.schema_path)?;
+    }
+    ensure_migration_dir(&command.migrations_dir)?;
+
+    match_dialect!(&command.dialect, |dialect| run_schema_inner(
+        dialect, command, config
+    ))
+}
+
+fn run_schema_inner<D>(dialect: D, command: SchemaCommand, config: &Config) -> anyhow::Result<()>
+where
+    D: TreeDiffer + TreeMigrator + sql_schema::Parse + Render + Send + Sync,
+{
+    let filter = command.filter.build()?;
+    let format = config.format.options();
+    let (mut migrations, _) = parse_migrations(dialect.clone(), &command.migrations_dir)?;
+    let mut schema = if command.schema_path == "-" {
+        SyntaxTree::empty()
+    } else {
+        parse_schema_file(
+            dialect.clone(),
+            &command.schema_path,
+            Some(&command.migrations_dir),
+            false,
+        )?
+    }
+    .with_format(format);
+    filter.apply(&mut migrations);
+    filter.apply(&mut schema);
+
+    let diff = schema.diff(&migrations)?.unwrap_or_else(SyntaxTree::empty);
+    let original = schema.clone();
+    let mut schema = schema.migrate(&diff)?;
+    schema.sort(command.order.order());
+    if command.group_by_kind {
+        schema.sort_by_kind();
+    }
+    if command.schema_path.is_dir() {
+        return write_schema_dir(
+            dialect,
+            &schema,
+            &command.schema_path,
+            &command.migrations_dir,
+            command.split_by,
+            format,
+        );
+    }
+    // the statements that are still the same are written the way they were
+    let sql = match command.schema_path.extension() {
+        _ if command.schema_path == "-" => None,
+        Some("hcl") => None,
+        _ => fs::read_to_string(&command.schema_path)
+            .ok()
+            .map(without_pg_dump_boilerplate),
+    };
+    let render = |schema: &SyntaxTree<D>| match &sql {
+        Some(sql) => schema.to_sql_keeping(&original, sql),
+        None => schema.to_sql(),
+    };
+    if !command.group_by_kind {
+        return write_schema(&render(&schema), &command.schema_path);
+    }
+    let mut groups = Vec::new();
+    for change in schema.changes() {
+        if !groups.contains(&change.object) {
+            groups.push(change.object);
+        }
+    }
+    let groups = groups
+        .into_iter()
+        .map(|kind| {
+            let mut group = schema.clone();
+            group.retain(|change| change.object == kind);
+            // the headers kept along with the statements they were before (and the blank lines
+            // after them) are written again
+            let mut header = false;
+            let sql = render(&group)
+                .lines()
+                .filter(|line| {
+                    header = KINDS.iter().any(|&(_, h)| *line == h) || header && line.is_empty();
+                    !header
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let header = KINDS
+                .iter()
+                .find(|&&(k, _)| k == kind)
+                .map_or("-- other", |(_, h)| h);
+            format!("{header}\n\n{sql}")
+        })
+        .collect::<Vec<_>>();
+    let lines_between = "\n".repeat(usize::from(format.lines_between) + 1);
+    write_schema(&groups.join(&lines_between), &command.schema_path)
+}
+
+/// the header comment of each kind of object with `--group-by-kind`
+const KINDS: [(ObjectKind, &str); 8] = [
+    (ObjectKind::Extension, "-- extensions"),
+    (ObjectKind::Type, "-- types"),
+    (ObjectKind::Domain, "-- domains"),
+    (ObjectKind::Function, "-- functions"),
+    (ObjectKind::Table, "-- tables"),
+    (ObjectKind::View, "-- views"),
+    (ObjectKind::Index, "-- indexes"),
+    (ObjectKind::Other, "-- other"),
+];
+
+/// writes each statement back to the file that defined the same object (by its kind and name,
+/// see [SyntaxTree::objects]), and any new objects to `schema.sql` in `dir` (removing files that no
+/// longer define anything)
+///
+/// with `split_by`, every statement is written to a file named after its object instead
+fn write_schema_dir<Dialect>(
+    dialect: Dialect,
+    schema: &SyntaxTree<Dialect>,
+    dir: &Utf8Path,
+    migrations_dir: &Utf8Path,
+    split_by: Option<SplitBy>,
+    format: FormatOptions,
+) -> anyhow::Result<()>
+where
+    Dialect: TreeDiffer + sql_schema::Parse + Render + Clone,
+{
+    // statements that don't create an object (e.g. an INSERT) can only be matched by their SQL
+    fn object_keys<Dialect: TreeDiffer>(tree: &SyntaxTree<Dialect>) -> Vec<(ObjectKind, String)> {
+        tree.objects()
+            .into_iter()
+            .zip(tree.changes())
+            .map(|(object, change)| object.unwrap_or((ObjectKind::Other, change.sql)))
+            .collect()
+    }
+
+    let mut files = Vec::new();
+    let mut origins = HashMap::new();
+    // the statements that are still the same are written the way they were
+    let mut kept = Vec::new();
+    for path in schema_files(dir, Some(migrations_dir))? {
+        let tree = SyntaxTree::from_file(dialect.clone(), &path)?.with_format(format);
+        for key in object_keys(&tree) {
+            origins.insert(key, path.clone());
+        }
+        let sql = fs::read_to_string(&path).with_context(|| format!("reading {path}"))?;
+        kept.extend(
+            tree.statements()
+                .iter()
+                .cloned()
+                .zip(tree.source_sql(&sql))
+                .filter_map(|(statement, source)| Some((statement, source?))),
+        );
+        files.push((path, Vec::new()));
+    }
+
+    let new_path = dir.join("schema.sql");
+    let keys = object_keys(schema);
+    for ((change, key), statement) in schema.changes().into_iter().zip(keys).zip(schema.split()) {
+        let sql = match kept
+            .iter()
+            .position(|(s, _)| statement.statements() == std::slice::from_ref(s))
+        {
+            Some(i) => kept.remove(i).1,
+            None => statement.to_sql(),
+        };
+        let path = match split_by {
+            Some(split_by) => dir.join(split_path(split_by, &change)),
+            None => origins.get(&key).unwrap_or(&new_path).clone(),
+        };
+        match files.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, statements)) => statements.push(sql),
+            None => files.push((path, vec![sql])),
+        }
+    }
+
+    for (path, statements) in files {
+        // every object in the file was dropped
+        if statements.is_empty() {
+            info!("removing {path}");
+            fs::remove_file(&path)?;
+            continue;
+        }
+        let contents = statements.join("\n\n");
+        if fs::read_to_string(&path).ok().as_deref() != Some(contents.as_str()) {
+            write_schema(&contents, &path)?;
+        }
+    }
+    Ok(())
+}
+
+/// the path (relative to the schema dir) of the file an object is written to with `--split-by`
+fn split_path(split_by: SplitBy, change: &Change) -> Utf8PathBuf {
+    let file_name = |name: &str| format!("{}.sql", name.replace('"', ""));
+    match (split_by, change.object, change.table.as_deref()) {
+        (SplitBy::Table, ObjectKind::Index, Some(table)) => {
+            Utf8Path::new("tables").join(file_name(table))
+        }
+        // unnamed indexes are grouped by table
+        (SplitBy::Object, ObjectKind::Index, Some(table)) if change.name.is_empty() => {
+            Utf8Path::new("indexes").join(file_name(table))
+        }
+        (_, _, _) if change.name.is_empty() => Utf8PathBuf::from("schema.sql"),
+        (_, object, _) => {
+            let dir = match object {
+                ObjectKind::Index => "indexes".to_owned(),
+                object => format!("{object}s"),
+            };
+            Utf8Path::new(&dir).join(file_name(&change.name))
+        }
+    }
+}
+
+/// create a new migration from edits to schema file
+fn run_migration(command: MigrationCommand, config: &Config) -> anyhow::Result<()> {
+    if command.schema_path == "-" {
+        if command.watch {
+            return Err(anyhow!("can't watch stdin"));
+        }
+    } else if !command.check {
+        ensure_schema_file(&command.schema_path)?;
+    }
+    if !command.check {
+        ensure_migration_dir(&command.migrations_dir)?;
+    }
+
+    if command.watch {
+        return match_dialect!(&command.dialect, |dialect| watch_migration(
+            dialect, &command, config
+        ));
+    }
+
+    match_dialect!(&command.dialect, |dialect| run_migration_inner(
+        dialect, &command, config
+    ))
+}
+
+/// print the pending migration whenever the schema file changes and write it on demand
+fn watch_migration<D>(dialect: D, command: &MigrationCommand, config: &Config) -> anyhow::Result<()>
+where
+    D: TreeDiffer + TreeMigrator + ZeroDowntime + sql_schema::Parse + Render + Send + Sync,
+{
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lines() {
+            if line.is_err() || tx.send(()).is_err() {
+                break;
+            }
+        }
+    });
+
+    info!(
+        "watching {} (press enter to write the pending migration, ctrl-c to quit)",
+        command.schema_path
+    );
+    let filter = command.filter.build()?;
+    let format = config.format.options();
+    let mut last_modified = None;
+    loop {
+        let modified = schema_modified(&command.schema_path, &command.migrations_dir)?;
+        if last_modified != Some(modified) {
+            last_modified = Some(modified);
+            let pending = parse_migrations(dialect.clone(), &command.migrations_dir).and_then(
+                |(migrations, _)| {
+                    let mut migrations = migrations.with_format(format);
+                    let mut schema = parse_schema_file(
+                        dialect.clone(),
+                        &command.schema_path,
+                        Some(&command.migrations_dir),
+                        command.lenient,
+                    )?
+                    .with_format(format);
+                    no_duplicates(&schema, &command.schema_path)?;
+                    filter.apply(&mut migrations);
+                    filter.apply(&mut schema);
+                    Ok(migrations.diff(&schema)?)
+                },
+            );
+            // keep watching while the schema file is mid-edit
+            match pending {
+                Ok(Some(diff)) => println!("{diff}\n"),
+                Ok(None) => info!("existing migrations and the schema file are the same"),
+                Err(err) => error!("{err:?}"),
+            }
+        }
+
+        match rx.recv_timeout(Duration::from_millis(500)) {
+            Ok(()) => {
+                if let Err(err) = run_migration_inner(dialect.clone(), command, config) {
+                    error!("{err:?}");
+                }
+                // show the (now empty) pending migration again
+                last_modified = None;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // stdin was closed, so there's nothing left to wait on but the schema file
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(Duration::from_millis(500)),
+        }
+    }
+}
+
+/// the most recent modification time of the schema file (or any file in the schema dir)
+fn schema_modified(path: &Utf8Path, migrations_dir: &Utf8Path) -> anyhow::Result<SystemTime> {
+    if !path.is_dir() {
+        return Ok(fs::metadata(path)?.modified()?);
+    }
+    // include the dir itself so removing a file counts as a change
+    let mut modified = fs::metadata(path)?.modified()?;
+    for path in schema_files(path, Some(migrations_dir))? {
+        modified = modified.max(fs::metadata(&path)?.modified()?);
+    }
+    Ok(modified)
+}
+
+fn run_migration_inner<D>(
+    dialect: D,
+    command: &MigrationCommand,
+    config: &Config,
+) -> anyhow::Result<()>
+where
+    D: TreeDiffer + TreeMigrator + ZeroDowntime + sql_schema::Parse + Render + Send + Sync,
+{
+    let filter = command.filter.build()?;
+    let format = config.format.options();
+    // fail before writing anything when there's no editor to open
+    let editor = command.edit.then(editor).transpose()?;
+    if command.zero_downtime
+        && !dialect.concurrent_indexes()
+        && !dialect.deferred_validation()
+        && command.dialect != Dialect::MySql
+    {
+        warn!(
+            "--zero-downtime has nothing to rewrite for the {} dialect",
+            command.dialect
+        );
+    }
+    if command.concurrent_indexes && !dialect.concurrent_indexes() {
+        warn!(
+            "--concurrent-indexes has no effect for the {} dialect",
+            command.dialect
+        );
+    }
+    if command.rebuild_tables && !dialect.supports().rebuilds_tables() {
+        warn!(
+            "--rebuild-tables has nothing to rebuild for the {} dialect",
+            command.dialect
+        );
+    }
+    let (migrations, opts) = parse_migrations(dialect.clone(), &command.migrations_dir)?;
+    let mut migrations = migrations.with_format(format);
+    let opts = opts
+        .with_config(config)?
+        .with_timestamp(&command.timestamp)
+        .reconcile(command.include_down);
+    let mut schema = parse_schema_file(
+        dialect,
+        &command.schema_path,
+        Some(&command.migrations_dir),
+        command.lenient,
+    )?
+    .with_format(format);
+    no_duplicates(&schema, &command.schema_path)?;
+    filter.apply(&mut migrations);
+    filter.apply(&mut schema);
+    let (diff, backfills) = match migrations.diff(&schema)? {
+        Some(diff) if command.backfill_stubs => {
+            let (diff, backfills) = diff.defer_not_null();
+            (Some(diff), backfills)
+        }
+        diff => (diff, Vec::new()),
+    };
+    let diff = match command.rebuild_tables {
+        true => rebuild_tables(&migrations, &schema, diff)?,
+        false => diff,
+    };
+    let if_exists = config
+        .migration
+        .if_exists
+        .map_or_else(Default::default, Into::into);
+    let up_migration = match &diff {
+        Some(diff) if command.zero_downtime => Some(diff.plan_zero_downtime()),
+        Some(diff) if command.concurrent_indexes => Some(diff.build_indexes_concurrently()),
+        _ => diff.clone(),
+    }
+    .map(|up_migration| up_migration.with_if_exists(if_exists));
+    if let Some(up_migration) = up_migration.as_ref().filter(|_| command.zero_downtime) {
+        for lock in up_migration.lock_impacts().into_iter().flatten() {
+            if lock.rewrite && lock.level >= LockLevel::Share {
+                warn!(
+                    "--zero-downtime can't avoid rewriting {}, which blocks writes to it until it's done",
+                    lock.table
+                );
+            }
+        }
+    }
+    match up_migration {
+        Some(up_migration) if command.check => {
+            match command.format {
+                Format::Text => println!("{}", up_migration_sql(&up_migration, &backfills)),
+                Format::Json => print_changes([(&up_migration, None)])?,
+            }
+            Err(PendingMigration.into())
+        }
+        // the schema came from stdin, so the migration goes to stdout
+        Some(up_migration) if command.schema_path == "-" => match command.format {
+            Format::Text => {
+                println!("{}", up_migration_sql(&up_migration, &backfills));
+                Ok(())
+            }
+            Format::Json => print_changes([(&up_migration, None)]),
+        },
+        Some(up_migration) => {
+            if command.format == Format::Text && tracing::enabled!(Level::INFO) {
+                anstream::eprintln!(
+                    "{}",
+                    render_plan(&up_migration.changes(), &up_migration.lock_impacts())
+                );
+            }
+            // in watch mode, pressing enter after seeing the migration is the confirmation
+            if !command.yes && !command.watch && !confirm_destructive(&up_migration)? {
+                return Err(anyhow!("aborted"));
+            }
+            let summary = up_migration.summary();
+            // statements that can't run inside a transaction get migrations of their own
+            let transactions = up_migration.split_transactions();
+            let parts = if command.split_statements {
+                split_migration(
+                    &migrations,
+                    up_migration.split(),
+                    opts.include_down,
+                    if_exists,
+                )?
+            } else if transactions.len() > 1 {
+                info!(
+                    "splitting the migration into {} migrations, since some of it can't run inside a transaction",
+                    transactions.len()
+                );
+                split_migration(&migrations, transactions, opts.include_down, if_exists)?
+            } else {
+                let down_migration = opts.include_down.then(|| {
+                    down_migration(
+                        &schema,
+                        &migrations,
+                        &up_migration,
+                        command.rebuild_tables,
+                        if_exists,
+                    )
+                });
+                vec![(up_migration, down_migration)]
+            };
+            let header = config.header(command.header_file.as_deref())?;
+            let schema_sql = schema.to_sql();
+
+            let split = parts.len() > 1;
+            // only a name given with --name is slugified, so generated ones keep their separator
+            let generated = MigrationOptions {
+                verbatim_name: true,
+                ..opts.clone()
+            };
+            let mut migrations = Vec::with_capacity(parts.len());
+            let mut next = 0;
+            for (n, (up_migration, down_migration)) in parts.into_iter().enumerate() {
+                let (name, name_opts) = match command.name.as_ref() {
+                    _ if opts.num_migrations == 0 && n == 0 => {
+                        ("initial_schema".to_owned(), &generated)
+                    }
+                    Some(name) => (name.clone(), &opts),
+                    None if command.generic_name => (GENERIC_NAME.to_owned(), &generated),
+                    None => {
+                        // a whole migration is named for what it changes rather than how (e.g.
+                        // before --zero-downtime splits it into steps)
+                        let changes = match &diff {
+                            Some(diff) if !split => diff,
+                            _ => &up_migration,
+                        };
+                        let name = name_gen::generate_name(changes)
+                            .build()
+                            .unwrap_or_else(|| GENERIC_NAME.to_owned());
+                        (name, &generated)
+                    }
+                };
+                // migrations resolved before this one haven't been written yet, so start after them
+                let (used, up_path, down_path) =
+                    name_opts.resolve_unused_paths(&command.migrations_dir, name.clone(), next)?;
+                let header = header.as_ref().map(|template| {
+                    render_header(template, &name, &schema_sql, opts.timestamp(used))
+                });
+                next = used + 1;
+                migrations.push((up_migration, up_path, down_migration, down_path, header));
+            }
+            if command.format == Format::Json {
+                print_changes(
+                    migrations
+                        .iter()
+                        .map(|(up_migration, up_path, ..)| (up_migration, Some(up_path.as_path()))),
+                )?;
+            }
+
+            let mut paths = Vec::new();
+            for (up_migration, up_path, down_migration, down_path, header) in migrations {
+                let up_outside = up_migration.outside_transaction();
+                // the down migration is SQL by now (or a stub that parses as nothing)
+                let down_outside = down_migration.as_deref().is_some_and(|down| {
+                    SyntaxTree::parse(D::default(), down)
+                        .is_ok_and(|down| down.outside_transaction())
+                });
+                let up_migration = up_migration_sql(&up_migration, &backfills);
+                let (mut up_migration, mut down_migration) =
+                    opts.sections(up_migration, down_migration);
+                let (mut up_header, mut down_header) = (header.clone(), header);
+                if up_outside {
+                    (up_migration, up_header) =
+                        opts.no_transaction(up_migration, &up_path, up_header, UP_MARKER)?;
+                }
+                if down_outside {
+                    match (down_migration.take(), &down_path) {
+                        (Some(down), Some(down_path)) => {
+                            let (down, header) =
+                                opts.no_transaction(down, down_path, down_header, DOWN_MARKER)?;
+                            (down_migration, down_header) = (Some(down), header);
+                        }
+                        // it's a section of the up migration
+                        _ => {
+                            (up_migration, up_header) = opts.no_transaction(
+                                up_migration,
+                                &up_path,
+                                up_header,
+                                DOWN_MARKER,
+                            )?;
+                        }
+                    }
+                }
+                write_migration(up_migration, &up_path, up_header.as_deref())?;
+                if let (Some(down_migration), Some(down_path)) = (down_migration, &down_path) {
+                    write_migration(down_migration, down_path, down_header.as_deref())?;
+                }
+                run_post_migration_hook(config, &up_path, down_path.as_deref())?;
+                paths.push(up_path);
+                paths.extend(down_path);
+            }
+            info!("{summary}");
+            if let Some(editor) = editor {
+                edit_migration(&editor, &paths)?;
+            }
+            Ok(())
+        }
+        None => {
+            if command.format == Format::Json {
+                print_changes::<D>(None)?;
+            }
+            info!("existing migrations and the schema file are the same");
+            Ok(())
+        }
+    }
+}
+
+/// the SQL for an up migration, followed by templates for the backfills of the tables it alters
+fn up_migration_sql<D: Render>(up_migration: &SyntaxTree<D>, backfills: &[Backfill]) -> String {
+    let changes = up_migration.changes();
+    let stubs = backfills
+        .iter()
+        .filter(|backfill| {
+            let table = backfill.table.to_string();
+            changes
+                .iter()
+                .any(|change| change.operation == Operation::Alter && change.name == table)
+        })
+        .map(ToString::to_string)
+        .collect::<String>();
+    if stubs.is_empty() {
+        return up_migration.to_sql();
+    }
+    format!("{}\n\n{}", up_migration.to_sql(), stubs.trim_end())
+}
+
+/// the SQL that reverts `migrations` to `schema` (i.e. undoes `up_migration`), or a stub listing
+/// what can't be reverted when it can't be generated
+fn down_migration<D>(
+    schema: &SyntaxTree<D>,
+    migrations: &SyntaxTree<D>,
+    up_migration: &SyntaxTree<D>,
+    rebuild: bool,
+    if_exists: sql_schema::IfExists,
+) -> String
+where
+    D: TreeDiffer + TreeMigrator + ZeroDowntime + sql_schema::Parse + Render,
+{
+    let err = match schema.diff(migrations) {
+        // the rebuild only parses SQL it generates itself
+        Ok(down_migration) if rebuild => {
+            return rebuild_tables(schema, migrations, down_migration.clone())
+                .unwrap_or(down_migration)
+                .map(|down_migration| down_migration.with_if_exists(if_exists).to_sql())
+                .unwrap_or_default()
+        }
+        Ok(down_migration) => {
+            return down_migration
+                .map(|down_migration| down_migration.with_if_exists(if_exists).to_sql())
+                .unwrap_or_default()
+        }
+        Err(err) => err,
+    };
+    warn!("the down migration needs to be written by hand: {err}");
+    let irreversible = migrations
+        .irreversible_changes(up_migration)
+        .unwrap_or_default();
+    if irreversible.is_empty() {
+        return format!("-- IRREVERSIBLE: {}\n", err.kind());
+    }
+    irreversible
+        .iter()
+        .map(|IrreversibleChange { change, reason }| {
+            format!(
+                "-- IRREVERSIBLE: {} {} {} ({})\n",
+                change.operation,
+                change.object,
+                change.name,
+                reason.kind()
+            )
+        })
+        .collect()
+}
+
+/// rebuilds the tables `diff` (from `a` to `b`) changes in ways the dialect can't with ALTER
+/// TABLE, including changes it can't express with ALTER TABLE
+fn rebuild_tables<D>(
+    a: &SyntaxTree<D>,
+    b: &SyntaxTree<D>,
+    diff: Option<SyntaxTree<D>>,
+) -> Result<Option<SyntaxTree<D>>, sql_schema::ParseError>
+where
+    D: ZeroDowntime + sql_schema::Parse + Default + Clone,
+{
+    let diff = diff.unwrap_or_else(SyntaxTree::empty);
+    let rebuilt = a.rebuild_tables(b, &diff)?;
+    Ok((!rebuilt.statements().is_empty()).then_some(rebuilt))
+}
+
+/// an up migration and its down migration (when `include_down` is set)
+type MigrationPair<D> = (SyntaxTree<D>, Option<String>);
+
+/// splits a migration into the migrations `parts` (e.g. one per statement), each with a down
+/// migration that reverts just that part
+fn split_migration<D>(
+    migrations: &SyntaxTree<D>,
+    parts: Vec<SyntaxTree<D>>,
+    include_down: bool,
+    if_exists: sql_schema::IfExists,
+) -> anyhow::Result<Vec<MigrationPair<D>>>
+where
+    D: TreeDiffer + TreeMigrator + ZeroDowntime + sql_schema::Parse + Render,
+{
+    let mut before = migrations.clone();
+    let mut pairs = Vec::with_capacity(parts.len());
+    for up_migration in parts {
+        let after = before.clone().migrate(&up_migration)?;
+        let down_migration =
+            include_down.then(|| down_migration(&after, &before, &up_migration, false, if_exists));
+        pairs.push((up_migration, down_migration));
+        before = after;
+    }
+    Ok(pairs)
+}
+
+/// renders a summary of the changes in a migration, similar to `terraform plan`, along with the
+/// lock each change takes
+fn render_plan(changes: &[Change], locks: &[Option<LockImpact>]) -> String {
+    use anstyle::{AnsiColor, Style};
+
+    let style = |operation| match operation {
+        Operation::Create => AnsiColor::Green.on_default(),
+        Operation::Drop => AnsiColor::Red.on_default(),
+        Operation::Alter => AnsiColor::Yellow.on_default(),
+        _ => Style::new(),
+    };
+    // highlight what each line of SQL does, e.g. the ADD in `ALTER TABLE foo ADD COLUMN bar`
+    let highlight = |line: &str| {
+        line.split(' ')
+            .map(|word| {
+                let style = match word {
+                    "CREATE" | "ADD" => style(Operation::Create),
+                    "DROP" => style(Operation::Drop),
+                    "ALTER" | "RENAME" => style(Operation::Alter),
+                    _ => return word.to_owned(),
+                };
+                format!("{style}{word}{style:#}")
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut out = String::from("the migration will make the following changes:\n");
+    let mut counts = HashMap::<Operation, usize>::new();
+    for (change, lock) in changes.iter().zip(locks) {
+        *counts.entry(change.operation).or_default() += 1;
+        let style = style(change.operation);
+        let symbol = match change.operation {
+            Operation::Create => "+",
+            Operation::Drop => "-",
+            Operation::Alter => "~",
+            _ => "*",
+        };
+        let bold = style.bold();
+        let warning = if change.destructive {
+            format!(" {bold}(may lose data){bold:#}")
+        } else {
+            String::new()
+        };
+        out.push_str(&format!(
+            "\n  {style}{symbol}{style:#} {bold}{} {} {}{bold:#}{warning}\n",
+            change.operation, change.object, change.name
+        ));
+        if let Some(lock) = lock {
+            let dimmed = Style::new().dimmed();
+            let rewrite = if lock.rewrite { " and rewrites it" } else { "" };
+            out.push_str(&format!(
+                "    {dimmed}locks {} ({}){rewrite}{dimmed:#}\n",
+                lock.table, lock.level
+            ));
+        }
+        for line in change.sql.lines() {
+            out.push_str(&format!("      {}\n", highlight(line)));
+        }
+    }
+    let count = |operation| counts.get(&operation).copied().unwrap_or_default();
+    out.push_str(&format!(
+        "\n{} to create, {} to alter, {} to drop",
+        count(Operation::Create),
+        count(Operation::Alter),
+        count(Operation::Drop)
+    ));
+    out
+}
+
+/// asks before writing a migration that can lose data, listing the statements responsible
+fn confirm_destructive<Dialect>(migration: &SyntaxTree<Dialect>) -> anyhow::Result<bool> {
+    let destructive = migration
+        .changes()
+        .into_iter()
+        .filter(|change| change.destructive)
+        .collect::<Vec<_>>();
+    if destructive.is_empty() {
+        return Ok(true);
+    }
+
+    eprintln!("the migration may lose data:");
+    for change in &destructive {
+        eprintln!("\n{}", change.sql);
+    }
+    if !io::stdin().is_terminal() {
+        eprintln!("\npass --yes to write it anyway");
+        return Ok(false);
+    }
+    eprint!("\nwrite it anyway? [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// print a JSON description of each change in `trees` to stdout
+fn print_changes<'a, Dialect: 'a>(
+    trees: impl IntoIterator<Item = (&'a SyntaxTree<Dialect>, Option<&'a Utf8Path>)>,
+) -> anyhow::Result<()> {
+    #[derive(serde::Serialize)]
+    struct Output<'a> {
+        changes: Vec<ChangeOutput<'a>>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct ChangeOutput<'a> {
+        #[serde(flatten)]
+        change: Change,
+        path: Option<&'a str>,
+    }
+
+    let changes = trees
+        .into_iter()
+        .flat_map(|(tree, path)| {
+            tree.changes().into_iter().map(move |change| ChangeOutput {
+                change,
+                path: path.map(Utf8Path::as_str),
+            })
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&Output { changes })?);
+    Ok(())
+}
+
+/// create empty migration files for a hand-written migration
+fn run_new(command: NewCommand, config: &Config) -> anyhow::Result<()> {
+    ensure_migration_dir(&command.migrations_dir)?;
+
+    let migrations = find_migrations(&command.migrations_dir, find_options())?;
+    let opts = MigrationOptions::detect(&command.migrations_dir, &migrations)?
+        .with_config(config)?
+        .with_timestamp(&command.timestamp)
+        .reconcile(command.include_down);
+    let (up_path, down_path) = opts.resolve_paths(&command.migrations_dir, command.name)?;
+    for path in [Some(&up_path), down_path.as_ref()].into_iter().flatten() {
+        info!("writing {path}");
+        if let Some(parent) = path.parent() {
+            ensure_migration_dir(parent)?;
+        }
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .context(format!("path: {path}"))?;
+    }
+    // a migration holding both starts out with empty sections
+    if opts.single_file {
+        fs::write(&up_path, migrations::join_sections("", ""))?;
+    }
+    run_post_migration_hook(config, &up_path, down_path.as_deref())
+}
+
+/// print the migration between two arbitrary inputs
+fn run_diff(command: DiffCommand) -> anyhow::Result<()> {
+    if command.a == "-" && command.b == "-" {
+        return Err(anyhow!("only one input can be read from stdin"));
+    }
+
+    match_dialect!(&command.dialect, |dialect| run_diff_inner(dialect, command))
+}
+
+fn run_diff_inner<D>(dialect: D, command: DiffCommand) -> anyhow::Result<()>
+where
+    D: TreeDiffer + TreeMigrator + sql_schema::Parse + Send + Sync,
+{
+    let a = load_schema(dialect.clone(), &command.a, command.lenient)?;
+    let b = load_schema(dialect, &command.b, command.lenient)?;
+    let diff = a.diff(&b)?;
+    if command.format == Format::Json {
+        return print_changes(diff.as_ref().map(|diff| (diff, None)));
+    }
+    match diff {
+        Some(diff) => println!("{diff}"),
+        None => info!("{} and {} are the same", command.a, command.b),
+    }
+    Ok(())
+}
+
+/// fail with the pending migration if the schema file and migrations don't match
+fn run_check(command: CheckCommand) -> anyhow::Result<()> {
+    match_dialect!(&command.dialect, |dialect| run_check_inner(
+        dialect, command
+    ))
+}
+
+fn run_check_inner<D>(dialect: D, command: CheckCommand) -> anyhow::Result<()>
+where
+    D: TreeDiffer + TreeMigrator + sql_schema::Parse + Send + Sync,
+{
+    let filter = command.filter.build()?;
+    let (mut migrations, _) = parse_migrations(dialect.clone(), &command.migrations_dir)?;
+    let mut schema = parse_schema_file(
+        dialect,
+        &command.schema_path,
+        Some(&command.migrations_dir),
+        command.lenient,
+    )?;
+    for problem in schema.validate().problems {
+        warn!("{problem}");
+    }
+    filter.apply(&mut migrations);
+    filter.apply(&mut schema);
+    // diffs both ways and applies each diff to catch diffs that don't do what they say
+    let report = sql_schema::verify(&migrations, &schema);
+    for discrepancy in &report.discrepancies {
+        warn!("{discrepancy}");
+    }
+    if command.format == Format::Json {
+        print_changes(report.forward.as_ref().map(|diff| (diff, None)))?;
+    }
+    match &report.forward {
+        Some(diff) => {
+            if command.format == Format::Text {
+                println!("{diff}");
+            }
+            Err(anyhow!(
+                "{} doesn't match {}",
+                command.schema_path,
+                command.migrations_dir
+            ))
+        }
+        None if !report.converges() => Err(anyhow!(
+            "couldn't verify that {} matches {}",
+            command.schema_path,
+            command.migrations_dir
+        )),
+        None => {
+            info!("existing migrations and the schema file are the same");
+            Ok(())
+        }
+    }
+}
+
+/// replace the migrations before a version with a single baseline migration
+fn run_squash(command: SquashCommand, config: &Config) -> anyhow::Result<()> {
+    match_dialect!(&command.dialect, |dialect| run_squash_inner(
+        dialect, command, config
+    ))
+}
+
+fn run_squash_inner<D>(dialect: D, command: SquashCommand, config: &Config) -> anyhow::Result<()>
+where
+    D: TreeDiffer + TreeMigrator + sql_schema::Parse + Render,
+{
+    let dir = &command.migrations_dir;
+    let migrations = find_migrations(dir, find_options())?;
+    let end = migrations
+        .iter()
+        .position(|m| m.version() == command.before || m.name == command.before)
+        .ok_or_else(|| anyhow!("no migration found for version {}", command.before))?;
+    let squashed = &migrations[..end];
+    let Some(last) = squashed.last() else {
+        info!("no migrations before {}", command.before);
+        return Ok(());
+    };
+
+    let migrations = squashed
+        .iter()
+        .map(|migration| {
+            let path = &migration.up;
+            debug!("parsing {path}");
+            SyntaxTree::from_migration(dialect.clone(), path)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut baseline = SyntaxTree::empty().with_format(config.format.options());
+    baseline.apply_all(migrations)?;
+
+    // reuse the version of the last squashed migration so the baseline sorts before the rest
+    let path = last.up.strip_prefix(dir)?;
+    let path_template = PathTemplate::parse_within(path.as_str(), find_options().date_bounds)
+        .context(format!("path: {path}"))?;
+    let path_data = TemplateData {
+        name: command.name,
+        verbatim_name: config.migration.verbatim_name,
+        ..path_template.template_data()?
+    };
+    let header = config.header(None)?.map(|template| {
+        let timestamp = path_data.offset_timestamp();
+        render_header(&template, &path_data.name, &baseline.to_sql(), timestamp)
+    });
+    let up_path = dir.join(path_template.resolve(&path_data));
+    let down_path = last.down.as_ref().map(|_| {
+        let path_data = TemplateData {
+            up_down: Some(UpDown::Down),
+            ..path_data.clone()
+        };
+        dir.join(path_template.resolve(&path_data))
+    });
+
+    if let Some(down_path) = &down_path {
+        let down_migration = baseline
+            .diff(&SyntaxTree::empty())?
+            .unwrap_or_else(SyntaxTree::empty);
+        write_migration(baseline.to_sql(), &up_path, header.as_deref())?;
+        write_migration(down_migration.to_sql(), down_path, header.as_deref())?;
+    } else {
+        write_migration(baseline.to_sql(), &up_path, header.as_deref())?;
+    }
+
+    // the originals are only removed once the baseline that replaces them is written
+    if command.delete {
+        let written = [Some(&up_path), down_path.as_ref()];
+        for migration in squashed {
+            for path in [Some(&migration.up), migration.down.as_ref()]
+                .into_iter()
+                .flatten()
+                // the baseline can take the place of the last squashed migration
+                .filter(|path| !written.contains(&Some(*path)))
+            {
+                info!("removing {path}");
+                fs::remove_file(path)?;
+                // clean up the dir when using the directory-per-migration layout
+                if let Some(parent) = path.parent().filter(|p| *p != dir) {
+                    if parent.read_dir_utf8()?.next().is_none() {
+                        fs::remove_dir(parent)?;
+                    }
+                }
+            }
+        }
+    }
+    run_post_migration_hook(config, &up_path, down_path.as_deref())?;
+
+    if !command.delete {
+        warn!(
+            "the {} squashed migrations must be removed before {up_path} is applied",
+            squashed.len()
+        );
+    }
+    Ok(())
+}
+
+/// write the schema of a live database to the schema file
+fn run_introspect(command: IntrospectCommand) -> anyhow::Result<()> {
+    let (_, schema) = introspect_database(&command.database_url, &command.db_schema)?;
+    write_schema(&schema, &command.schema_path)
+}
+
+/// diff a live database against the schema file
+fn run_drift(command: DriftCommand) -> anyhow::Result<()> {
+    let (dialect, database) = introspect_database(&command.database_url, &command.db_schema)?;
+    match_dialect!(&dialect, |dialect| run_drift_inner(
+        dialect,
+        command,
+        database.as_str()
+    ))
+}
+
+fn run_drift_inner<D>(dialect: D, command: DriftCommand, database: &str) -> anyhow::Result<()>
+where
+    D: TreeDiffer + TreeMigrator + sql_schema::Parse,
+{
+    let database =
+        SyntaxTree::parse(dialect.clone(), database).context(command.database_url.clone())?;
+    let schema = parse_schema_file(
+        dialect,
+        &command.schema_path,
+        Some(&command.migrations_dir),
+        false,
+    )?;
+    match database.diff(&schema)? {
+        Some(diff) => {
+            println!("{diff}");
+            if command.check {
+                return Err(anyhow!(
+                    "the database has drifted from {}",
+                    command.schema_path
+                ));
+            }
+        }
+        None => info!("the database matches {}", command.schema_path),
+    }
+    Ok(())
+}
+
+/// print each migration along with whether it's been applied, failing when an applied migration
+/// has been edited since (unless it's repeatable, which makes it pending again)
+fn run_status(command: StatusCommand) -> anyhow::Result<()> {
+    let migrations = find_migrations(&command.migrations_dir, find_options())?;
+    let mut applied = applied_migrations(&command.database_url, &command.migrations_table)?;
+    let mut pending = 0;
+    let mut modified = 0;
+    let mut dirty = None;
+    for migration in &migrations {
+        let status = match applied.iter().position(|m| m.matches(migration.version())) {
+            Some(i) => {
+                let sql = fs::read(&migration.up)?;
+                let applied = applied.remove(i);
+                if applied.dirty == Some(true) {
+                    dirty = Some(&migration.name);
+                    "dirty"
+                } else if applied.is_modified(sql) {
+                    // repeatable migrations are applied again whenever they change
+                    if migration.is_repeatable() {
+                        pending += 1;
+                        "pending"
+                    } else {
+                        modified += 1;
+                        "modified"
+                    }
+                } else {
+                    "applied"
+                }
+            }
+            // tables like golang-migrate's only record the most recent migration
+            None if applied.iter().any(|m| m.covers(migration.version())) => "applied",
+            None => {
+                pending += 1;
+                "pending"
+            }
+        };
+        println!("{status:<8} {}", migration.name);
+    }
+    for migration in &applied {
+        println!(
+            "{:<8} {} (not in the migrations dir)",
+            "missing", migration.version
+        );
+    }
+    info!(
+        "{} of {} migrations applied",
+        migrations.len() - pending,
+        migrations.len()
+    );
+    if let Some(name) = dirty {
+        return Err(anyhow!(
+            "{name} failed partway through being applied (it's marked dirty)"
+        ));
+    }
+    if modified > 0 {
+        return Err(anyhow!(
+            "{modified} migration(s) have been edited since they were applied"
+        ));
+    }
+    Ok(())
+}
+
+/// connects to the database at `url` and reads the migrations recorded in `table`
+fn applied_migrations(url: &str, table: &TrackingTable) -> anyhow::Result<Vec<AppliedMigration>> {
+    match Dialect::from_database_url(url)? {
+        Dialect::PostgreSql => {
+            #[cfg(feature = "postgres")]
+            {
+                let mut client = postgres::Client::connect(url, postgres::NoTls)?;
+                Ok(tracking::applied(&mut client, table)?)
+            }
+            #[cfg(not(feature = "postgres"))]
+            {
+                let _ = table;
+                Err(anyhow!(
+                    "sql-schema was built without postgres support (enable the `postgres` feature)"
+                ))
+            }
+        }
+        Dialect::SQLite => {
+            let path = sqlite_path(url);
+            #[cfg(feature = "sqlite")]
+            {
+                let mut conn = rusqlite::Connection::open_with_flags(
+                    path,
+                    rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+                )?;
+                Ok(tracking::applied(&mut conn, table)?)
+            }
+            #[cfg(not(feature = "sqlite"))]
+            {
+                let _ = (path, table);
+                Err(anyhow!(
+                    "sql-schema was built without sqlite support (enable the `sqlite` feature)"
+                ))
+            }
+        }
+        // database urls have a specific dialect
+        dialect => Err(anyhow!("Oops, we can't connect to {dialect} databases")),
+    }
+}
+
+/// the path of the database file in a `sqlite://` url
+fn sqlite_path(url: &str) -> &str {
+    let path = url.trim_start_matches("sqlite:").trim_start_matches("//");
+    path.split_once('?').map_or(path, |(path, _)| path)
+}
+
+/// connects to the database at `url` and renders its schema, returning it along with its dialect
+fn introspect_database(url: &str, db_schema: &str) -> anyhow::Result<(Dialect, String)> {
+    match Dialect::from_database_url(url)? {
+        Dialect::PostgreSql => {
+            #[cfg(feature = "postgres")]
+            {
+                let mut client = postgres::Client::connect(url, postgres::NoTls)?;
+                let tree = sql_schema::introspect::postgres(&mut client, db_schema)?;
+                Ok((Dialect::PostgreSql, tree.to_sql()))
+            }
+            #[cfg(not(feature = "postgres"))]
+            {
+                let _ = db_schema;
+                Err(anyhow!(
+                    "sql-schema was built without postgres support (enable the `postgres` feature)"
+                ))
+            }
+        }
+        Dialect::SQLite => {
+            let path = sqlite_path(url);
+            #[cfg(feature = "sqlite")]
+            {
+                let conn = rusqlite::Connection::open_with_flags(
+                    path,
+                    rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+                )?;
+                let tree = sql_schema::introspect::sqlite(&conn)?;
+                Ok((Dialect::SQLite, tree.to_sql()))
+            }
+            #[cfg(not(feature = "sqlite"))]
+            {
+                let _ = path;
+                Err(anyhow!(
+                    "sql-schema was built without sqlite support (enable the `sqlite` feature)"
+                ))
+            }
+        }
+        // database urls have a specific dialect
+        dialect => Err(anyhow!("Oops, we can't connect to {dialect} databases")),
+    }
+}
+
+/// apply the migrations that haven't been applied to a live database in order, each in a
+/// transaction along with recording it in the tracking table (unless it's marked
+/// `-- no-transaction`), failing before applying any when an applied migration has been edited
+/// since (unless it's repeatable, which applies it again), then run the seed files with `--seed`
+// only used to connect, which needs the postgres or sqlite feature
+#[cfg_attr(
+    not(any(feature = "postgres", feature = "sqlite")),
+    allow(unused_variables)
+)]
+fn run_apply(command: ApplyCommand, config: &Config) -> anyhow::Result<()> {
+    with_connection!(&command.database_url, true, |conn| apply(
+        conn, &command, config
+    ))
+}
+
+/// applies the pending migrations over `conn`
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn apply<C: tracking::Connection>(
+    conn: &mut C,
+    command: &ApplyCommand,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let migrations = find_migrations(&command.migrations_dir, find_options())?;
+    // the table is created the way the compat tool expects (golang-migrate only records the most
+    // recent migration)
+    let layout = match config.migration.compat {
+        Some(Compat::GolangMigrate) => tracking::Layout::golang_migrate(),
+        _ => tracking::Layout::default(),
+    };
+    let mut runner = Runner::new(conn, command.migrations_table.clone(), layout)?;
+    let records = runner.applied()?;
+    check_dirty(&records)?;
+    let mut pending = Vec::new();
+    let mut modified = Vec::new();
+    for migration in &migrations {
+        let file = fs::read_to_string(&migration.up)?;
+        match records.iter().find(|r| r.matches(migration.version())) {
+            // repeatable migrations are applied again whenever they change
+            Some(record) if record.is_modified(&file) && migration.is_repeatable() => {}
+            Some(record) if record.is_modified(&file) => {
+                modified.push(&migration.name);
+                continue;
+            }
+            Some(_) => continue,
+            // tables like golang-migrate's only record the most recent migration
+            None if records.iter().any(|r| r.covers(migration.version())) => continue,
+            None => {}
+        }
+        pending.push((migration, file));
+    }
+    if !modified.is_empty() {
+        for name in &modified {
+            println!("modified {name}");
+        }
+        return Err(anyhow!(
+            "{} migration(s) have been edited since they were applied",
+            modified.len()
+        ));
+    }
+    for (migration, file) in &pending {
+        let (up, _) = migrations::split_sections(file);
+        let version = migration.version();
+        let name = migration
+            .name
+            .file_name()
+            .unwrap_or(migration.name.as_str());
+        let description = name
+            .strip_prefix(version)
+            .unwrap_or(name)
+            .trim_start_matches('_');
+        let record = tracking::Record {
+            version,
+            description: if description.is_empty() {
+                name
+            } else {
+                description
+            },
+            checksum: tracking::checksum(file),
+        };
+        debug!("applying {}", migration.name);
+        runner
+            .apply(&record, up, migrations::runs_in_transaction(file))
+            .with_context(|| format!("applying {}", migration.name))?;
+        info!("applied {}", migration.name);
+    }
+    if pending.is_empty() {
+        info!("no pending migrations to apply");
+    }
+    drop(runner);
+
+    if command.seed {
+        let files = load::sql_files(&command.seeds_dir)?;
+        let mut sql = String::from("BEGIN;\n");
+        for path in &files {
+            debug!("reading {path}");
+            sql += &fs::read_to_string(path)?;
+            sql += "\n;\n";
+        }
+        sql += "COMMIT;";
+        if let Err(err) = conn.execute(&sql) {
+            let _ = conn.execute("ROLLBACK");
+            return Err(err).context(format!("seeding from {}", command.seeds_dir));
+        }
+        info!("ran {} seed files from {}", files.len(), command.seeds_dir);
+    }
+    Ok(())
+}
+
+/// fails when the most recent migration failed partway through being applied (tables like
+/// golang-migrate's mark it dirty)
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn check_dirty(records: &[AppliedMigration]) -> anyhow::Result<()> {
+    match records.iter().find(|m| m.dirty == Some(true)) {
+        Some(dirty) => Err(anyhow!(
+            "migration {} failed partway through being applied (it's marked dirty), so it has to \
+            be fixed by hand",
+            dirty.version
+        )),
+        None => Ok(()),
+    }
+}
+
+/// run the down migrations of the most recently applied migrations (most recent first), each in a
+/// transaction along with removing it from the tracking table, or print them with `--dry-run`
+fn run_rollback(command: RollbackCommand) -> anyhow::Result<()> {
+    let migrations = find_migrations(&command.migrations_dir, find_options())?;
+    // repeatable migrations aren't versioned, so there's nothing to roll back to
+    let migrations = migrations
+        .iter()
+        .filter(|m| !m.is_repeatable())
+        .collect::<Vec<_>>();
+    match &command.database_url {
+        Some(url) if !command.dry_run => {
+            with_connection!(url, false, |conn| rollback(conn, &command, &migrations))
+        }
+        _ => {
+            let mut stdout = io::stdout().lock();
+            for migration in rolled_back(&command, &migrations)?.iter().rev() {
+                let down = migration.down.as_ref().unwrap_or(&migration.up);
+                let (sql, _) = read_down(migration)?;
+                writeln!(stdout, "-- {down}\n{}\n", sql.trim_end())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// runs the down migrations of the `migrations` that `command` rolls back over `conn`
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn rollback<C: tracking::Connection>(
+    conn: &mut C,
+    command: &RollbackCommand,
+    migrations: &[&MigrationFile],
+) -> anyhow::Result<()> {
+    let mut runner = Runner::new(
+        conn,
+        command.migrations_table.clone(),
+        tracking::Layout::default(),
+    )?;
+    let records = runner.applied()?;
+    check_dirty(&records)?;
+    // the version of each applied migration as it's recorded
+    let applied = migrations
+        .iter()
+        .filter_map(|m| match records.iter().find(|r| r.matches(m.version())) {
+            Some(record) => Some((*m, record.version.clone())),
+            // tables like golang-migrate's only record the most recent migration
+            None if records.iter().any(|r| r.covers(m.version())) => {
+                Some((*m, m.version().to_owned()))
+            }
+            None => None,
+        })
+        .collect::<Vec<_>>();
+    let files = applied.iter().map(|(m, _)| *m).collect::<Vec<_>>();
+    let rolled_back = rolled_back(command, &files)?;
+    let keep = applied.len() - rolled_back.len();
+    // read them all up front so a missing down migration doesn't stop partway
+    let downs = rolled_back
+        .iter()
+        .map(|migration| read_down(migration))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    for (i, (sql, transaction)) in downs.iter().enumerate().rev() {
+        let (migration, version) = &applied[keep + i];
+        let previous = (keep + i)
+            .checked_sub(1)
+            .map(|previous| applied[previous].0.version());
+        debug!("rolling back {}", migration.name);
+        runner
+            .rollback(version, previous, sql, *transaction)
+            .with_context(|| format!("rolling back {}", migration.name))?;
+        info!("rolled back {}", migration.name);
+    }
+    if rolled_back.is_empty() {
+        info!("no applied migrations to roll back");
+    }
+    Ok(())
+}
+
+/// the last of `migrations` that `command` rolls back (`--steps`, `--to`, or just the last one)
+fn rolled_back<'a>(
+    command: &RollbackCommand,
+    migrations: &'a [&'a MigrationFile],
+) -> anyhow::Result<&'a [&'a MigrationFile]> {
+    let keep = match (&command.to, command.steps) {
+        (Some(version), _) => {
+            migrations
+                .iter()
+                .position(|m| m.version() == version || m.name == *version)
+                .ok_or_else(|| anyhow!("no migration found for version {version}"))?
+                + 1
+        }
+        (None, steps) => migrations.len().saturating_sub(steps.unwrap_or(1)),
+    };
+    Ok(&migrations[keep..])
+}
+
+/// the down migration of `migration` along with whether it can run in a transaction
+fn read_down(migration: &MigrationFile) -> anyhow::Result<(String, bool)> {
+    let down = migration.down.as_ref().unwrap_or(&migration.up);
+    debug!("reading {down}");
+    let sql = migration
+        .read_down()?
+        .ok_or_else(|| anyhow!("{} has no down migration", migration.up))?;
+    let transaction = match migration.down {
+        Some(_) => migrations::runs_in_transaction(&sql),
+        None => migrations::down_runs_in_transaction(&fs::read_to_string(&migration.up)?),
+    };
+    Ok((sql, transaction))
+}
+
+/// reformat SQL files the same way generated migrations and schema files are formatted
+fn run_fmt(command: FmtCommand, config: &Config) -> anyhow::Result<()> {
+    let mut format = config.format.clone();
+    if let Some(indent) = command.indent {
+        format.indent = Some(indent);
+        format.tabs = false;
+    }
+    format.tabs |= command.tabs;
+    if command.uppercase || command.lowercase {
+        format.uppercase = Some(command.uppercase);
+    }
+    format.lines_between = command.lines_between.or(format.lines_between);
+    let format = format.options();
+
+    let paths = if command.paths.is_empty() {
+        [DEFAULT_SCHEMA_PATH, DEFAULT_MIGRATIONS_DIR]
+            .into_iter()
+            .map(Utf8PathBuf::from)
+            .filter(|path| path.exists())
+            .collect()
+    } else {
+        command.paths.clone()
+    };
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(load::sql_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files.dedup();
+
+    let unformatted = match_dialect!(&command.dialect, |dialect| fmt_files(
+        dialect,
+        &files,
+        format,
+        command.check
+    ))?;
+    if command.check && unformatted > 0 {
+        return Err(anyhow!("{unformatted} file(s) aren't formatted"));
+    }
+    Ok(())
+}
+
+/// formats each file, returning how many weren't already formatted
+fn fmt_files<Dialect>(
+    dialect: Dialect,
+    files: &[Utf8PathBuf],
+    format: FormatOptions,
+    check: bool,
+) -> anyhow::Result<usize>
+where
+    Dialect: sql_schema::Parse + Render + Clone,
+{
+    let mut unformatted = 0;
+    for path in files {
+        if fmt_file(dialect.clone(), path, format, check).context(format!("path: {path}"))? {
+            unformatted += 1;
+        }
+    }
+    Ok(unformatted)
+}
+
+/// formats a single file (or only reports it with `check`), returning whether it changed
+///
+/// leading comments (e.g. a migration header) are kept, but files with comments anywhere else are
+/// skipped since they would be lost
+fn fmt_file<Dialect>(
+    dialect: Dialect,
+    path: &Utf8Path,
+    format: FormatOptions,
+    check: bool,
+) -> anyhow::Result<bool>
+where
+    Dialect: sql_schema::Parse + Render,
+{
+    let contents = fs::read_to_string(path)?;
+    let body_start = contents
+        .lines()
+        .take_while(|line| line.trim().is_empty() || line.trim_start().starts_with("--"))
+        .map(|line| line.len() + 1)
+        .sum::<usize>()
+        .min(contents.len());
+    let (header, body) = contents.split_at(body_start);
+    if body.contains("--") || body.contains("/*") {
+        warn!("skipping {path} (formatting would remove its comments)");
+        return Ok(false);
+    }
+
+    let tree = SyntaxTree::parse(dialect, body)?.with_format(format);
+    let mut formatted = tree.to_sql();
+    if formatted.is_empty() {
+        return Ok(false);
+    }
+    if !header.trim().is_empty() {
+        formatted = format!("{}\n\n{formatted}", header.trim_end());
+    }
+    if contents.ends_with('\n') {
+        formatted.push('\n');
+    }
+    if formatted == contents {
+        return Ok(false);
+    }
+    if check {
+        warn!("{path} isn't formatted");
+    } else {
+        info!("formatting {path}");
+        fs::write(path, formatted)?;
+    }
+    Ok(true)
+}
+
+/// report problems in the migrations dir, using the most recent migration (or the compat profile)
+/// as the convention
+fn run_validate(command: ValidateCommand, config: &Config) -> anyhow::Result<()> {
+    let dir = &command.migrations_dir;
+    let migrations = find_migrations(dir, find_options())?;
+    let opts = MigrationOptions::detect(dir, &migrations)?;
+    // with an explicit template or a compat profile, that's the convention to follow
+    let convention = match (&config.migration.path_template, config.migration.compat) {
+        (Some(template), _) => format!("the path template {template}"),
+        (None, Some(compat)) => format!("{compat}'s naming"),
+        (None, None) => "the naming of the most recent migration".to_owned(),
+    };
+    let opts = opts.with_config(config)?;
+    let mut problems = Vec::new();
+
+    let mut versions: HashMap<&str, Vec<&Utf8Path>> = HashMap::new();
+    for migration in &migrations {
+        versions
+            .entry(migration.version())
+            .or_default()
+            .push(&migration.name);
+    }
+    for migration in &migrations {
+        if let Some(names) = versions.remove(migration.version()) {
+            if names.len() > 1 {
+                let names = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+                problems.push(format!(
+                    "version {} is used by more than one migration: {}",
+                    migration.version(),
+                    names.join(", ")
+                ));
+            }
+        }
+    }
+
+    let mut last_counter: Option<(usize, &Utf8Path)> = None;
+    for migration in &migrations {
+        let path = migration.up.strip_prefix(dir)?;
+        let counter = PathTemplate::parse_within(path.as_str(), find_options().date_bounds)
+            .ok()
+            .and_then(|t| t.template_data().ok())
+            .and_then(|data| data.counter);
+        if let (Some(counter), Some((last, last_name))) = (counter, last_counter) {
+            if counter > last + 1 {
+                problems.push(format!(
+                    "{} skips from {last} to {counter} (after {last_name})",
+                    migration.name
+                ));
+            }
+        }
+        last_counter = counter.map(|counter| (counter, migration.name.as_path()));
+    }
+
+    // repeatable migrations don't have down migrations or follow the versioned naming
+    let versioned = migrations
+        .iter()
+        .filter(|m| !m.is_repeatable())
+        .collect::<Vec<_>>();
+
+    if opts.include_down {
+        for migration in &versioned {
+            if migration.read_down()?.is_none() {
+                problems.push(format!("{} has no down migration", migration.up));
+            }
+        }
+    }
+
+    // a path matches the convention when resolving the latest migration's template with the
+    // path's own data reproduces it
+    for path in versioned
+        .iter()
+        .flat_map(|m| [Some(&m.up), m.down.as_ref()])
+        .flatten()
+    {
+        let rel = path.strip_prefix(dir)?;
+        let data = PathTemplate::parse_within(rel.as_str(), find_options().date_bounds)
+            .ok()
+            .and_then(|t| t.template_data().ok());
+        if data.is_none_or(|data| opts.path_template.resolve(&data) != rel.as_str()) {
+            problems.push(format!("{path} doesn't follow {convention}"));
+        }
+    }
+
+    if problems.is_empty() {
+        info!("{} migrations in {dir} look good", migrations.len());
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{problem}");
+    }
+    Err(anyhow!("found {} problem(s) in {dir}", problems.len()))
+}
+
+/// print the dependency graph (or entity-relationship diagram) of the schema file
+fn run_graph(command: GraphCommand) -> anyhow::Result<()> {
+    let rendered = match_dialect!(&command.dialect, |dialect| parse_schema_file(
+        dialect,
+        &command.schema_path,
+        Some(&command.migrations_dir),
+        false,
+    )
+    .map(|schema| match (command.format, command.erd) {
+        (GraphFormat::Dot, false) => schema.graph().to_dot(),
+        (GraphFormat::Mermaid, false) => schema.graph().to_mermaid(),
+        (GraphFormat::Dot, true) => schema.to_dot_erd(),
+        (GraphFormat::Mermaid, true) => schema.to_mermaid_erd(),
+    }))?;
+    println!("{rendered}");
+    Ok(())
+}
+
+/// print model definitions generated from the schema file
+#[cfg(feature = "codegen")]
+fn run_codegen(command: CodegenCommand) -> anyhow::Result<()> {
+    let schema = match_dialect!(&command.dialect, |dialect| parse_schema_file(
+        dialect,
+        &command.schema_path,
+        Some(&command.migrations_dir),
+        false,
+    )
+    .map(|schema| schema.schema()))?;
+    match command.lang {
+        CodegenLang::Rust => {
+            let mut options = RustOptions::default();
+            options.derives = command.derives;
+            print!("{}", codegen::rust(&schema, &options));
+        }
+    }
+    Ok(())
+}
+
+/// print the schema as Atlas HCL, warning about what's left out
+#[cfg(feature = "atlas")]
+fn run_atlas(command: AtlasCommand) -> anyhow::Result<()> {
+    let (hcl, left_out) = match_dialect!(&command.dialect, |dialect| -> anyhow::Result<_> {
+        let schema = parse_schema_file(
+            dialect,
+            &command.schema_path,
+            Some(&command.migrations_dir),
+            false,
+        )?;
+        Ok(atlas::to_hcl(&schema)?)
+    })?;
+    for change in &left_out {
+        warn!(
+            "left out {} {} {}: Atlas's HCL has no equivalent",
+            change.operation, change.object, change.name
+        );
+    }
+    print!("{hcl}");
+    Ok(())
+}
+
+/// parse every seed file, reporting the ones that don't parse or that change the schema
+fn run_seed(command: SeedCommand) -> anyhow::Result<()> {
+    let dir = &command.seeds_dir;
+    let files = load::sql_files(dir)?;
+    let mut problems = Vec::new();
+    for path in &files {
+        let seed = match_dialect!(&command.dialect, |dialect| -> anyhow::Result<_> {
+            Ok(SyntaxTree::from_file(dialect, path)?.changes())
+        });
+        match seed {
+            Ok(changes) => problems.extend(
+                changes
+                    .iter()
+                    .filter(|change| change.operation != Operation::Other)
+                    .map(|change| {
+                        format!(
+                            "{path} should only contain data, but it would {} {} {}",
+                            change.operation, change.object, change.name
+                        )
+                    }),
+            ),
+            Err(err) => problems.push(format!("{err:#}")),
+        }
+    }
+
+    if problems.is_empty() {
+        info!("{} seed files in {dir} look good", files.len());
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{problem}");
+    }
+    Err(anyhow!("found {} problem(s) in {dir}", problems.len()))
+}
+
+/// print the schema converted to another dialect, warning about the statements left out
+fn run_transpile(command: TranspileCommand) -> anyhow::Result<()> {
+    match_dialect!(&command.from, |from| match_dialect!(&command.to, |to| {
+        run_transpile_inner(from, to, &command.path)
+    }))
+}
+
+fn run_transpile_inner<From, To>(from: From, to: To, path: &Utf8Path) -> anyhow::Result<()>
+where
+    From: sql_schema::Parse + Clone,
+    To: Transpile + Render,
+{
+    let schema = parse_schema_file(from, path, None, false)?;
+    let (schema, untranslatable) = schema.transpile(to);
+    for Untranslatable { change, reason } in &untranslatable {
+        warn!(
+            "left out {} {} {}: {reason}",
+            change.operation, change.object, change.name
+        );
+    }
+    println!("{}", schema.to_sql());
+    Ok(())
+}
+
+/// write the schema files as one schema, listing every object that's created more than once
+fn run_merge(command: MergeCommand, config: &Config) -> anyhow::Result<()> {
+    let mut files = Vec::new();
+    for path in &command.paths {
+        if path.is_dir() {
+            files.extend(schema_files(path, Some(&command.migrations_dir))?);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    let format = config.format.options();
+    let schema = match_dialect!(&command.dialect, |dialect| {
+        SyntaxTree::merge_files(dialect, &files).map(|schema| schema.with_format(format).to_sql())
+    });
+    if let Err(load::LoadError::Conflicts(conflicts)) = &schema {
+        for conflict in conflicts {
+            error!("{conflict}");
+        }
+    }
+    write_schema(&schema?, &command.output)
+}
+
+/// print a completion script for the given shell
+fn run_completions(command: CompletionsCommand) -> anyhow::Result<()> {
+    let mut cmd = Args::command();
+    let name = cmd.get_name().to_owned();
+    clap_complete::generate(command.shell, &mut cmd, name, &mut io::stdout());
+    Ok(())
+}
+
+fn write_migration(
+    migration: impl fmt::Display,
+    path: &Utf8Path,
+    header: Option<&str>,
+) -> anyhow::Result<()> {
+    info!("writing {path}");
+    if let Some(parent) = path.parent() {
+        debug!("creating {parent}");
+        ensure_migration_dir(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    if let Some(header) = header {
+        writeln!(file, "{}\n", header.trim_end())?;
+    }
+    file.write_all(migration.to_string().as_bytes())?;
+    // make sure it's on disk before anything that relies on it (e.g. squash removing the
+    // migrations it replaces)
+    file.sync_all()?;
+    Ok(())
+}
+
+/// runs the `post_migration` hook with the paths of the migration files that were just written
+///
+/// `{path}` runs the hook once per file, `{paths}` once with every file
+fn run_post_migration_hook(
+    config: &Config,
+    up_path: &Utf8Path,
+    down_path: Option<&Utf8Path>,
+) -> anyhow::Result<()> {
+    let Some(hook) = &config.hooks.post_migration else {
+        return Ok(());
+    };
+    let paths = [Some(up_path), down_path]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    let commands = if hook.contains("{path}") {
+        paths
+            .iter()
+            .map(|path| hook.replace("{path}", &shell_quote(path.as_str())))
+            .collect()
+    } else {
+        let quoted = paths.iter().map(|path| shell_quote(path.as_str()));
+        vec![hook.replace("{paths}", &quoted.collect::<Vec<_>>().join(" "))]
+    };
+    for command in commands {
+        info!("running {command}");
+        let status = shell(&command)
+            .status()
+            .context(format!("hook: {command}"))?;
+        if !status.success() {
+            return Err(anyhow!("hook `{command}` failed ({status})"));
+        }
+    }
+    Ok(())
+}
+
+/// the user's editor from `$VISUAL` or `$EDITOR`
+fn editor() -> anyhow::Result<String> {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .find_map(|var| {
+            env::var(var)
+                .ok()
+                .filter(|editor| !editor.trim().is_empty())
+        })
+        .ok_or_else(|| anyhow!("--edit requires $VISUAL or $EDITOR to be set"))
+}
+
+/// opens the migration files in the editor, waiting for it to exit
+fn edit_migration(editor: &str, paths: &[Utf8PathBuf]) -> anyhow::Result<()> {
+    // the editor may include arguments (e.g. `code --wait`)
+    let paths = paths
+        .iter()
+        .map(|path| shell_quote(path.as_str()))
+        .collect::<Vec<_>>();
+    let command = format!("{editor} {}", paths.join(" "));
+    debug!("running {command}");
+    let status = shell(&command)
+        .status()
+        .context(format!("editor: {editor}"))?;
+    if !status.success() {
+        return Err(anyhow!("`{editor}` failed ({status})"));
+    }
+    Ok(())
+}
+
+/// a command that runs `command` through the platform's shell
+fn shell(command: &str) -> process::Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
+
+fn shell_quote(s: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{s}\"")
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+/// fills in the placeholders of a migration header template (unknown placeholders are left as-is),
+/// with `timestamp` being the one the migration is named with
+fn render_header(
+    template: &str,
+    name: &str,
+    schema: &str,
+    timestamp: DateTime<FixedOffset>,
+) -> String {
+    use sha2::{Digest, Sha256};
+
+    template
+        .replace("{timestamp}", &timestamp.to_rfc3339())
+        .replace("{version}", env!("CARGO_PKG_VERSION"))
+        .replace("{name}", name)
+        .replace(
+            "{schema_hash}",
+            &format!("{:x}", Sha256::digest(schema.as_bytes())),
+        )
+}
+
+/// writes the schema file, or prints the schema when `path` is `-`
+fn write_schema(schema: &str, path: &Utf8Path) -> anyhow::Result<()> {
+    if path == "-" {
+        println!("{schema}");
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    info!("writing {path}");
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?
+        .write_all(schema.as_bytes())?;
+    Ok(())
+}
+
+fn ensure_schema_file(path: &Utf8Path) -> anyhow::Result<()> {
+    if !path.try_exists()? {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        info!("creating {path}");
+        File::create(path)?;
+    }
+    let meta = fs::metadata(path)?;
+    if !meta.is_file() && !meta.is_dir() {
+        return Err(anyhow!("schema path must be a file or directory"));
+    }
+    Ok(())
+}
+
+fn ensure_migration_dir(dir: &Utf8Path) -> anyhow::Result<()> {
+    if !dir.try_exists()? {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// loads a schema from a SQL file, a migrations directory, or stdin (`-`)
+fn load_schema<Dialect>(
+    dialect: Dialect,
+    path: &Utf8Path,
+    lenient: bool,
+) -> anyhow::Result<SyntaxTree<Dialect>>
+where
+    Dialect: TreeDiffer + TreeMigrator + sql_schema::Parse + Send + Sync,
+{
+    if path.is_dir() {
+        let (tree, _) = parse_migrations(dialect, path)?;
+        return Ok(tree);
+    }
+    parse_schema_file(dialect, path, None, lenient)
+}
+
+/// parses the schema file (or every file in the schema dir), or stdin when `path` is `-`
+fn parse_schema_file<Dialect>(
+    dialect: Dialect,
+    path: &Utf8Path,
+    migrations_dir: Option<&Utf8Path>,
+    lenient: bool,
+) -> anyhow::Result<SyntaxTree<Dialect>>
+where
+    Dialect: sql_schema::Parse + Clone,
+{
+    if path.extension() == Some("hcl") {
+        #[cfg(feature = "atlas")]
+        {
+            let hcl = fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+            return atlas::from_hcl(dialect, &hcl).with_context(|| format!("path: {path}"));
+        }
+        #[cfg(not(feature = "atlas"))]
+        return Err(anyhow!(
+            "can't read {path}: sql-schema was built without Atlas HCL support (enable the `atlas` feature)"
+        ));
+    }
+    if lenient {
+        return parse_schema_file_lenient(dialect, path, migrations_dir);
+    }
+    if path == "-" {
+        let data = without_pg_dump_boilerplate(io::read_to_string(io::stdin())?);
+        return SyntaxTree::parse(dialect, data.as_str()).context("path: -");
+    }
+    if path.is_dir() {
+        let files = schema_files(path, migrations_dir)?;
+        return Ok(SyntaxTree::parse_files(dialect, files)?);
+    }
+    let sql = fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+    if pg_dump::is_dump(&sql) {
+        let sql = pg_dump::normalize(&sql);
+        return SyntaxTree::parse(dialect, sql.as_str()).with_context(|| format!("path: {path}"));
+    }
+    Ok(SyntaxTree::from_file(dialect, path)?)
+}
+
+/// leaves out the settings, ownership, etc. when `sql` is the output of `pg_dump --schema-only`
+fn without_pg_dump_boilerplate(sql: String) -> String {
+    match pg_dump::is_dump(&sql) {
+        true => pg_dump::normalize(&sql),
+        false => sql,
+    }
+}
+
+/// fails with each table, index, and type the schema creates more than once, since the diff would
+/// only compare one of them
+fn no_duplicates<Dialect>(schema: &SyntaxTree<Dialect>, path: &Utf8Path) -> anyhow::Result<()> {
+    let duplicates = schema.duplicates();
+    if duplicates.is_empty() {
+        return Ok(());
+    }
+    let duplicates = duplicates
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    Err(anyhow!(
+        "{path} creates the same object more than once:\n{}",
+        duplicates.join("\n")
+    ))
+}
+
+/// parses the schema like [parse_schema_file], leaving out (with a warning) the statements that
+/// can't be parsed or diffed
+fn parse_schema_file_lenient<Dialect>(
+    dialect: Dialect,
+    path: &Utf8Path,
+    migrations_dir: Option<&Utf8Path>,
+) -> anyhow::Result<SyntaxTree<Dialect>>
+where
+    Dialect: sql_schema::Parse + Clone,
+{
+    let sources = if path == "-" {
+        let sql = io::read_to_string(io::stdin())?;
+        vec![(None, without_pg_dump_boilerplate(sql))]
+    } else {
+        let files = match path.is_dir() {
+            true => schema_files(path, migrations_dir)?,
+            false => vec![path.to_owned()],
+        };
+        files
+            .into_iter()
+            .map(|path| {
+                let sql = fs::read_to_string(&path).with_context(|| format!("reading {path}"))?;
+                anyhow::Ok((Some(path), without_pg_dump_boilerplate(sql)))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+    let (mut schema, _) = SyntaxTree::parse_lenient(dialect.clone(), "");
+    for (path, sql) in sources {
+        let (tree, skipped) = SyntaxTree::parse_lenient(dialect.clone(), sql.as_str());
+        for skipped in skipped {
+            match &path {
+                Some(path) => warn!("left out a statement: {}", skipped.with_path(path)),
+                None => warn!("left out a statement: {skipped}"),
+            }
+        }
+        for statement in tree.into_statements() {
+            schema.push(statement);
+        }
+    }
+    Ok(schema)
+}
+
+/// finds the files in the schema dir, skipping the migrations and seeds dirs when they're nested
+/// inside (as they are by default)
+fn schema_files(
+    dir: &Utf8Path,
+    migrations_dir: Option<&Utf8Path>,
+) -> anyhow::Result<Vec<Utf8PathBuf>> {
+    let mut files = load::sql_files(dir)?;
+    files.retain(|path| {
+        !path
+            .strip_prefix(dir)
+            .is_ok_and(|rel| rel.components().any(|c| c.as_str() == SEEDS_DIR_NAME))
+    });
+    let Some(migrations_dir) = migrations_dir.and_then(|dir| dir.canonicalize_utf8().ok()) else {
+        return Ok(files);
+    };
+    let mut schema_files = Vec::with_capacity(files.len());
+    for path in files {
+        if !path.canonicalize_utf8()?.starts_with(&migrations_dir) {
+            schema_files.push(path);
+        }
+    }
+    Ok(schema_files)
+}
+
+/// the numeric version a migration name starts with (e.g. a timestamp or counter), including
+/// dashes between the digits (e.g. diesel's `2024-01-01-000000`)
+///
+/// the name of a migration dir is used for its `up.sql` and `down.sql`
+fn version_number(path: &Utf8Path) -> Option<&str> {
+    let stem = path.file_stem()?;
+    let name = if ["up", "down", "do", "undo"]
+        .iter()
+        .any(|word| stem.eq_ignore_ascii_case(word))
+    {
+        path.parent()?.file_name()?
+    } else {
+        path.file_name()?
+    };
+    let end = name
+        .find(|c: char| !c.is_ascii_digit() && c != '-')
+        .unwrap_or(name.len());
+    let version = name[..end].trim_end_matches('-');
+    (!version.is_empty()).then_some(version)
+}
+
+/// builds a [SyntaxTree] by applying each migration in order
+fn parse_migrations<Dialect>(
+    dialect: Dialect,
+    dir: &Utf8Path,
+) -> anyhow::Result<(SyntaxTree<Dialect>, MigrationOptions)>
+where
+    Dialect: TreeDiffer + TreeMigrator + sql_schema::Parse + Send + Sync,
+{
+    let migrations = find_migrations(dir, find_options())?;
+    let opts = MigrationOptions::detect(dir, &migrations)?;
+    let paths = migrations
+        .iter()
+        .map(|m| m.up.as_path())
+        .collect::<Vec<_>>();
+    let tree = SnapshotCache::default().migrate(dialect, dir, &paths)?;
+    Ok((tree, opts))
+}
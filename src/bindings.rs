@@ -0,0 +1,82 @@
+//! what the JS and Python bindings share: picking a dialect by name and returning SQL
+
+use std::error::Error;
+
+use crate::{Parse, Render, SyntaxTree, TreeDiffer, TreeMigrator};
+
+pub(crate) type Result<T, E = Box<dyn Error>> = std::result::Result<T, E>;
+
+/// calls `$f` with the dialect named `$dialect` (`generic`, `postgresql`, `sqlite`, `mysql`, or
+/// `mssql`, or an alias [Dialect](crate::dialect::Dialect) accepts)
+macro_rules! with_dialect {
+    ( $dialect:expr, $f:expr ) => {
+        match $dialect.parse::<$crate::dialect::Dialect>() {
+            Ok($crate::dialect::Dialect::Generic) => $f($crate::dialect::Generic),
+            Ok($crate::dialect::Dialect::PostgreSql) => $f($crate::dialect::PostgreSQL),
+            Ok($crate::dialect::Dialect::SQLite) => $f($crate::dialect::SQLite),
+            Ok($crate::dialect::Dialect::MySql) => $f($crate::dialect::MySQL),
+            Ok($crate::dialect::Dialect::MsSql) => $f($crate::dialect::MsSQL),
+            Err(err) => Err(err.into()),
+        }
+    };
+}
+pub(crate) use with_dialect;
+
+/// the schema, formatted
+pub(crate) fn parse<Dialect: Parse + Render>(dialect: Dialect, sql: &str) -> Result<String> {
+    Ok(SyntaxTree::parse(dialect, sql)?.to_sql())
+}
+
+/// the migration from schema `a` to schema `b`, or `None` when they're the same
+pub(crate) fn diff<Dialect>(dialect: Dialect, a: &str, b: &str) -> Result<Option<String>>
+where
+    Dialect: Parse + TreeDiffer + Render,
+{
+    let a = SyntaxTree::parse(dialect.clone(), a)?;
+    let b = SyntaxTree::parse(dialect, b)?;
+    Ok(a.diff(&b)?.map(|diff| diff.to_sql()))
+}
+
+/// the schema after applying `migration` to `schema`
+pub(crate) fn migrate<Dialect>(dialect: Dialect, schema: &str, migration: &str) -> Result<String>
+where
+    Dialect: Parse + TreeMigrator + Render,
+{
+    let schema = SyntaxTree::parse(dialect.clone(), schema)?;
+    let migration = SyntaxTree::parse(dialect, migration)?;
+    Ok(schema.migrate(&migration)?.to_sql())
+}
+
+/// a name describing `migration`, e.g. `create_users` (only the Python bindings have this)
+#[cfg(any(feature = "python", test))]
+pub(crate) fn name<Dialect: Parse>(
+    dialect: Dialect,
+    migration: &str,
+    max_len: Option<usize>,
+) -> Result<Option<String>> {
+    let migration = SyntaxTree::parse(dialect, migration)?;
+    Ok(crate::name_gen::generate_name(&migration)
+        .maybe_max_len(max_len)
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn by_dialect_name() {
+        let a = "CREATE TABLE users (id INT);";
+        let b = "CREATE TABLE users (id INT, name TEXT);";
+        let diff = with_dialect!("postgresql", |dialect| diff(dialect, a, b))
+            .unwrap()
+            .unwrap();
+        assert_eq!(diff, "ALTER TABLE\n  users\nADD\n  COLUMN name TEXT;");
+        let schema = with_dialect!("postgresql", |dialect| migrate(dialect, a, &diff)).unwrap();
+        assert_eq!(schema, "CREATE TABLE users (id INT, name TEXT);");
+        let name = with_dialect!("sqlite", |dialect| name(dialect, &diff, None)).unwrap();
+        assert_eq!(name.as_deref(), Some("alter_users_add_name"));
+        let err = with_dialect!("oracle", |dialect| parse(dialect, a)).unwrap_err();
+        assert_eq!(err.to_string(), "Oops, we don't know the oracle dialect");
+    }
+}
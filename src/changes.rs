@@ -0,0 +1,677 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    ast::{
+        AlterColumnOperation, AlterTable, AlterTableOperation, AlterType, AlterTypeOperation,
+        CreateDomain, CreateExtension, CreateFunction, CreateIndex, CreateTable, CreateView,
+        DropDomain, DropExtension, DropFunction, ObjectType, Statement,
+    },
+    diff::generic::statement::index_name,
+    graph::Graph,
+    provenance,
+    schema::Index,
+    DiffError, FormatOptions, MigrateError, SyntaxTree, TreeDiffer, TreeMigrator,
+};
+
+/// a change that can't be reverted by a generated down migration
+#[derive(Debug)]
+pub struct IrreversibleChange {
+    pub change: Change,
+    /// why the down migration couldn't be generated
+    pub reason: DiffError,
+}
+
+/// a single statement of a migration, described for tooling
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Change {
+    pub object: ObjectKind,
+    pub name: String,
+    pub operation: Operation,
+    /// the table an index is on
+    pub table: Option<String>,
+    /// true when applying the change can lose data
+    pub destructive: bool,
+    pub sql: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum ObjectKind {
+    Table,
+    View,
+    Index,
+    Type,
+    Extension,
+    Domain,
+    Function,
+    Other,
+}
+
+impl fmt::Display for ObjectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Table => "table",
+            Self::View => "view",
+            Self::Index => "index",
+            Self::Type => "type",
+            Self::Extension => "extension",
+            Self::Domain => "domain",
+            Self::Function => "function",
+            Self::Other => "other",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Operation {
+    Create,
+    Alter,
+    Drop,
+    Other,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Create => "create",
+            Self::Alter => "alter",
+            Self::Drop => "drop",
+            Self::Other => "other",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl ObjectKind {
+    /// where objects of this kind go when a schema is grouped by kind (see
+    /// [SyntaxTree::sort_by_kind]): what other objects can depend on comes first
+    fn rank(self) -> u8 {
+        match self {
+            Self::Extension => 0,
+            Self::Type => 1,
+            Self::Domain => 2,
+            // before the tables whose defaults and checks call them
+            Self::Function => 3,
+            Self::Table => 4,
+            Self::View => 5,
+            Self::Index => 6,
+            Self::Other => 7,
+        }
+    }
+}
+
+/// how [SyntaxTree::sort] orders the statements of a schema
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Order {
+    /// the order they're in (for a schema built from migrations, the order they were applied in)
+    #[default]
+    Migration,
+    /// by the name of the object, with indexes right after the table they're on
+    Alphabetical,
+    /// extensions, types, and domains first, then tables and views (each after the ones it
+    /// depends on), then indexes and everything else
+    Dependency,
+}
+
+/// how many of each kind of change a migration makes, to review it at a glance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChangeSummary {
+    pub tables_added: usize,
+    /// tables altered in place (counted once however many statements alter them)
+    pub tables_altered: usize,
+    pub tables_dropped: usize,
+    pub columns_added: usize,
+    pub columns_dropped: usize,
+    /// columns renamed or whose type, default, nullability, etc. changed
+    pub columns_altered: usize,
+    /// indexes created or dropped
+    pub indexes_changed: usize,
+    /// statements changing anything else (views, types, extensions, etc.)
+    pub other: usize,
+    /// statements that can lose data (see [Change::destructive])
+    pub destructive: usize,
+}
+
+impl ChangeSummary {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for ChangeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = [
+            (self.tables_added, "table added", "tables added"),
+            (self.tables_altered, "table changed", "tables changed"),
+            (self.tables_dropped, "table dropped", "tables dropped"),
+            (self.columns_added, "column added", "columns added"),
+            (self.columns_dropped, "column dropped", "columns dropped"),
+            (self.columns_altered, "column changed", "columns changed"),
+            (self.indexes_changed, "index changed", "indexes changed"),
+            (self.other, "other change", "other changes"),
+            (
+                self.destructive,
+                "destructive operation",
+                "destructive operations",
+            ),
+        ];
+        let parts = counts
+            .into_iter()
+            .filter(|(n, ..)| *n > 0)
+            .map(|(n, one, many)| format!("{n} {}", if n == 1 { one } else { many }))
+            .collect::<Vec<_>>();
+        if parts.is_empty() {
+            return write!(f, "no changes");
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// describes each statement in the tree (usually the result of [SyntaxTree::diff])
+    pub fn changes(&self) -> Vec<Change> {
+        self.tree
+            .iter()
+            .map(|s| classify(s, &self.format))
+            .collect()
+    }
+
+    /// counts the changes the tree (usually the result of [SyntaxTree::diff]) makes by kind
+    pub fn summary(&self) -> ChangeSummary {
+        let mut summary = ChangeSummary::default();
+        let mut altered = HashSet::new();
+        for s in &self.tree {
+            let change = classify(s, &self.format);
+            summary.destructive += usize::from(change.destructive);
+            match (change.object, change.operation) {
+                (ObjectKind::Table, Operation::Create) => summary.tables_added += 1,
+                (ObjectKind::Table, Operation::Drop) => summary.tables_dropped += 1,
+                (ObjectKind::Table, Operation::Alter) => {
+                    altered.insert(change.name);
+                }
+                (ObjectKind::Index, _) => summary.indexes_changed += 1,
+                _ => summary.other += 1,
+            }
+            let Statement::AlterTable(AlterTable { operations, .. }) = s else {
+                continue;
+            };
+            for op in operations {
+                match op {
+                    AlterTableOperation::AddColumn { .. } => summary.columns_added += 1,
+                    AlterTableOperation::DropColumn { column_names, .. } => {
+                        summary.columns_dropped += column_names.len()
+                    }
+                    AlterTableOperation::AlterColumn { .. }
+                    | AlterTableOperation::RenameColumn { .. }
+                    | AlterTableOperation::ChangeColumn { .. }
+                    | AlterTableOperation::ModifyColumn { .. } => summary.columns_altered += 1,
+                    _ => {}
+                }
+            }
+        }
+        summary.tables_altered = altered.len();
+        summary
+    }
+
+    /// reorders the statements (and their provenance), e.g. before writing a schema file
+    pub fn sort(&mut self, order: Order) {
+        let changes = self.changes();
+        let order = match order {
+            Order::Migration => return,
+            Order::Alphabetical => {
+                let key = |name: &str| name.replace(['"', '`'], "").to_lowercase();
+                let mut order = (0..changes.len()).collect::<Vec<_>>();
+                order.sort_by_cached_key(|&i| match (changes[i].object, &changes[i].table) {
+                    (ObjectKind::Index, Some(table)) => (key(table), 1, key(&changes[i].name)),
+                    _ => (key(&changes[i].name), 0, String::new()),
+                });
+                order
+            }
+            Order::Dependency => dependency_order(&changes, &self.graph()),
+        };
+        self.reorder(order);
+    }
+
+    /// groups the statements by kind (extensions, types, domains, tables, views, indexes, then
+    /// everything else), keeping the order they're in within each group
+    pub fn sort_by_kind(&mut self) {
+        let changes = self.changes();
+        let mut order = (0..changes.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| changes[i].object.rank());
+        self.reorder(order);
+    }
+
+    /// puts statement `order[i]` (and its provenance) at `i`
+    fn reorder(&mut self, order: Vec<usize>) {
+        let mut tree = std::mem::take(&mut self.tree)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        let mut provenance = std::mem::take(&mut self.provenance);
+        let known = !provenance.is_empty();
+        for i in order {
+            self.tree.extend(tree[i].take());
+            if known {
+                self.provenance.push(provenance[i].take());
+            }
+        }
+    }
+
+    /// keeps only the statements whose [Change] matches `f`
+    pub fn retain(&mut self, mut f: impl FnMut(&Change) -> bool) {
+        let format = self.format;
+        self.retain_with_provenance(|s| f(&classify(s, &format)));
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect>
+where
+    Dialect: TreeDiffer + TreeMigrator,
+{
+    /// the changes in `migration` that a down migration can't revert, found by applying it to
+    /// this tree one statement at a time
+    pub fn irreversible_changes(
+        &self,
+        migration: &Self,
+    ) -> Result<Vec<IrreversibleChange>, MigrateError> {
+        let mut before = self.clone();
+        let mut irreversible = Vec::new();
+        for statement in migration.split() {
+            let after = before.clone().migrate(&statement)?;
+            if let Err(reason) = after.diff(&before) {
+                irreversible.push(IrreversibleChange {
+                    change: classify(&statement.tree[0], &self.format),
+                    reason,
+                });
+            }
+            before = after;
+        }
+        Ok(irreversible)
+    }
+}
+
+impl<Dialect: TreeDiffer> SyntaxTree<Dialect> {
+    /// the kind and name of the object each statement creates, in the same order as
+    /// [SyntaxTree::changes], or `None` for a statement that doesn't create one (e.g. an INSERT)
+    ///
+    /// names are written without quotes (e.g. `public.users` for `"public"."users"`), and an
+    /// unnamed index is given the name the database gives it (see [Unnamed]), or else its table
+    /// and columns (e.g. `users(email)`)
+    ///
+    /// [Unnamed]: crate::Unnamed
+    pub fn objects(&self) -> Vec<Option<(ObjectKind, String)>> {
+        self.tree
+            .iter()
+            .map(|s| match s {
+                Statement::CreateIndex(index @ CreateIndex { name: None, .. }) => {
+                    let name = match index_name(&self.dialect, index) {
+                        Some(name) => name.to_string().replace('"', ""),
+                        None => {
+                            let Index { table, columns, .. } = Index::from(index);
+                            format!("{table}({})", columns.join(", "))
+                        }
+                    };
+                    Some((ObjectKind::Index, name))
+                }
+                s => {
+                    let (kind, name) = provenance::object(s)?;
+                    Some((kind, name.replace('"', "")))
+                }
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn classify(s: &Statement, format: &FormatOptions) -> Change {
+    let mut table = None;
+    let (object, name, operation, destructive) = match s {
+        Statement::CreateTable(CreateTable { name, .. }) => (
+            ObjectKind::Table,
+            name.to_string(),
+            Operation::Create,
+            false,
+        ),
+        Statement::CreateView(CreateView { name, .. }) => {
+            (ObjectKind::View, name.to_string(), Operation::Create, false)
+        }
+        Statement::AlterTable(AlterTable {
+            name, operations, ..
+        }) => (
+            ObjectKind::Table,
+            name.to_string(),
+            Operation::Alter,
+            operations.iter().any(is_destructive_alter_table),
+        ),
+        Statement::CreateIndex(CreateIndex {
+            name, table_name, ..
+        }) => {
+            table = Some(table_name.to_string());
+            (
+                ObjectKind::Index,
+                name.as_ref().map(ToString::to_string).unwrap_or_default(),
+                Operation::Create,
+                false,
+            )
+        }
+        Statement::CreateType { name, .. } => {
+            (ObjectKind::Type, name.to_string(), Operation::Create, false)
+        }
+        Statement::AlterType(AlterType { name, operation }) => (
+            ObjectKind::Type,
+            name.to_string(),
+            Operation::Alter,
+            !matches!(
+                operation,
+                AlterTypeOperation::AddValue(_) | AlterTypeOperation::RenameValue(_)
+            ),
+        ),
+        Statement::CreateExtension(CreateExtension { name, .. }) => (
+            ObjectKind::Extension,
+            name.to_string(),
+            Operation::Create,
+            false,
+        ),
+        Statement::DropExtension(DropExtension { names, .. }) => (
+            ObjectKind::Extension,
+            join_names(names),
+            Operation::Drop,
+            true,
+        ),
+        Statement::CreateDomain(CreateDomain { name, .. }) => (
+            ObjectKind::Domain,
+            name.to_string(),
+            Operation::Create,
+            false,
+        ),
+        Statement::DropDomain(DropDomain { name, .. }) => {
+            (ObjectKind::Domain, name.to_string(), Operation::Drop, true)
+        }
+        Statement::CreateFunction(CreateFunction { name, .. }) => (
+            ObjectKind::Function,
+            name.to_string(),
+            Operation::Create,
+            false,
+        ),
+        Statement::DropFunction(DropFunction { func_desc, .. }) => {
+            let names = func_desc.iter().map(|f| &f.name).collect::<Vec<_>>();
+            (
+                ObjectKind::Function,
+                join_names(&names),
+                Operation::Drop,
+                false,
+            )
+        }
+        Statement::Drop {
+            object_type, names, ..
+        } => {
+            let object = match object_type {
+                ObjectType::Table => ObjectKind::Table,
+                ObjectType::View => ObjectKind::View,
+                ObjectType::Index => ObjectKind::Index,
+                ObjectType::Type => ObjectKind::Type,
+                _ => ObjectKind::Other,
+            };
+            // dropping an index or view loses no data
+            let destructive = !matches!(object, ObjectKind::Index | ObjectKind::View);
+            (object, join_names(names), Operation::Drop, destructive)
+        }
+        _ => (ObjectKind::Other, String::new(), Operation::Other, false),
+    };
+    Change {
+        object,
+        name,
+        operation,
+        table,
+        destructive,
+        sql: crate::format_statement(s, format),
+    }
+}
+
+/// the statements grouped by kind (see [SyntaxTree::sort_by_kind]), with each table and view
+/// after the ones it depends on (or where it was when they depend on each other)
+fn dependency_order(changes: &[Change], graph: &Graph) -> Vec<usize> {
+    let mut pending = (0..changes.len()).collect::<Vec<_>>();
+    pending.sort_by_key(|&i| changes[i].object.rank());
+    let created =
+        |i: usize, name: &str| changes[i].operation == Operation::Create && changes[i].name == name;
+    let mut order = Vec::with_capacity(pending.len());
+    while let Some(&first) = pending.first() {
+        let rank = changes[first].object.rank();
+        let ready = pending
+            .iter()
+            .take_while(|&&i| changes[i].object.rank() == rank)
+            .position(|&i| {
+                graph
+                    .edges
+                    .iter()
+                    .filter(|edge| created(i, &edge.from))
+                    .all(|edge| !pending.iter().any(|&j| j != i && created(j, &edge.to)))
+            })
+            .unwrap_or(0);
+        order.push(pending.remove(ready));
+    }
+    order
+}
+
+fn is_destructive_alter_table(op: &AlterTableOperation) -> bool {
+    match op {
+        AlterTableOperation::DropColumn { .. } => true,
+        // changing a column's type may truncate or fail to convert existing values
+        AlterTableOperation::AlterColumn {
+            op: AlterColumnOperation::SetDataType { .. },
+            ..
+        } => true,
+        _ => false,
+    }
+}
+
+fn join_names(names: &[impl fmt::Display]) -> String {
+    names
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::{Generic, PostgreSQL};
+
+    #[test]
+    fn sort() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE INDEX users_name_idx ON users (name); \
+            CREATE VIEW active AS SELECT * FROM users; \
+            CREATE TABLE posts (id INT, user_id INT REFERENCES users (id)); \
+            CREATE TABLE users (id INT, name TEXT, mood mood); \
+            CREATE TYPE mood AS ENUM ('ok');",
+        )
+        .unwrap();
+        let names = |tree: &SyntaxTree<Generic>| {
+            tree.changes()
+                .into_iter()
+                .map(|c| c.name)
+                .collect::<Vec<_>>()
+        };
+
+        let mut sorted = tree.clone();
+        sorted.sort(Order::Migration);
+        assert_eq!(sorted.statements(), tree.statements());
+
+        let mut sorted = tree.clone();
+        sorted.sort(Order::Alphabetical);
+        assert_eq!(
+            names(&sorted),
+            ["active", "mood", "posts", "users", "users_name_idx"]
+        );
+
+        let mut sorted = tree.clone();
+        sorted.sort(Order::Dependency);
+        assert_eq!(
+            names(&sorted),
+            ["mood", "users", "posts", "active", "users_name_idx"]
+        );
+        // the provenance moves along with the statements
+        assert_eq!(sorted.provenance(0).unwrap().index(), 4);
+
+        let mut sorted = tree.clone();
+        sorted.sort_by_kind();
+        assert_eq!(
+            names(&sorted),
+            ["mood", "posts", "users", "active", "users_name_idx"]
+        );
+    }
+
+    #[test]
+    fn classify_changes() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE foo (id INT); \
+            ALTER TABLE bar ADD COLUMN baz TEXT; \
+            ALTER TABLE bar DROP COLUMN qux; \
+            DROP INDEX bar_idx; \
+            DROP TABLE baz;",
+        )
+        .unwrap();
+        let actual = tree
+            .changes()
+            .into_iter()
+            .map(|c| (c.object, c.name, c.operation, c.destructive))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actual,
+            vec![
+                (
+                    ObjectKind::Table,
+                    "foo".to_owned(),
+                    Operation::Create,
+                    false
+                ),
+                (ObjectKind::Table, "bar".to_owned(), Operation::Alter, false),
+                (ObjectKind::Table, "bar".to_owned(), Operation::Alter, true),
+                (
+                    ObjectKind::Index,
+                    "bar_idx".to_owned(),
+                    Operation::Drop,
+                    false
+                ),
+                (ObjectKind::Table, "baz".to_owned(), Operation::Drop, true),
+            ]
+        );
+        assert_eq!(tree.changes()[0].sql, "CREATE TABLE foo (id INT);");
+    }
+
+    #[test]
+    fn objects() {
+        let sql = "CREATE TABLE \"public\".\"users\" (id INT, email TEXT); \
+            CREATE INDEX ON users (email); \
+            CREATE VIEW active AS SELECT id FROM users; \
+            CREATE FUNCTION one() RETURNS INT LANGUAGE SQL AS 'SELECT 1'; \
+            INSERT INTO users VALUES (1, 'a');";
+        let object = |kind, name: &str| Some((kind, name.to_owned()));
+        let tree = SyntaxTree::parse(PostgreSQL, sql).unwrap();
+        assert_eq!(
+            tree.objects(),
+            [
+                object(ObjectKind::Table, "public.users"),
+                object(ObjectKind::Index, "users_email_idx"),
+                object(ObjectKind::View, "active"),
+                object(ObjectKind::Function, "one"),
+                None,
+            ]
+        );
+        // an unnamed index is named for its table and columns when its default name isn't known
+        let tree = SyntaxTree::parse(Generic, sql).unwrap();
+        assert_eq!(tree.objects()[1], object(ObjectKind::Index, "users(email)"));
+    }
+
+    #[test]
+    fn summary() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE foo (id INT); \
+            ALTER TABLE bar ADD COLUMN baz TEXT; \
+            ALTER TABLE bar DROP COLUMN qux, ALTER COLUMN id SET NOT NULL; \
+            CREATE INDEX foo_id ON foo (id); \
+            CREATE TYPE status AS ENUM ('a'); \
+            DROP TABLE baz;",
+        )
+        .unwrap();
+        let summary = tree.summary();
+        assert_eq!(
+            summary,
+            ChangeSummary {
+                tables_added: 1,
+                tables_altered: 1,
+                tables_dropped: 1,
+                columns_added: 1,
+                columns_dropped: 1,
+                columns_altered: 1,
+                indexes_changed: 1,
+                other: 1,
+                destructive: 2,
+            }
+        );
+        assert_eq!(
+            summary.to_string(),
+            "1 table added, 1 table changed, 1 table dropped, 1 column added, 1 column dropped, \
+            1 column changed, 1 index changed, 1 other change, 2 destructive operations"
+        );
+        assert_eq!(ChangeSummary::default().to_string(), "no changes");
+    }
+
+    #[test]
+    fn retain_changes() {
+        let mut tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE foo (id INT); \
+            CREATE TABLE bar (id INT); \
+            CREATE INDEX bar_idx ON bar (id);",
+        )
+        .unwrap();
+        tree.retain(|c| c.name != "bar" && c.table.as_deref() != Some("bar"));
+        assert_eq!(tree.to_string(), "CREATE TABLE foo (id INT);");
+    }
+
+    #[test]
+    fn irreversible_changes() {
+        let tree = SyntaxTree::parse(Generic, "CREATE TYPE status AS ENUM ('a');").unwrap();
+        let migration = SyntaxTree::parse(
+            Generic,
+            "ALTER TYPE status ADD VALUE 'b'; \
+            CREATE TABLE foo (id INT);",
+        )
+        .unwrap();
+        let actual = tree
+            .irreversible_changes(&migration)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.change.object, c.change.name, c.reason.kind().to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actual,
+            vec![(
+                ObjectKind::Type,
+                "status".to_owned(),
+                "removing enum labels is not supported".to_owned()
+            )]
+        );
+    }
+}
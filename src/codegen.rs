@@ -0,0 +1,236 @@
+/*!
+Generate model definitions from a schema: a struct for each table and an enum for each enum type.
+
+Column types are mapped to the Rust types a database driver usually decodes them into (e.g.
+`TIMESTAMPTZ` to `chrono::DateTime<chrono::Utc>` and `UUID` to `uuid::Uuid`), nullable columns are
+wrapped in `Option`, and types that aren't known are mapped to `String` with a comment saying what
+they were.
+*/
+
+use std::fmt::Write;
+
+use crate::{
+    schema::{Column, Schema, Table, TypeKind},
+    validate::same_name,
+};
+
+/// how [rust] writes the structs and enums
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RustOptions {
+    /// derived by each struct and enum, along with `Debug`, `Clone`, and `PartialEq` (e.g.
+    /// `serde::Serialize`)
+    pub derives: Vec<String>,
+}
+
+/// the Rust structs for the tables of `schema` and enums for its enum types
+pub fn rust(schema: &Schema, options: &RustOptions) -> String {
+    let derives = |base: &[&str]| {
+        let derives = base
+            .iter()
+            .copied()
+            .chain(options.derives.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        format!("#[derive({})]", derives.join(", "))
+    };
+    let mut items = Vec::new();
+    for ty in &schema.types {
+        let TypeKind::Enum { labels } = &ty.kind else {
+            continue;
+        };
+        let mut item = derives(&["Debug", "Clone", "Copy", "PartialEq", "Eq", "Hash"]);
+        let _ = writeln!(item, "\npub enum {} {{", type_name(&ty.name));
+        for label in labels {
+            let _ = writeln!(item, "    {},", type_name(label));
+        }
+        item.push('}');
+        items.push(item);
+    }
+    for table in &schema.tables {
+        items.push(rust_struct(
+            schema,
+            table,
+            &derives(&["Debug", "Clone", "PartialEq"]),
+        ));
+    }
+    let mut out = items.join("\n\n");
+    out.push('\n');
+    out
+}
+
+fn rust_struct(schema: &Schema, table: &Table, derives: &str) -> String {
+    let mut item = format!("{derives}\npub struct {} {{\n", type_name(&table.name));
+    for column in &table.columns {
+        let (ty, known) = rust_type(schema, column);
+        let ty = match column.nullable {
+            true => format!("Option<{ty}>"),
+            false => ty,
+        };
+        let _ = write!(item, "    pub {}: {ty},", field_name(&column.name));
+        if !known {
+            let _ = write!(item, " // {}", column.data_type);
+        }
+        item.push('\n');
+    }
+    item.push('}');
+    item
+}
+
+/// the Rust type of `column`, and whether its SQL type was known
+fn rust_type(schema: &Schema, column: &Column) -> (String, bool) {
+    let enum_type = |name: &str| {
+        schema
+            .types
+            .iter()
+            .find(|ty| matches!(ty.kind, TypeKind::Enum { .. }) && same_name(&ty.name, name))
+    };
+    let mut data_type = column.data_type.as_str();
+    // domains are mapped like the type they're based on
+    if let Some(domain) = schema
+        .domains
+        .iter()
+        .find(|d| same_name(&d.name, data_type))
+    {
+        data_type = &domain.data_type;
+    }
+    if let Some(element) = data_type.strip_suffix("[]") {
+        let element = Column {
+            data_type: element.to_owned(),
+            nullable: false,
+            ..column.clone()
+        };
+        let (ty, known) = rust_type(schema, &element);
+        return (format!("Vec<{ty}>"), known);
+    }
+    if let Some(ty) = enum_type(data_type) {
+        return (type_name(&ty.name), true);
+    }
+    // the type without its length, precision, etc. (e.g. `VARCHAR` for `VARCHAR(10)`)
+    let base = data_type
+        .split_once('(')
+        .map_or(data_type, |(base, _)| base)
+        .trim()
+        .to_ascii_lowercase();
+    let ty = match base.as_str() {
+        "bool" | "boolean" => "bool",
+        "tinyint" | "int1" => "i8",
+        "smallint" | "int2" | "smallserial" | "serial2" => "i16",
+        "int" | "integer" | "int4" | "mediumint" | "serial" | "serial4" => "i32",
+        "bigint" | "int8" | "bigserial" | "serial8" => "i64",
+        "real" | "float4" => "f32",
+        "double" | "double precision" | "float" | "float8" => "f64",
+        "numeric" | "decimal" => "rust_decimal::Decimal",
+        "text" | "varchar" | "character varying" | "char" | "character" | "nvarchar" | "nchar"
+        | "clob" | "string" | "citext" => "String",
+        "bytea" | "blob" | "binary" | "varbinary" => "Vec<u8>",
+        "uuid" => "uuid::Uuid",
+        "json" | "jsonb" => "serde_json::Value",
+        "date" => "chrono::NaiveDate",
+        "time" | "time without time zone" => "chrono::NaiveTime",
+        "timestamp" | "timestamp without time zone" | "datetime" => "chrono::NaiveDateTime",
+        "timestamptz" | "timestamp with time zone" => "chrono::DateTime<chrono::Utc>",
+        _ => return ("String".to_owned(), false),
+    };
+    (ty.to_owned(), true)
+}
+
+/// `UpperCamelCase` of the last part of `name` (e.g. `UserAccounts` for `public.user_accounts`)
+fn type_name(name: &str) -> String {
+    let name = name.rsplit('.').next().unwrap_or(name);
+    let mut out = String::new();
+    let mut upper = true;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            upper = true;
+            continue;
+        }
+        match upper {
+            true => out.extend(c.to_uppercase()),
+            false => out.push(c),
+        }
+        upper = false;
+    }
+    identifier(out)
+}
+
+/// `snake_case` of `name` (e.g. `first_name` for `First Name`)
+fn field_name(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            out.extend(c.to_lowercase());
+        } else if !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    identifier(out)
+}
+
+/// `name` as a valid identifier (e.g. `r#type` for `type` and `_1` for `1`)
+fn identifier(name: String) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern",
+        "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use",
+        "where", "while", "abstract", "become", "box", "do", "final", "gen", "macro", "override",
+        "priv", "try", "typeof", "unsized", "virtual", "yield",
+    ];
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return format!("_{name}");
+    }
+    match name.as_str() {
+        "self" | "Self" | "super" | "crate" => format!("{name}_"),
+        _ if KEYWORDS.contains(&name.as_str()) => format!("r#{name}"),
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dialect::Generic, SyntaxTree};
+
+    #[test]
+    fn rust() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TYPE order_status AS ENUM ('pending', 'in_progress', 'shipped');
+            CREATE DOMAIN email AS TEXT;
+            CREATE TABLE public.user_accounts (
+                id UUID PRIMARY KEY,
+                email email NOT NULL,
+                \"Display Name\" VARCHAR(100),
+                created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                tags TEXT[] NOT NULL,
+                type order_status,
+                location POINT
+            );",
+        )
+        .unwrap();
+        let options = RustOptions {
+            derives: vec!["serde::Serialize".to_owned()],
+        };
+        assert_eq!(
+            super::rust(&tree.schema(), &options),
+            "\
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum OrderStatus {
+    Pending,
+    InProgress,
+    Shipped,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct UserAccounts {
+    pub id: uuid::Uuid,
+    pub email: String,
+    pub display_name: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub tags: Vec<String>,
+    pub r#type: Option<OrderStatus>,
+    pub location: Option<String>, // POINT
+}
+"
+        );
+    }
+}
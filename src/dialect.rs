@@ -1,3 +1,7 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+use thiserror::Error;
+
 use crate::sealed::Sealed;
 
 #[derive(Debug, Default, Clone)]
@@ -9,6 +13,319 @@ pub struct PostgreSQL;
 #[derive(Debug, Default, Clone)]
 pub struct SQLite;
 
+/// MySQL (and MariaDB)
+#[derive(Debug, Default, Clone)]
+pub struct MySQL;
+
+/// Microsoft SQL Server (T-SQL)
+#[derive(Debug, Default, Clone)]
+pub struct MsSQL;
+
+/// a dialect of your own (e.g. for a fork of PostgreSQL): SQL is parsed with the sqlparser dialect
+/// it's made from and otherwise treated like [Generic]
+#[derive(Debug, Clone)]
+pub struct Custom(Arc<dyn sqlparser::dialect::Dialect>);
+
+impl Custom {
+    pub fn new(dialect: impl sqlparser::dialect::Dialect) -> Self {
+        Self(Arc::new(dialect))
+    }
+
+    pub(crate) fn sqlparser_dialect(&self) -> &dyn sqlparser::dialect::Dialect {
+        self.0.as_ref()
+    }
+}
+
+impl From<Box<dyn sqlparser::dialect::Dialect>> for Custom {
+    fn from(dialect: Box<dyn sqlparser::dialect::Dialect>) -> Self {
+        Self(dialect.into())
+    }
+}
+
+/// parses generic SQL until a dialect is given
+impl Default for Custom {
+    fn default() -> Self {
+        Self::new(sqlparser::dialect::GenericDialect {})
+    }
+}
+
 impl Sealed for Generic {}
 impl Sealed for PostgreSQL {}
 impl Sealed for SQLite {}
+impl Sealed for MySQL {}
+impl Sealed for MsSQL {}
+impl Sealed for Custom {}
+
+/// what a dialect's DDL can do, which decides how the changes between two schemas are written
+/// for it (e.g. rebuilding a table instead of altering its columns)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// `ALTER TABLE ... DROP COLUMN`
+    pub drop_column: bool,
+    /// `ALTER TABLE ... ALTER COLUMN`, to change a column's type, nullability, or default
+    pub alter_column: bool,
+    /// `ALTER TABLE ... ADD CONSTRAINT` and `DROP CONSTRAINT`
+    pub alter_constraints: bool,
+    /// `ALTER TABLE ... RENAME COLUMN ... TO ...`
+    pub rename_column: bool,
+    /// `ALTER TYPE ... ADD VALUE` can run in a transaction along with statements that use the new
+    /// label
+    pub add_value_in_transaction: bool,
+    /// `IF EXISTS` and `IF NOT EXISTS` within `ALTER` statements (e.g. `ALTER TABLE IF EXISTS ...
+    /// ADD COLUMN IF NOT EXISTS ...`)
+    pub alter_if_exists: bool,
+    /// `DROP INDEX ... ON ...` names the table the index is on
+    pub drop_index_on_table: bool,
+}
+
+impl Capabilities {
+    /// some changes to a table can only be made by rebuilding it
+    pub fn rebuilds_tables(&self) -> bool {
+        !(self.drop_column && self.alter_column && self.alter_constraints && self.rename_column)
+    }
+}
+
+/// everything generic SQL can do
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            drop_column: true,
+            alter_column: true,
+            alter_constraints: true,
+            rename_column: true,
+            add_value_in_transaction: true,
+            alter_if_exists: true,
+            drop_index_on_table: false,
+        }
+    }
+}
+
+/// the [Capabilities] of a dialect
+pub trait Supports: Sealed {
+    fn supports(&self) -> Capabilities {
+        Capabilities::default()
+    }
+}
+
+impl Supports for Generic {}
+
+impl Supports for Custom {}
+
+impl Supports for PostgreSQL {
+    fn supports(&self) -> Capabilities {
+        Capabilities {
+            // before PostgreSQL 12 it can't run in a transaction at all, and since then the new
+            // label can't be used until the transaction that added it commits
+            add_value_in_transaction: false,
+            ..Default::default()
+        }
+    }
+}
+
+impl Supports for SQLite {
+    fn supports(&self) -> Capabilities {
+        Capabilities {
+            // DROP COLUMN and RENAME COLUMN are supported since 3.35 and 3.25
+            alter_column: false,
+            alter_constraints: false,
+            alter_if_exists: false,
+            ..Default::default()
+        }
+    }
+}
+
+impl Supports for MySQL {
+    fn supports(&self) -> Capabilities {
+        Capabilities {
+            // MariaDB has them, but MySQL doesn't
+            alter_if_exists: false,
+            drop_index_on_table: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl Supports for MsSQL {
+    fn supports(&self) -> Capabilities {
+        Capabilities {
+            // only DROP ... IF EXISTS
+            alter_if_exists: false,
+            drop_index_on_table: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// one of the built-in dialects, picked at runtime (e.g. from a flag, config file, or database URL)
+///
+/// it's parsed from its name or a common alias (`postgres`, `pg`, `sqlite3`, `mariadb`,
+/// `sqlserver`), ignoring case
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum), clap(rename_all = "lower"))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+#[non_exhaustive]
+pub enum Dialect {
+    #[default]
+    Generic,
+    #[cfg_attr(feature = "clap", value(alias = "postgres", alias = "pg"))]
+    PostgreSql,
+    #[cfg_attr(feature = "clap", value(alias = "sqlite3"))]
+    SQLite,
+    #[cfg_attr(feature = "clap", value(alias = "mariadb"))]
+    MySql,
+    #[cfg_attr(feature = "clap", value(alias = "sqlserver", alias = "tsql"))]
+    MsSql,
+}
+
+impl Dialect {
+    /// the dialect of the database at `url`, from its scheme (e.g. `postgres://`)
+    pub fn from_database_url(url: &str) -> Result<Self, DialectError> {
+        let (scheme, _) = url
+            .split_once(':')
+            .ok_or_else(|| DialectError::InvalidUrl(url.to_owned()))?;
+        match scheme.to_ascii_lowercase().as_str() {
+            "postgres" | "postgresql" => Ok(Self::PostgreSql),
+            "sqlite" | "sqlite3" => Ok(Self::SQLite),
+            "mysql" | "mariadb" => Ok(Self::MySql),
+            "mssql" | "sqlserver" => Ok(Self::MsSql),
+            _ => Err(DialectError::UnknownScheme(scheme.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // NOTE: this must match how clap::ValueEnum displays variants
+        let name = match self {
+            Self::Generic => "generic",
+            Self::PostgreSql => "postgresql",
+            Self::SQLite => "sqlite",
+            Self::MySql => "mysql",
+            Self::MsSql => "mssql",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = DialectError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "generic" => Ok(Self::Generic),
+            "postgresql" | "postgres" | "pg" => Ok(Self::PostgreSql),
+            "sqlite" | "sqlite3" => Ok(Self::SQLite),
+            "mysql" | "mariadb" => Ok(Self::MySql),
+            "mssql" | "sqlserver" | "tsql" => Ok(Self::MsSql),
+            _ => Err(DialectError::Unknown(s.to_owned())),
+        }
+    }
+}
+
+impl TryFrom<&str> for Dialect {
+    type Error = DialectError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl TryFrom<String> for Dialect {
+    type Error = DialectError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Dialect> for String {
+    fn from(dialect: Dialect) -> Self {
+        dialect.to_string()
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DialectError {
+    #[error("Oops, we don't know the {0} dialect")]
+    Unknown(String),
+    #[error("Oops, {0} isn't a database URL")]
+    InvalidUrl(String),
+    #[error("Oops, we don't know which dialect {0}:// URLs are")]
+    UnknownScheme(String),
+    #[error("Oops, {0} databases aren't supported yet")]
+    Unsupported(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let parsed = [
+            "generic",
+            "PostgreSQL",
+            "postgres",
+            "pg",
+            "sqlite",
+            "sqlite3",
+            "MariaDB",
+            "sqlserver",
+        ]
+        .map(|s| s.parse::<Dialect>().unwrap());
+        assert_eq!(
+            parsed,
+            [
+                Dialect::Generic,
+                Dialect::PostgreSql,
+                Dialect::PostgreSql,
+                Dialect::PostgreSql,
+                Dialect::SQLite,
+                Dialect::SQLite,
+                Dialect::MySql,
+                Dialect::MsSql
+            ]
+        );
+        assert_eq!(Dialect::try_from("pg").unwrap().to_string(), "postgresql");
+        assert_eq!(
+            "oracle".parse::<Dialect>().unwrap_err().to_string(),
+            "Oops, we don't know the oracle dialect"
+        );
+    }
+
+    #[test]
+    fn supports() {
+        assert!(!Generic.supports().rebuilds_tables());
+        assert!(!PostgreSQL.supports().rebuilds_tables());
+        assert!(!PostgreSQL.supports().add_value_in_transaction);
+        assert!(SQLite.supports().rebuilds_tables());
+        assert!(SQLite.supports().drop_column);
+        assert!(!SQLite.supports().alter_if_exists);
+        assert!(!MySQL.supports().rebuilds_tables());
+        assert!(MySQL.supports().drop_index_on_table);
+        assert!(!MsSQL.supports().rebuilds_tables());
+    }
+
+    #[test]
+    fn from_database_url() {
+        let dialect = |url| Dialect::from_database_url(url);
+        assert_eq!(dialect("postgres://localhost/app"), Ok(Dialect::PostgreSql));
+        assert_eq!(dialect("sqlite:app.db"), Ok(Dialect::SQLite));
+        assert_eq!(dialect("mysql://localhost/app"), Ok(Dialect::MySql));
+        assert_eq!(dialect("sqlserver://localhost/app"), Ok(Dialect::MsSql));
+        assert_eq!(
+            dialect("oracle://localhost/app"),
+            Err(DialectError::UnknownScheme("oracle".to_owned()))
+        );
+        assert_eq!(
+            dialect("app.db"),
+            Err(DialectError::InvalidUrl("app.db".to_owned()))
+        );
+    }
+}
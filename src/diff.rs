@@ -4,8 +4,12 @@ use bon::bon;
 use thiserror::Error;
 
 use crate::{
-    ast::{CreateDomain, CreateExtension, CreateIndex, CreateTable, CreateType, Statement},
-    dialect::{Generic, PostgreSQL, SQLite},
+    ast::{
+        AlterColumnOperation, AlterTableOperation, ColumnOption, CreateDomain, CreateExtension,
+        CreateIndex, CreateTable, CreateType, Statement,
+    },
+    dialect::{Custom, Generic, MsSQL, MySQL, PostgreSQL, SQLite, Supports},
+    provenance::Provenance,
     sealed::Sealed,
 };
 
@@ -16,6 +20,8 @@ pub struct DiffError {
     kind: DiffErrorKind,
     statement_a: Option<Box<Statement>>,
     statement_b: Option<Box<Statement>>,
+    provenance_a: Option<Box<Provenance>>,
+    provenance_b: Option<Box<Provenance>>,
 }
 
 impl fmt::Display for DiffError {
@@ -25,11 +31,18 @@ impl fmt::Display for DiffError {
             "Oops, we couldn't diff that: {reason}",
             reason = self.kind
         )?;
-        if let Some(statement_a) = &self.statement_a {
-            write!(f, "\n\nStatement A:\n{statement_a}")?;
-        }
-        if let Some(statement_b) = &self.statement_b {
-            write!(f, "\n\nStatement B:\n{statement_b}")?;
+        let statements = [
+            ("Statement A", &self.statement_a, &self.provenance_a),
+            ("Statement B", &self.statement_b, &self.provenance_b),
+        ];
+        for (label, statement, provenance) in statements {
+            let Some(statement) = statement else {
+                continue;
+            };
+            match provenance {
+                Some(provenance) => write!(f, "\n\n{label} ({provenance}):\n{statement}")?,
+                None => write!(f, "\n\n{label}:\n{statement}")?,
+            }
         }
         Ok(())
     }
@@ -47,8 +60,44 @@ impl DiffError {
             kind,
             statement_a: statement_a.map(Box::new),
             statement_b: statement_b.map(Box::new),
+            provenance_a: None,
+            provenance_b: None,
+        }
+    }
+
+    /// notes where the statements were parsed from
+    pub(crate) fn with_provenance(self, a: Option<Provenance>, b: Option<Provenance>) -> Self {
+        Self {
+            provenance_a: a.map(Box::new),
+            provenance_b: b.map(Box::new),
+            ..self
         }
     }
+
+    /// why the statements couldn't be diffed
+    pub fn kind(&self) -> &DiffErrorKind {
+        &self.kind
+    }
+
+    /// the statement from the old schema, when there is one
+    pub fn statement_a(&self) -> Option<&Statement> {
+        self.statement_a.as_deref()
+    }
+
+    /// the statement from the new schema, when there is one
+    pub fn statement_b(&self) -> Option<&Statement> {
+        self.statement_b.as_deref()
+    }
+
+    /// where the statement from the old schema was parsed from, when that's known
+    pub fn provenance_a(&self) -> Option<&Provenance> {
+        self.provenance_a.as_deref()
+    }
+
+    /// where the statement from the new schema was parsed from, when that's known
+    pub fn provenance_b(&self) -> Option<&Provenance> {
+        self.provenance_b.as_deref()
+    }
 }
 
 #[derive(Error, Debug)]
@@ -58,14 +107,73 @@ pub enum DiffErrorKind {
     DropUnnamedIndex,
     #[error("can't compare unnamed index")]
     CompareUnnamedIndex,
+    #[error("can't drop unnamed constraint")]
+    DropUnnamedConstraint,
     #[error("removing enum labels is not supported")]
     RemoveEnumLabel,
     #[error("not yet supported")]
     NotImplemented,
+    /// a [DiffHandler](crate::DiffHandler) failed
+    #[error("{0}")]
+    Handler(crate::HandlerError),
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for DiffError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let code = match self.kind {
+            DiffErrorKind::DropUnnamedIndex => "drop_unnamed_index",
+            DiffErrorKind::CompareUnnamedIndex => "compare_unnamed_index",
+            DiffErrorKind::DropUnnamedConstraint => "drop_unnamed_constraint",
+            DiffErrorKind::RemoveEnumLabel => "remove_enum_label",
+            DiffErrorKind::NotImplemented => "not_implemented",
+            DiffErrorKind::Handler(_) => "handler",
+        };
+        Some(Box::new(format!("sql_schema::diff::{code}")))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let help = match self.kind {
+            DiffErrorKind::DropUnnamedIndex | DiffErrorKind::CompareUnnamedIndex => {
+                "give the index a name so it can be told apart from the others"
+            }
+            DiffErrorKind::DropUnnamedConstraint => {
+                "give the constraint a name (the one the database gave it) so it can be dropped"
+            }
+            DiffErrorKind::RemoveEnumLabel
+            | DiffErrorKind::NotImplemented
+            | DiffErrorKind::Handler(_) => "write this part of the migration by hand",
+        };
+        Some(Box::new(help))
+    }
 }
 
 pub type Result<T, E = DiffError> = std::result::Result<T, E>;
 
+/// the kinds of objects a database names itself when they're created without a name (see
+/// [StatementDiffer::default_name])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Unnamed {
+    PrimaryKey,
+    Unique,
+    ForeignKey,
+    Check,
+    Index,
+}
+
+/// whether a statement in a schema is one the diff can compare
+pub(crate) fn is_supported(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::CreateTable(_)
+            | Statement::CreateIndex(_)
+            | Statement::CreateType { .. }
+            | Statement::CreateExtension(_)
+            | Statement::CreateDomain(_)
+    )
+}
+
 pub trait TreeDiffer: StatementDiffer + Sealed {
     fn diff_tree(&self, a: &[Statement], b: &[Statement]) -> Result<Option<Vec<Statement>>> {
         generic::tree::tree_diff(self, a, b)
@@ -119,11 +227,24 @@ pub trait TreeDiffer: StatementDiffer + Sealed {
 
 impl TreeDiffer for Generic {}
 
+impl TreeDiffer for Custom {}
+
 impl TreeDiffer for PostgreSQL {}
 
 impl TreeDiffer for SQLite {}
 
-pub trait StatementDiffer: fmt::Debug + Default + Clone + Sized + Sealed {
+impl TreeDiffer for MySQL {}
+
+impl TreeDiffer for MsSQL {}
+
+pub trait StatementDiffer: Supports + fmt::Debug + Default + Clone + Sized + Sealed {
+    /// the name the database gives an unnamed constraint or index on `columns` of `table`, so it's
+    /// compared to a named one (e.g. introspected from the database) the way the database would,
+    /// or `None` when that can't be told
+    fn default_name(&self, _table: &str, _columns: &[String], _kind: Unnamed) -> Option<String> {
+        None
+    }
+
     fn diff(&self, sa: &Statement, sb: &Statement) -> Result<Option<Vec<Statement>>> {
         generic::statement::diff(self, sa, sb)
     }
@@ -133,7 +254,7 @@ pub trait StatementDiffer: fmt::Debug + Default + Clone + Sized + Sealed {
         a: &CreateTable,
         b: &CreateTable,
     ) -> Result<Option<Vec<Statement>>> {
-        generic::statement::compare_create_table(a, b)
+        generic::statement::compare_create_table(self, a, b)
     }
 
     fn compare_create_index(
@@ -141,7 +262,7 @@ pub trait StatementDiffer: fmt::Debug + Default + Clone + Sized + Sealed {
         a: &CreateIndex,
         b: &CreateIndex,
     ) -> Result<Option<Vec<Statement>>> {
-        generic::statement::compare_create_index(a, b)
+        generic::statement::compare_create_index(self, a, b)
     }
 
     fn compare_create_type(
@@ -152,6 +273,14 @@ pub trait StatementDiffer: fmt::Debug + Default + Clone + Sized + Sealed {
         generic::statement::compare_create_type(a, b)
     }
 
+    fn compare_create_extension(
+        &self,
+        a: &CreateExtension,
+        b: &CreateExtension,
+    ) -> Result<Option<Vec<Statement>>> {
+        generic::statement::compare_create_extension(a, b)
+    }
+
     fn compare_create_domain(
         &self,
         a: &CreateDomain,
@@ -163,6 +292,148 @@ pub trait StatementDiffer: fmt::Debug + Default + Clone + Sized + Sealed {
 
 impl StatementDiffer for Generic {}
 
-impl StatementDiffer for PostgreSQL {}
+impl StatementDiffer for Custom {}
+
+impl StatementDiffer for PostgreSQL {
+    /// e.g. `users_pkey`, `users_email_key`, `posts_user_id_fkey`, and `users_lower_idx` (for an
+    /// index on `lower(email)`)
+    ///
+    /// names longer than PostgreSQL's limit of 63 bytes aren't told, since it shortens them (and
+    /// makes them unique) in ways that depend on the rest of the database
+    fn default_name(&self, table: &str, columns: &[String], kind: Unnamed) -> Option<String> {
+        let (columns, suffix) = match kind {
+            Unnamed::PrimaryKey => (&[][..], "pkey"),
+            Unnamed::Unique => (columns, "key"),
+            Unnamed::ForeignKey => (columns, "fkey"),
+            Unnamed::Check => (columns, "check"),
+            Unnamed::Index => (columns, "idx"),
+        };
+        let mut name = table.rsplit('.').next().unwrap_or(table).to_owned();
+        for column in columns {
+            let column = column.trim_matches('"');
+            name.push('_');
+            // an expression is named for the function it calls, if any
+            match column.split_once('(') {
+                Some((function, _)) if is_identifier(function) => name.push_str(function),
+                _ if is_identifier(column) => name.push_str(column),
+                _ => name.push_str("expr"),
+            }
+        }
+        name.push('_');
+        name.push_str(suffix);
+        (name.len() <= 63).then_some(name)
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
 
 impl StatementDiffer for SQLite {}
+
+impl StatementDiffer for MySQL {
+    /// a unique key or index is named after its first column (foreign keys and checks are numbered
+    /// per table, e.g. `posts_ibfk_1`, so those can't be told)
+    fn default_name(&self, _table: &str, columns: &[String], kind: Unnamed) -> Option<String> {
+        match kind {
+            Unnamed::Unique | Unnamed::Index => {
+                let column = columns.first()?.trim_matches('`');
+                is_identifier(column).then(|| column.to_owned())
+            }
+            _ => None,
+        }
+    }
+
+    /// changes to a column's type or nullability are written as `MODIFY COLUMN` with the whole
+    /// column, since MySQL's `ALTER COLUMN` can only change its default
+    fn compare_create_table(
+        &self,
+        a: &CreateTable,
+        b: &CreateTable,
+    ) -> Result<Option<Vec<Statement>>> {
+        let Some(mut statements) = generic::statement::compare_create_table(self, a, b)? else {
+            return Ok(None);
+        };
+        for s in &mut statements {
+            if let Statement::AlterTable(alter) = s {
+                alter.operations = modify_columns(std::mem::take(&mut alter.operations), b);
+            }
+        }
+        Ok(Some(statements))
+    }
+}
+
+impl StatementDiffer for MsSQL {
+    /// columns are added without the `COLUMN` keyword, which T-SQL doesn't have
+    fn compare_create_table(
+        &self,
+        a: &CreateTable,
+        b: &CreateTable,
+    ) -> Result<Option<Vec<Statement>>> {
+        let Some(mut statements) = generic::statement::compare_create_table(self, a, b)? else {
+            return Ok(None);
+        };
+        for s in &mut statements {
+            let Statement::AlterTable(alter) = s else {
+                continue;
+            };
+            for op in &mut alter.operations {
+                if let AlterTableOperation::AddColumn { column_keyword, .. } = op {
+                    *column_keyword = false;
+                }
+            }
+        }
+        Ok(Some(statements))
+    }
+}
+
+/// replaces the `ALTER COLUMN` operations on each column of `table` with one `MODIFY COLUMN`
+fn modify_columns(
+    operations: Vec<AlterTableOperation>,
+    table: &CreateTable,
+) -> Vec<AlterTableOperation> {
+    let mut modified = Vec::new();
+    let mut operations = operations
+        .into_iter()
+        .map(|op| match op {
+            AlterTableOperation::AlterColumn {
+                column_name,
+                op:
+                    AlterColumnOperation::SetDataType { .. }
+                    | AlterColumnOperation::SetNotNull
+                    | AlterColumnOperation::DropNotNull,
+            } if !modified.contains(&column_name) => {
+                let column = table
+                    .columns
+                    .iter()
+                    .find(|c| c.name == column_name)
+                    .expect("column is in the table");
+                modified.push(column_name);
+                // constraints on the column are added and dropped separately
+                let options = column.options.iter().filter(|def| {
+                    !matches!(
+                        def.option,
+                        ColumnOption::PrimaryKey(_)
+                            | ColumnOption::Unique(_)
+                            | ColumnOption::ForeignKey(_)
+                            | ColumnOption::Check(_)
+                    )
+                });
+                AlterTableOperation::ModifyColumn {
+                    col_name: column.name.clone(),
+                    data_type: column.data_type.clone(),
+                    options: options.map(|def| def.option.clone()).collect(),
+                    column_position: None,
+                }
+            }
+            op => op,
+        })
+        .collect::<Vec<_>>();
+    // the rest of what's altered (i.e. the default) is part of the modified column
+    operations.retain(|op| {
+        !matches!(op, AlterTableOperation::AlterColumn { column_name, .. }
+            if modified.contains(column_name))
+    });
+    operations
+}
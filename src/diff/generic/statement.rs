@@ -1,12 +1,16 @@
-use std::{cmp::Ordering, collections::HashSet};
+use std::cmp::Ordering;
 
 use crate::{
     ast::{
-        AlterTable, AlterTableOperation, AlterType, AlterTypeAddValue, AlterTypeAddValuePosition,
-        AlterTypeOperation, AlterTypeRenameValue, AttachedToken, CreateDomain, CreateIndex,
-        CreateTable, CreateType, DropDomain, ObjectType, Statement, UserDefinedTypeRepresentation,
+        AlterColumnOperation, AlterTable, AlterTableOperation, AlterType, AlterTypeAddValue,
+        AlterTypeAddValuePosition, AlterTypeOperation, AlterTypeRenameValue, AttachedToken,
+        CheckConstraint, ColumnOption, CreateDomain, CreateExtension, CreateIndex, CreateTable,
+        CreateType, DropDomain, DropExtension, ForeignKeyConstraint, Ident, IndexColumn,
+        ObjectName, ObjectNamePart, ObjectType, PrimaryKeyConstraint, ReferentialAction, Statement,
+        TableConstraint, UniqueConstraint, UserDefinedTypeRepresentation,
     },
-    diff::{DiffError, DiffErrorKind, Result, StatementDiffer},
+    diff::{DiffError, DiffErrorKind, Result, StatementDiffer, Unnamed},
+    schema::{self, ConstraintKind, Index, Table},
 };
 
 pub fn diff<Dialect>(
@@ -45,6 +49,10 @@ where
             ),
             _ => Ok(None),
         },
+        Statement::CreateExtension(a) => match sb {
+            Statement::CreateExtension(b) => dialect.compare_create_extension(a, b),
+            _ => Ok(None),
+        },
         Statement::CreateDomain(a) => match sb {
             Statement::CreateDomain(b) => dialect.compare_create_domain(a, b),
             _ => Ok(None),
@@ -57,44 +65,136 @@ where
     }
 }
 
-pub fn compare_create_table(a: &CreateTable, b: &CreateTable) -> Result<Option<Vec<Statement>>> {
+pub fn compare_create_table<Dialect>(
+    dialect: &Dialect,
+    a: &CreateTable,
+    b: &CreateTable,
+) -> Result<Option<Vec<Statement>>>
+where
+    Dialect: StatementDiffer,
+{
     if a == b {
         return Ok(None);
     }
 
-    let a_column_names: HashSet<_> = a.columns.iter().map(|c| c.name.value.clone()).collect();
-    let b_column_names: HashSet<_> = b.columns.iter().map(|c| c.name.value.clone()).collect();
+    let column = |table: &CreateTable, name: &str| {
+        table
+            .columns
+            .iter()
+            .find(|c| c.name.value == name)
+            .cloned()
+            .expect("column is in the table")
+    };
+    let (constraints_a, constraints_b) = (constraints(a), constraints(b));
+    let (mut table_a, mut table_b) = (Table::from(a), Table::from(b));
+    name_constraints(dialect, &mut table_a, &constraints_a);
+    name_constraints(dialect, &mut table_b, &constraints_b);
+    let diff = table_a.diff(&table_b);
+    let constraint = |table: &Table, constraints: &[(Option<Ident>, TableConstraint)], c| {
+        let index = table
+            .constraints
+            .iter()
+            .position(|other| other == c)
+            .expect("constraint is in the table");
+        constraints[index].clone()
+    };
 
-    let operations: Vec<_> = a
-        .columns
-        .iter()
-        .filter_map(|ac| {
-            if b_column_names.contains(&ac.name.value) {
-                None
-            } else {
-                // drop column if it only exists in `a`
-                Some(AlterTableOperation::DropColumn {
-                    column_names: vec![ac.name.clone()],
-                    if_exists: a.if_not_exists,
-                    drop_behavior: None,
-                    has_column_keyword: true,
-                })
+    let mut operations = Vec::new();
+    // drop constraints that only exist in `a`, except those that go with a dropped column
+    for c in &diff.dropped_constraints {
+        let (column, constraint) = constraint(&table_a, &constraints_a, c);
+        let dropped = |name: &String| diff.dropped_columns.contains(name);
+        let goes_with_column = column.is_some_and(|column| dropped(&column.value))
+            || c.columns()
+                .is_some_and(|columns| columns.iter().any(dropped));
+        if goes_with_column {
+            continue;
+        }
+        let name = constraint_name(&constraint)
+            .cloned()
+            .or_else(|| c.name.clone().map(Ident::new));
+        match name {
+            Some(name) => operations.push(AlterTableOperation::DropConstraint {
+                if_exists: false,
+                name,
+                drop_behavior: None,
+            }),
+            // the table has to be rebuilt to drop it (see SyntaxTree::rebuild_tables)
+            None if !dialect.supports().alter_constraints => {}
+            None => {
+                return Err(DiffError::builder()
+                    .kind(DiffErrorKind::DropUnnamedConstraint)
+                    .statement_a(Statement::CreateTable(a.clone()))
+                    .statement_b(Statement::CreateTable(b.clone()))
+                    .build())
             }
-        })
-        .chain(b.columns.iter().filter_map(|bc| {
-            if a_column_names.contains(&bc.name.value) {
-                None
-            } else {
-                // add the column if it only exists in `b`
-                Some(AlterTableOperation::AddColumn {
-                    column_keyword: true,
-                    if_not_exists: a.if_not_exists,
-                    column_def: bc.clone(),
-                    column_position: None,
-                })
+        }
+    }
+    // drop columns that only exist in `a`
+    operations.extend(
+        diff.dropped_columns
+            .iter()
+            .map(|name| AlterTableOperation::DropColumn {
+                column_names: vec![column(a, name).name],
+                if_exists: a.if_not_exists,
+                drop_behavior: None,
+                has_column_keyword: true,
+            }),
+    );
+    // add columns that only exist in `b`
+    operations.extend(
+        diff.added_columns
+            .iter()
+            .map(|name| AlterTableOperation::AddColumn {
+                column_keyword: true,
+                if_not_exists: a.if_not_exists,
+                column_def: column(b, name),
+                column_position: None,
+            }),
+    );
+    // alter the type, nullability, and default of the columns in both
+    for altered in &diff.altered_columns {
+        let column_def = column(b, &altered.name);
+        let alter_column = |op| AlterTableOperation::AlterColumn {
+            column_name: column_def.name.clone(),
+            op,
+        };
+        if altered.data_type.is_some() {
+            operations.push(alter_column(AlterColumnOperation::SetDataType {
+                data_type: column_def.data_type.clone(),
+                using: None,
+                had_set: false,
+            }));
+        }
+        match altered.nullable {
+            Some(true) => operations.push(alter_column(AlterColumnOperation::DropNotNull)),
+            Some(false) => operations.push(alter_column(AlterColumnOperation::SetNotNull)),
+            None => {}
+        }
+        match &altered.default {
+            Some(Some(_)) => {
+                let value = column_def.options.iter().find_map(|def| match &def.option {
+                    ColumnOption::Default(value) => Some(value.clone()),
+                    _ => None,
+                });
+                let value = value.expect("column has a default");
+                operations.push(alter_column(AlterColumnOperation::SetDefault { value }));
             }
-        }))
-        .collect();
+            Some(None) => operations.push(alter_column(AlterColumnOperation::DropDefault)),
+            None => {}
+        }
+    }
+    // add constraints that only exist in `b`, except those declared on an added column
+    for c in &diff.added_constraints {
+        let (column, constraint) = constraint(&table_b, &constraints_b, c);
+        if column.is_some_and(|column| diff.added_columns.contains(&column.value)) {
+            continue;
+        }
+        operations.push(AlterTableOperation::AddConstraint {
+            constraint,
+            not_valid: false,
+        });
+    }
 
     if operations.is_empty() {
         return Ok(None);
@@ -112,19 +212,121 @@ pub fn compare_create_table(a: &CreateTable, b: &CreateTable) -> Result<Option<V
     })]))
 }
 
-pub fn compare_create_index(a: &CreateIndex, b: &CreateIndex) -> Result<Option<Vec<Statement>>> {
-    if a == b {
+/// the constraints of a table in the order [Table::from] has them, each as a table constraint
+/// along with the column it's declared on (when it's declared on one)
+fn constraints(table: &CreateTable) -> Vec<(Option<Ident>, TableConstraint)> {
+    let mut constraints = Vec::new();
+    for column in &table.columns {
+        let name = &column.name;
+        for def in &column.options {
+            let constraint = match &def.option {
+                ColumnOption::PrimaryKey(pk) => TableConstraint::PrimaryKey(PrimaryKeyConstraint {
+                    name: def.name.clone().or(pk.name.clone()),
+                    columns: vec![IndexColumn::from(name.clone())],
+                    ..pk.clone()
+                }),
+                ColumnOption::Unique(unique) => TableConstraint::Unique(UniqueConstraint {
+                    name: def.name.clone().or(unique.name.clone()),
+                    columns: vec![IndexColumn::from(name.clone())],
+                    ..unique.clone()
+                }),
+                ColumnOption::ForeignKey(fk) => TableConstraint::ForeignKey(ForeignKeyConstraint {
+                    name: def.name.clone().or(fk.name.clone()),
+                    columns: vec![name.clone()],
+                    ..fk.clone()
+                }),
+                ColumnOption::Check(check) => TableConstraint::Check(CheckConstraint {
+                    name: def.name.clone().or(check.name.clone()),
+                    ..check.clone()
+                }),
+                _ => continue,
+            };
+            constraints.push((Some(name.clone()), constraint));
+        }
+    }
+    constraints.extend(
+        table
+            .constraints
+            .iter()
+            .filter(|c| schema::table_constraint(c).is_some())
+            .map(|c| (None, c.clone())),
+    );
+    constraints
+}
+
+/// names the unnamed constraints of `table` the way the database does (see
+/// [StatementDiffer::default_name]), so they're compared to named ones by name
+fn name_constraints<Dialect>(
+    dialect: &Dialect,
+    table: &mut Table,
+    constraints: &[(Option<Ident>, TableConstraint)],
+) where
+    Dialect: StatementDiffer,
+{
+    let names = table
+        .columns
+        .iter()
+        .map(|c| c.name.clone())
+        .collect::<Vec<_>>();
+    for (c, (column, _)) in table.constraints.iter_mut().zip(constraints) {
+        if c.name.is_some() {
+            continue;
+        }
+        let (kind, columns) = match &c.kind {
+            ConstraintKind::PrimaryKey { columns } => (Unnamed::PrimaryKey, columns.clone()),
+            ConstraintKind::Unique { columns } => (Unnamed::Unique, columns.clone()),
+            ConstraintKind::ForeignKey { columns, .. } => (Unnamed::ForeignKey, columns.clone()),
+            // a check is named for the column it's declared on, or else the first one it uses
+            ConstraintKind::Check { expr } => {
+                let column = column.as_ref().map(|c| c.value.clone()).or_else(|| {
+                    expr.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                        .find(|word| names.iter().any(|name| name == word))
+                        .map(str::to_owned)
+                });
+                (Unnamed::Check, column.into_iter().collect())
+            }
+        };
+        c.name = dialect.default_name(&table.name, &columns, kind);
+    }
+}
+
+fn constraint_name(constraint: &TableConstraint) -> Option<&Ident> {
+    match constraint {
+        TableConstraint::PrimaryKey(c) => c.name.as_ref(),
+        TableConstraint::Unique(c) => c.name.as_ref(),
+        TableConstraint::ForeignKey(c) => c.name.as_ref(),
+        TableConstraint::Check(c) => c.name.as_ref(),
+        _ => None,
+    }
+}
+
+pub fn compare_create_index<Dialect>(
+    dialect: &Dialect,
+    a: &CreateIndex,
+    b: &CreateIndex,
+) -> Result<Option<Vec<Statement>>>
+where
+    Dialect: StatementDiffer,
+{
+    // IF NOT EXISTS and CONCURRENTLY are how an index is created, not what it is, and an unnamed
+    // index is only compared to one with the name the database would give it
+    let same = CreateIndex {
+        name: b.name.clone(),
+        if_not_exists: b.if_not_exists,
+        concurrently: b.concurrently,
+        ..a.clone()
+    };
+    if same == *b {
         return Ok(None);
     }
 
-    if a.name.is_none() || b.name.is_none() {
-        Err(DiffError::builder()
+    let (Some(name), Some(_)) = (index_name(dialect, a), index_name(dialect, b)) else {
+        return Err(DiffError::builder()
             .kind(DiffErrorKind::CompareUnnamedIndex)
             .statement_a(Statement::CreateIndex(a.clone()))
             .statement_b(Statement::CreateIndex(b.clone()))
-            .build())?;
-    }
-    let name = a.name.clone().unwrap();
+            .build());
+    };
 
     Ok(Some(vec![
         Statement::Drop {
@@ -135,12 +337,53 @@ pub fn compare_create_index(a: &CreateIndex, b: &CreateIndex) -> Result<Option<V
             restrict: false,
             purge: false,
             temporary: false,
-            table: None,
+            table: dialect
+                .supports()
+                .drop_index_on_table
+                .then(|| a.table_name.clone()),
         },
         Statement::CreateIndex(b.clone()),
     ]))
 }
 
+/// the name of the index, or the one the database gives it when it's unnamed (see
+/// [StatementDiffer::default_name])
+pub(crate) fn index_name<Dialect>(dialect: &Dialect, index: &CreateIndex) -> Option<ObjectName>
+where
+    Dialect: StatementDiffer,
+{
+    if let Some(name) = &index.name {
+        return Some(name.clone());
+    }
+    let Index { table, columns, .. } = Index::from(index);
+    let name = dialect.default_name(&table, &columns, Unnamed::Index)?;
+    Some(ObjectName::from(vec![Ident::new(name)]))
+}
+
+/// whether `a` and `b` are the same index (by name), counting an unnamed one as having the name
+/// the database gives it
+pub(crate) fn same_index<Dialect>(dialect: &Dialect, a: &CreateIndex, b: &CreateIndex) -> bool
+where
+    Dialect: StatementDiffer,
+{
+    if let (Some(a), Some(b)) = (&a.name, &b.name) {
+        return a == b;
+    }
+    match (index_name(dialect, a), index_name(dialect, b)) {
+        (Some(a), Some(b)) => unquoted(&a) == unquoted(&b),
+        _ => a.name == b.name,
+    }
+}
+
+/// the last part of the name without quotes, e.g. `users_email_idx` for `public."users_email_idx"`
+fn unquoted(name: &ObjectName) -> String {
+    match name.0.last() {
+        Some(ObjectNamePart::Identifier(ident)) => ident.value.clone(),
+        Some(part) => part.to_string(),
+        None => String::new(),
+    }
+}
+
 pub fn compare_create_type(a: &CreateType, b: &CreateType) -> Result<Option<Vec<Statement>>> {
     if a == b {
         return Ok(None);
@@ -257,6 +500,32 @@ pub fn compare_create_type(a: &CreateType, b: &CreateType) -> Result<Option<Vec<
     ))
 }
 
+pub fn compare_create_extension(
+    a: &CreateExtension,
+    b: &CreateExtension,
+) -> Result<Option<Vec<Statement>>> {
+    let same = CreateExtension {
+        if_not_exists: b.if_not_exists,
+        ..a.clone()
+    };
+    if same == *b {
+        return Ok(None);
+    }
+
+    Ok(Some(vec![
+        Statement::DropExtension(DropExtension {
+            names: vec![a.name.clone()],
+            if_exists: true,
+            cascade_or_restrict: if a.cascade {
+                Some(ReferentialAction::Cascade)
+            } else {
+                None
+            },
+        }),
+        Statement::CreateExtension(b.clone()),
+    ]))
+}
+
 pub fn compare_create_domain(a: &CreateDomain, b: &CreateDomain) -> Result<Option<Vec<Statement>>> {
     if a == b {
         return Ok(None);
@@ -3,7 +3,10 @@ use crate::{
         CreateDomain, CreateExtension, CreateIndex, CreateTable, CreateType, DropDomain,
         DropExtension, Statement,
     },
-    diff::{DiffError, DiffErrorKind, Result, StatementDiffer, TreeDiffer},
+    diff::{
+        generic::statement::{index_name, same_index},
+        DiffError, DiffErrorKind, Result, StatementDiffer, TreeDiffer,
+    },
 };
 
 pub fn tree_diff<Dialect>(
@@ -52,7 +55,7 @@ where
                     _ => false,
                 })),
                 Statement::CreateIndex(b) => Ok(a.iter().find(|sa| match sa {
-                    Statement::CreateIndex(a) => a.name == b.name,
+                    Statement::CreateIndex(a) => same_index(dialect, a, b),
                     _ => false,
                 })),
                 Statement::CreateType { name: b_name, .. } => Ok(a.iter().find(|sa| match sa {
@@ -104,12 +107,24 @@ where
     MF: Fn(&&Statement) -> bool,
     DF: Fn() -> Result<Option<Vec<Statement>>>,
 {
-    b.iter().find(match_fn).map_or_else(
+    let res = b.iter().find(match_fn).map_or_else(
         // drop the statement if it wasn't found in `other`
         drop_fn,
         // otherwise diff the two statements
         |sb| StatementDiffer::diff(dialect, sa, sb),
-    )
+    );
+    if let Ok(Some(diff)) = &res {
+        tracing::debug!(statement = %sa, diff = %display_all(diff), "statement changed");
+    }
+    res
+}
+
+fn display_all(statements: &[Statement]) -> String {
+    statements
+        .iter()
+        .map(|s| format!("{s};"))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 pub fn find_and_compare_create_table<Dialect>(
@@ -158,11 +173,11 @@ where
         sa,
         b,
         |sb| match sb {
-            Statement::CreateIndex(b) => a.name == b.name,
+            Statement::CreateIndex(b) => same_index(dialect, a, b),
             _ => false,
         },
         || {
-            let name = a.name.clone().ok_or_else(|| {
+            let name = index_name(dialect, a).ok_or_else(|| {
                 DiffError::builder()
                     .kind(DiffErrorKind::DropUnnamedIndex)
                     .statement_a(sa.clone())
@@ -177,7 +192,10 @@ where
                 restrict: false,
                 purge: false,
                 temporary: false,
-                table: None,
+                table: dialect
+                    .supports()
+                    .drop_index_on_table
+                    .then(|| a.table_name.clone()),
             }]))
         },
     )
@@ -0,0 +1,467 @@
+/*!
+Dependencies between the objects of a schema (foreign keys, columns using custom types, and the
+relations a view selects from), rendered as DOT or Mermaid, along with entity-relationship diagrams
+of the tables built from the same foreign keys.
+*/
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{
+        ColumnOption, CreateDomain, CreateTable, CreateView, DataType, Query, SetExpr, Statement,
+        TableConstraint, TableFactor, TableWithJoins,
+    },
+    changes::ObjectKind,
+    schema::{ConstraintKind, Table},
+    SyntaxTree,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    pub kind: ObjectKind,
+}
+
+/// `from` depends on `to`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+    /// the columns involved (empty for views)
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EdgeKind {
+    ForeignKey,
+    TypeUsage,
+    ViewReference,
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// builds the dependency graph of the tables, views, types, and domains in the tree
+    ///
+    /// edges to objects that aren't defined in the tree are left out
+    pub fn graph(&self) -> Graph {
+        let mut graph = Graph::default();
+        for s in &self.tree {
+            let node = match s {
+                Statement::CreateTable(CreateTable { name, .. }) => (name, ObjectKind::Table),
+                Statement::CreateView(CreateView { name, .. }) => (name, ObjectKind::View),
+                Statement::CreateType { name, .. } => (name, ObjectKind::Type),
+                Statement::CreateDomain(CreateDomain { name, .. }) => (name, ObjectKind::Domain),
+                _ => continue,
+            };
+            graph.nodes.push(Node {
+                name: node.0.to_string(),
+                kind: node.1,
+            });
+        }
+
+        let mut edges = Vec::new();
+        for s in &self.tree {
+            match s {
+                Statement::CreateTable(CreateTable {
+                    name,
+                    columns,
+                    constraints,
+                    ..
+                }) => {
+                    let from = name.to_string();
+                    for column in columns {
+                        for option in &column.options {
+                            if let ColumnOption::ForeignKey(fk) = &option.option {
+                                edges.push(Edge {
+                                    from: from.clone(),
+                                    to: fk.foreign_table.to_string(),
+                                    kind: EdgeKind::ForeignKey,
+                                    label: column.name.to_string(),
+                                });
+                            }
+                        }
+                        if let DataType::Custom(ty, _) = &column.data_type {
+                            edges.push(Edge {
+                                from: from.clone(),
+                                to: ty.to_string(),
+                                kind: EdgeKind::TypeUsage,
+                                label: column.name.to_string(),
+                            });
+                        }
+                    }
+                    for constraint in constraints {
+                        if let TableConstraint::ForeignKey(fk) = constraint {
+                            edges.push(Edge {
+                                from: from.clone(),
+                                to: fk.foreign_table.to_string(),
+                                kind: EdgeKind::ForeignKey,
+                                label: join(&fk.columns),
+                            });
+                        }
+                    }
+                }
+                Statement::CreateView(CreateView { name, query, .. }) => {
+                    let mut relations = Vec::new();
+                    query_relations(query, &mut relations);
+                    relations.dedup();
+                    edges.extend(relations.into_iter().map(|to| Edge {
+                        from: name.to_string(),
+                        to,
+                        kind: EdgeKind::ViewReference,
+                        label: String::new(),
+                    }));
+                }
+                _ => {}
+            }
+        }
+        // e.g. CTEs, built-in types, and tables managed elsewhere
+        edges.retain(|edge| graph.nodes.iter().any(|node| node.name == edge.to));
+        graph.edges = edges;
+        graph
+    }
+}
+
+fn query_relations(query: &Query, relations: &mut Vec<String>) {
+    if let Some(with) = &query.with {
+        for cte in &with.cte_tables {
+            query_relations(&cte.query, relations);
+        }
+    }
+    set_expr_relations(&query.body, relations);
+}
+
+fn set_expr_relations(body: &SetExpr, relations: &mut Vec<String>) {
+    match body {
+        SetExpr::Select(select) => {
+            for table in &select.from {
+                table_relations(table, relations);
+            }
+        }
+        SetExpr::Query(query) => query_relations(query, relations),
+        SetExpr::SetOperation { left, right, .. } => {
+            set_expr_relations(left, relations);
+            set_expr_relations(right, relations);
+        }
+        _ => {}
+    }
+}
+
+fn table_relations(table: &TableWithJoins, relations: &mut Vec<String>) {
+    let factors = std::iter::once(&table.relation).chain(table.joins.iter().map(|j| &j.relation));
+    for factor in factors {
+        match factor {
+            TableFactor::Table { name, .. } => relations.push(name.to_string()),
+            TableFactor::Derived { subquery, .. } => query_relations(subquery, relations),
+            TableFactor::NestedJoin {
+                table_with_joins, ..
+            } => table_relations(table_with_joins, relations),
+            _ => {}
+        }
+    }
+}
+
+fn join(names: &[impl ToString]) -> String {
+    names
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Graph {
+    /// renders the graph in Graphviz's DOT language
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph schema {\n    rankdir=LR;\n");
+        for node in &self.nodes {
+            let shape = match node.kind {
+                ObjectKind::Table => "box",
+                ObjectKind::View => "box, style=dashed",
+                _ => "ellipse",
+            };
+            let _ = writeln!(out, "    {} [shape={shape}];", dot_id(&node.name));
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::ViewReference => ", style=dashed",
+                _ => "",
+            };
+            let _ = writeln!(
+                out,
+                "    {} -> {} [label={}{style}];",
+                dot_id(&edge.from),
+                dot_id(&edge.to),
+                dot_id(&edge.label),
+            );
+        }
+        out.push('}');
+        out
+    }
+
+    /// renders the graph as a Mermaid flowchart
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for node in &self.nodes {
+            let id = mermaid_id(&node.name);
+            let label = node.name.replace('"', "#quot;");
+            let _ = match node.kind {
+                ObjectKind::Table => writeln!(out, "    {id}[\"{label}\"]"),
+                ObjectKind::View => writeln!(out, "    {id}[/\"{label}\"/]"),
+                _ => writeln!(out, "    {id}([\"{label}\"])"),
+            };
+        }
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                EdgeKind::ViewReference => "-.->",
+                _ => "-->",
+            };
+            let from = mermaid_id(&edge.from);
+            let to = mermaid_id(&edge.to);
+            let _ = if edge.label.is_empty() {
+                writeln!(out, "    {from} {arrow} {to}")
+            } else {
+                writeln!(out, "    {from} {arrow}|\"{}\"| {to}", edge.label)
+            };
+        }
+        out.truncate(out.trim_end().len());
+        out
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// renders the tables, their columns, and the foreign keys between them as a Mermaid
+    /// entity-relationship diagram
+    pub fn to_mermaid_erd(&self) -> String {
+        let (tables, foreign_keys) = self.erd();
+        let mut out = String::from("erDiagram\n");
+        for (name, table) in &tables {
+            let _ = writeln!(out, "    {} {{", mermaid_id(name));
+            for column in &table.columns {
+                let keys = keys(table, &column.name).join(", ");
+                let _ = writeln!(
+                    out,
+                    "        {} {}{}{keys}",
+                    mermaid_type(&column.data_type),
+                    mermaid_id(&column.name),
+                    if keys.is_empty() { "" } else { " " },
+                );
+            }
+            let _ = writeln!(out, "    }}");
+        }
+        for edge in &foreign_keys {
+            // optional when any of the referencing columns is nullable
+            let optional = tables
+                .iter()
+                .find(|(name, _)| *name == edge.from)
+                .is_none_or(|(_, table)| {
+                    edge.label
+                        .split(", ")
+                        .any(|c| table.column(c).is_none_or(|c| c.nullable))
+                });
+            let _ = writeln!(
+                out,
+                "    {} }}o--{} {} : \"{}\"",
+                mermaid_id(&edge.from),
+                if optional { "o|" } else { "||" },
+                mermaid_id(&edge.to),
+                edge.label.replace('"', "#quot;"),
+            );
+        }
+        out.truncate(out.trim_end().len());
+        out
+    }
+
+    /// renders the tables, their columns, and the foreign keys between them as a Graphviz
+    /// entity-relationship diagram (each table a record listing its columns)
+    pub fn to_dot_erd(&self) -> String {
+        let (tables, foreign_keys) = self.erd();
+        let mut out = String::from("digraph schema {\n    rankdir=LR;\n    node [shape=record];\n");
+        for (name, table) in &tables {
+            let mut label = record_field(name);
+            label.push('|');
+            for column in &table.columns {
+                let mut field = format!("{} : {}", column.name, column.data_type);
+                for key in keys(table, &column.name) {
+                    let _ = write!(field, " {key}");
+                }
+                label.push_str(&record_field(&field));
+                label.push_str("\\l");
+            }
+            let _ = writeln!(out, "    {} [label=\"{{{label}}}\"];", dot_id(name));
+        }
+        for edge in &foreign_keys {
+            let _ = writeln!(
+                out,
+                "    {} -> {} [label={}];",
+                dot_id(&edge.from),
+                dot_id(&edge.to),
+                dot_id(&edge.label),
+            );
+        }
+        out.push('}');
+        out
+    }
+
+    /// the tables (by their name in the [Graph]) and the foreign keys between them
+    fn erd(&self) -> (Vec<(String, Table)>, Vec<Edge>) {
+        let graph = self.graph();
+        let tables = self
+            .tree
+            .iter()
+            .filter_map(|s| match s {
+                Statement::CreateTable(table) => Some((table.name.to_string(), Table::from(table))),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let foreign_keys = graph
+            .edges
+            .into_iter()
+            .filter(|edge| edge.kind == EdgeKind::ForeignKey)
+            .collect();
+        (tables, foreign_keys)
+    }
+}
+
+/// the keys `column` is part of (`PK`, `FK`, and `UK`)
+fn keys(table: &Table, column: &str) -> Vec<&'static str> {
+    let mut keys = Vec::new();
+    for constraint in &table.constraints {
+        let (key, columns) = match &constraint.kind {
+            ConstraintKind::PrimaryKey { columns } => ("PK", columns),
+            ConstraintKind::ForeignKey { columns, .. } => ("FK", columns),
+            ConstraintKind::Unique { columns } => ("UK", columns),
+            _ => continue,
+        };
+        if columns.iter().any(|c| c == column) && !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// record labels treat braces, pipes, and angle brackets as structure
+fn record_field(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>' | '"' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// mermaid attribute types can't contain spaces, commas, etc. (e.g. `DOUBLE_PRECISION`)
+fn mermaid_type(data_type: &str) -> String {
+    data_type
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() || "_-[]()".contains(c) => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// mermaid ids can't contain quotes, dots, etc.
+fn mermaid_id(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Generic;
+
+    #[test]
+    fn graph() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TYPE status AS ENUM ('a', 'b'); \
+            CREATE TABLE users (id INT PRIMARY KEY, status status); \
+            CREATE TABLE posts (id INT, user_id INT REFERENCES users (id), \
+                FOREIGN KEY (id) REFERENCES external (id)); \
+            CREATE VIEW active_users AS WITH a AS (SELECT * FROM users) \
+                SELECT * FROM a JOIN posts ON posts.user_id = a.id;",
+        )
+        .unwrap();
+        let graph = tree.graph();
+        assert_eq!(
+            graph.to_mermaid(),
+            "flowchart LR
+    status([\"status\"])
+    users[\"users\"]
+    posts[\"posts\"]
+    active_users[/\"active_users\"/]
+    users -->|\"status\"| status
+    posts -->|\"user_id\"| users
+    active_users -.-> users
+    active_users -.-> posts"
+        );
+        assert_eq!(
+            graph.to_dot(),
+            "digraph schema {
+    rankdir=LR;
+    \"status\" [shape=ellipse];
+    \"users\" [shape=box];
+    \"posts\" [shape=box];
+    \"active_users\" [shape=box, style=dashed];
+    \"users\" -> \"status\" [label=\"status\"];
+    \"posts\" -> \"users\" [label=\"user_id\"];
+    \"active_users\" -> \"users\" [label=\"\", style=dashed];
+    \"active_users\" -> \"posts\" [label=\"\", style=dashed];
+}"
+        );
+    }
+
+    #[test]
+    fn erd() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE NOT NULL); \
+            CREATE TABLE posts (id INT PRIMARY KEY, user_id INT NOT NULL REFERENCES users (id), \
+                editor_id INT, score DOUBLE PRECISION, \
+                FOREIGN KEY (editor_id) REFERENCES users (id)); \
+            CREATE VIEW recent_posts AS SELECT * FROM posts;",
+        )
+        .unwrap();
+        assert_eq!(
+            tree.to_mermaid_erd(),
+            "erDiagram
+    users {
+        INT id PK
+        VARCHAR(255) email UK
+    }
+    posts {
+        INT id PK
+        INT user_id FK
+        INT editor_id FK
+        DOUBLE_PRECISION score
+    }
+    posts }o--|| users : \"user_id\"
+    posts }o--o| users : \"editor_id\""
+        );
+        assert_eq!(
+            tree.to_dot_erd(),
+            "digraph schema {
+    rankdir=LR;
+    node [shape=record];
+    \"users\" [label=\"{users|id : INT PK\\lemail : VARCHAR(255) UK\\l}\"];
+    \"posts\" [label=\"{posts|id : INT PK\\luser_id : INT FK\\leditor_id : INT FK\\lscore : DOUBLE PRECISION\\l}\"];
+    \"posts\" -> \"users\" [label=\"user_id\"];
+    \"posts\" -> \"users\" [label=\"editor_id\"];
+}"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+/*!
+Diff and migrate support for statements this crate doesn't handle (e.g. vendor-specific DDL), added
+by registering a handler on a tree with [SyntaxTree::with_diff_handler] or
+[SyntaxTree::with_migrate_handler].
+
+Each statement a handler [handles](DiffHandler::handles) is passed to it instead of the built-in
+diff or migration, and what it returns is added after the rest of the result.
+*/
+
+use std::{borrow::Cow, error::Error, fmt, sync::Arc};
+
+use crate::{
+    ast::Statement, DiffError, DiffErrorKind, MigrateError, MigrateErrorKind, SyntaxTree, Warning,
+};
+
+/// the error a handler fails with, shown as the reason of the [DiffError] or [MigrateError]
+pub type HandlerError = Box<dyn Error + Send + Sync>;
+
+/// diffs the statements of a kind the built-in diff doesn't support
+pub trait DiffHandler: fmt::Debug + Send + Sync {
+    /// whether `statement` (from either schema) is diffed by this handler
+    fn handles(&self, statement: &Statement) -> bool;
+
+    /// the statements that turn the handled statements of the old schema (`a`) into those of the
+    /// new one (`b`), or none when they're the same
+    fn diff(&self, a: &[&Statement], b: &[&Statement]) -> Result<Vec<Statement>, HandlerError>;
+}
+
+/// applies the statements of a kind the built-in migration doesn't support
+pub trait MigrateHandler: fmt::Debug + Send + Sync {
+    /// whether `statement` (from the schema or the migration) is applied by this handler
+    fn handles(&self, statement: &Statement) -> bool;
+
+    /// the handled statements of the schema (`schema`) after applying the handled statements of
+    /// the migration (`migration`)
+    fn migrate(
+        &self,
+        schema: Vec<Statement>,
+        migration: &[&Statement],
+    ) -> Result<Vec<Statement>, HandlerError>;
+}
+
+/// the statements of each tree left for the built-in diff or migration, and what the handlers
+/// returned
+type Split<'a, A> = (A, Cow<'a, [Statement]>, Vec<Statement>);
+
+/// called with each [Warning] (see [SyntaxTree::on_warning])
+type WarningCallback = Arc<dyn Fn(&Warning) + Send + Sync>;
+
+/// the handlers registered on a tree
+#[derive(Clone, Default)]
+pub(crate) struct Handlers {
+    diff: Vec<Arc<dyn DiffHandler>>,
+    migrate: Vec<Arc<dyn MigrateHandler>>,
+    warning: Vec<WarningCallback>,
+}
+
+impl fmt::Debug for Handlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handlers")
+            .field("diff", &self.diff)
+            .field("migrate", &self.migrate)
+            .field("warning", &self.warning.len())
+            .finish()
+    }
+}
+
+impl Handlers {
+    pub(crate) fn on_warning(&mut self, f: impl Fn(&Warning) + Send + Sync + 'static) {
+        self.warning.push(Arc::new(f));
+    }
+
+    /// whether any callbacks are registered, so warnings are worth looking for
+    pub(crate) fn warns(&self) -> bool {
+        !self.warning.is_empty()
+    }
+
+    /// passes each warning to the callbacks
+    pub(crate) fn warn(&self, warnings: Vec<Warning>) {
+        for warning in &warnings {
+            for f in &self.warning {
+                f(warning);
+            }
+        }
+    }
+
+    /// runs the diff handlers on the statements they handle, returning the rest of `a` and `b`
+    /// along with what the handlers returned
+    pub(crate) fn diff<'a>(
+        &self,
+        a: &'a [Statement],
+        b: &'a [Statement],
+    ) -> Result<Split<'a, Cow<'a, [Statement]>>, DiffError> {
+        if self.diff.is_empty() {
+            return Ok((a.into(), b.into(), Vec::new()));
+        }
+        let handler = |s: &Statement| self.diff.iter().position(|h| h.handles(s));
+        let unhandled = |tree: &[Statement]| {
+            tree.iter()
+                .filter(|s| handler(s).is_none())
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let mut handled = Vec::new();
+        for (i, h) in self.diff.iter().enumerate() {
+            let claimed = |tree: &'a [Statement]| {
+                tree.iter()
+                    .filter(|s| handler(s) == Some(i))
+                    .collect::<Vec<_>>()
+            };
+            let statements = h.diff(&claimed(a), &claimed(b)).map_err(|err| {
+                DiffError::builder()
+                    .kind(DiffErrorKind::Handler(err))
+                    .build()
+            })?;
+            handled.extend(statements);
+        }
+        Ok((unhandled(a).into(), unhandled(b).into(), handled))
+    }
+
+    /// runs the migrate handlers on the statements they handle, returning the rest of `schema` and
+    /// `migration` along with what the handlers returned
+    pub(crate) fn migrate<'a>(
+        &self,
+        schema: Vec<Statement>,
+        migration: &'a [Statement],
+    ) -> Result<Split<'a, Vec<Statement>>, MigrateError> {
+        if self.migrate.is_empty() {
+            return Ok((schema, migration.into(), Vec::new()));
+        }
+        let handler = |s: &Statement| self.migrate.iter().position(|h| h.handles(s));
+        let mut claimed = vec![Vec::new(); self.migrate.len()];
+        let mut unhandled = Vec::new();
+        for s in schema {
+            match handler(&s) {
+                Some(i) => claimed[i].push(s),
+                None => unhandled.push(s),
+            }
+        }
+        let mut handled = Vec::new();
+        for ((i, h), schema) in self.migrate.iter().enumerate().zip(claimed) {
+            let migration = migration
+                .iter()
+                .filter(|s| handler(s) == Some(i))
+                .collect::<Vec<_>>();
+            let statements = h.migrate(schema, &migration).map_err(|err| {
+                MigrateError::builder()
+                    .kind(MigrateErrorKind::Handler(err))
+                    .build()
+            })?;
+            handled.extend(statements);
+        }
+        let migration = migration
+            .iter()
+            .filter(|s| handler(s).is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+        Ok((unhandled, migration.into(), handled))
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// diffs the statements `handler` handles with it (carried over to the result of
+    /// [SyntaxTree::diff] and [SyntaxTree::migrate])
+    ///
+    /// each statement is diffed by the first handler registered that handles it
+    pub fn with_diff_handler(mut self, handler: impl DiffHandler + 'static) -> Self {
+        self.handlers.diff.push(Arc::new(handler));
+        self
+    }
+
+    /// applies the statements `handler` handles with it (carried over to the result of
+    /// [SyntaxTree::diff] and [SyntaxTree::migrate])
+    ///
+    /// each statement is applied by the first handler registered that handles it
+    pub fn with_migrate_handler(mut self, handler: impl MigrateHandler + 'static) -> Self {
+        self.handlers.migrate.push(Arc::new(handler));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Generic;
+
+    /// diffs and applies `CREATE FUNCTION` by replacing functions whose definition changed
+    #[derive(Debug)]
+    struct Functions;
+
+    fn function_name(s: &Statement) -> Option<String> {
+        match s {
+            Statement::CreateFunction(f) => Some(f.name.to_string()),
+            Statement::DropFunction(drop) => Some(drop.func_desc[0].name.to_string()),
+            _ => None,
+        }
+    }
+
+    impl DiffHandler for Functions {
+        fn handles(&self, statement: &Statement) -> bool {
+            matches!(statement, Statement::CreateFunction(_))
+        }
+
+        fn diff(&self, a: &[&Statement], b: &[&Statement]) -> Result<Vec<Statement>, HandlerError> {
+            let mut statements = Vec::new();
+            for sb in b {
+                if let (false, Statement::CreateFunction(f)) = (a.contains(sb), sb) {
+                    let mut f = f.clone();
+                    f.or_replace = true;
+                    statements.push(Statement::CreateFunction(f));
+                }
+            }
+            for sa in a {
+                if !b.iter().any(|sb| function_name(sb) == function_name(sa)) {
+                    return Err(format!("dropping {sa} isn't supported").into());
+                }
+            }
+            Ok(statements)
+        }
+    }
+
+    impl MigrateHandler for Functions {
+        fn handles(&self, statement: &Statement) -> bool {
+            function_name(statement).is_some()
+        }
+
+        fn migrate(
+            &self,
+            mut schema: Vec<Statement>,
+            migration: &[&Statement],
+        ) -> Result<Vec<Statement>, HandlerError> {
+            for s in migration {
+                schema.retain(|sa| function_name(sa) != function_name(s));
+                if let Statement::CreateFunction(_) = s {
+                    schema.push((*s).clone());
+                }
+            }
+            Ok(schema)
+        }
+    }
+
+    fn parse(sql: &str) -> SyntaxTree<Generic> {
+        SyntaxTree::parse(Generic, sql)
+            .unwrap()
+            .with_diff_handler(Functions)
+            .with_migrate_handler(Functions)
+    }
+
+    #[test]
+    fn diff() {
+        let a = parse(
+            "CREATE TABLE foo (id INT);
+            CREATE FUNCTION one() RETURNS INT AS 'SELECT 1';",
+        );
+        let b = parse(
+            "CREATE TABLE foo (id INT);
+            CREATE FUNCTION one() RETURNS BIGINT AS 'SELECT 1';",
+        );
+        assert_eq!(
+            a.diff(&b).unwrap().unwrap().statements()[0].to_string(),
+            "CREATE OR REPLACE FUNCTION one() RETURNS BIGINT AS 'SELECT 1'"
+        );
+        assert!(a.diff(&a).unwrap().is_none());
+        let err = a.diff(&parse("CREATE TABLE foo (id INT);")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Oops, we couldn't diff that: dropping CREATE FUNCTION one() RETURNS INT AS 'SELECT 1' isn't supported"
+        );
+    }
+
+    #[test]
+    fn migrate() {
+        let schema = parse(
+            "CREATE FUNCTION one() RETURNS INT AS 'SELECT 1';
+            CREATE TABLE foo (id INT);",
+        );
+        let migration = parse(
+            "DROP FUNCTION one;
+            CREATE FUNCTION two() RETURNS INT AS 'SELECT 2';
+            ALTER TABLE foo ADD COLUMN name TEXT;",
+        );
+        assert_eq!(
+            schema.migrate(&migration).unwrap().to_string(),
+            "CREATE TABLE foo (id INT, name TEXT);\n\nCREATE FUNCTION two() RETURNS INT AS 'SELECT 2';"
+        );
+    }
+}
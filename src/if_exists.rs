@@ -0,0 +1,149 @@
+/*!
+Write every `CREATE`, `DROP`, and `ALTER` statement of a migration with (or without) `IF NOT EXISTS`
+and `IF EXISTS`, so a team can standardize on migrations that can (or can't) be run twice.
+*/
+
+use crate::{
+    ast::{
+        AlterTable, AlterTableOperation, AlterType, AlterTypeOperation, CreateExtension,
+        CreateIndex, CreateTable, DropDomain, DropExtension, Statement,
+    },
+    dialect::Supports,
+    SyntaxTree,
+};
+
+/// whether statements are written with `IF EXISTS` and `IF NOT EXISTS`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IfExists {
+    /// on every statement that can have them
+    Always,
+    /// on none of them
+    Never,
+    /// the way the diff wrote them (e.g. a table created with `IF NOT EXISTS` in the schema is
+    /// dropped with `IF EXISTS`)
+    #[default]
+    Preserve,
+}
+
+impl<Dialect: Supports> SyntaxTree<Dialect> {
+    /// writes each statement with `IF EXISTS` and `IF NOT EXISTS` following `policy`, where the
+    /// dialect has them
+    ///
+    /// views are left alone (PostgreSQL has no `CREATE VIEW IF NOT EXISTS`), and so are unnamed
+    /// indexes, which can't be created with `IF NOT EXISTS`
+    pub fn with_if_exists(mut self, policy: IfExists) -> Self {
+        let flag = match policy {
+            IfExists::Always => true,
+            IfExists::Never => false,
+            IfExists::Preserve => return self,
+        };
+        let alter = self.dialect.supports().alter_if_exists;
+        for s in &mut self.tree {
+            match s {
+                Statement::CreateTable(CreateTable { if_not_exists, .. })
+                | Statement::CreateExtension(CreateExtension { if_not_exists, .. }) => {
+                    *if_not_exists = flag
+                }
+                Statement::CreateIndex(CreateIndex {
+                    name: Some(_),
+                    if_not_exists,
+                    ..
+                }) => *if_not_exists = flag,
+                Statement::Drop { if_exists, .. }
+                | Statement::DropExtension(DropExtension { if_exists, .. })
+                | Statement::DropDomain(DropDomain { if_exists, .. }) => *if_exists = flag,
+                Statement::AlterTable(AlterTable {
+                    if_exists,
+                    operations,
+                    ..
+                }) if alter => {
+                    *if_exists = flag;
+                    for op in operations {
+                        match op {
+                            AlterTableOperation::AddColumn { if_not_exists, .. } => {
+                                *if_not_exists = flag
+                            }
+                            AlterTableOperation::DropColumn { if_exists, .. }
+                            | AlterTableOperation::DropConstraint { if_exists, .. } => {
+                                *if_exists = flag
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Statement::AlterType(AlterType {
+                    operation: AlterTypeOperation::AddValue(add),
+                    ..
+                }) if alter => add.if_not_exists = flag,
+                _ => {}
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::{PostgreSQL, SQLite};
+
+    #[test]
+    fn with_if_exists() {
+        let sql = |tree: SyntaxTree<_>| {
+            tree.into_statements()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        let tree = SyntaxTree::parse(
+            PostgreSQL,
+            "CREATE TABLE IF NOT EXISTS users (id INT); \
+            CREATE INDEX users_id ON users(id); \
+            CREATE INDEX ON users(id); \
+            ALTER TABLE users ADD COLUMN name TEXT; \
+            DROP TABLE IF EXISTS posts;",
+        )
+        .unwrap();
+        assert_eq!(
+            sql(tree.clone().with_if_exists(IfExists::Always)),
+            [
+                "CREATE TABLE IF NOT EXISTS users (id INT)",
+                "CREATE INDEX IF NOT EXISTS users_id ON users(id)",
+                "CREATE INDEX ON users(id)",
+                "ALTER TABLE IF EXISTS users ADD COLUMN IF NOT EXISTS name TEXT",
+                "DROP TABLE IF EXISTS posts",
+            ]
+        );
+        assert_eq!(
+            sql(tree.clone().with_if_exists(IfExists::Never)),
+            [
+                "CREATE TABLE users (id INT)",
+                "CREATE INDEX users_id ON users(id)",
+                "CREATE INDEX ON users(id)",
+                "ALTER TABLE users ADD COLUMN name TEXT",
+                "DROP TABLE posts",
+            ]
+        );
+        assert_eq!(
+            sql(tree.clone().with_if_exists(IfExists::Preserve)),
+            sql(tree)
+        );
+
+        // a schema the migrations were applied to is still the same
+        let schema = SyntaxTree::parse(
+            PostgreSQL,
+            "CREATE INDEX users_id ON users(id); CREATE EXTENSION hstore;",
+        )
+        .unwrap();
+        let applied = schema.clone().with_if_exists(IfExists::Always);
+        assert!(applied.diff(&schema).unwrap().is_none());
+
+        // SQLite has no ALTER TABLE IF EXISTS
+        let tree = SyntaxTree::parse(SQLite, "ALTER TABLE users ADD COLUMN name TEXT").unwrap();
+        assert_eq!(
+            tree.with_if_exists(IfExists::Always).statements()[0].to_string(),
+            "ALTER TABLE users ADD COLUMN name TEXT"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+/*!
+Build a [SyntaxTree] from the catalog of a live database.
+
+Each object is rendered back into DDL by the database itself (e.g. `pg_get_constraintdef`) and then parsed
+with the matching dialect, so the result can be diffed against a schema file like any other [SyntaxTree].
+*/
+
+use thiserror::Error;
+
+use crate::{ParseError, SyntaxTree};
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum IntrospectError {
+    #[cfg(feature = "postgres")]
+    #[error("Oops, we couldn't query the database: {0}")]
+    Postgres(#[from] ::postgres::Error),
+    #[cfg(feature = "sqlite")]
+    #[error("Oops, we couldn't query the database: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+pub type Result<T, E = IntrospectError> = std::result::Result<T, E>;
+
+/// introspects the tables, indexes, types, domains, and extensions of `schema` (e.g. "public")
+#[cfg(feature = "postgres")]
+pub fn postgres(
+    client: &mut ::postgres::Client,
+    schema: &str,
+) -> Result<SyntaxTree<crate::dialect::PostgreSQL>> {
+    // objects in the search path are rendered without a schema qualifier
+    client.query_one(
+        "SELECT set_config('search_path', quote_ident($1), false)",
+        &[&schema],
+    )?;
+
+    let mut statements = Vec::new();
+
+    for row in client.query(
+        "SELECT format('CREATE EXTENSION IF NOT EXISTS %I', extname)
+        FROM pg_extension
+        WHERE extname <> 'plpgsql'
+        ORDER BY extname",
+        &[],
+    )? {
+        statements.push(row.get::<_, String>(0));
+    }
+
+    for row in client.query(
+        "SELECT format(
+            'CREATE TYPE %I AS ENUM (%s)',
+            t.typname,
+            string_agg(quote_literal(e.enumlabel), ', ' ORDER BY e.enumsortorder)
+        )
+        FROM pg_type t
+        JOIN pg_enum e ON e.enumtypid = t.oid
+        JOIN pg_namespace n ON n.oid = t.typnamespace
+        WHERE n.nspname = $1
+        GROUP BY t.oid, t.typname
+        ORDER BY t.oid",
+        &[&schema],
+    )? {
+        statements.push(row.get::<_, String>(0));
+    }
+
+    for row in client.query(
+        "SELECT format(
+            'CREATE DOMAIN %I AS %s%s%s%s',
+            t.typname,
+            format_type(t.typbasetype, t.typtypmod),
+            CASE WHEN t.typdefault IS NOT NULL THEN ' DEFAULT ' || t.typdefault ELSE '' END,
+            CASE WHEN t.typnotnull THEN ' NOT NULL' ELSE '' END,
+            coalesce((
+                SELECT string_agg(' ' || pg_get_constraintdef(c.oid), '' ORDER BY c.conname)
+                FROM pg_constraint c
+                WHERE c.contypid = t.oid
+            ), '')
+        )
+        FROM pg_type t
+        JOIN pg_namespace n ON n.oid = t.typnamespace
+        WHERE n.nspname = $1 AND t.typtype = 'd'
+        ORDER BY t.oid",
+        &[&schema],
+    )? {
+        statements.push(row.get::<_, String>(0));
+    }
+
+    for row in client.query(
+        "SELECT format(
+            'CREATE TABLE %I (%s)',
+            c.relname,
+            concat_ws(', ',
+                (
+                    SELECT string_agg(
+                        format('%I ', a.attname)
+                        || CASE
+                            -- serial columns own the sequence backing their default
+                            WHEN s.serial IS NOT NULL THEN s.serial
+                            ELSE format_type(a.atttypid, a.atttypmod)
+                        END
+                        || CASE a.attidentity
+                            WHEN 'a' THEN ' GENERATED ALWAYS AS IDENTITY'
+                            WHEN 'd' THEN ' GENERATED BY DEFAULT AS IDENTITY'
+                            ELSE ''
+                        END
+                        || CASE
+                            WHEN a.attgenerated = 's' THEN ' GENERATED ALWAYS AS (' || pg_get_expr(d.adbin, d.adrelid) || ') STORED'
+                            WHEN s.serial IS NOT NULL THEN ''
+                            WHEN d.adbin IS NOT NULL THEN ' DEFAULT ' || pg_get_expr(d.adbin, d.adrelid)
+                            ELSE ''
+                        END
+                        || CASE WHEN a.attnotnull AND s.serial IS NULL THEN ' NOT NULL' ELSE '' END,
+                        ', ' ORDER BY a.attnum
+                    )
+                    FROM pg_attribute a
+                    LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+                    LEFT JOIN LATERAL (
+                        SELECT CASE format_type(a.atttypid, NULL)
+                            WHEN 'integer' THEN 'serial'
+                            WHEN 'bigint' THEN 'bigserial'
+                            WHEN 'smallint' THEN 'smallserial'
+                        END AS serial
+                        WHERE a.attidentity = ''
+                            AND pg_get_expr(d.adbin, d.adrelid) LIKE 'nextval(%'
+                            AND pg_get_serial_sequence(c.oid::regclass::text, a.attname) IS NOT NULL
+                    ) s ON true
+                    WHERE a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped
+                ),
+                (
+                    SELECT string_agg(
+                        format('CONSTRAINT %I %s', con.conname, pg_get_constraintdef(con.oid)),
+                        ', ' ORDER BY
+                            CASE con.contype WHEN 'p' THEN 0 WHEN 'u' THEN 1 WHEN 'f' THEN 2 ELSE 3 END,
+                            con.conname
+                    )
+                    FROM pg_constraint con
+                    WHERE con.conrelid = c.oid
+                )
+            )
+        )
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = $1 AND c.relkind IN ('r', 'p') AND NOT c.relispartition
+        -- creation order tends to respect foreign key dependencies
+        ORDER BY c.oid",
+        &[&schema],
+    )? {
+        statements.push(row.get::<_, String>(0));
+    }
+
+    for row in client.query(
+        "SELECT replace(pg_get_indexdef(i.indexrelid), format(' ON %I.', n.nspname), ' ON ')
+        FROM pg_index i
+        JOIN pg_class c ON c.oid = i.indexrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = $1
+            -- skip indexes backing constraints, they're part of CREATE TABLE
+            AND NOT EXISTS (SELECT 1 FROM pg_constraint con WHERE con.conindid = i.indexrelid)
+        ORDER BY c.oid",
+        &[&schema],
+    )? {
+        // btree is the default index method, so it's typically omitted from schema files
+        let sql = row.get::<_, String>(0).replace(" USING btree (", " (");
+        statements.push(sql);
+    }
+
+    let sql = statements.join(";\n");
+    Ok(SyntaxTree::parse(crate::dialect::PostgreSQL, sql.as_str())?)
+}
+
+/// introspects the tables and indexes of a SQLite database
+#[cfg(feature = "sqlite")]
+pub fn sqlite(conn: &rusqlite::Connection) -> Result<SyntaxTree<crate::dialect::SQLite>> {
+    let mut stmt = conn.prepare(
+        "SELECT sql
+        FROM sqlite_master
+        WHERE type IN ('table', 'index')
+            AND sql IS NOT NULL
+            AND name NOT LIKE 'sqlite_%'
+        ORDER BY type DESC, rowid",
+    )?;
+    let statements = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let sql = statements.join(";\n");
+    Ok(SyntaxTree::parse(crate::dialect::SQLite, sql.as_str())?)
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqlite() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL UNIQUE);
+            CREATE INDEX users_email_idx ON users (email);",
+        )
+        .unwrap();
+
+        let tree = sqlite(&conn).unwrap();
+        assert_eq!(
+            tree.to_string(),
+            "CREATE TABLE users (\n  id INTEGER PRIMARY KEY,\n  email TEXT NOT NULL UNIQUE\n);\n\nCREATE INDEX users_email_idx ON users(email);"
+        );
+    }
+}
@@ -1,61 +1,279 @@
 use std::fmt;
 
+use bon::bon;
+
 use self::ast::Statement;
 
+/// the version of sqlparser whose AST [SyntaxTree::statements] returns
+pub use sqlparser;
+
 pub use self::{
     diff::TreeDiffer,
+    handler::{DiffHandler, HandlerError, MigrateHandler},
+    if_exists::IfExists,
     migration::TreeMigrator,
-    parser::{Parse, ParseError},
+    parser::{Parse, ParseError, Skipped},
+    plan::{Backfill, ExpandContract, LockImpact, LockLevel, ZeroDowntime},
+    provenance::Provenance,
+    render::Render,
+    verify::verify,
+    warning::Warning,
 };
 
 mod ast;
+#[cfg(feature = "atlas")]
+pub mod atlas;
+#[cfg(any(feature = "wasm", feature = "python"))]
+mod bindings;
+pub mod changes;
+#[cfg(feature = "codegen")]
+pub mod codegen;
 pub mod dialect;
 mod diff;
+pub mod graph;
+mod handler;
+mod if_exists;
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub mod introspect;
+pub mod load;
 mod migration;
+pub mod migrations;
 pub mod name_gen;
 mod parser;
 pub mod path_template;
+pub mod pg_dump;
+mod plan;
+mod provenance;
+#[cfg(feature = "python")]
+pub mod python;
+mod rebuild;
+mod render;
+pub mod schema;
 mod sealed;
+pub mod tracking;
+pub mod transpile;
+pub mod validate;
+pub mod verify;
+mod warning;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 #[derive(Debug, Clone)]
 pub struct SyntaxTree<Dialect> {
     dialect: Dialect,
+    format: FormatOptions,
     pub(crate) tree: Vec<Statement>,
+    /// where each statement was parsed from (empty when none of it is known)
+    pub(crate) provenance: Vec<Option<Provenance>>,
+    handlers: handler::Handlers,
 }
 
 impl<Dialect: Default> SyntaxTree<Dialect> {
     pub fn empty() -> Self {
         Self {
             dialect: Default::default(),
+            format: FormatOptions::default(),
             tree: Vec::with_capacity(0),
+            provenance: Vec::new(),
+            handlers: Default::default(),
+        }
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// sets how the tree is rendered as SQL (carried over to the result of [SyntaxTree::diff] and
+    /// [SyntaxTree::migrate])
+    pub fn with_format(self, format: FormatOptions) -> Self {
+        Self { format, ..self }
+    }
+
+    /// the parsed statements, in order
+    pub fn statements(&self) -> &[Statement] {
+        &self.tree
+    }
+
+    pub fn into_statements(self) -> Vec<Statement> {
+        self.tree
+    }
+
+    /// appends a statement to the end of the tree
+    pub fn push(&mut self, statement: Statement) {
+        if !self.provenance.is_empty() {
+            self.provenance.resize(self.tree.len(), None);
+            self.provenance.push(None);
+        }
+        self.tree.push(statement);
+    }
+
+    /// keeps only the statements matching `f` (see [SyntaxTree::retain] to match on a [Change]
+    /// instead)
+    ///
+    /// [Change]: changes::Change
+    pub fn retain_statements(&mut self, f: impl FnMut(&Statement) -> bool) {
+        self.retain_with_provenance(f);
+    }
+}
+
+impl<Dialect: Default> From<Vec<Statement>> for SyntaxTree<Dialect> {
+    fn from(tree: Vec<Statement>) -> Self {
+        Self {
+            dialect: Default::default(),
+            format: FormatOptions::default(),
+            tree,
+            provenance: Vec::new(),
+            handlers: Default::default(),
+        }
+    }
+}
+
+impl<Dialect: Clone> SyntaxTree<Dialect> {
+    /// splits the tree into one tree per statement
+    pub fn split(&self) -> Vec<Self> {
+        self.tree
+            .iter()
+            .enumerate()
+            .map(|(i, s)| Self {
+                dialect: self.dialect.clone(),
+                format: self.format,
+                tree: vec![s.clone()],
+                provenance: self.provenance(i).cloned().into_iter().map(Some).collect(),
+                handlers: self.handlers.clone(),
+            })
+            .collect()
+    }
+}
+
+/// how a [SyntaxTree] is laid out when rendered as SQL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FormatOptions {
+    pub indent: Indent,
+    /// uppercase (`Some(true)`) or lowercase (`Some(false)`) reserved keywords, leaving them as
+    /// they are by default
+    pub uppercase: Option<bool>,
+    /// blank lines between statements
+    pub lines_between: u8,
+}
+
+impl FormatOptions {
+    pub fn new(indent: Indent, uppercase: Option<bool>) -> Self {
+        Self {
+            indent,
+            uppercase,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_lines_between(self, lines_between: u8) -> Self {
+        Self {
+            lines_between,
+            ..self
         }
     }
 }
 
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: Indent::Spaces(2),
+            uppercase: None,
+            lines_between: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(u8),
+    Tabs,
+}
+
 impl<Dialect> SyntaxTree<Dialect>
 where
     Dialect: Parse,
 {
     pub fn parse<'a>(dialect: Dialect, sql: impl Into<&'a str>) -> Result<Self, ParseError> {
-        let tree = dialect.parse_sql::<Dialect>(sql)?;
-        Ok(Self { dialect, tree })
+        let (tree, provenance) = dialect.parse_located(sql)?;
+        Ok(Self {
+            dialect,
+            format: FormatOptions::default(),
+            tree,
+            provenance: provenance.into_iter().map(Some).collect(),
+            handlers: Default::default(),
+        })
+    }
+
+    /// parses a schema, leaving out the statements that can't be parsed and the ones the diff
+    /// doesn't support (e.g. `CREATE FUNCTION`) instead of failing, and returning what was left out
+    pub fn parse_lenient<'a>(dialect: Dialect, sql: impl Into<&'a str>) -> (Self, Vec<Skipped>) {
+        let (mut tree, errors) = dialect.parse_statements(sql);
+        let mut skipped = errors
+            .into_iter()
+            .map(Skipped::Unparsable)
+            .collect::<Vec<_>>();
+        tree.retain(|s| {
+            let supported = diff::is_supported(s);
+            if !supported {
+                skipped.push(Skipped::Unsupported(Box::new(s.clone())));
+            }
+            supported
+        });
+        let tree = Self {
+            dialect,
+            format: FormatOptions::default(),
+            tree,
+            provenance: Vec::new(),
+            handlers: Default::default(),
+        };
+        (tree, skipped)
     }
 }
 
-pub use diff::DiffError;
-pub use migration::MigrateError;
+#[bon]
+impl SyntaxTree<dialect::Custom> {
+    /// parses SQL with a sqlparser dialect of your own (see [dialect::Custom]), e.g.
+    /// `SyntaxTree::builder().sqlparser_dialect(Box::new(MyDialect)).sql(sql).parse()`
+    #[builder(start_fn = builder, finish_fn = parse)]
+    pub fn parse_custom(
+        sqlparser_dialect: Box<dyn sqlparser::dialect::Dialect>,
+        sql: &str,
+        #[builder(default)] format: FormatOptions,
+    ) -> Result<Self, ParseError> {
+        let dialect = dialect::Custom::from(sqlparser_dialect);
+        Ok(SyntaxTree::parse(dialect, sql)?.with_format(format))
+    }
+}
+
+pub use diff::{DiffError, DiffErrorKind, Unnamed};
+pub use migration::{ApplyError, MigrateError, MigrateErrorKind};
 
 impl<Dialect> SyntaxTree<Dialect>
 where
     Dialect: TreeDiffer,
 {
+    /// the migration from this tree to `other` (diffing the statements a handler of this tree
+    /// handles with it, see [DiffHandler])
     pub fn diff(&self, other: &SyntaxTree<Dialect>) -> Result<Option<Self>, DiffError> {
-        Ok(
-            TreeDiffer::diff_tree(&self.dialect, &self.tree, &other.tree)?.map(|tree| Self {
-                dialect: self.dialect.clone(),
-                tree,
-            }),
-        )
+        let (a, b, handled) = self.handlers.diff(&self.tree, &other.tree)?;
+        let mut tree = TreeDiffer::diff_tree(&self.dialect, &a, &b).map_err(|err| {
+            let a = self.provenance_of(err.statement_a());
+            let b = other.provenance_of(err.statement_b());
+            err.with_provenance(a, b)
+        })?;
+        if self.handlers.warns() {
+            self.handlers
+                .warn(warning::diff_warnings(&a, &b, self.dialect.supports()));
+        }
+        if !handled.is_empty() {
+            tree.get_or_insert_with(Vec::new).extend(handled);
+        }
+        Ok(tree.map(|tree| Self {
+            dialect: self.dialect.clone(),
+            format: self.format,
+            tree,
+            provenance: Vec::new(),
+            handlers: self.handlers.clone(),
+        }))
     }
 }
 
@@ -63,33 +281,126 @@ impl<Dialect> SyntaxTree<Dialect>
 where
     Dialect: TreeMigrator,
 {
-    pub fn migrate(self, other: &SyntaxTree<Dialect>) -> Result<Self, MigrateError> {
-        let tree = TreeMigrator::migrate_tree(&self.dialect, self.tree, &other.tree)?;
-        Ok(Self {
-            dialect: self.dialect.clone(),
-            tree,
-        })
+    /// applies the migration `other` to this tree (applying the statements a handler of this tree
+    /// handles with it, see [MigrateHandler])
+    pub fn migrate(mut self, other: &SyntaxTree<Dialect>) -> Result<Self, MigrateError> {
+        self.migrate_in_place(other)?;
+        Ok(self)
+    }
+
+    /// applies each migration to this tree in order, without copying the tree for each one like
+    /// [SyntaxTree::migrate] does
+    ///
+    /// when a migration can't be applied, the error says which one and the tree is left as it was
+    /// before any of them were applied
+    pub fn apply_all(
+        &mut self,
+        migrations: impl IntoIterator<Item = SyntaxTree<Dialect>>,
+    ) -> Result<(), ApplyError> {
+        // each migration takes the statements it's applied to, so they're kept to put back
+        let (tree, provenance) = (self.tree.clone(), self.provenance.clone());
+        for (index, migration) in migrations.into_iter().enumerate() {
+            if let Err(source) = self.migrate_in_place(&migration) {
+                (self.tree, self.provenance) = (tree, provenance);
+                let path = migration.provenance(0).and_then(Provenance::path);
+                return Err(ApplyError::new(index, path, source));
+            }
+        }
+        Ok(())
+    }
+
+    fn migrate_in_place(&mut self, other: &SyntaxTree<Dialect>) -> Result<(), MigrateError> {
+        // the statements are moved into the migration, so what they create is all that's left to
+        // tell where each migrated one came from
+        let objects = match self.provenance.is_empty() {
+            true => Vec::new(),
+            false => self.tree.iter().map(provenance::object).collect(),
+        };
+        let tree = std::mem::take(&mut self.tree);
+        let (a, b, handled) = self.handlers.migrate(tree, &other.tree)?;
+        let skipped = match self.handlers.warns() {
+            true => warning::migrate_warnings(&a, &b),
+            false => Vec::new(),
+        };
+        let mut tree = TreeMigrator::migrate_tree(&self.dialect, a, &b).map_err(|err| {
+            let a = err
+                .subject()
+                .and_then(|s| provenance::of_object(&objects, &self.provenance, s));
+            let b = other.provenance_of(err.migration());
+            err.with_provenance(a, b)
+        })?;
+        tree.extend(handled);
+        self.handlers.warn(skipped);
+        self.provenance = provenance::carry_over(&objects, &self.provenance, other, &tree);
+        self.tree = tree;
+        Ok(())
     }
 }
 
 impl<Dialect> fmt::Display for SyntaxTree<Dialect> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut iter = self.tree.iter().peekable();
-        while let Some(s) = iter.next() {
-            let formatted = sqlformat::format(
-                format!("{s};").as_str(),
-                &sqlformat::QueryParams::None,
-                &sqlformat::FormatOptions::default(),
-            );
-            write!(f, "{formatted}")?;
-            if iter.peek().is_some() {
-                write!(f, "\n\n")?;
+        let statements = self.formatted(|s| format!("{s};"));
+        write!(f, "{}", self.join(statements, |_| false))
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// formats each statement as written by `sql`
+    fn formatted(&self, sql: impl Fn(&Statement) -> String) -> Vec<String> {
+        self.tree
+            .iter()
+            .map(|s| match s {
+                // sqlformat breaks a line after `BEGIN`, as if it started a block
+                Statement::StartTransaction { .. } => sql(s),
+                _ => format_sql(&sql(s), &self.format),
+            })
+            .collect()
+    }
+
+    /// joins the SQL of each statement with [FormatOptions::lines_between] blank lines, ending the
+    /// batch before and after each statement `own_batch` says has to be in one of its own with a
+    /// `GO` line (unless its SQL starts with one already)
+    fn join(&self, statements: Vec<String>, own_batch: impl Fn(&Statement) -> bool) -> String {
+        let separator = "\n".repeat(usize::from(self.format.lines_between) + 1);
+        let mut sql = String::new();
+        for (i, statement) in statements.into_iter().enumerate() {
+            if i > 0 {
+                let batch = own_batch(&self.tree[i - 1]) || own_batch(&self.tree[i]);
+                let separated = statement
+                    .lines()
+                    .next()
+                    .is_some_and(parser::is_batch_separator);
+                if batch && !separated {
+                    sql.push_str("\nGO");
+                }
+                sql.push_str(&separator);
             }
+            sql.push_str(&statement);
         }
-        Ok(())
+        sql
     }
 }
 
+fn format_statement(s: &Statement, options: &FormatOptions) -> String {
+    format_sql(&format!("{s};"), options)
+}
+
+fn format_sql(sql: &str, options: &FormatOptions) -> String {
+    let indent = match options.indent {
+        Indent::Spaces(n) => sqlformat::Indent::Spaces(n),
+        Indent::Tabs => sqlformat::Indent::Tabs,
+    };
+    sqlformat::format(
+        sql,
+        &sqlformat::QueryParams::None,
+        &sqlformat::FormatOptions {
+            indent,
+            uppercase: options.uppercase,
+            ..Default::default()
+        },
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::dialect::Generic;
@@ -147,7 +458,245 @@ mod tests {
         assert_eq!(actual.to_string(), tc.expect, "{tc:?}");
     }
 
+    #[test]
+    fn format_options() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE foo (id INT, name TEXT, CHECK (LENGTH(name) > 0 AND LENGTH(name) < 100))",
+        )
+        .unwrap()
+        .with_format(FormatOptions::new(Indent::Tabs, Some(false)));
+        assert_eq!(
+            tree.to_string(),
+            "create table foo (\n\tid INT,\n\tname TEXT,\n\tcheck (\n\t\tLENGTH(name) > 0\n\t\tand LENGTH(name) < 100\n\t)\n);"
+        );
+    }
+
+    #[test]
+    fn statements() {
+        let mut tree =
+            SyntaxTree::parse(Generic, "CREATE TABLE foo (id INT); DROP TABLE bar;").unwrap();
+        let extra = SyntaxTree::<Generic>::parse(Generic, "CREATE INDEX idx ON foo (id);")
+            .unwrap()
+            .into_statements();
+        tree.push(extra[0].clone());
+        tree.retain_statements(|s| !matches!(s, Statement::Drop { .. }));
+        assert_eq!(tree.statements().len(), 2);
+        let tree = SyntaxTree::<Generic>::from(tree.into_statements());
+        assert_eq!(
+            tree.to_string(),
+            "CREATE TABLE foo (id INT);\n\nCREATE INDEX idx ON foo(id);"
+        );
+    }
+
+    #[test]
+    fn lines_between() {
+        let tree = SyntaxTree::parse(Generic, "DROP TABLE foo; DROP TABLE bar;").unwrap();
+        let format = FormatOptions::default();
+        assert_eq!(
+            tree.clone()
+                .with_format(format.with_lines_between(0))
+                .to_string(),
+            "DROP TABLE foo;\nDROP TABLE bar;"
+        );
+        assert_eq!(
+            tree.with_format(format.with_lines_between(2)).to_string(),
+            "DROP TABLE foo;\n\n\nDROP TABLE bar;"
+        );
+    }
+
+    #[test]
+    fn custom_dialect() {
+        // identifiers can start with `$`, which no built-in dialect allows
+        #[derive(Debug)]
+        struct DollarDialect;
+
+        impl sqlparser::dialect::Dialect for DollarDialect {
+            fn is_identifier_start(&self, ch: char) -> bool {
+                ch.is_alphabetic() || ch == '_' || ch == '$'
+            }
+
+            fn is_identifier_part(&self, ch: char) -> bool {
+                ch.is_alphanumeric() || ch == '_' || ch == '$'
+            }
+        }
+
+        let parse = |sql| {
+            SyntaxTree::builder()
+                .sqlparser_dialect(Box::new(DollarDialect))
+                .sql(sql)
+                .parse()
+                .unwrap()
+        };
+        let a = parse("CREATE TABLE $users (id INT);");
+        let b = parse("CREATE TABLE $users (id INT, $name TEXT);");
+        assert_eq!(
+            a.diff(&b).unwrap().unwrap().to_string(),
+            "ALTER TABLE\n  $users\nADD\n  COLUMN $name TEXT;"
+        );
+    }
+
+    #[test]
+    fn split() {
+        let tree =
+            SyntaxTree::parse(Generic, "CREATE TABLE foo (id INT); DROP TABLE bar;").unwrap();
+        let actual = tree
+            .split()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, ["CREATE TABLE foo (id INT);", "DROP TABLE bar;"]);
+    }
+
+    #[test]
+    fn parse_lenient() {
+        let (tree, skipped) = SyntaxTree::parse_lenient(
+            Generic,
+            "CREATE TABLE foo (id INT);
+            CREATE TABLE bar (id INT,);
+            CREATE VIEW baz AS SELECT id FROM foo;
+            CREATE INDEX foo_id ON foo (id)",
+        );
+        assert_eq!(
+            tree.to_string(),
+            "CREATE TABLE foo (id INT);\n\nCREATE INDEX foo_id ON foo(id);"
+        );
+        let skipped = skipped
+            .iter()
+            .map(|s| match s {
+                Skipped::Unparsable(err) => format!("{:?}", err.location()),
+                s => s.to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            [
+                "Some((2, 38))",
+                "Oops, we can't diff CREATE VIEW baz AS SELECT id FROM foo"
+            ]
+        );
+    }
+
+    #[test]
+    fn error_kinds() {
+        let a =
+            SyntaxTree::parse(Generic, "CREATE TYPE status AS ENUM ('open', 'closed');").unwrap();
+        let b = SyntaxTree::parse(Generic, "CREATE TYPE status AS ENUM ('open');").unwrap();
+        let err = a.diff(&b).unwrap_err();
+        assert!(matches!(err.kind(), DiffErrorKind::RemoveEnumLabel));
+        assert_eq!(
+            err.statement_a().unwrap().to_string(),
+            "CREATE TYPE status AS ENUM ('open', 'closed')"
+        );
+        assert_eq!(
+            err.statement_b().unwrap().to_string(),
+            "CREATE TYPE status AS ENUM ('open')"
+        );
+
+        let a = SyntaxTree::parse(Generic, "CREATE TABLE foo (id INT);").unwrap();
+        let b = SyntaxTree::parse(Generic, "ALTER TABLE foo DROP PRIMARY KEY;").unwrap();
+        let err = a.migrate(&b).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            MigrateErrorKind::AlterTableOpNotImplemented(_)
+        ));
+        assert_eq!(
+            err.subject().unwrap().to_string(),
+            "CREATE TABLE foo (id INT)"
+        );
+        assert_eq!(
+            err.migration().unwrap().to_string(),
+            "ALTER TABLE foo DROP PRIMARY KEY"
+        );
+    }
+
+    #[test]
+    fn alter_table_converges() {
+        let schemas = [
+            "CREATE TABLE foo (id INT, bar TEXT DEFAULT 'a', baz INT CONSTRAINT foo_baz_check CHECK (baz > 0));",
+            "CREATE TABLE foo (id BIGINT CONSTRAINT foo_pkey PRIMARY KEY, bar TEXT NOT NULL, baz INT, CONSTRAINT foo_bar_key UNIQUE (bar));",
+            "CREATE TABLE foo (id BIGINT, bar VARCHAR(255) CONSTRAINT foo_bar_fkey REFERENCES bar (name), qux INT CONSTRAINT foo_qux_key UNIQUE);",
+        ];
+        for a in schemas {
+            for b in schemas {
+                let a = SyntaxTree::parse(Generic, a).unwrap();
+                let b = SyntaxTree::parse(Generic, b).unwrap();
+                let Some(diff) = a.diff(&b).unwrap() else {
+                    continue;
+                };
+                let migrated = a.migrate(&diff).unwrap();
+                assert!(migrated.diff(&b).unwrap().is_none(), "{diff}");
+            }
+        }
+        // MySQL modifies the whole column instead
+        for a in schemas {
+            for b in schemas {
+                let a = SyntaxTree::parse(dialect::MySQL, a).unwrap();
+                let b = SyntaxTree::parse(dialect::MySQL, b).unwrap();
+                let Some(diff) = a.diff(&b).unwrap() else {
+                    continue;
+                };
+                let migrated = a.migrate(&diff).unwrap();
+                assert!(migrated.diff(&b).unwrap().is_none(), "{diff}");
+            }
+        }
+    }
+
+    #[test]
+    fn drop_unnamed_constraint() {
+        let sql_a = "CREATE TABLE foo (id INT, bar INT CHECK (bar > 0));";
+        let sql_b = "CREATE TABLE foo (id INT, bar INT);";
+        let a = SyntaxTree::parse(Generic, sql_a).unwrap();
+        let b = SyntaxTree::parse(Generic, sql_b).unwrap();
+        let err = a.diff(&b).unwrap_err();
+        assert!(matches!(err.kind(), DiffErrorKind::DropUnnamedConstraint));
+
+        // SQLite rebuilds the table to drop it instead
+        let a = SyntaxTree::parse(dialect::SQLite, sql_a).unwrap();
+        let b = SyntaxTree::parse(dialect::SQLite, sql_b).unwrap();
+        assert!(a.diff(&b).unwrap().is_none());
+    }
+
+    #[test]
+    fn apply_all() {
+        let mut schema = SyntaxTree::empty();
+        let migrations = [
+            "CREATE TABLE foo (id INT);",
+            "ALTER TABLE foo ADD COLUMN name TEXT;",
+            "CREATE TABLE bar (id INT);",
+        ]
+        .map(|sql| SyntaxTree::parse(Generic, sql).unwrap());
+        schema.apply_all(migrations).unwrap();
+        assert_eq!(
+            schema.to_string(),
+            "CREATE TABLE foo (id INT, name TEXT);\n\nCREATE TABLE bar (id INT);"
+        );
+
+        let migrations = ["DROP TABLE bar;", "ALTER TABLE foo DROP PRIMARY KEY;"]
+            .map(|sql| SyntaxTree::parse(Generic, sql).unwrap());
+        let err = schema.clone().apply_all(migrations.clone()).unwrap_err();
+        assert_eq!(err.index(), 1);
+        assert_eq!(err.to_string(), "Oops, we couldn't apply migration 2");
+        let migrations = migrations.map(|migration| migration.with_path("0002.sql"));
+        let err = schema.apply_all(migrations).unwrap_err();
+        assert_eq!(err.to_string(), "Oops, we couldn't apply 0002.sql");
+        assert!(matches!(
+            err.error().kind(),
+            MigrateErrorKind::AlterTableOpNotImplemented(_)
+        ));
+        // the tree is the same as before, including the migration that was applied
+        assert_eq!(
+            schema.to_string(),
+            "CREATE TABLE foo (id INT, name TEXT);\n\nCREATE TABLE bar (id INT);"
+        );
+    }
+
     mod test_diff {
+        use crate::{
+            dialect::{MsSQL, MySQL, PostgreSQL},
+            diff::StatementDiffer,
+        };
+
         use super::*;
 
         test_case!(
@@ -219,6 +768,78 @@ mod tests {
                 expect: "ALTER TABLE\n  foo DROP COLUMN bar;",
             },
 
+            alter_column_type_a {
+                sql_a: "CREATE TABLE foo (id INT, bar TEXT);",
+                sql_b: "CREATE TABLE foo (id BIGINT, bar TEXT);",
+                expect: "ALTER TABLE\n  foo\nALTER COLUMN\n  id TYPE BIGINT;",
+            },
+
+            alter_column_not_null_a {
+                sql_a: "CREATE TABLE foo (id INT, bar TEXT);",
+                sql_b: "CREATE TABLE foo (id INT, bar TEXT NOT NULL);",
+                expect: "ALTER TABLE\n  foo\nALTER COLUMN\n  bar\nSET\n  NOT NULL;",
+            },
+
+            alter_column_not_null_b {
+                sql_a: "CREATE TABLE foo (id INT, bar TEXT NOT NULL);",
+                sql_b: "CREATE TABLE foo (id INT, bar TEXT);",
+                expect: "ALTER TABLE\n  foo\nALTER COLUMN\n  bar DROP NOT NULL;",
+            },
+
+            alter_column_default_a {
+                sql_a: "CREATE TABLE foo (id INT, bar TEXT DEFAULT 'a');",
+                sql_b: "CREATE TABLE foo (id INT, bar TEXT DEFAULT 'b');",
+                expect: "ALTER TABLE\n  foo\nALTER COLUMN\n  bar\nSET\n  DEFAULT 'b';",
+            },
+
+            alter_column_default_b {
+                sql_a: "CREATE TABLE foo (id INT, bar TEXT DEFAULT 'a');",
+                sql_b: "CREATE TABLE foo (id INT, bar TEXT);",
+                expect: "ALTER TABLE\n  foo\nALTER COLUMN\n  bar DROP DEFAULT;",
+            },
+
+            alter_column_a {
+                sql_a: "CREATE TABLE foo (id INT, bar TEXT);",
+                sql_b: "CREATE TABLE foo (id INT, bar VARCHAR(255) NOT NULL DEFAULT '');",
+                expect: "ALTER TABLE\n  foo\nALTER COLUMN\n  bar TYPE VARCHAR(255),\nALTER COLUMN\n  bar\nSET\n  NOT NULL,\nALTER COLUMN\n  bar\nSET\n  DEFAULT '';",
+            },
+
+            add_constraint_a {
+                sql_a: "CREATE TABLE foo (id INT, bar TEXT);",
+                sql_b: "CREATE TABLE foo (id INT, bar TEXT, CONSTRAINT foo_bar_key UNIQUE (bar));",
+                expect: "ALTER TABLE\n  foo\nADD\n  CONSTRAINT foo_bar_key UNIQUE (bar);",
+            },
+
+            add_constraint_b {
+                sql_a: "CREATE TABLE foo (id INT, bar_id INT);",
+                sql_b: "CREATE TABLE foo (id INT, bar_id INT REFERENCES bar (id) ON DELETE CASCADE);",
+                expect: "ALTER TABLE\n  foo\nADD\n  FOREIGN KEY (bar_id) REFERENCES bar(id) ON DELETE CASCADE;",
+            },
+
+            drop_constraint_a {
+                sql_a: "CREATE TABLE foo (id INT, bar INT CONSTRAINT foo_bar_check CHECK (bar > 0));",
+                sql_b: "CREATE TABLE foo (id INT, bar INT);",
+                expect: "ALTER TABLE\n  foo DROP CONSTRAINT foo_bar_check;",
+            },
+
+            alter_constraint_a {
+                sql_a: "CREATE TABLE foo (id INT, bar INT, CONSTRAINT foo_bar_check CHECK (bar > 0));",
+                sql_b: "CREATE TABLE foo (id INT, bar INT, CONSTRAINT foo_bar_check CHECK (bar >= 0));",
+                expect: "ALTER TABLE\n  foo DROP CONSTRAINT foo_bar_check,\nADD\n  CONSTRAINT foo_bar_check CHECK (bar >= 0);",
+            },
+
+            drop_column_b {
+                sql_a: "CREATE TABLE foo (id INT, bar INT UNIQUE, baz INT, PRIMARY KEY (id, baz));",
+                sql_b: "CREATE TABLE foo (id INT NOT NULL);",
+                expect: "ALTER TABLE\n  foo DROP COLUMN bar,\n  DROP COLUMN baz;",
+            },
+
+            add_column_b {
+                sql_a: "CREATE TABLE foo (id INT);",
+                sql_b: "CREATE TABLE foo (id INT, bar_id INT NOT NULL REFERENCES bar (id), UNIQUE (bar_id));",
+                expect: "ALTER TABLE\n  foo\nADD\n  COLUMN bar_id INT NOT NULL REFERENCES bar (id),\nADD\n  UNIQUE (bar_id);",
+            },
+
             create_index_a {
                 sql_a: "CREATE UNIQUE INDEX title_idx ON films (title);",
                 sql_b: "CREATE UNIQUE INDEX title_idx ON films ((lower(title)));",
@@ -279,6 +900,18 @@ mod tests {
                 expect: "DROP EXTENSION hstore;\n\nCREATE EXTENSION IF NOT EXISTS \"uuid-ossp\";",
             },
 
+            create_extension_b {
+                sql_a: "CREATE EXTENSION hstore;",
+                sql_b: "CREATE EXTENSION hstore; CREATE TABLE foo (id INT PRIMARY KEY);",
+                expect: "CREATE TABLE foo (id INT PRIMARY KEY);",
+            },
+
+            create_extension_c {
+                sql_a: "CREATE EXTENSION hstore;",
+                sql_b: "CREATE EXTENSION hstore WITH SCHEMA ext;",
+                expect: "DROP EXTENSION IF EXISTS hstore;\n\nCREATE EXTENSION hstore WITH SCHEMA ext;",
+            },
+
             => |ast_a, ast_b| {
                 ast_a.diff(&ast_b)
             }
@@ -303,10 +936,154 @@ mod tests {
                 ast_a.diff(&ast_b)
             }
         );
+
+        // unnamed indexes are compared by the name PostgreSQL gives them
+        test_case!(
+            @dialect(PostgreSQL)
+
+            drop_unnamed_index_a {
+                sql_a: "CREATE TABLE users (email TEXT); CREATE INDEX ON users (lower(email));",
+                sql_b: "CREATE TABLE users (email TEXT);",
+                expect: "DROP INDEX users_lower_idx;",
+            },
+
+            edit_unnamed_index_a {
+                sql_a: "CREATE INDEX users_email_idx ON users (email);",
+                sql_b: "CREATE UNIQUE INDEX ON users (email);",
+                expect: "DROP INDEX users_email_idx;\n\nCREATE UNIQUE INDEX ON users(email);",
+            },
+
+            => |ast_a, ast_b| {
+                ast_a.diff(&ast_b)
+            }
+        );
+
+        // MySQL changes a column's type or nullability by modifying the whole column, and drops
+        // indexes from their table
+        test_case!(
+            @dialect(MySQL)
+
+            modify_column_a {
+                sql_a: "CREATE TABLE users (id INT PRIMARY KEY, name TEXT);",
+                sql_b: "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255) NOT NULL DEFAULT '');",
+                expect: "ALTER TABLE\n  users\nMODIFY\n  COLUMN name VARCHAR(255) NOT NULL DEFAULT '';",
+            },
+
+            drop_index_a {
+                sql_a: "CREATE TABLE users (email TEXT); CREATE INDEX users_email ON users (email);",
+                sql_b: "CREATE TABLE users (email TEXT);",
+                expect: "DROP INDEX users_email ON users;",
+            },
+
+            => |ast_a, ast_b| {
+                ast_a.diff(&ast_b)
+            }
+        );
+
+        // T-SQL adds columns without the COLUMN keyword, and drops indexes from their table
+        test_case!(
+            @dialect(MsSQL)
+
+            add_column_without_keyword_a {
+                sql_a: "CREATE TABLE users (id INT); CREATE INDEX users_id ON users (id);",
+                sql_b: "CREATE TABLE users (id INT, name NVARCHAR(MAX));",
+                expect: "ALTER TABLE\n  users\nADD\n  name NVARCHAR(MAX);\n\nDROP INDEX users_id ON users;",
+            },
+
+            => |ast_a, ast_b| {
+                ast_a.diff(&ast_b)
+            }
+        );
+
+        #[test]
+        fn default_names() {
+            let dialect = PostgreSQL;
+            let name = |columns: &[&str], kind| {
+                let columns = columns.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+                dialect.default_name("public.users", &columns, kind)
+            };
+            assert_eq!(name(&["id"], Unnamed::PrimaryKey).unwrap(), "users_pkey");
+            assert_eq!(
+                name(&["email"], Unnamed::Unique).unwrap(),
+                "users_email_key"
+            );
+            assert_eq!(
+                name(&["team_id", "\"Role\""], Unnamed::ForeignKey).unwrap(),
+                "users_team_id_Role_fkey"
+            );
+            assert_eq!(
+                name(&["(a + b)"], Unnamed::Index).unwrap(),
+                "users_expr_idx"
+            );
+            assert_eq!(name(&[&"x".repeat(60)], Unnamed::Index), None);
+            assert_eq!(
+                Generic.default_name("users", &[], Unnamed::PrimaryKey),
+                None
+            );
+
+            // an introspected index matches the unnamed one in the schema file
+            let schema = SyntaxTree::parse(PostgreSQL, "CREATE INDEX ON users (email);").unwrap();
+            let introspected =
+                SyntaxTree::parse(PostgreSQL, "CREATE INDEX users_email_idx ON users (email);")
+                    .unwrap();
+            assert!(introspected.diff(&schema).unwrap().is_none());
+            assert!(schema.diff(&introspected).unwrap().is_none());
+        }
+
+        #[test]
+        fn default_constraint_names() {
+            let diff = |a: &str, b: &str| {
+                let a = SyntaxTree::parse(PostgreSQL, a).unwrap();
+                let b = SyntaxTree::parse(PostgreSQL, b).unwrap();
+                a.diff(&b).unwrap().map(|diff| diff.to_string())
+            };
+            // each kind of introspected constraint matches the unnamed one in the schema file
+            for (unnamed, named) in [
+                (
+                    "CREATE TABLE users (id INT PRIMARY KEY);",
+                    "CREATE TABLE users (id INT, CONSTRAINT users_pkey PRIMARY KEY (id));",
+                ),
+                (
+                    "CREATE TABLE users (email TEXT UNIQUE);",
+                    "CREATE TABLE users (email TEXT CONSTRAINT users_email_key UNIQUE);",
+                ),
+                (
+                    "CREATE TABLE users (team_id INT, FOREIGN KEY (team_id) REFERENCES teams (id));",
+                    "CREATE TABLE users (team_id INT, CONSTRAINT users_team_id_fkey FOREIGN KEY (team_id) REFERENCES teams (id));",
+                ),
+                (
+                    "CREATE TABLE users (age INT CHECK (age >= 0));",
+                    "CREATE TABLE users (age INT CONSTRAINT users_age_check CHECK (age >= 0));",
+                ),
+                (
+                    "CREATE TABLE users (age INT, CHECK (0 <= age));",
+                    "CREATE TABLE users (age INT, CONSTRAINT users_age_check CHECK (0 <= age));",
+                ),
+            ] {
+                assert_eq!(diff(named, unnamed), None, "{unnamed}");
+                assert_eq!(diff(unnamed, named), None, "{unnamed}");
+            }
+
+            // an unnamed constraint is dropped by the name the database gave it
+            assert_eq!(
+                diff(
+                    "CREATE TABLE users (email TEXT UNIQUE);",
+                    "CREATE TABLE users (email TEXT);",
+                )
+                .unwrap(),
+                "ALTER TABLE\n  users DROP CONSTRAINT users_email_key;"
+            );
+            // the name doesn't match when it's not the default one
+            assert!(diff(
+                "CREATE TABLE users (email TEXT CONSTRAINT email_unique UNIQUE);",
+                "CREATE TABLE users (email TEXT UNIQUE);",
+            )
+            .is_some());
+        }
     }
 
     mod migrate {
-        use crate::dialect::PostgreSQL;
+        use crate::dialect::{MySQL, PostgreSQL};
 
         use super::*;
 
@@ -337,6 +1114,18 @@ mod tests {
                 expect: "CREATE TABLE bar (id INT PRIMARY KEY);",
             },
 
+            alter_table_drop_column_b {
+                sql_a: "CREATE TABLE bar (bar TEXT, baz INT, id INT, UNIQUE (bar, baz), PRIMARY KEY (id))",
+                sql_b: "ALTER TABLE bar DROP COLUMN bar",
+                expect: "CREATE TABLE bar (baz INT, id INT, PRIMARY KEY (id));",
+            },
+
+            alter_table_drop_constraint_b {
+                sql_a: "CREATE TABLE bar (bar TEXT CONSTRAINT bar_bar_key UNIQUE, id INT PRIMARY KEY)",
+                sql_b: "ALTER TABLE bar DROP CONSTRAINT bar_bar_key",
+                expect: "CREATE TABLE bar (bar TEXT, id INT PRIMARY KEY);",
+            },
+
             alter_table_alter_column_a {
                 sql_a: "CREATE TABLE bar (bar TEXT, id INT PRIMARY KEY)",
                 sql_b: "ALTER TABLE bar ALTER COLUMN bar SET NOT NULL",
@@ -445,6 +1234,18 @@ mod tests {
                 expect: "CREATE EXTENSION IF NOT EXISTS \"uuid-ossp\";",
             },
 
+            alter_table_rename_a {
+                sql_a: "CREATE TABLE foo (id INT); CREATE INDEX foo_idx ON foo (id);",
+                sql_b: "ALTER TABLE foo RENAME TO bar;",
+                expect: "CREATE TABLE bar (id INT);\n\nCREATE INDEX foo_idx ON bar(id);",
+            },
+
+            rebuild_table_a {
+                sql_a: "CREATE TABLE foo (id INT, name TEXT); CREATE INDEX foo_idx ON foo (name);",
+                sql_b: "PRAGMA foreign_keys = 0; BEGIN; CREATE TABLE new_foo (id INT NOT NULL, name TEXT); INSERT INTO new_foo (id, name) SELECT id, name FROM foo; DROP TABLE foo; ALTER TABLE new_foo RENAME TO foo; CREATE INDEX foo_idx ON foo (name); PRAGMA foreign_key_check; COMMIT; PRAGMA foreign_keys = 1;",
+                expect: "CREATE TABLE foo (id INT NOT NULL, name TEXT);\n\nCREATE INDEX foo_idx ON foo(name);",
+            },
+
             => |ast_a, ast_b| {
                 Some(ast_a.migrate(&ast_b)).transpose()
             }
@@ -471,6 +1272,38 @@ mod tests {
                 expect: "CREATE DOMAIN positive_int AS INTEGER CHECK (VALUE > 0);",
             },
 
+            alter_table_add_constraint_a {
+                sql_a: "CREATE TABLE bar (id INT PRIMARY KEY, foo_id INT)",
+                sql_b: "ALTER TABLE bar ADD CONSTRAINT bar_foo_id_fkey FOREIGN KEY (foo_id) REFERENCES foo (id) NOT VALID; ALTER TABLE bar VALIDATE CONSTRAINT bar_foo_id_fkey;",
+                expect: "CREATE TABLE bar (\n  id INT PRIMARY KEY,\n  foo_id INT,\n  CONSTRAINT bar_foo_id_fkey FOREIGN KEY (foo_id) REFERENCES foo(id)\n);",
+            },
+
+            alter_table_drop_constraint_a {
+                sql_a: "CREATE TABLE bar (id INT PRIMARY KEY, foo_id INT, CONSTRAINT bar_foo_id_check CHECK (foo_id IS NOT NULL))",
+                sql_b: "ALTER TABLE bar ALTER COLUMN foo_id SET NOT NULL; ALTER TABLE bar DROP CONSTRAINT bar_foo_id_check;",
+                expect: "CREATE TABLE bar (id INT PRIMARY KEY, foo_id INT NOT NULL);",
+            },
+
+            create_index_concurrently_a {
+                sql_a: "CREATE TABLE bar (id INT PRIMARY KEY)",
+                sql_b: "CREATE INDEX CONCURRENTLY bar_idx ON bar (id);",
+                expect: "CREATE TABLE bar (id INT PRIMARY KEY);\n\nCREATE INDEX bar_idx ON bar(id);",
+            },
+
+            => |ast_a, ast_b| {
+                Some(ast_a.migrate(&ast_b)).transpose()
+            }
+        );
+
+        test_case!(
+            @dialect(MySQL)
+
+            alter_table_modify_column_a {
+                sql_a: "CREATE TABLE bar (id INT PRIMARY KEY, name TEXT DEFAULT ''); CREATE INDEX bar_idx ON bar (name);",
+                sql_b: "ALTER TABLE bar MODIFY COLUMN name VARCHAR(255) NOT NULL, ALGORITHM = COPY; DROP INDEX bar_idx ON bar;",
+                expect: "CREATE TABLE bar (id INT PRIMARY KEY, name VARCHAR(255) NOT NULL);",
+            },
+
             => |ast_a, ast_b| {
                 Some(ast_a.migrate(&ast_b)).transpose()
             }
@@ -0,0 +1,341 @@
+/*!
+Load a schema that's split across a directory of SQL files (e.g. one file per table under `schema/tables`).
+*/
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{collections::HashMap, fs};
+use std::{fmt, io};
+
+#[cfg(not(target_arch = "wasm32"))]
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use thiserror::Error;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    ast::Statement,
+    changes::{classify, Operation},
+    FormatOptions, Parse, Provenance, SyntaxTree,
+};
+use crate::{changes::ObjectKind, path_template, ApplyError, ParseError};
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum LoadError {
+    #[error("Oops, we couldn't read {path}: {source}")]
+    Io {
+        path: Utf8PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Oops, we couldn't parse {path}")]
+    Parse {
+        path: Utf8PathBuf,
+        #[source]
+        source: ParseError,
+    },
+    #[error("Oops, {} object(s) are defined more than once", .0.len())]
+    Conflicts(Vec<Conflict>),
+    #[error("Oops, {path} is missing a name")]
+    MissingName { path: Utf8PathBuf },
+    #[error("Oops, we couldn't tell how {path} is named")]
+    PathTemplate {
+        path: Utf8PathBuf,
+        #[source]
+        source: path_template::ParseError,
+    },
+    #[error(transparent)]
+    Apply(ApplyError),
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for LoadError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let code = match self {
+            Self::Io { .. } => "sql_schema::load::io",
+            Self::Parse { .. } => "sql_schema::load::parse",
+            Self::Conflicts(_) => "sql_schema::load::conflicts",
+            Self::MissingName { .. } => "sql_schema::load::missing_name",
+            Self::PathTemplate { .. } => "sql_schema::load::path_template",
+            Self::Apply(err) => return miette::Diagnostic::code(err),
+        };
+        Some(Box::new(code))
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn miette::Diagnostic> {
+        match self {
+            Self::Parse { source, .. } => Some(source),
+            Self::PathTemplate { source, .. } => Some(source),
+            Self::Apply(err) => miette::Diagnostic::diagnostic_source(err),
+            _ => None,
+        }
+    }
+}
+
+/// an object created by more than one statement when merging schema files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub object: ObjectKind,
+    pub name: String,
+    /// the file it was first created in
+    pub first: Utf8PathBuf,
+    /// the file it was created in again (the same as `first` for duplicates within a file)
+    pub second: Utf8PathBuf,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            object,
+            name,
+            first,
+            second,
+        } = self;
+        if first == second {
+            write!(f, "{object} {name} is created more than once in {first}")
+        } else {
+            write!(f, "{object} {name} is created in both {first} and {second}")
+        }
+    }
+}
+
+pub type Result<T, E = LoadError> = std::result::Result<T, E>;
+
+#[cfg(not(target_arch = "wasm32"))]
+/// finds every `.sql` file under `dir` sorted by path
+pub fn sql_files(dir: impl AsRef<Utf8Path>) -> Result<Vec<Utf8PathBuf>> {
+    fn collect(dir: &Utf8Path, files: &mut Vec<Utf8PathBuf>) -> Result<()> {
+        let io_err = |source| LoadError::Io {
+            path: dir.to_owned(),
+            source,
+        };
+        for entry in dir.read_dir_utf8().map_err(io_err)? {
+            let entry = entry.map_err(io_err)?;
+            let file_type = entry.file_type().map_err(io_err)?;
+            let path = entry.into_path();
+            if file_type.is_dir() {
+                collect(&path, files)?;
+            } else if path.extension() == Some("sql") {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    collect(dir.as_ref(), &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<Dialect> SyntaxTree<Dialect>
+where
+    Dialect: Parse,
+{
+    /// parses a single schema file
+    pub fn from_file(dialect: Dialect, path: impl AsRef<Utf8Path>) -> Result<Self> {
+        Self::parse_files(dialect, [path])
+    }
+
+    /// parses the up section of a migration file (all of it unless it's split into
+    /// [sections](crate::migrations::split_sections))
+    pub fn from_migration(dialect: Dialect, path: impl AsRef<Utf8Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let sql = read_file(path)?;
+        let (up, _) = crate::migrations::split_sections(&sql);
+        let (tree, provenance) = parse_sql(&dialect, path, up)?;
+        Ok(Self {
+            dialect,
+            format: FormatOptions::default(),
+            tree,
+            provenance: provenance.into_iter().map(Some).collect(),
+            handlers: Default::default(),
+        })
+    }
+
+    /// parses every `.sql` file under `dir` (in path order) as a single schema
+    pub fn from_dir(dialect: Dialect, dir: impl AsRef<Utf8Path>) -> Result<Self> {
+        Self::parse_files(dialect, sql_files(dir)?)
+    }
+
+    /// parses each file in order as a single schema
+    pub fn parse_files(
+        dialect: Dialect,
+        paths: impl IntoIterator<Item = impl AsRef<Utf8Path>>,
+    ) -> Result<Self> {
+        let mut tree = Vec::new();
+        let mut provenance = Vec::new();
+        for path in paths {
+            let (statements, located) = parse_file(&dialect, path.as_ref())?;
+            tree.extend(statements);
+            provenance.extend(located.into_iter().map(Some));
+        }
+        Ok(Self {
+            dialect,
+            format: FormatOptions::default(),
+            tree,
+            provenance,
+            handlers: Default::default(),
+        })
+    }
+
+    /// parses each file in order as a single schema, failing with every table, index, type, etc.
+    /// that's created more than once
+    pub fn merge_files(
+        dialect: Dialect,
+        paths: impl IntoIterator<Item = impl AsRef<Utf8Path>>,
+    ) -> Result<Self> {
+        let format = FormatOptions::default();
+        let mut tree = Vec::new();
+        let mut provenance = Vec::new();
+        let mut created = HashMap::<(ObjectKind, String), Utf8PathBuf>::new();
+        let mut conflicts = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            let (statements, located) = parse_file(&dialect, path)?;
+            provenance.extend(located.into_iter().map(Some));
+            for s in statements {
+                let change = classify(&s, &format);
+                if change.operation == Operation::Create && !change.name.is_empty() {
+                    let key = (change.object, change.name);
+                    match created.get(&key) {
+                        Some(first) => conflicts.push(Conflict {
+                            object: key.0,
+                            name: key.1,
+                            first: first.clone(),
+                            second: path.to_owned(),
+                        }),
+                        None => {
+                            created.insert(key, path.to_owned());
+                        }
+                    }
+                }
+                tree.push(s);
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(LoadError::Conflicts(conflicts));
+        }
+        Ok(Self {
+            dialect,
+            format,
+            tree,
+            provenance,
+            handlers: Default::default(),
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_file(path: &Utf8Path) -> Result<String> {
+    fs::read_to_string(path).map_err(|source| LoadError::Io {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_file<Dialect: Parse>(
+    dialect: &Dialect,
+    path: &Utf8Path,
+) -> Result<(Vec<Statement>, Vec<Provenance>)> {
+    parse_sql(dialect, path, &read_file(path)?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_sql<Dialect: Parse>(
+    dialect: &Dialect,
+    path: &Utf8Path,
+    sql: &str,
+) -> Result<(Vec<Statement>, Vec<Provenance>)> {
+    let (statements, provenance) =
+        dialect
+            .parse_located(sql)
+            .map_err(|source| LoadError::Parse {
+                path: path.to_owned(),
+                source: source.with_path(path),
+            })?;
+    let provenance = provenance.into_iter().map(|p| p.with_path(path)).collect();
+    Ok((statements, provenance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Generic;
+
+    #[test]
+    fn from_file() {
+        let path = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("sql-schema-file-{}.sql", std::process::id()));
+        fs::write(&path, "CREATE TABLE users (id INT);\nCREATE TABLE (").unwrap();
+
+        let tree = SyntaxTree::from_file(Generic, &path);
+        fs::remove_file(&path).unwrap();
+        let err = tree.unwrap_err();
+        assert!(matches!(&err, LoadError::Parse { path: p, .. } if *p == path));
+        assert_eq!(err.to_string(), format!("Oops, we couldn't parse {path}"));
+        assert!(matches!(
+            SyntaxTree::from_file(Generic, &path),
+            Err(LoadError::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn from_dir() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("sql-schema-load-{}", std::process::id()));
+        fs::create_dir_all(dir.join("tables")).unwrap();
+        fs::create_dir_all(dir.join("types")).unwrap();
+        fs::write(dir.join("tables/users.sql"), "CREATE TABLE users (id INT);").unwrap();
+        fs::write(
+            dir.join("types/status.sql"),
+            "CREATE TYPE status AS ENUM ('a');",
+        )
+        .unwrap();
+        fs::write(dir.join("README.md"), "not sql").unwrap();
+
+        let tree = SyntaxTree::from_dir(Generic, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            tree.unwrap().to_string(),
+            "CREATE TABLE users (id INT);\n\nCREATE TYPE status AS ENUM ('a');"
+        );
+    }
+
+    #[test]
+    fn merge_files() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("sql-schema-merge-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.sql"), "CREATE TABLE users (id INT);").unwrap();
+        fs::write(dir.join("b.sql"), "CREATE TABLE posts (id INT);").unwrap();
+        fs::write(dir.join("c.sql"), "CREATE TABLE users (id BIGINT);").unwrap();
+
+        let merged = SyntaxTree::merge_files(Generic, [dir.join("a.sql"), dir.join("b.sql")]);
+        let conflict = SyntaxTree::merge_files(Generic, sql_files(&dir).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            merged.unwrap().to_string(),
+            "CREATE TABLE users (id INT);\n\nCREATE TABLE posts (id INT);"
+        );
+        let Err(LoadError::Conflicts(conflicts)) = conflict else {
+            panic!("expected a conflict");
+        };
+        assert_eq!(
+            conflicts
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [format!(
+                "table users is created in both {} and {}",
+                dir.join("a.sql"),
+                dir.join("c.sql")
+            )]
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt;
 
 use bon::bon;
+use camino::{Utf8Path, Utf8PathBuf};
 use sqlparser::ast::{CreateDomain, CreateIndex};
 use thiserror::Error;
 
@@ -9,7 +10,8 @@ use crate::{
         AlterTable, AlterTableOperation, AlterType, AlterTypeOperation, CreateExtension,
         CreateTable, CreateType, Statement,
     },
-    dialect::{Generic, PostgreSQL, SQLite},
+    dialect::{Custom, Generic, MsSQL, MySQL, PostgreSQL, SQLite},
+    provenance::Provenance,
     sealed::Sealed,
 };
 
@@ -20,6 +22,8 @@ pub struct MigrateError {
     kind: MigrateErrorKind,
     statement_a: Option<Box<Statement>>,
     statement_b: Option<Box<Statement>>,
+    provenance_a: Option<Box<Provenance>>,
+    provenance_b: Option<Box<Provenance>>,
 }
 
 impl fmt::Display for MigrateError {
@@ -29,11 +33,18 @@ impl fmt::Display for MigrateError {
             "Oops, we couldn't migrate that: {reason}",
             reason = self.kind
         )?;
-        if let Some(statement_a) = &self.statement_a {
-            write!(f, "\n\nSubject:\n{statement_a}")?;
-        }
-        if let Some(statement_b) = &self.statement_b {
-            write!(f, "\n\nMigration:\n{statement_b}")?;
+        let statements = [
+            ("Subject", &self.statement_a, &self.provenance_a),
+            ("Migration", &self.statement_b, &self.provenance_b),
+        ];
+        for (label, statement, provenance) in statements {
+            let Some(statement) = statement else {
+                continue;
+            };
+            match provenance {
+                Some(provenance) => write!(f, "\n\n{label} ({provenance}):\n{statement}")?,
+                None => write!(f, "\n\n{label}:\n{statement}")?,
+            }
         }
         Ok(())
     }
@@ -42,7 +53,7 @@ impl fmt::Display for MigrateError {
 #[bon]
 impl MigrateError {
     #[builder]
-    fn new(
+    pub(crate) fn new(
         kind: MigrateErrorKind,
         statement_a: Option<Statement>,
         statement_b: Option<Statement>,
@@ -51,19 +62,135 @@ impl MigrateError {
             kind,
             statement_a: statement_a.map(Box::new),
             statement_b: statement_b.map(Box::new),
+            provenance_a: None,
+            provenance_b: None,
         }
     }
+
+    /// notes where the statements were parsed from
+    pub(crate) fn with_provenance(self, a: Option<Provenance>, b: Option<Provenance>) -> Self {
+        Self {
+            provenance_a: a.map(Box::new),
+            provenance_b: b.map(Box::new),
+            ..self
+        }
+    }
+
+    /// why the migration couldn't be applied
+    pub fn kind(&self) -> &MigrateErrorKind {
+        &self.kind
+    }
+
+    /// the statement being migrated, when there is one
+    pub fn subject(&self) -> Option<&Statement> {
+        self.statement_a.as_deref()
+    }
+
+    /// the statement from the migration, when there is one
+    pub fn migration(&self) -> Option<&Statement> {
+        self.statement_b.as_deref()
+    }
+
+    /// where the statement being migrated was first parsed from (before any earlier migrations
+    /// were applied to it), when that's known
+    pub fn subject_provenance(&self) -> Option<&Provenance> {
+        self.provenance_a.as_deref()
+    }
+
+    /// where the statement from the migration was parsed from, when that's known
+    pub fn migration_provenance(&self) -> Option<&Provenance> {
+        self.provenance_b.as_deref()
+    }
 }
 
 #[derive(Error, Debug)]
 #[non_exhaustive]
-enum MigrateErrorKind {
+pub enum MigrateErrorKind {
     #[error("ALTER TABLE operation \"{0}\" not yet supported")]
     AlterTableOpNotImplemented(Box<AlterTableOperation>),
     #[error("invalid ALTER TYPE operation \"{0}\"")]
     AlterTypeInvalidOp(Box<AlterTypeOperation>),
     #[error("not yet supported")]
     NotImplemented,
+    /// a [MigrateHandler](crate::MigrateHandler) failed
+    #[error("{0}")]
+    Handler(crate::HandlerError),
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for MigrateError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let code = match self.kind {
+            MigrateErrorKind::AlterTableOpNotImplemented(_) => "alter_table_op_not_implemented",
+            MigrateErrorKind::AlterTypeInvalidOp(_) => "alter_type_invalid_op",
+            MigrateErrorKind::NotImplemented => "not_implemented",
+            MigrateErrorKind::Handler(_) => "handler",
+        };
+        Some(Box::new(format!("sql_schema::migrate::{code}")))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        match self.kind {
+            MigrateErrorKind::AlterTypeInvalidOp(_) | MigrateErrorKind::Handler(_) => None,
+            _ => Some(Box::new(
+                "sql-schema can't apply this statement to the schema yet; write the schema change by hand",
+            )),
+        }
+    }
+}
+
+/// a migration [SyntaxTree::apply_all](crate::SyntaxTree::apply_all) couldn't apply
+#[derive(Error, Debug)]
+pub struct ApplyError {
+    index: usize,
+    path: Option<Utf8PathBuf>,
+    #[source]
+    source: MigrateError,
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "Oops, we couldn't apply {path}"),
+            None => write!(f, "Oops, we couldn't apply migration {}", self.index + 1),
+        }
+    }
+}
+
+impl ApplyError {
+    pub(crate) fn new(index: usize, path: Option<&Utf8Path>, source: MigrateError) -> Self {
+        Self {
+            index,
+            path: path.map(ToOwned::to_owned),
+            source,
+        }
+    }
+
+    /// the position of the migration that failed (counted from 0)
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// the file the migration was read from, when it was read from one
+    pub fn path(&self) -> Option<&Utf8Path> {
+        self.path.as_deref()
+    }
+
+    /// why the migration couldn't be applied
+    pub fn error(&self) -> &MigrateError {
+        &self.source
+    }
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for ApplyError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new("sql_schema::apply"))
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn miette::Diagnostic> {
+        Some(&self.source)
+    }
 }
 
 type Result<T, E = MigrateError> = std::result::Result<T, E>;
@@ -121,10 +248,16 @@ pub trait TreeMigrator: StatementMigrator + Sealed {
 
 impl TreeMigrator for Generic {}
 
+impl TreeMigrator for Custom {}
+
 impl TreeMigrator for PostgreSQL {}
 
 impl TreeMigrator for SQLite {}
 
+impl TreeMigrator for MySQL {}
+
+impl TreeMigrator for MsSQL {}
+
 pub trait StatementMigrator: fmt::Debug + Default + Clone + Sized + Sealed {
     fn migrate(&self, a: &Statement, b: &Statement) -> Result<Vec<Statement>> {
         generic::statement::migrate(self, a, b)
@@ -165,6 +298,12 @@ pub trait StatementMigrator: fmt::Debug + Default + Clone + Sized + Sealed {
 
 impl StatementMigrator for Generic {}
 
+impl StatementMigrator for Custom {}
+
 impl StatementMigrator for PostgreSQL {}
 
 impl StatementMigrator for SQLite {}
+
+impl StatementMigrator for MySQL {}
+
+impl StatementMigrator for MsSQL {}
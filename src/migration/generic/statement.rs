@@ -2,10 +2,12 @@ use crate::{
     ast::{
         AlterColumnOperation, AlterTable, AlterTableOperation, AlterType,
         AlterTypeAddValuePosition, AlterTypeOperation, ColumnOption, ColumnOptionDef, CreateDomain,
-        CreateExtension, CreateIndex, CreateTable, CreateType, GeneratedAs, ObjectName,
-        ObjectNamePart, ObjectType, Statement, UserDefinedTypeRepresentation,
+        CreateExtension, CreateIndex, CreateTable, CreateType, GeneratedAs, Ident, ObjectName,
+        ObjectNamePart, ObjectType, RenameTableNameKind, Statement, TableConstraint,
+        UserDefinedTypeRepresentation,
     },
     migration::{MigrateError, MigrateErrorKind, Result, StatementMigrator},
+    schema,
 };
 
 pub fn migrate<Dialect: StatementMigrator>(
@@ -74,6 +76,27 @@ pub fn migrate_create_index<Dialect: StatementMigrator>(
     sb: &Statement,
 ) -> Result<Vec<Statement>> {
     match sb {
+        Statement::Drop {
+            object_type: ObjectType::Table,
+            names,
+            ..
+        } => {
+            assert!(
+                names.contains(&a.table_name),
+                "attempt to apply DROP {names:?} to index on {}",
+                a.table_name
+            );
+            Ok(Vec::with_capacity(0))
+        }
+        Statement::AlterTable(b) => {
+            let mut a = a.clone();
+            for op in &b.operations {
+                if let AlterTableOperation::RenameTable { table_name } = op {
+                    a.table_name = renamed(&a.table_name, table_name);
+                }
+            }
+            Ok(vec![Statement::CreateIndex(a)])
+        }
         Statement::Drop {
             object_type, names, ..
         } => {
@@ -195,8 +218,15 @@ pub fn migrate_alter_table<Dialect: StatementMigrator>(
                 a.columns.push(column_def.clone());
             }
             AlterTableOperation::DropColumn { column_names, .. } => {
-                a.columns
-                    .retain(|c| !column_names.iter().any(|name| c.name.value == name.value));
+                let dropped = |name: &str| column_names.iter().any(|c| c.value == name);
+                a.columns.retain(|c| !dropped(&c.name.value));
+                // the constraints on a column are dropped along with it
+                a.constraints.retain(|c| {
+                    let columns = schema::table_constraint(c)
+                        .and_then(|c| c.columns().map(<[String]>::to_vec))
+                        .unwrap_or_default();
+                    !columns.iter().any(|column| dropped(column))
+                });
             }
             AlterTableOperation::AlterColumn { column_name, op } => {
                 a.columns.iter_mut().for_each(|c| {
@@ -253,12 +283,61 @@ pub fn migrate_alter_table<Dialect: StatementMigrator>(
                     }
                 });
             }
+            AlterTableOperation::AddConstraint { constraint, .. } => {
+                a.constraints.push(constraint.clone());
+            }
+            AlterTableOperation::ModifyColumn {
+                col_name,
+                data_type,
+                options,
+                ..
+            } => {
+                for c in a.columns.iter_mut().filter(|c| c.name == *col_name) {
+                    // the constraints on the column are kept (e.g. its primary key)
+                    let constraints = c.options.drain(..).filter(|def| {
+                        matches!(
+                            def.option,
+                            ColumnOption::PrimaryKey(_)
+                                | ColumnOption::Unique(_)
+                                | ColumnOption::ForeignKey(_)
+                                | ColumnOption::Check(_)
+                        )
+                    });
+                    let constraints = constraints.collect::<Vec<_>>();
+                    c.data_type = data_type.clone();
+                    c.options = options
+                        .iter()
+                        .map(|option| ColumnOptionDef {
+                            name: None,
+                            option: option.clone(),
+                        })
+                        .chain(constraints)
+                        .collect();
+                }
+            }
+            // validating a constraint added as NOT VALID doesn't change the schema, and neither
+            // does how MySQL makes a change (`ALGORITHM = ...` and `LOCK = ...`)
+            AlterTableOperation::ValidateConstraint { .. }
+            | AlterTableOperation::Algorithm { .. }
+            | AlterTableOperation::Lock { .. } => {}
+            AlterTableOperation::RenameTable { table_name } => {
+                a.name = renamed(&a.name, table_name);
+            }
+            AlterTableOperation::DropConstraint { name, .. } => {
+                a.constraints.retain(|c| constraint_name(c) != Some(name));
+                for column in &mut a.columns {
+                    column
+                        .options
+                        .retain(|def| column_constraint_name(def) != Some(name));
+                }
+            }
             op => {
                 return Err(MigrateError::builder()
                     .kind(MigrateErrorKind::AlterTableOpNotImplemented(Box::new(
                         op.clone(),
                     )))
                     .statement_a(Statement::CreateTable(a.clone()))
+                    .statement_b(Statement::AlterTable(b.clone()))
                     .build())
             }
         }
@@ -267,6 +346,44 @@ pub fn migrate_alter_table<Dialect: StatementMigrator>(
     Ok(vec![Statement::CreateTable(a)])
 }
 
+/// the name of a table after `RENAME TO` (which keeps the schema it's in when the new name isn't
+/// qualified)
+fn renamed(name: &ObjectName, new_name: &RenameTableNameKind) -> ObjectName {
+    let (RenameTableNameKind::As(new_name) | RenameTableNameKind::To(new_name)) = new_name;
+    match new_name.0.as_slice() {
+        [part] => {
+            let mut name = name.clone();
+            name.0.pop();
+            name.0.push(part.clone());
+            name
+        }
+        _ => new_name.clone(),
+    }
+}
+
+fn constraint_name(constraint: &TableConstraint) -> Option<&Ident> {
+    match constraint {
+        TableConstraint::Unique(c) => c.name.as_ref(),
+        TableConstraint::PrimaryKey(c) => c.name.as_ref(),
+        TableConstraint::ForeignKey(c) => c.name.as_ref(),
+        TableConstraint::Check(c) => c.name.as_ref(),
+        TableConstraint::Index(c) => c.name.as_ref(),
+        _ => None,
+    }
+}
+
+/// the name of a constraint declared on a column (e.g. `CONSTRAINT users_email_key UNIQUE`)
+fn column_constraint_name(def: &ColumnOptionDef) -> Option<&Ident> {
+    let name = match &def.option {
+        ColumnOption::PrimaryKey(c) => c.name.as_ref(),
+        ColumnOption::Unique(c) => c.name.as_ref(),
+        ColumnOption::ForeignKey(c) => c.name.as_ref(),
+        ColumnOption::Check(c) => c.name.as_ref(),
+        _ => return None,
+    };
+    def.name.as_ref().or(name)
+}
+
 pub fn migrate_alter_type<Dialect: StatementMigrator>(
     _dialect: &Dialect,
     a: &CreateType,
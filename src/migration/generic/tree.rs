@@ -1,7 +1,7 @@
 use crate::{
     ast::{
-        AlterTable, CreateDomain, CreateExtension, CreateIndex, CreateTable, CreateType,
-        DropExtension, ObjectType, Statement,
+        AlterTable, AlterTableOperation, CreateDomain, CreateExtension, CreateIndex, CreateTable,
+        CreateType, DropExtension, ObjectType, Statement,
     },
     migration::{MigrateError, MigrateErrorKind, Result, StatementMigrator, TreeMigrator},
 };
@@ -36,9 +36,19 @@ pub fn migrate_tree<Dialect: TreeMigrator>(
                 .build()),
         })
         // CREATE table etc.
-        .chain(b.iter().filter_map(|sb| match sb {
-            Statement::CreateTable(_)
-            | Statement::CreateIndex { .. }
+        .chain(b.iter().enumerate().filter_map(|(i, sb)| match sb {
+            // building an index concurrently is how it's applied, not part of the schema
+            Statement::CreateIndex(index) if index.concurrently => {
+                Some(Ok(vec![Statement::CreateIndex(CreateIndex {
+                    concurrently: false,
+                    ..index.clone()
+                })]))
+            }
+            // tables can be altered later on in the migration that creates them
+            Statement::CreateTable(table) => {
+                Some(dialect.match_and_migrate_create_table(sb, table, &b[i + 1..]))
+            }
+            Statement::CreateIndex { .. }
             | Statement::CreateType { .. }
             | Statement::CreateExtension { .. }
             | Statement::CreateDomain(..) => Some(Ok(vec![sb.clone()])),
@@ -61,12 +71,20 @@ where
     Dialect: StatementMigrator,
     MF: Fn(&&Statement) -> bool,
 {
-    b.iter().find(match_fn).map_or_else(
-        // keep the statement as-is if there's no counterpart
-        || Ok(vec![sa.clone()]),
-        // otherwise diff the two statements
-        |sb| StatementMigrator::migrate(dialect, sa, sb),
-    )
+    // keep the statement as-is if there's no counterpart, otherwise apply each counterpart in
+    // order (e.g. several ALTER TABLEs for one table)
+    let mut migrated = vec![sa.clone()];
+    for sb in b.iter().filter(match_fn) {
+        tracing::debug!(statement = %sa, migration = %sb, "migrating statement");
+        migrated = migrated
+            .iter()
+            .map(|sa| StatementMigrator::migrate(dialect, sa, sb))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+    }
+    Ok(migrated)
 }
 
 pub fn match_and_migrate_create_table<Dialect: TreeMigrator>(
@@ -92,11 +110,23 @@ pub fn match_and_migrate_create_index<Dialect: TreeMigrator>(
 ) -> Result<Vec<Statement>> {
     match_and_migrate(dialect, sa, b, |sb| match sb {
         Statement::Drop {
-            object_type, names, ..
-        } => {
-            *object_type == ObjectType::Index
-                && names.len() == 1
-                && Some(&names[0]) == a.name.as_ref()
+            object_type: ObjectType::Index,
+            names,
+            ..
+        } => names.len() == 1 && Some(&names[0]) == a.name.as_ref(),
+        // dropping or renaming the table drops or moves its indexes along with it
+        Statement::Drop {
+            object_type: ObjectType::Table,
+            names,
+            ..
+        } => names.contains(&a.table_name),
+        Statement::AlterTable(AlterTable {
+            name, operations, ..
+        }) => {
+            *name == a.table_name
+                && operations
+                    .iter()
+                    .any(|op| matches!(op, AlterTableOperation::RenameTable { .. }))
         }
         _ => false,
     })
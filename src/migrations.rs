@@ -0,0 +1,553 @@
+/*!
+Build a schema from a directory of migrations, the same way the `sql-schema` CLI does.
+
+Each migration is a `.sql` file, e.g. `0001_create_users.sql`, `0001_create_users.up.sql`, or
+`0001_create_users/up.sql` (`.pgsql` and `.ddl` files are migrations too, and [Options] can name
+other extensions). Down migrations (`.down.sql`, `.undo.sql`, `down.sql`, or `undo.sql`, in any
+case such as `.DOWN.sql`) are paired with the up migration of the same name and never applied, and a down migration without
+one is skipped. Directories holding seed data (named `seeds` by default) are skipped too.
+
+A migration can also hold its down migration in the same file, after a `-- migrate:down` line
+(dbmate's layout, which starts the up migration with `-- migrate:up`). Only the up section is
+applied.
+
+Flyway's repeatable migrations (`R__<name>.sql`) aren't versioned, so they're applied after all the
+others (in order of name). Since the schema is rebuilt from every migration, editing one of them
+re-applies its new content.
+*/
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    load::{LoadError, Result},
+    path_template::{DateBounds, PathTemplate},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{path_template::UpDown, FormatOptions, Parse, SyntaxTree, TreeMigrator};
+
+/// directories with this name hold seed data, so they're skipped by default
+pub const SEEDS_DIR_NAME: &str = "seeds";
+
+/// starts the up section of a migration holding both its up and down migrations (dbmate's layout)
+pub const UP_MARKER: &str = "-- migrate:up";
+
+/// starts the down section of a migration holding both its up and down migrations
+pub const DOWN_MARKER: &str = "-- migrate:down";
+
+/// the first line of a migration that can't run inside a transaction (e.g. one building an index
+/// with `CREATE INDEX CONCURRENTLY`), the way sqlx marks them
+pub const NO_TRANSACTION_MARKER: &str = "-- no-transaction";
+
+/// dbmate's option on the `-- migrate:up` line for a migration that can't run inside a
+/// transaction
+pub const DBMATE_NO_TRANSACTION: &str = "transaction:false";
+
+/// the file extensions of migrations (without the `.`) unless [Options] says otherwise
+pub const DEFAULT_EXTENSIONS: &[&str] = &["sql", "pgsql", "ddl"];
+
+/// how [find_migrations] reads the migrations dir
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Options {
+    /// directories with this name are skipped (none are when `None`)
+    pub skip_dir: Option<String>,
+    /// files with one of these extensions (without the `.`) are migrations
+    pub extensions: Vec<String>,
+    /// the dates a timestamp in a migration name plausibly falls between
+    pub date_bounds: DateBounds,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            skip_dir: Some(SEEDS_DIR_NAME.to_owned()),
+            extensions: DEFAULT_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+            date_bounds: DateBounds::default(),
+        }
+    }
+}
+
+/// a migration in the migrations dir along with its down counterpart (if any)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationFile {
+    /// path relative to the migrations dir without any up/down suffix or file extension
+    pub name: Utf8PathBuf,
+    pub up: Utf8PathBuf,
+    pub down: Option<Utf8PathBuf>,
+}
+
+impl MigrationFile {
+    /// the leading version component of the migration name (e.g. a timestamp or counter), or the
+    /// whole name of a [repeatable](MigrationFile::is_repeatable) migration
+    pub fn version(&self) -> &str {
+        let name = self.name.file_name().unwrap_or(self.name.as_str());
+        if self.is_repeatable() {
+            return name;
+        }
+        name.split_once('_').map_or(name, |(version, _)| version)
+    }
+
+    /// whether this is one of Flyway's repeatable migrations (`R__<name>.sql`), which is applied
+    /// after the versioned ones, and again whenever its content changes
+    pub fn is_repeatable(&self) -> bool {
+        self.name
+            .file_name()
+            .is_some_and(|name| name.starts_with("R__"))
+    }
+
+    /// the down migration, read from its own file or the down section of the up migration
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_down(&self) -> Result<Option<String>> {
+        let path = self.down.as_ref().unwrap_or(&self.up);
+        let sql = fs::read_to_string(path).map_err(|source| LoadError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        if self.down.is_some() {
+            return Ok(Some(sql));
+        }
+        Ok(split_sections(&sql).1.map(ToOwned::to_owned))
+    }
+}
+
+/// the migrations in a dir and the naming convention they follow
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migrations {
+    /// sorted by path
+    pub files: Vec<MigrationFile>,
+    /// the naming convention of the most recent migration (the default when there are none)
+    pub path_template: PathTemplate,
+}
+
+impl Migrations {
+    /// finds the migrations in `dir` and detects their naming convention
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn find(dir: impl AsRef<Utf8Path>, options: &Options) -> Result<Self> {
+        let dir = dir.as_ref();
+        let files = find_migrations(dir, options)?;
+        let path_template = detect_path_template(dir, &files, options)?;
+        Ok(Self {
+            files,
+            path_template,
+        })
+    }
+
+    /// whether migrations are named with an up/down suffix (and so come with down migrations)
+    pub fn includes_down(&self) -> bool {
+        self.path_template.includes_up_down()
+    }
+}
+
+/// builds the schema by applying each migration in `dir` in order, returning it along with the
+/// migrations that were found
+///
+/// when a migration can't be parsed, the ones before it are still applied so an error applying
+/// one of those is reported first
+#[cfg(not(target_arch = "wasm32"))]
+pub fn schema_from_migrations<Dialect>(
+    dir: impl AsRef<Utf8Path>,
+    dialect: Dialect,
+    options: &Options,
+) -> Result<(SyntaxTree<Dialect>, Migrations)>
+where
+    Dialect: TreeMigrator + Parse + Clone,
+{
+    let migrations = Migrations::find(dir, options)?;
+    let mut schema = SyntaxTree {
+        dialect: dialect.clone(),
+        format: FormatOptions::default(),
+        tree: Vec::new(),
+        provenance: Vec::new(),
+        handlers: Default::default(),
+    };
+    let mut parse_error = None;
+    let trees = migrations.files.iter().map_while(|migration| {
+        SyntaxTree::from_migration(dialect.clone(), &migration.up)
+            .map_err(|err| parse_error = Some(err))
+            .ok()
+    });
+    schema.apply_all(trees).map_err(LoadError::Apply)?;
+    if let Some(err) = parse_error {
+        return Err(err);
+    }
+    Ok((schema, migrations))
+}
+
+/// whether `sql` is split into up and down sections, i.e. it has a `-- migrate:up` line
+pub fn has_sections(sql: &str) -> bool {
+    find_marker(sql, UP_MARKER).is_some()
+}
+
+/// splits a migration into its up section and its down section (if it has a `-- migrate:down`
+/// line)
+///
+/// the up section starts at the beginning of `sql` (the markers are comments), so line numbers in
+/// it still point into the file
+pub fn split_sections(sql: &str) -> (&str, Option<&str>) {
+    match find_marker(sql, DOWN_MARKER) {
+        Some(i) => {
+            let down = sql[i..].split_once('\n').map_or("", |(_, down)| down);
+            (&sql[..i], Some(down))
+        }
+        None => (sql, None),
+    }
+}
+
+/// whether the migration can run inside a transaction, i.e. it doesn't start with
+/// [NO_TRANSACTION_MARKER] or have dbmate's [DBMATE_NO_TRANSACTION] on its up marker
+pub fn runs_in_transaction(sql: &str) -> bool {
+    let first = sql.lines().next().unwrap_or_default().trim_end();
+    if first == NO_TRANSACTION_MARKER {
+        return false;
+    }
+    match find_marker(sql, UP_MARKER) {
+        Some(offset) => {
+            let line = sql[offset..].lines().next().unwrap_or_default();
+            !line
+                .split_whitespace()
+                .any(|word| word == DBMATE_NO_TRANSACTION)
+        }
+        None => true,
+    }
+}
+
+/// whether the down section of a migration can run inside a transaction, i.e. its down marker
+/// doesn't have [DBMATE_NO_TRANSACTION]
+pub fn down_runs_in_transaction(sql: &str) -> bool {
+    find_marker(sql, DOWN_MARKER).is_none_or(|offset| {
+        let line = sql[offset..].lines().next().unwrap_or_default();
+        !line
+            .split_whitespace()
+            .any(|word| word == DBMATE_NO_TRANSACTION)
+    })
+}
+
+/// writes the up and down migrations into the sections of a single migration
+pub fn join_sections(up: &str, down: &str) -> String {
+    format!(
+        "{UP_MARKER}\n{}\n\n{DOWN_MARKER}\n{}\n",
+        up.trim_end(),
+        down.trim_end()
+    )
+}
+
+/// the offset of the first line starting with `marker`
+fn find_marker(sql: &str, marker: &str) -> Option<usize> {
+    let mut offset = 0;
+    for line in sql.split_inclusive('\n') {
+        if line.trim_start().starts_with(marker) {
+            return Some(offset);
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// the naming convention of the most recent of `migrations` (in `dir`), or the default when there
+/// are none
+///
+/// a counter continues from the highest one among `migrations`, which isn't necessarily the most
+/// recent by path (e.g. `10_posts/up.sql` sorts before `9_users/up.sql`)
+pub fn detect_path_template(
+    dir: impl AsRef<Utf8Path>,
+    migrations: &[MigrationFile],
+    options: &Options,
+) -> Result<PathTemplate> {
+    let parse = |path: &str| PathTemplate::parse_within(path, options.date_bounds);
+    let dir = dir.as_ref();
+    // repeatable migrations sort last but aren't versioned, so there's nothing to follow
+    let mut versioned = migrations.iter().filter(|m| !m.is_repeatable());
+    let Some(migration) = versioned.next_back() else {
+        return Ok(PathTemplate::default());
+    };
+    let path = migration.up.strip_prefix(dir).unwrap_or(&migration.up);
+    let template = parse(path.as_str()).map_err(|source| LoadError::PathTemplate {
+        path: path.to_owned(),
+        source,
+    })?;
+    let highest = versioned
+        .filter_map(|m| parse(m.up.strip_prefix(dir).ok()?.as_str()).ok())
+        .filter_map(|t| Some((t.template_data().ok()?.counter?, t)))
+        .max_by_key(|(counter, _)| *counter);
+    let counter = template.template_data().ok().and_then(|data| data.counter);
+    match highest {
+        Some((highest, from)) if counter < Some(highest) => Ok(template.continue_from(&from)),
+        _ => Ok(template),
+    }
+}
+
+/// finds all migrations in `dir` sorted by path (with repeatable migrations last), pairing each
+/// with its down migration
+#[cfg(not(target_arch = "wasm32"))]
+pub fn find_migrations(dir: impl AsRef<Utf8Path>, options: &Options) -> Result<Vec<MigrationFile>> {
+    fn collect_sql_files(
+        dir: &Utf8Path,
+        options: &Options,
+        files: &mut Vec<Utf8PathBuf>,
+    ) -> Result<()> {
+        let io_err = |source| LoadError::Io {
+            path: dir.to_owned(),
+            source,
+        };
+        for entry in dir.read_dir_utf8().map_err(io_err)? {
+            let entry = entry.map_err(io_err)?;
+            let meta = entry.metadata().map_err(io_err)?;
+            let path = entry.into_path();
+            // step into any dir we encounter (except seed data)
+            if meta.is_dir()
+                && options.skip_dir.is_some()
+                && path.file_name() == options.skip_dir.as_deref()
+            {
+                continue;
+            }
+            if meta.is_dir() {
+                collect_sql_files(&path, options, files)?;
+                continue;
+            }
+            // skip over non-file entries and files that aren't migrations
+            let migration = path
+                .extension()
+                .is_some_and(|ext| options.extensions.iter().any(|e| e == ext));
+            if meta.is_file() && migration {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// `up`, `do`, `down`, or `undo` in any case (e.g. `UP` or `Down`)
+    fn up_down(word: &str) -> Option<UpDown> {
+        match word.to_ascii_lowercase().as_str() {
+            "up" | "do" => Some(UpDown::Up),
+            "down" | "undo" => Some(UpDown::Down),
+            _ => None,
+        }
+    }
+
+    fn split_stem(stem: &str) -> (&str, Option<UpDown>) {
+        stem.rsplit_once('.')
+            .and_then(|(stem, suffix)| Some((stem, Some(up_down(suffix)?))))
+            .unwrap_or((stem, None))
+    }
+
+    let dir = dir.as_ref();
+    let mut files = Vec::new();
+    collect_sql_files(dir, options, &mut files)?;
+    files.sort();
+
+    let mut migrations: Vec<MigrationFile> = Vec::new();
+    let mut downs = Vec::new();
+    for path in files {
+        let rel = path.strip_prefix(dir).unwrap_or(&path);
+        let (name, up_down) = match rel.file_stem() {
+            Some(stem) if up_down(stem).is_some() => {
+                (rel.parent().map(Utf8Path::to_owned), up_down(stem))
+            }
+            Some(stem) => {
+                let (stem, up_down) = split_stem(stem);
+                (Some(rel.with_file_name(stem)), up_down)
+            }
+            None => (None, None),
+        };
+        let Some(name) = name.filter(|name| !name.as_str().is_empty()) else {
+            return Err(LoadError::MissingName { path });
+        };
+        match up_down {
+            Some(UpDown::Down) => downs.push((name, path)),
+            _ => migrations.push(MigrationFile {
+                name,
+                up: path,
+                down: None,
+            }),
+        }
+    }
+
+    // down migrations without an up migration are skipped
+    for (name, path) in downs {
+        if let Some(migration) = migrations.iter_mut().find(|m| m.name == name) {
+            migration.down = Some(path);
+        }
+    }
+    // the sort is stable, so repeatable migrations stay in order of name
+    migrations.sort_by_key(MigrationFile::is_repeatable);
+
+    Ok(migrations)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{dialect::Generic, path_template::TemplateData};
+
+    fn write(dir: &Utf8Path, files: &[(&str, &str)]) {
+        for (path, sql) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, sql).unwrap();
+        }
+    }
+
+    #[test]
+    fn find() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("sql-schema-find-{}", std::process::id()));
+        write(
+            &dir,
+            &[
+                ("0001_users.UP.sql", ""),
+                ("0001_users.DOWN.sql", ""),
+                ("R__views.sql", ""),
+                ("0002_posts/up.sql", ""),
+                ("0002_posts/undo.sql", ""),
+                ("0003_orphan.down.sql", ""),
+                ("0004_comments.up.pgsql", ""),
+                ("0004_comments.down.pgsql", ""),
+                ("0005_tags.psql", ""),
+                ("README.md", ""),
+                ("seeds/0001_users.sql", ""),
+            ],
+        );
+
+        let migrations = Migrations::find(&dir, &Options::default());
+        let options = Options {
+            skip_dir: None,
+            ..Default::default()
+        };
+        let unskipped = find_migrations(&dir, &options);
+        let options = Options {
+            extensions: vec!["psql".to_owned()],
+            ..Default::default()
+        };
+        let psql = find_migrations(&dir, &options);
+        fs::remove_dir_all(&dir).unwrap();
+        let migrations = migrations.unwrap();
+        let files = migrations
+            .files
+            .iter()
+            .map(|m| {
+                let down = m.down.as_ref().map(|down| down.strip_prefix(&dir).unwrap());
+                (m.name.as_str(), m.version(), down.map(Utf8Path::as_str))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            [
+                ("0001_users", "0001", Some("0001_users.DOWN.sql")),
+                ("0002_posts", "0002", Some("0002_posts/undo.sql")),
+                ("0004_comments", "0004", Some("0004_comments.down.pgsql")),
+                ("R__views", "R__views", None),
+            ]
+        );
+        assert!(migrations.includes_down());
+        assert_eq!(migrations.path_template.extension(), "pgsql");
+        assert_eq!(unskipped.unwrap().len(), 5);
+        assert_eq!(psql.unwrap()[0].name, "0005_tags");
+    }
+
+    #[test]
+    fn highest_counter() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("sql-schema-counter-{}", std::process::id()));
+        write(
+            &dir,
+            &[
+                ("9_users/up.sql", ""),
+                ("9_users/down.sql", ""),
+                ("10_posts/up.sql", ""),
+                ("10_posts/down.sql", ""),
+            ],
+        );
+
+        let migrations = Migrations::find(&dir, &Options::default());
+        fs::remove_dir_all(&dir).unwrap();
+        let migrations = migrations.unwrap();
+        assert_eq!(migrations.files[1].name, "9_users");
+        let data = TemplateData {
+            name: "comments".to_owned(),
+            up_down: Some(UpDown::Up),
+            ..Default::default()
+        };
+        assert_eq!(
+            migrations.path_template.resolve(&data),
+            "11_comments/up.sql"
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let sql = join_sections("CREATE TABLE users (id INT);", "DROP TABLE users;");
+        assert!(has_sections(&sql));
+        assert_eq!(
+            split_sections(&sql),
+            (
+                "-- migrate:up\nCREATE TABLE users (id INT);\n\n",
+                Some("DROP TABLE users;\n")
+            )
+        );
+        assert!(!has_sections("CREATE TABLE users (id INT);"));
+        assert_eq!(split_sections("SELECT 1;"), ("SELECT 1;", None));
+
+        assert!(runs_in_transaction(&sql));
+        assert!(!runs_in_transaction(
+            "-- no-transaction\nCREATE INDEX CONCURRENTLY ON users (id);"
+        ));
+        assert!(!runs_in_transaction(
+            "-- migrate:up transaction:false\nCREATE INDEX CONCURRENTLY ON users (id);\n"
+        ));
+        assert!(down_runs_in_transaction(&sql));
+        assert!(!down_runs_in_transaction(
+            "-- migrate:up\nSELECT 1;\n\n-- migrate:down transaction:false\nDROP INDEX CONCURRENTLY i;\n"
+        ));
+    }
+
+    #[test]
+    fn schema() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("sql-schema-migrations-{}", std::process::id()));
+        write(
+            &dir,
+            &[
+                ("0001_users.sql", "CREATE TABLE users (id INT);"),
+                (
+                    "0002_name.sql",
+                    "-- migrate:up\nALTER TABLE users ADD COLUMN name TEXT;\n\n\
+                    -- migrate:down\nALTER TABLE users DROP COLUMN name;\n",
+                ),
+            ],
+        );
+        let migrated = schema_from_migrations(&dir, Generic, &Options::default());
+        write(
+            &dir,
+            &[
+                ("0003_drop.sql", "ALTER TABLE users DROP PRIMARY KEY;"),
+                ("0004_typo.sql", "CREATE TABL posts (id INT);"),
+            ],
+        );
+        let err = schema_from_migrations(&dir, Generic, &Options::default());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let (schema, migrations) = migrated.unwrap();
+        assert_eq!(
+            schema.to_string(),
+            "CREATE TABLE users (id INT, name TEXT);"
+        );
+        assert_eq!(migrations.files.len(), 2);
+        assert!(!migrations.includes_down());
+        let Err(LoadError::Apply(err)) = err else {
+            panic!("expected an apply error");
+        };
+        assert_eq!(err.index(), 2);
+        assert_eq!(err.path(), Some(dir.join("0003_drop.sql").as_path()));
+    }
+}
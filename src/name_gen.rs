@@ -1,61 +1,110 @@
+/*!
+Name a migration after what it changes, e.g. `create_users__alter_posts_add_title`, the way the
+`sql-schema` CLI names the migrations it generates.
+
+Each statement that creates, alters, renames, or drops something (a table, index, type, view,
+function, trigger, sequence, domain, or extension) is named on its own, and those names are joined
+in order, leaving out repeats. The ALTER TABLEs on a table are named together, counting more than
+two operations by what they change (e.g. `alter_users_add_3_columns`). Statements are left off the
+end (adding `etc`) until the name fits.
+*/
+
 use crate::{
     ast::{
-        AlterTable, AlterTableOperation, AlterType, ColumnDef, CreateIndex, CreateTable,
-        ObjectName, ObjectType, RenameTableNameKind, Statement,
+        AlterTable, AlterTableOperation, AlterType, ColumnDef, CreateDomain, CreateExtension,
+        CreateFunction, CreateIndex, CreateTable, CreateTrigger, CreateView, DropDomain,
+        DropExtension, DropFunction, DropTrigger, ObjectName, ObjectType, RenameTableNameKind,
+        Statement, TableConstraint,
     },
     SyntaxTree,
 };
 
+/// how the verbs in a name are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum VerbStyle {
+    /// e.g. `create_users`
+    #[default]
+    Imperative,
+    /// e.g. `created_users`
+    PastTense,
+}
+
+impl VerbStyle {
+    fn verb(self, verb: &'static str) -> &'static str {
+        match (self, verb) {
+            (Self::PastTense, "add") => "added",
+            (Self::PastTense, "alter") => "altered",
+            (Self::PastTense, "create") => "created",
+            (Self::PastTense, "drop") => "dropped",
+            (Self::PastTense, "rename") => "renamed",
+            (Self::PastTense, "replace") => "replaced",
+            (Self::PastTense, "validate") => "validated",
+            _ => verb,
+        }
+    }
+}
+
+/// a name describing the statements of `tree`, or `None` when none of them are named (e.g. they
+/// only insert data)
+///
+/// - `max_len`: the longest the name can be (50 by default)
+/// - `max_parts`: the most statements named before adding `etc` (all of them by default)
+/// - `separator`: what the name of each statement is joined with (`__` by default)
+/// - `verb_style`: how the verbs are written (see [VerbStyle])
 #[bon::builder(finish_fn = build)]
 pub fn generate_name<Dialect>(
     #[builder(start_fn)] tree: &SyntaxTree<Dialect>,
     max_len: Option<usize>,
+    max_parts: Option<usize>,
+    separator: Option<&str>,
+    verb_style: Option<VerbStyle>,
 ) -> Option<String> {
-    let mut parts = tree
-        .tree
-        .iter()
-        .filter_map(|s| match s {
-            Statement::CreateTable(CreateTable { name, .. }) => Some(format!("create_{name}")),
+    let style = verb_style.unwrap_or_default();
+    // the operations of every ALTER TABLE on a table are named together, where it's first altered
+    let mut named = Vec::new();
+    for statement in &tree.tree {
+        match statement {
             Statement::AlterTable(AlterTable {
                 name, operations, ..
-            }) => alter_table_name(name, operations),
-            Statement::Drop {
-                object_type, names, ..
-            } => {
-                let object_type = match object_type {
-                    ObjectType::Table => String::new(),
-                    _ => object_type.to_string().to_lowercase() + "_",
-                };
-                let names = names
-                    .iter()
-                    .map(ToString::to_string)
-                    .collect::<Vec<String>>()
-                    .join("_and_");
-                Some(format!("drop_{object_type}{names}"))
-            }
-            Statement::CreateType { name, .. } => Some(format!("create_type_{name}")),
-            Statement::AlterType(AlterType { name, .. }) => Some(format!("alter_type_{name}")),
-            Statement::CreateIndex(CreateIndex {
-                name, table_name, ..
-            }) => {
-                let name = name.as_ref().map(|n| format!("_{n}")).unwrap_or_default();
-                Some(format!("create_{table_name}{name}"))
-            }
-            _ => None,
-        })
-        .collect::<Vec<_>>();
+            }) => match named.iter_mut().find_map(|part| match part {
+                Part::AlterTable(table, ops) if *table == name => Some(ops),
+                _ => None,
+            }) {
+                Some(ops) => ops.extend(operations),
+                None => named.push(Part::AlterTable(name, operations.iter().collect())),
+            },
+            statement => named.extend(statement_name(statement, style).map(Part::Name)),
+        }
+    }
+    let mut parts = Vec::<String>::new();
+    for part in named {
+        let name = match part {
+            Part::Name(name) => Some(name),
+            Part::AlterTable(table, ops) => alter_table_name(table, &ops, style),
+        };
+        // e.g. an index dropped and created again under the same name
+        if let Some(name) = name.filter(|name| !parts.contains(name)) {
+            parts.push(name);
+        }
+    }
 
     let mut suffix = None;
-    let mut name = parts.join("__");
+    if let Some(max_parts) = max_parts.filter(|max| parts.len() > *max) {
+        suffix = Some("etc");
+        parts.truncate(max_parts);
+    }
+    let separator = separator.unwrap_or("__");
+    let mut name = parts.join(separator);
     let max_len = max_len.unwrap_or(50);
     while name.len() > max_len {
         suffix = Some("etc");
         parts.pop();
-        name = parts.join("__");
+        name = parts.join(separator);
     }
 
     if let Some(suffix) = suffix {
-        name = format!("{name}__{suffix}");
+        name = format!("{name}{separator}{suffix}");
     }
 
     if name.is_empty() {
@@ -65,49 +114,217 @@ pub fn generate_name<Dialect>(
     }
 }
 
-fn alter_table_name(name: &ObjectName, operations: &[AlterTableOperation]) -> Option<String> {
-    let mut table_verb = "alter";
-    let ops = operations
-        .iter()
-        .filter_map(|op| match op {
+/// a part of a migration's name
+enum Part<'a> {
+    Name(String),
+    /// the operations of every ALTER TABLE on the table
+    AlterTable(&'a ObjectName, Vec<&'a AlterTableOperation>),
+}
+
+/// the name of a statement other than ALTER TABLE, or `None` when it isn't named
+fn statement_name(statement: &Statement, style: VerbStyle) -> Option<String> {
+    let verb = |verb| style.verb(verb);
+    match statement {
+        Statement::CreateTable(CreateTable { name, .. }) => {
+            Some(format!("{}_{name}", verb("create")))
+        }
+        Statement::Drop {
+            object_type, names, ..
+        } => {
+            let object_type = match object_type {
+                ObjectType::Table => String::new(),
+                // e.g. `materialized_view_`
+                _ => object_type.to_string().to_lowercase().replace(' ', "_") + "_",
+            };
+            Some(format!(
+                "{}_{object_type}{}",
+                verb("drop"),
+                join_names(names)
+            ))
+        }
+        Statement::CreateView(CreateView {
+            name,
+            or_replace,
+            materialized,
+            ..
+        }) => {
+            let verb = verb(if *or_replace { "replace" } else { "create" });
+            let kind = if *materialized {
+                "materialized_view"
+            } else {
+                "view"
+            };
+            Some(format!("{verb}_{kind}_{name}"))
+        }
+        Statement::AlterView { name, .. } => Some(format!("{}_view_{name}", verb("alter"))),
+        Statement::CreateFunction(CreateFunction {
+            name, or_replace, ..
+        }) => {
+            let verb = verb(if *or_replace { "replace" } else { "create" });
+            Some(format!("{verb}_function_{name}"))
+        }
+        Statement::DropFunction(DropFunction { func_desc, .. }) => {
+            let names = func_desc.iter().map(|f| &f.name).collect::<Vec<_>>();
+            Some(format!("{}_function_{}", verb("drop"), join_names(&names)))
+        }
+        Statement::DropProcedure { proc_desc, .. } => {
+            let names = proc_desc.iter().map(|f| &f.name).collect::<Vec<_>>();
+            Some(format!("{}_procedure_{}", verb("drop"), join_names(&names)))
+        }
+        Statement::CreateTrigger(CreateTrigger {
+            name, or_replace, ..
+        }) => {
+            let verb = verb(if *or_replace { "replace" } else { "create" });
+            Some(format!("{verb}_trigger_{name}"))
+        }
+        Statement::DropTrigger(DropTrigger { trigger_name, .. }) => {
+            Some(format!("{}_trigger_{trigger_name}", verb("drop")))
+        }
+        Statement::CreateSequence { name, .. } => {
+            Some(format!("{}_sequence_{name}", verb("create")))
+        }
+        Statement::CreateDomain(CreateDomain { name, .. }) => {
+            Some(format!("{}_domain_{name}", verb("create")))
+        }
+        Statement::DropDomain(DropDomain { name, .. }) => {
+            Some(format!("{}_domain_{name}", verb("drop")))
+        }
+        Statement::CreateExtension(CreateExtension { name, .. }) => {
+            Some(format!("{}_extension_{name}", verb("create")))
+        }
+        Statement::DropExtension(DropExtension { names, .. }) => {
+            Some(format!("{}_extension_{}", verb("drop"), join_names(names)))
+        }
+        Statement::CreateType { name, .. } => Some(format!("{}_type_{name}", verb("create"))),
+        Statement::AlterType(AlterType { name, .. }) => {
+            Some(format!("{}_type_{name}", verb("alter")))
+        }
+        Statement::CreateIndex(CreateIndex {
+            name, table_name, ..
+        }) => {
+            let name = name.as_ref().map(|n| format!("_{n}")).unwrap_or_default();
+            Some(format!("{}_{table_name}{name}", verb("create")))
+        }
+        _ => None,
+    }
+}
+
+/// e.g. `alter_users_add_email`, summarizing more than two operations by what they change (e.g.
+/// `alter_users_add_3_columns_drop_name`)
+fn alter_table_name(
+    name: &ObjectName,
+    operations: &[&AlterTableOperation],
+    style: VerbStyle,
+) -> Option<String> {
+    let verb = |verb| style.verb(verb);
+    let mut table_verb = verb("alter");
+    // each operation's verb and what it changes, e.g. ("add", "column", "add_email")
+    let mut ops = Vec::<(&str, &str, String)>::new();
+    for op in operations {
+        let op = match op {
             AlterTableOperation::AddColumn {
                 column_def: ColumnDef { name, .. },
                 ..
-            } => Some(format!("add_{name}")),
-            AlterTableOperation::DropColumn { column_names, .. } => Some(format!(
-                "drop_{}",
-                column_names
-                    .iter()
-                    .map(|ident| ident.value.clone())
-                    .collect::<Vec<_>>()
-                    .join("_")
-            )),
+            } => ("add", "column", format!("{}_{name}", verb("add"))),
+            AlterTableOperation::DropColumn { column_names, .. } => (
+                "drop",
+                "column",
+                format!(
+                    "{}_{}",
+                    verb("drop"),
+                    column_names
+                        .iter()
+                        .map(|ident| ident.value.clone())
+                        .collect::<Vec<_>>()
+                        .join("_")
+                ),
+            ),
             AlterTableOperation::RenameColumn {
                 old_column_name,
                 new_column_name,
-            } => Some(format!("rename_{old_column_name}_to_{new_column_name}")),
-            AlterTableOperation::AlterColumn { column_name, .. } => {
-                Some(format!("alter_{column_name}"))
+            } => (
+                "rename",
+                "column",
+                format!("{}_{old_column_name}_to_{new_column_name}", verb("rename")),
+            ),
+            AlterTableOperation::AlterColumn { column_name, .. } => (
+                "alter",
+                "column",
+                format!("{}_{column_name}", verb("alter")),
+            ),
+            AlterTableOperation::AddConstraint { constraint, .. } => {
+                let Some(name) = constraint_name(constraint) else {
+                    continue;
+                };
+                ("add", "constraint", format!("{}_{name}", verb("add")))
+            }
+            AlterTableOperation::ValidateConstraint { name } => (
+                "validate",
+                "constraint",
+                format!("{}_{name}", verb("validate")),
+            ),
+            AlterTableOperation::DropConstraint { name, .. } => {
+                ("drop", "constraint", format!("{}_{name}", verb("drop")))
             }
             AlterTableOperation::RenameTable { table_name } => {
-                table_verb = "rename";
-                Some(format!(
-                    "to_{table_name}",
-                    table_name = match table_name {
-                        RenameTableNameKind::As(name) => name,
-                        RenameTableNameKind::To(name) => name,
-                    }
-                ))
+                table_verb = verb("rename");
+                let table_name = match table_name {
+                    RenameTableNameKind::As(name) => name,
+                    RenameTableNameKind::To(name) => name,
+                };
+                ("rename", "table", format!("to_{table_name}"))
             }
-            _ => None,
+            _ => continue,
+        };
+        // e.g. a column's type and nullability altered one after the other
+        if !ops.contains(&op) {
+            ops.push(op);
+        }
+    }
+
+    if ops.is_empty() {
+        return Some(format!("{table_verb}_{name}"));
+    }
+    if ops.len() <= 2 {
+        let ops = ops.into_iter().map(|(.., op)| op).collect::<Vec<_>>();
+        return Some(format!("{table_verb}_{name}_{}", ops.join("_")));
+    }
+    // operations doing the same to the same kind of thing are counted, in order of the first
+    let mut groups = Vec::<((&str, &str), Vec<String>)>::new();
+    for (op_verb, noun, op) in ops {
+        match groups.iter_mut().find(|(key, _)| *key == (op_verb, noun)) {
+            Some((_, ops)) => ops.push(op),
+            None => groups.push(((op_verb, noun), vec![op])),
+        }
+    }
+    let summary = groups
+        .into_iter()
+        .map(|((op_verb, noun), mut ops)| match ops.len() {
+            1 => ops.remove(0),
+            n => format!("{}_{n}_{noun}s", verb(op_verb)),
         })
         .collect::<Vec<_>>();
+    Some(format!("{table_verb}_{name}_{}", summary.join("_")))
+}
 
-    Some(if ops.is_empty() || ops.len() > 2 {
-        format!("{table_verb}_{name}")
-    } else {
-        format!("{table_verb}_{name}_{}", ops.join("_"))
-    })
+/// e.g. `foo_and_bar`
+fn join_names(names: &[impl std::fmt::Display]) -> String {
+    names
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("_and_")
+}
+
+fn constraint_name(constraint: &TableConstraint) -> Option<String> {
+    match constraint {
+        TableConstraint::ForeignKey(c) => c.name.as_ref(),
+        TableConstraint::Check(c) => c.name.as_ref(),
+        TableConstraint::Unique(c) => c.name.as_ref(),
+        TableConstraint::PrimaryKey(c) => c.name.as_ref(),
+        _ => None,
+    }
+    .map(|name| name.value.clone())
 }
 
 #[cfg(test)]
@@ -127,6 +344,26 @@ mod tests {
         assert_eq!(actual, Some(tc.name.to_owned()), "{tc:?}");
     }
 
+    #[test]
+    fn options() {
+        let tree = SyntaxTree::parse(
+            dialect::Generic,
+            "CREATE TABLE foo (id INT); ALTER TABLE bar ADD COLUMN id INT; DROP TABLE baz;",
+        )
+        .unwrap();
+        let name = generate_name(&tree)
+            .max_parts(2)
+            .separator("-")
+            .verb_style(VerbStyle::PastTense)
+            .build();
+        assert_eq!(
+            name.as_deref(),
+            Some("created_foo-altered_bar_added_id-etc")
+        );
+        let name = generate_name(&tree).max_len(20).build();
+        assert_eq!(name.as_deref(), Some("create_foo__etc"));
+    }
+
     macro_rules! test_case {
         (
             $(
@@ -177,6 +414,10 @@ mod tests {
             sql: "ALTER TABLE foo RENAME bar TO id;",
             name: "alter_foo_rename_bar_to_id",
         },
+        validate_constraint {
+            sql: "ALTER TABLE posts VALIDATE CONSTRAINT posts_user_id_fkey;",
+            name: "alter_posts_validate_posts_user_id_fkey",
+        },
         rename_table {
             sql: "ALTER TABLE foo RENAME TO bar;",
             name: "rename_foo_to_bar",
@@ -201,5 +442,88 @@ mod tests {
             sql: "DROP INDEX title_idx",
             name: "drop_index_title_idx",
         },
+        create_view {
+            sql: "CREATE VIEW active_users AS SELECT * FROM users WHERE active;",
+            name: "create_view_active_users",
+        },
+        replace_view {
+            sql: "CREATE OR REPLACE VIEW active_users AS SELECT id FROM users;",
+            name: "replace_view_active_users",
+        },
+        create_materialized_view {
+            sql: "CREATE MATERIALIZED VIEW totals AS SELECT count(*) FROM users;",
+            name: "create_materialized_view_totals",
+        },
+        alter_many_columns {
+            sql: "ALTER TABLE users ADD COLUMN a INT, ADD COLUMN b INT, ADD COLUMN c INT, DROP COLUMN d;",
+            name: "alter_users_add_3_columns_drop_d",
+        },
+        alter_table_twice {
+            sql: "ALTER TABLE users ADD COLUMN a INT; CREATE TABLE posts (id INT); ALTER TABLE users ADD COLUMN b INT;",
+            name: "alter_users_add_a_add_b__create_posts",
+        },
+        alter_column_twice {
+            sql: "ALTER TABLE users ALTER COLUMN email SET DATA TYPE TEXT; ALTER TABLE users ALTER COLUMN email SET NOT NULL;",
+            name: "alter_users_alter_email",
+        },
+        alter_six_operations {
+            sql: "ALTER TABLE users ADD COLUMN a INT; ALTER TABLE users ADD COLUMN b INT; ALTER TABLE users ADD COLUMN c INT; ALTER TABLE users DROP COLUMN d; ALTER TABLE users DROP COLUMN e; ALTER TABLE users ALTER COLUMN f SET NOT NULL;",
+            name: "alter_users_add_3_columns_drop_2_columns_alter_f",
+        },
+        drop_index_twice {
+            sql: "DROP INDEX title_idx; DROP INDEX title_idx;",
+            name: "drop_index_title_idx",
+        },
+        drop_materialized_view {
+            sql: "DROP MATERIALIZED VIEW totals;",
+            name: "drop_materialized_view_totals",
+        },
+        create_sequence {
+            sql: "CREATE SEQUENCE order_numbers;",
+            name: "create_sequence_order_numbers",
+        },
+        drop_sequence {
+            sql: "DROP SEQUENCE order_numbers;",
+            name: "drop_sequence_order_numbers",
+        },
     );
+
+    #[test]
+    fn postgres_objects() {
+        for (sql, name) in [
+            (
+                "CREATE FUNCTION refresh_totals() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;",
+                "create_function_refresh_totals",
+            ),
+            (
+                "CREATE OR REPLACE FUNCTION refresh_totals() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;",
+                "replace_function_refresh_totals",
+            ),
+            (
+                "DROP FUNCTION refresh_totals;",
+                "drop_function_refresh_totals",
+            ),
+            (
+                "CREATE TRIGGER update_totals AFTER INSERT ON orders FOR EACH ROW EXECUTE FUNCTION refresh_totals();",
+                "create_trigger_update_totals",
+            ),
+            (
+                "DROP TRIGGER update_totals ON orders;",
+                "drop_trigger_update_totals",
+            ),
+            (
+                "CREATE DOMAIN email AS TEXT CHECK (VALUE LIKE '%@%');",
+                "create_domain_email",
+            ),
+            ("DROP DOMAIN email;", "drop_domain_email"),
+            ("CREATE EXTENSION pgcrypto;", "create_extension_pgcrypto"),
+            (
+                "DROP EXTENSION pgcrypto, citext;",
+                "drop_extension_pgcrypto_and_citext",
+            ),
+        ] {
+            let tree = SyntaxTree::parse(dialect::PostgreSQL, sql).unwrap();
+            assert_eq!(generate_name(&tree).build().as_deref(), Some(name), "{sql}");
+        }
+    }
 }
@@ -1,24 +1,320 @@
+use std::{borrow::Cow, fmt, iter, ops::Range};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use sqlparser::{
+    keywords::Keyword,
+    parser::{Parser, ParserError},
+    tokenizer::Token,
+};
 use thiserror::Error;
 
-use crate::{ast, dialect, sealed::Sealed};
+use crate::{ast, dialect, provenance::Provenance, sealed::Sealed};
 
+/// SQL that couldn't be parsed, along with where in it parsing failed (when sqlparser says)
 #[derive(Error, Debug)]
-#[error("Oops, we couldn't parse that!")]
-pub struct ParseError(#[from] sqlparser::parser::ParserError);
+pub struct ParseError {
+    error: ParserError,
+    sql: String,
+    path: Option<Utf8PathBuf>,
+    // boxed to keep the error small
+    related: Box<[ParseError]>,
+}
+
+impl ParseError {
+    fn new(error: ParserError, sql: &str) -> Self {
+        Self {
+            error,
+            sql: sql.to_owned(),
+            path: None,
+            related: Box::default(),
+        }
+    }
+
+    /// names the file the SQL was read from in the error message
+    pub fn with_path(self, path: impl AsRef<Utf8Path>) -> Self {
+        let path = path.as_ref();
+        Self {
+            path: Some(path.to_owned()),
+            related: self
+                .related
+                .into_vec()
+                .into_iter()
+                .map(|err| err.with_path(path))
+                .collect(),
+            ..self
+        }
+    }
+
+    /// the errors in the statements after the one this is for
+    pub fn related(&self) -> &[ParseError] {
+        &self.related
+    }
+
+    /// sqlparser's error message, without the location
+    pub fn message(&self) -> &str {
+        let message = match &self.error {
+            ParserError::TokenizerError(message) | ParserError::ParserError(message) => message,
+            ParserError::RecursionLimitExceeded => "recursion limit exceeded",
+        };
+        message
+            .rfind(" at Line: ")
+            .map_or(message, |i| &message[..i])
+    }
+
+    /// the line and column (both counted from 1) parsing failed at
+    pub fn location(&self) -> Option<(usize, usize)> {
+        let message = match &self.error {
+            ParserError::TokenizerError(message) | ParserError::ParserError(message) => message,
+            ParserError::RecursionLimitExceeded => return None,
+        };
+        let (line, column) = message
+            .rsplit_once(" at Line: ")?
+            .1
+            .split_once(", Column: ")?;
+        Some((line.parse().ok()?, column.parse().ok()?))
+    }
+
+    /// the byte range of the SQL to point at
+    fn span(&self) -> Option<Range<usize>> {
+        let start = match self.location() {
+            Some((line, column)) => byte_offset(&self.sql, line, column)?,
+            None if self.message().ends_with("found: EOF") => self.sql.trim_end().len(),
+            None => return None,
+        };
+        let len = self.sql[start..].chars().next().map_or(0, char::len_utf8);
+        Some(start..start + len)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_snippet(f)?;
+        // miette renders the related errors itself
+        if cfg!(feature = "miette") {
+            return Ok(());
+        }
+        for err in &self.related {
+            writeln!(f, "\n")?;
+            err.fmt_snippet(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl ParseError {
+    fn fmt_snippet(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let title = format!("Oops, we couldn't parse that: {}", self.message());
+        // miette renders the snippet from the diagnostic's labels instead
+        let span = self.span().filter(|_| !cfg!(feature = "miette"));
+        let Some(span) = span else {
+            return write!(f, "{title}");
+        };
+        let origin = self.path.as_ref().map(|path| path.as_str());
+        let mut snippet = annotate_snippets::Snippet::source(&self.sql)
+            .fold(true)
+            .annotation(annotate_snippets::Level::Error.span(span));
+        if let Some(origin) = origin {
+            snippet = snippet.origin(origin);
+        }
+        let message = annotate_snippets::Level::Error
+            .title(&title)
+            .snippet(snippet);
+        let renderer = annotate_snippets::Renderer::plain();
+        let rendered = renderer.render(message);
+        write!(f, "{rendered}")
+    }
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for ParseError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new("sql_schema::parse"))
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(self)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let span = self.span()?;
+        Some(Box::new(iter::once(miette::LabeledSpan::underline(span))))
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn miette::Diagnostic> + 'a>> {
+        if self.related.is_empty() {
+            return None;
+        }
+        Some(Box::new(
+            self.related
+                .iter()
+                .map(|err| err as &dyn miette::Diagnostic),
+        ))
+    }
+}
+
+/// the SQL, named for the file it was read from
+#[cfg(feature = "miette")]
+impl miette::SourceCode for ParseError {
+    fn read_span<'a>(
+        &'a self,
+        span: &miette::SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn miette::SpanContents<'a> + 'a>, miette::MietteError> {
+        let contents = self
+            .sql
+            .read_span(span, context_lines_before, context_lines_after)?;
+        let Some(path) = &self.path else {
+            return Ok(contents);
+        };
+        Ok(Box::new(miette::MietteSpanContents::new_named(
+            path.to_string(),
+            contents.data(),
+            *contents.span(),
+            contents.line(),
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+/// the byte offset of a line and column (chars, both counted from 1) in `sql`
+pub(crate) fn byte_offset(sql: &str, line: usize, column: usize) -> Option<usize> {
+    let start = iter::once(0)
+        .chain(sql.match_indices('\n').map(|(i, _)| i + 1))
+        .nth(line.checked_sub(1)?)?;
+    let offset = sql[start..]
+        .chars()
+        .take(column.checked_sub(1)?)
+        .map(char::len_utf8)
+        .sum::<usize>();
+    Some(start + offset)
+}
 
 pub trait Parse: Sealed {
     fn parse_sql<'a, Dialect>(
         &self,
         sql: impl Into<&'a str>,
     ) -> Result<Vec<ast::Statement>, ParseError>;
+
+    /// parses the statements like [Parse::parse_sql], along with where in `sql` each one is
+    fn parse_located<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> Result<(Vec<ast::Statement>, Vec<Provenance>), ParseError>;
+
+    /// parses each statement on its own, returning the ones that parsed along with an error for
+    /// each one that didn't
+    fn parse_statements<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> (Vec<ast::Statement>, Vec<ParseError>);
 }
 
 fn parse_sql<'a>(
-    dialect: Box<dyn sqlparser::dialect::Dialect>,
+    dialect: &dyn sqlparser::dialect::Dialect,
     sql: impl Into<&'a str>,
 ) -> Result<Vec<ast::Statement>, ParseError> {
-    let tree = sqlparser::parser::Parser::parse_sql(dialect.as_ref(), sql.into())?;
-    Ok(tree)
+    parse_located(dialect, sql).map(|(tree, _)| tree)
+}
+
+fn parse_located<'a>(
+    dialect: &dyn sqlparser::dialect::Dialect,
+    sql: impl Into<&'a str>,
+) -> Result<(Vec<ast::Statement>, Vec<Provenance>), ParseError> {
+    let sql = sql.into();
+    located_statements(dialect, sql).map_err(|error| {
+        // parse the rest of the statements on their own to report every error at once
+        let (_, mut errors) = parse_statements(dialect, sql);
+        if errors.is_empty() {
+            return ParseError::new(error, sql);
+        }
+        let mut err = errors.remove(0);
+        err.related = errors.into();
+        err
+    })
+}
+
+/// [Parser::parse_statements], noting where each statement starts and ends
+fn located_statements(
+    dialect: &dyn sqlparser::dialect::Dialect,
+    sql: &str,
+) -> Result<(Vec<ast::Statement>, Vec<Provenance>), ParserError> {
+    let mut parser = Parser::new(dialect).try_with_sql(sql)?;
+    let offset = |location: sqlparser::tokenizer::Location| {
+        let (line, column) = (location.line as usize, location.column as usize);
+        byte_offset(sql, line, column).unwrap_or(sql.len())
+    };
+    let mut statements = Vec::new();
+    let mut provenance = Vec::new();
+    loop {
+        let delimited = parser.consume_token(&Token::SemiColon);
+        while parser.consume_token(&Token::SemiColon) {}
+        let next = parser.peek_token();
+        match &next.token {
+            Token::EOF => break,
+            Token::Word(word)
+                if !statements.is_empty() && !delimited && word.keyword == Keyword::END =>
+            {
+                break
+            }
+            _ if !statements.is_empty() && !delimited => {
+                return parser.expected("end of statement", next);
+            }
+            _ => {}
+        }
+        statements.push(parser.parse_statement()?);
+        let start = next.span.start;
+        let end = parser.get_current_token().span.end;
+        // the current token is whitespace after some statements (e.g. CREATE VIEW), which is
+        // left out
+        let span = offset(start)..offset(end);
+        let span = span.start..span.start + sql[span].trim_end().len();
+        provenance.push(Provenance::new(
+            provenance.len(),
+            span,
+            (start.line as usize, start.column as usize),
+        ));
+    }
+    Ok((statements, provenance))
+}
+
+fn parse_statements<'a>(
+    dialect: &dyn sqlparser::dialect::Dialect,
+    sql: impl Into<&'a str>,
+) -> (Vec<ast::Statement>, Vec<ParseError>) {
+    let sql = sql.into();
+    let mut parser = match Parser::new(dialect).try_with_sql(sql) {
+        Ok(parser) => parser,
+        Err(error) => return (Vec::new(), vec![ParseError::new(error, sql)]),
+    };
+    let at_end =
+        |parser: &Parser| matches!(parser.peek_token().token, Token::SemiColon | Token::EOF);
+    let mut statements = Vec::new();
+    let mut errors = Vec::new();
+    loop {
+        while parser.consume_token(&Token::SemiColon) {}
+        if parser.peek_token().token == Token::EOF {
+            break;
+        }
+        let statement = parser
+            .parse_statement()
+            .and_then(|statement| match at_end(&parser) {
+                true => Ok(statement),
+                false => parser.expected("end of statement", parser.peek_token()),
+            });
+        match statement {
+            Ok(statement) => statements.push(statement),
+            Err(error) => {
+                errors.push(ParseError::new(error, sql));
+                // carry on from the next statement
+                while !at_end(&parser) {
+                    parser.next_token();
+                }
+            }
+        }
+    }
+    (statements, errors)
 }
 
 impl Parse for dialect::Generic {
@@ -26,7 +322,21 @@ impl Parse for dialect::Generic {
         &self,
         sql: impl Into<&'a str>,
     ) -> Result<Vec<ast::Statement>, ParseError> {
-        parse_sql(Box::new(sqlparser::dialect::GenericDialect {}), sql)
+        parse_sql(&sqlparser::dialect::GenericDialect {}, sql)
+    }
+
+    fn parse_located<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> Result<(Vec<ast::Statement>, Vec<Provenance>), ParseError> {
+        parse_located(&sqlparser::dialect::GenericDialect {}, sql)
+    }
+
+    fn parse_statements<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> (Vec<ast::Statement>, Vec<ParseError>) {
+        parse_statements(&sqlparser::dialect::GenericDialect {}, sql)
     }
 }
 
@@ -35,7 +345,21 @@ impl Parse for dialect::PostgreSQL {
         &self,
         sql: impl Into<&'a str>,
     ) -> Result<Vec<ast::Statement>, ParseError> {
-        parse_sql(Box::new(sqlparser::dialect::PostgreSqlDialect {}), sql)
+        parse_sql(&sqlparser::dialect::PostgreSqlDialect {}, sql)
+    }
+
+    fn parse_located<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> Result<(Vec<ast::Statement>, Vec<Provenance>), ParseError> {
+        parse_located(&sqlparser::dialect::PostgreSqlDialect {}, sql)
+    }
+
+    fn parse_statements<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> (Vec<ast::Statement>, Vec<ParseError>) {
+        parse_statements(&sqlparser::dialect::PostgreSqlDialect {}, sql)
     }
 }
 
@@ -44,6 +368,253 @@ impl Parse for dialect::SQLite {
         &self,
         sql: impl Into<&'a str>,
     ) -> Result<Vec<ast::Statement>, ParseError> {
-        parse_sql(Box::new(sqlparser::dialect::SQLiteDialect {}), sql)
+        parse_sql(&sqlparser::dialect::SQLiteDialect {}, sql)
+    }
+
+    fn parse_located<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> Result<(Vec<ast::Statement>, Vec<Provenance>), ParseError> {
+        parse_located(&sqlparser::dialect::SQLiteDialect {}, sql)
+    }
+
+    fn parse_statements<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> (Vec<ast::Statement>, Vec<ParseError>) {
+        parse_statements(&sqlparser::dialect::SQLiteDialect {}, sql)
+    }
+}
+
+impl Parse for dialect::MySQL {
+    fn parse_sql<'a, Dialect>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> Result<Vec<ast::Statement>, ParseError> {
+        parse_sql(&sqlparser::dialect::MySqlDialect {}, sql)
+    }
+
+    fn parse_located<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> Result<(Vec<ast::Statement>, Vec<Provenance>), ParseError> {
+        parse_located(&sqlparser::dialect::MySqlDialect {}, sql)
+    }
+
+    fn parse_statements<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> (Vec<ast::Statement>, Vec<ParseError>) {
+        parse_statements(&sqlparser::dialect::MySqlDialect {}, sql)
+    }
+}
+
+impl Parse for dialect::MsSQL {
+    fn parse_sql<'a, Dialect>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> Result<Vec<ast::Statement>, ParseError> {
+        let sql = end_batches(sql.into());
+        parse_sql(&sqlparser::dialect::MsSqlDialect {}, sql.as_ref())
+    }
+
+    fn parse_located<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> Result<(Vec<ast::Statement>, Vec<Provenance>), ParseError> {
+        let sql = end_batches(sql.into());
+        parse_located(&sqlparser::dialect::MsSqlDialect {}, sql.as_ref())
+    }
+
+    fn parse_statements<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> (Vec<ast::Statement>, Vec<ParseError>) {
+        let sql = end_batches(sql.into());
+        parse_statements(&sqlparser::dialect::MsSqlDialect {}, sql.as_ref())
+    }
+}
+
+/// a line separating batches in a SQL Server script (`GO`, optionally followed by how many times
+/// to run the batch), which sqlcmd and SSMS split scripts on rather than sending it to the server
+pub(crate) fn is_batch_separator(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    words.next().is_some_and(|go| go.eq_ignore_ascii_case("go"))
+        && words
+            .next()
+            .is_none_or(|count| count.parse::<u32>().is_ok())
+        && words.next().is_none()
+}
+
+/// ends each statement before a batch separator, which sqlparser doesn't know, by writing `;` in
+/// its place (and spaces over the rest, so the statements stay where they are in `sql`)
+fn end_batches(sql: &str) -> Cow<'_, str> {
+    if !sql.lines().any(is_batch_separator) {
+        return Cow::Borrowed(sql);
+    }
+    let lines = sql.split_inclusive('\n').map(|line| {
+        if !is_batch_separator(line) {
+            return Cow::Borrowed(line);
+        }
+        let go = line.find(|c: char| !c.is_whitespace()).unwrap_or_default();
+        let blank = line[go + 1..].replace(|c: char| !c.is_whitespace(), " ");
+        Cow::Owned(format!("{};{blank}", &line[..go]))
+    });
+    Cow::Owned(lines.collect())
+}
+
+impl Parse for dialect::Custom {
+    fn parse_sql<'a, Dialect>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> Result<Vec<ast::Statement>, ParseError> {
+        parse_sql(self.sqlparser_dialect(), sql)
+    }
+
+    fn parse_located<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> Result<(Vec<ast::Statement>, Vec<Provenance>), ParseError> {
+        parse_located(self.sqlparser_dialect(), sql)
+    }
+
+    fn parse_statements<'a>(
+        &self,
+        sql: impl Into<&'a str>,
+    ) -> (Vec<ast::Statement>, Vec<ParseError>) {
+        parse_statements(self.sqlparser_dialect(), sql)
+    }
+}
+
+/// a statement [SyntaxTree::parse_lenient](crate::SyntaxTree::parse_lenient) left out
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Skipped {
+    #[error(transparent)]
+    Unparsable(ParseError),
+    #[error("Oops, we can't diff {}", summary(.0))]
+    Unsupported(Box<ast::Statement>),
+}
+
+impl Skipped {
+    /// names the file the statement was read from in the error message
+    pub fn with_path(self, path: impl AsRef<Utf8Path>) -> Self {
+        match self {
+            Self::Unparsable(err) => Self::Unparsable(err.with_path(path)),
+            skipped => skipped,
+        }
+    }
+}
+
+/// the start of a statement, enough to find it by
+pub(crate) fn summary(statement: &ast::Statement) -> String {
+    let sql = statement.to_string();
+    match sql.char_indices().nth(60) {
+        Some((i, _)) => format!("{}...", &sql[..i]),
+        None => sql,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Generic;
+
+    #[test]
+    fn location() {
+        let sql = "CREATE TABLE a (\n  id INT,\n  name TEXT NOT\n);";
+        let err = Generic.parse_sql::<Generic>(sql).unwrap_err();
+        assert_eq!(err.location(), Some((3, 13)));
+        assert_eq!(
+            err.message(),
+            "Expected: ',' or ')' after column definition, found: NOT"
+        );
+
+        let err = Generic
+            .parse_sql::<Generic>("CREATE TABLE a (id INT")
+            .unwrap_err();
+        assert_eq!(err.location(), None);
+        assert_eq!(err.span(), Some(22..22));
+    }
+
+    #[test]
+    fn batch_separators() {
+        let sql = "CREATE TABLE a (id INT)\nGO\nCREATE VIEW v AS SELECT id FROM a\n  go 2\nCREATE TABL b;";
+        let (statements, errors) = dialect::MsSQL.parse_statements(sql);
+        assert_eq!(statements.len(), 2);
+        // the rest of the SQL stays where it was
+        assert_eq!(errors[0].location(), Some((5, 8)));
+        assert!(!is_batch_separator("GOTO end"));
+    }
+
+    #[test]
+    fn related() {
+        let sql = "CREATE TABLE a (id INT,);\nCREATE TABLE b (id INT);\nCREATE TABLE (id INT);\nCREATE TABL c;";
+        let err = Generic
+            .parse_sql::<Generic>(sql)
+            .unwrap_err()
+            .with_path("schema.sql");
+        assert_eq!(err.location(), Some((1, 24)));
+        let related = err
+            .related()
+            .iter()
+            .map(|err| (err.location(), err.path.as_deref().map(Utf8Path::as_str)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            related,
+            [
+                (Some((3, 14)), Some("schema.sql")),
+                (Some((4, 8)), Some("schema.sql"))
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "miette"))]
+    fn display() {
+        let sql = "CREATE TABLE a (\n  id INT,\n  name TEXT NOT\n);";
+        let err = Generic.parse_sql::<Generic>(sql).unwrap_err();
+        assert_eq!(
+            err.with_path("schema.sql").to_string(),
+            "error: Oops, we couldn't parse that: Expected: ',' or ')' after column definition, found: NOT
+ --> schema.sql:3:13
+  |
+3 |   name TEXT NOT
+  |             ^
+  |"
+        );
+
+        let err = Generic
+            .parse_sql::<Generic>("CREATE TABLE a (id INT")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("1 | CREATE TABLE a (id INT\n  |                       ^"));
+    }
+
+    #[test]
+    #[cfg(feature = "miette")]
+    fn diagnostic() {
+        use miette::Diagnostic;
+
+        let sql = "CREATE TABLE a (\n  id INT,\n  name TEXT NOT\n);";
+        let err = Generic
+            .parse_sql::<Generic>(sql)
+            .unwrap_err()
+            .with_path("schema.sql");
+        assert_eq!(
+            err.to_string(),
+            "Oops, we couldn't parse that: Expected: ',' or ')' after column definition, found: NOT"
+        );
+        let label = err.labels().unwrap().next().unwrap();
+        assert_eq!((label.offset(), label.len()), (39, 1));
+        let contents = err
+            .source_code()
+            .unwrap()
+            .read_span(label.inner(), 0, 0)
+            .unwrap();
+        assert_eq!(contents.name(), Some("schema.sql"));
+        assert_eq!(contents.data(), b"N");
+        assert_eq!((contents.line(), contents.column()), (2, 12));
     }
 }
@@ -1,31 +1,68 @@
 /*!
 Parse a migration path into a [PathTemplate] to later resolve the name of a new migration being written.
+
+A template can also be compiled from placeholders (e.g. `{timestamp}_{author}_{name}.sql` or refinery's
+`V{counter}__{name}.sql`) for conventions that can't be inferred from existing file names. Any other
+placeholder is a variable whose value is given when resolving (e.g. `{team}`).
+
+The file extension is kept as it is (e.g. `.pgsql` or `.ddl`), so new migrations are written with the same one.
 */
 
 pub use ast::{PathTemplate, Semver, TemplateData, UpDown};
 pub use chrono::{DateTime, Utc};
 pub use parser::ParseError;
 
+/// the file extension of a [PathTemplate] that isn't parsed from a path (without the `.`)
+pub const DEFAULT_EXTENSION: &str = "sql";
+
+/// the dates a timestamp in a migration name plausibly falls between (inclusive), so other numbers
+/// aren't mistaken for one (default is 2000 through 2100)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateBounds {
+    pub min: chrono::NaiveDate,
+    pub max: chrono::NaiveDate,
+}
+
+impl DateBounds {
+    /// from the start of `min_year` to the end of `max_year`
+    pub fn years(min_year: i32, max_year: i32) -> Option<Self> {
+        Some(Self {
+            min: chrono::NaiveDate::from_ymd_opt(min_year, 1, 1)?,
+            max: chrono::NaiveDate::from_ymd_opt(max_year, 12, 31)?,
+        })
+    }
+
+    pub fn contains(&self, date: chrono::NaiveDate) -> bool {
+        (self.min..=self.max).contains(&date)
+    }
+}
+
+impl Default for DateBounds {
+    fn default() -> Self {
+        Self::years(2000, 2100).expect("valid years")
+    }
+}
+
 mod parser {
-    use std::{cmp::Ordering, ops::Range};
+    use std::ops::Range;
 
     use chrono::NaiveDate;
     use thiserror::Error;
     use winnow::{
-        ascii::digit1,
-        combinator::{alt, fail, opt, repeat, separated},
+        ascii::{digit1, Caseless},
+        combinator::{alt, fail, not, opt, preceded, repeat, separated},
         error::{StrContext, StrContextValue},
         stream::AsChar,
-        token::{take_until, take_while},
+        token::{one_of, take_till, take_while},
         Parser, Result,
     };
 
     use super::{
         ast::{
-            Date, DateTime, DoUndo, EpochTimestamp, PaddedNumber, Segment, SegmentKind, Semver,
-            SubSecond, Time, Timestamp, Token,
+            Case, Date, DateTime, DoUndo, EpochTimestamp, PaddedNumber, Segment, SegmentKind,
+            Semver, SubSecond, Time, Timestamp, Token,
         },
-        PathTemplate, UpDown,
+        DateBounds, PathTemplate, UpDown,
     };
 
     #[derive(Error, Debug)]
@@ -42,6 +79,10 @@ mod parser {
             let m2 = lines.get(1).copied().unwrap_or(m1);
             let title =
                 format!("Oops, we couldn't sort out you're migration naming convention: {m1}");
+            // miette renders the snippet from the diagnostic's labels instead
+            if cfg!(feature = "miette") {
+                return write!(f, "{title}");
+            }
             let message = annotate_snippets::Level::Error.title(&title).snippet(
                 annotate_snippets::Snippet::source(&self.input)
                     .fold(true)
@@ -57,6 +98,24 @@ mod parser {
         }
     }
 
+    #[cfg(feature = "miette")]
+    impl miette::Diagnostic for ParseError {
+        fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+            Some(Box::new("sql_schema::path_template"))
+        }
+
+        fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+            Some(&self.input)
+        }
+
+        fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+            let label = self.message.split('\n').next_back().map(str::to_owned);
+            Some(Box::new(std::iter::once(
+                miette::LabeledSpan::new_with_span(label, self.span.clone()),
+            )))
+        }
+    }
+
     fn digit_n<'i>(n: usize) -> impl FnMut(&mut &'i str) -> Result<&'i str> {
         move |input: &mut &'i str| take_while(n, AsChar::is_dec_digit).parse_next(input)
     }
@@ -93,8 +152,9 @@ mod parser {
             .parse_next(input)
     }
 
+    /// e.g. `1.2.3`, or `1.2.3.4` with a build number
     fn semver(input: &mut &str) -> Result<Token> {
-        separated(3, digit1, '.')
+        separated(3..=4, digit1, '.')
             .map(|_: Vec<&str>| ()) // TODO: why is this map needed?
             .take()
             .parse_to::<Semver>()
@@ -102,16 +162,17 @@ mod parser {
             .parse_next(input)
     }
 
-    /// timestamps should all be after the year 2000
-    const MIN_DATE: NaiveDate = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
-    /// timestamps should all be before the year 2100
-    const MAX_DATE: NaiveDate = NaiveDate::from_ymd_opt(2100, 1, 1).unwrap();
-
-    fn datetime(input: &mut &str) -> Result<Token> {
+    fn datetime(bounds: DateBounds) -> impl FnMut(&mut &str) -> Result<Token> {
         fn year(input: &mut &str) -> Result<i32> {
-            ("20", digit_n(2))
-                .take()
+            digit_n(4).parse_to::<i32>().parse_next(input)
+        }
+
+        /// a two-digit year in this century (`00` is left to padded numbers such as `000522`)
+        fn short_year(input: &mut &str) -> Result<i32> {
+            digit_n(2)
                 .parse_to::<i32>()
+                .verify(|yy| *yy > 0)
+                .map(|yy| 2000 + yy)
                 .parse_next(input)
         }
 
@@ -132,7 +193,7 @@ mod parser {
         fn hour(input: &mut &str) -> Result<u32> {
             digit_n(2)
                 .parse_to::<u32>()
-                .verify(|hh| *hh <= 12 && *hh > 0)
+                .verify(|hh| *hh < 24)
                 .parse_next(input)
         }
 
@@ -217,25 +278,72 @@ mod parser {
                 .parse_next(input)
         }
 
-        (
-            year,
-            opt(sep_literal),
-            month,
-            opt(sep_literal),
-            day,
-            opt((opt(sep_literal), time)),
-        )
-            .map(|(year, s1, month, s2, day, time_or_rand)| {
-                let year_sep = s1.map(|s| s.to_string());
-                let month_sep = s2.map(|s| s.to_string());
-                let date = Date {
+        fn date(input: &mut &str) -> Result<Date> {
+            (year, opt(sep_literal), month, opt(sep_literal), day)
+                .map(|(year, s1, month, s2, day)| Date {
                     year,
-                    year_sep,
+                    year_sep: s1.map(|s| s.to_string()),
                     month,
-                    month_sep,
+                    month_sep: s2.map(|s| s.to_string()),
                     day,
-                };
+                    short_year: false,
+                })
+                .parse_next(input)
+        }
+
+        /// e.g. `23-01-04` (only with dashes, as `11.12.13` is a semver)
+        fn short_date(input: &mut &str) -> Result<Date> {
+            (short_year, "-", month, "-", day)
+                .map(|(year, s1, month, s2, day)| Date {
+                    year,
+                    year_sep: Some(s1.to_owned()),
+                    month,
+                    month_sep: Some(s2.to_owned()),
+                    day,
+                    short_year: true,
+                })
+                .parse_next(input)
+        }
+
+        /// a date with its optional separator and time
+        type DateAndTime<'i> = (Date, Option<(Option<&'i str>, Time)>);
+
+        /// e.g. `230104` or `230104120000`, which needs the seconds so as not to be mistaken for
+        /// an epoch timestamp (`2301041200`)
+        fn compact_short_date<'i>(input: &mut &'i str) -> Result<DateAndTime<'i>> {
+            let date = (
+                short_year,
+                month,
+                day,
+                opt((opt(sep_literal), time.verify(|t: &Time| t.second.is_some()))),
+            )
+                .map(|(year, month, day, time)| {
+                    let date = Date {
+                        year,
+                        month,
+                        day,
+                        short_year: true,
+                        ..Default::default()
+                    };
+                    (date, time)
+                })
+                .parse_next(input)?;
+            not(one_of(AsChar::is_dec_digit)).parse_next(input)?;
+            Ok(date)
+        }
+
+        // dates outside the bounds are more likely some other number
+        let in_bounds = move |date: &Date| {
+            NaiveDate::try_from(date.clone()).is_ok_and(|date| bounds.contains(date))
+        };
 
+        move |input: &mut &str| {
+            alt((
+                (date.verify(in_bounds), opt((opt(sep_literal), time))),
+                (short_date.verify(in_bounds), opt((opt(sep_literal), time))),
+                compact_short_date.verify(|(date, _): &DateAndTime| in_bounds(date)),
+            ))
+            .map(|(date, time_or_rand)| {
                 let (date_sep, time) = if let Some((s3, time)) = time_or_rand {
                     let date_sep = s3.map(|s| s.to_string());
                     (date_sep, Some(time))
@@ -250,77 +358,77 @@ mod parser {
                 }))
             })
             .parse_next(input)
-    }
-
-    fn validate_datetime<Z: chrono::TimeZone>(
-        ts: chrono::DateTime<Z>,
-    ) -> Option<chrono::DateTime<Z>> {
-        if matches!(
-            ts.date_naive().cmp(&MIN_DATE),
-            Ordering::Greater | Ordering::Equal
-        ) && matches!(
-            ts.date_naive().cmp(&MAX_DATE),
-            Ordering::Less | Ordering::Equal
-        ) {
-            Some(ts)
-        } else {
-            None
         }
     }
 
-    fn epoch_seconds(input: &mut &str) -> Result<EpochTimestamp> {
-        digit1
-            .take()
-            .parse_to::<i64>()
-            .verify_map(|secs| chrono::DateTime::from_timestamp(secs, 0))
-            .verify_map(validate_datetime)
-            .map(|ts| ts.timestamp())
-            .map(EpochTimestamp::Second)
-            .parse_next(input)
+    fn epoch_seconds(bounds: DateBounds) -> impl FnMut(&mut &str) -> Result<EpochTimestamp> {
+        move |input: &mut &str| {
+            digit1
+                .take()
+                .parse_to::<i64>()
+                .verify_map(|secs| chrono::DateTime::from_timestamp(secs, 0))
+                .verify(|ts| bounds.contains(ts.date_naive()))
+                .map(|ts| ts.timestamp())
+                .map(EpochTimestamp::Second)
+                .parse_next(input)
+        }
     }
 
-    fn epoch_millis(input: &mut &str) -> Result<EpochTimestamp> {
-        digit1
-            .take()
-            .parse_to::<i64>()
-            .verify_map(chrono::DateTime::from_timestamp_millis)
-            .verify_map(validate_datetime)
-            .map(|ts| ts.timestamp_millis())
-            .map(EpochTimestamp::Milli)
-            .parse_next(input)
+    fn epoch_millis(bounds: DateBounds) -> impl FnMut(&mut &str) -> Result<EpochTimestamp> {
+        move |input: &mut &str| {
+            digit1
+                .take()
+                .parse_to::<i64>()
+                .verify_map(chrono::DateTime::from_timestamp_millis)
+                .verify(|ts| bounds.contains(ts.date_naive()))
+                .map(|ts| ts.timestamp_millis())
+                .map(EpochTimestamp::Milli)
+                .parse_next(input)
+        }
     }
 
-    fn epoch_micros(input: &mut &str) -> Result<EpochTimestamp> {
-        digit1
-            .take()
-            .parse_to::<i64>()
-            .verify_map(chrono::DateTime::from_timestamp_micros)
-            .verify_map(validate_datetime)
-            .map(|ts| ts.timestamp_micros())
-            .map(EpochTimestamp::Micro)
-            .parse_next(input)
+    fn epoch_micros(bounds: DateBounds) -> impl FnMut(&mut &str) -> Result<EpochTimestamp> {
+        move |input: &mut &str| {
+            digit1
+                .take()
+                .parse_to::<i64>()
+                .verify_map(chrono::DateTime::from_timestamp_micros)
+                .verify(|ts| bounds.contains(ts.date_naive()))
+                .map(|ts| ts.timestamp_micros())
+                .map(EpochTimestamp::Micro)
+                .parse_next(input)
+        }
     }
 
-    fn epoch_nanos(input: &mut &str) -> Result<EpochTimestamp> {
-        digit1
-            .take()
-            .parse_to::<i64>()
-            .map(chrono::DateTime::from_timestamp_nanos)
-            .verify_map(validate_datetime)
-            .verify_map(|ts| ts.timestamp_nanos_opt())
-            .map(EpochTimestamp::Nano)
-            .parse_next(input)
+    fn epoch_nanos(bounds: DateBounds) -> impl FnMut(&mut &str) -> Result<EpochTimestamp> {
+        move |input: &mut &str| {
+            digit1
+                .take()
+                .parse_to::<i64>()
+                .map(chrono::DateTime::from_timestamp_nanos)
+                .verify(|ts| bounds.contains(ts.date_naive()))
+                .verify_map(|ts| ts.timestamp_nanos_opt())
+                .map(EpochTimestamp::Nano)
+                .parse_next(input)
+        }
     }
 
-    fn epoch_timestamp(input: &mut &str) -> Result<Token> {
-        alt((epoch_nanos, epoch_micros, epoch_millis, epoch_seconds))
+    fn epoch_timestamp(bounds: DateBounds) -> impl FnMut(&mut &str) -> Result<Token> {
+        move |input: &mut &str| {
+            alt((
+                epoch_nanos(bounds),
+                epoch_micros(bounds),
+                epoch_millis(bounds),
+                epoch_seconds(bounds),
+            ))
             .map(Timestamp::Epoch)
             .map(Token::Timestamp)
             .parse_next(input)
+        }
     }
 
     fn name(input: &mut &str) -> Result<Token> {
-        take_until(1.., '.')
+        take_till(1.., ('.', '/', '\\'))
             .map(|s: &str| Token::Name(s.to_owned()))
             .context(StrContext::Label("name"))
             .context(StrContext::Expected(StrContextValue::Description(
@@ -331,10 +439,18 @@ mod parser {
 
     fn updown(input: &mut &str) -> Result<Token> {
         alt((
-            "down".value(Token::UpDown(UpDown::Down)),
-            "undo".value(Token::DoUndo(DoUndo::Undo)),
-            "up".value(Token::UpDown(UpDown::Up)),
-            "do".value(Token::DoUndo(DoUndo::Do)),
+            Caseless("down")
+                .take()
+                .map(|s| Token::UpDown(UpDown::Down, Case::of(s))),
+            Caseless("undo")
+                .take()
+                .map(|s| Token::DoUndo(DoUndo::Undo, Case::of(s))),
+            Caseless("up")
+                .take()
+                .map(|s| Token::UpDown(UpDown::Up, Case::of(s))),
+            Caseless("do")
+                .take()
+                .map(|s| Token::DoUndo(DoUndo::Do, Case::of(s))),
         ))
         .context(StrContext::Label("updown"))
         .context(StrContext::Expected(StrContextValue::StringLiteral("up")))
@@ -359,71 +475,75 @@ mod parser {
             .parse_next(input)
     }
 
-    fn number(input: &mut &str) -> Result<Vec<Token>> {
-        (
-            alt((
-                datetime,
-                epoch_timestamp,
-                semver,
-                padded_number,
-                fail.context(StrContext::Label("number"))
-                    .context(StrContext::Expected(StrContextValue::Description(
-                        "datetime",
-                    )))
-                    .context(StrContext::Expected(StrContextValue::Description(
-                        "epoch timestamp",
-                    )))
-                    .context(StrContext::Expected(StrContextValue::Description(
-                        "padded number",
-                    )))
-                    .context(StrContext::Expected(StrContextValue::Description("semver"))),
-            )),
-            opt((
-                repeat(0.., sep).map(|t: Vec<_>| t),
-                alt((epoch_timestamp, random_number)),
-            )),
-        )
-            .map(|(t1, t2)| {
-                let mut tokens = vec![Some(t1)];
+    fn number(bounds: DateBounds) -> impl FnMut(&mut &str) -> Result<Vec<Token>> {
+        move |input: &mut &str| {
+            (
+                alt((
+                    datetime(bounds),
+                    epoch_timestamp(bounds),
+                    semver,
+                    padded_number,
+                    fail.context(StrContext::Label("number"))
+                        .context(StrContext::Expected(StrContextValue::Description(
+                            "datetime",
+                        )))
+                        .context(StrContext::Expected(StrContextValue::Description(
+                            "epoch timestamp",
+                        )))
+                        .context(StrContext::Expected(StrContextValue::Description(
+                            "padded number",
+                        )))
+                        .context(StrContext::Expected(StrContextValue::Description("semver"))),
+                )),
+                opt((
+                    repeat(0.., sep).map(|t: Vec<_>| t),
+                    alt((epoch_timestamp(bounds), random_number)),
+                )),
+            )
+                .map(|(t1, t2)| {
+                    let mut tokens = vec![Some(t1)];
 
-                if let Some((s, t)) = t2 {
-                    s.into_iter().for_each(|s| tokens.push(Some(s)));
-                    tokens.push(Some(t));
-                }
+                    if let Some((s, t)) = t2 {
+                        s.into_iter().for_each(|s| tokens.push(Some(s)));
+                        tokens.push(Some(t));
+                    }
 
-                tokens.into_iter().flatten().collect()
-            })
-            .parse_next(input)
+                    tokens.into_iter().flatten().collect()
+                })
+                .parse_next(input)
+        }
     }
 
-    fn dir_ident(input: &mut &str) -> Result<Segment> {
-        (
-            opt(prefix),
-            number,
-            opt((repeat(1.., sep).map(|t: Vec<_>| t), name)),
-        )
-            .map(|(prefix, number, name)| {
-                let mut children = vec![prefix];
-                number.into_iter().for_each(|s| children.push(Some(s)));
+    fn dir_ident(bounds: DateBounds) -> impl FnMut(&mut &str) -> Result<Segment> {
+        move |input: &mut &str| {
+            (
+                opt(prefix),
+                number(bounds),
+                opt((repeat(1.., sep).map(|t: Vec<_>| t), name)),
+            )
+                .map(|(prefix, number, name)| {
+                    let mut children = vec![prefix];
+                    number.into_iter().for_each(|s| children.push(Some(s)));
 
-                if let Some((sep, name)) = name {
-                    sep.into_iter().for_each(|s| children.push(Some(s)));
-                    children.push(Some(name));
-                }
+                    if let Some((sep, name)) = name {
+                        sep.into_iter().for_each(|s| children.push(Some(s)));
+                        children.push(Some(name));
+                    }
 
-                let tokens = children.into_iter().flatten().collect();
+                    let tokens = children.into_iter().flatten().collect();
 
-                Segment {
-                    kind: SegmentKind::Dir,
-                    tokens,
-                }
-            })
-            .parse_next(input)
+                    Segment {
+                        kind: SegmentKind::Dir,
+                        tokens,
+                    }
+                })
+                .parse_next(input)
+        }
     }
 
     fn file_ext(input: &mut &str) -> Result<Token> {
-        ".sql"
-            .value(Token::Extension)
+        preceded('.', take_while(1.., AsChar::is_alphanum))
+            .map(|ext: &str| Token::Extension(ext.to_owned()))
             .context(StrContext::Label("file ext"))
             .context(StrContext::Expected(StrContextValue::StringLiteral(".sql")))
             .parse_next(input)
@@ -438,36 +558,54 @@ mod parser {
             .parse_next(input)
     }
 
-    fn file_ident(input: &mut &str) -> Result<Segment> {
-        (
-            opt(prefix),
-            number,
-            opt((repeat(0.., sep).map(|t: Vec<_>| t), name)),
-            opt((dot, updown)),
-            file_ext,
-        )
-            .map(|(prefix, number, name, updown, ext)| {
-                let mut children = vec![prefix];
-                number.into_iter().for_each(|s| children.push(Some(s)));
+    fn file_ident(bounds: DateBounds) -> impl FnMut(&mut &str) -> Result<Segment> {
+        move |input: &mut &str| {
+            (
+                opt(prefix),
+                number(bounds),
+                opt((repeat(0.., sep).map(|t: Vec<_>| t), name)),
+                opt((dot, updown)),
+                file_ext,
+            )
+                .map(|(prefix, number, name, updown, ext)| {
+                    let mut children = vec![prefix];
+                    number.into_iter().for_each(|s| children.push(Some(s)));
 
-                if let Some((sep, name)) = name {
-                    sep.into_iter().for_each(|s| children.push(Some(s)));
-                    children.push(Some(name));
-                }
+                    if let Some((sep, name)) = name {
+                        sep.into_iter().for_each(|s| children.push(Some(s)));
+                        children.push(Some(name));
+                    }
 
-                if let Some((sep, updown)) = updown {
-                    children.push(Some(sep));
-                    children.push(Some(updown));
-                }
+                    if let Some((sep, updown)) = updown {
+                        children.push(Some(sep));
+                        children.push(Some(updown));
+                    }
 
-                children.push(Some(ext));
+                    children.push(Some(ext));
 
-                let tokens = children.into_iter().flatten().collect();
+                    let tokens = children.into_iter().flatten().collect();
 
-                Segment {
-                    kind: SegmentKind::File,
-                    tokens,
-                }
+                    Segment {
+                        kind: SegmentKind::File,
+                        tokens,
+                    }
+                })
+                .parse_next(input)
+        }
+    }
+
+    /// Flyway's repeatable migrations (`R__<name>.sql`), which aren't versioned
+    fn file_repeatable(input: &mut &str) -> Result<Segment> {
+        ("R__", name, file_ext)
+            .map(|(_, name, ext)| Segment {
+                kind: SegmentKind::File,
+                tokens: vec![
+                    Token::Prefix("R".to_owned()),
+                    Token::Underscore,
+                    Token::Underscore,
+                    name,
+                    ext,
+                ],
             })
             .parse_next(input)
     }
@@ -476,16 +614,115 @@ mod parser {
         alt(('/', '\\')).take().parse_next(input)
     }
 
-    fn path(input: &mut &str) -> Result<Vec<Segment>> {
-        alt((
-            (dir_ident, path_sep, file_nonident).map(|(dir, _sep, file)| vec![dir, file]),
-            file_ident.map(|file| vec![file]),
-        ))
-        .parse_next(input)
+    fn path(bounds: DateBounds) -> impl FnMut(&mut &str) -> Result<Vec<Segment>> {
+        move |input: &mut &str| {
+            alt((
+                (dir_ident(bounds), path_sep, file_nonident)
+                    .map(|(dir, _sep, file)| vec![dir, file]),
+                file_ident(bounds).map(|file| vec![file]),
+                file_repeatable.map(|file| vec![file]),
+            ))
+            .parse_next(input)
+        }
+    }
+
+    const PLACEHOLDERS: &str = "expected one of timestamp, counter, name, updown, branch, \
+         author, or a variable made of letters, digits, `_`, and `-`";
+
+    /// compiles an explicit template, e.g. `{timestamp}_{branch}_{name}.{updown}.sql`
+    pub fn compile(input: &str) -> std::result::Result<PathTemplate, ParseError> {
+        let err = |message: String, span: Range<usize>| ParseError {
+            message,
+            span,
+            input: input.to_owned(),
+        };
+        let Some((body, ext)) = input
+            .rsplit_once('.')
+            .filter(|(_, ext)| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        else {
+            return Err(err(
+                "template must end with a file extension (e.g. `.sql`)".to_owned(),
+                input.len()..input.len(),
+            ));
+        };
+
+        let mut segments = Vec::new();
+        let mut offset = 0;
+        let parts = body.split('/').collect::<Vec<_>>();
+        for (i, part) in parts.iter().enumerate() {
+            let mut tokens = Vec::new();
+            let mut rest = *part;
+            while !rest.is_empty() {
+                let start = offset + part.len() - rest.len();
+                if let Some(placeholder) = rest.strip_prefix('{') {
+                    let Some((name, tail)) = placeholder.split_once('}') else {
+                        return Err(err("unclosed placeholder".to_owned(), start..input.len()));
+                    };
+                    let token = match name {
+                        "timestamp" => {
+                            Token::Timestamp(Timestamp::Epoch(EpochTimestamp::Second(0)))
+                        }
+                        "counter" => Token::PaddedNumber(PaddedNumber {
+                            width: 1,
+                            number: 0,
+                        }),
+                        "name" => Token::Name(String::new()),
+                        "updown" => Token::UpDown(UpDown::Up, Case::Lower),
+                        "branch" => Token::Branch,
+                        "author" => Token::Author,
+                        // anything else is a variable set when resolving (e.g. `{team}`)
+                        _ if !name.is_empty()
+                            && name
+                                .chars()
+                                .all(|c| c.is_alphanumeric() || c == '_' || c == '-') =>
+                        {
+                            Token::Var(name.to_owned())
+                        }
+                        _ => {
+                            return Err(err(
+                                format!("unknown placeholder `{{{name}}}`\n{PLACEHOLDERS}"),
+                                start..start + name.len() + 2,
+                            ));
+                        }
+                    };
+                    tokens.push(token);
+                    rest = tail;
+                    continue;
+                }
+                let (token, tail) = match rest.split_at(1) {
+                    ("_", tail) => (Token::Underscore, tail),
+                    ("-", tail) => (Token::Dash, tail),
+                    (".", tail) => (Token::Dot, tail),
+                    _ => {
+                        let end = rest.find(['{', '_', '-', '.']).unwrap_or(rest.len());
+                        (Token::Prefix(rest[..end].to_owned()), &rest[end..])
+                    }
+                };
+                tokens.push(token);
+                rest = tail;
+            }
+            let kind = if i + 1 == parts.len() {
+                tokens.push(Token::Extension(ext.to_owned()));
+                SegmentKind::File
+            } else {
+                SegmentKind::Dir
+            };
+            offset += part.len() + 1;
+            segments.push(Segment { kind, tokens });
+        }
+
+        let template = PathTemplate { segments };
+        if !template.tokens().any(|t| matches!(t, Token::Name(_))) {
+            return Err(err(
+                "template must include `{name}`".to_owned(),
+                0..input.len(),
+            ));
+        }
+        Ok(template)
     }
 
-    pub fn parse(input: &str) -> std::result::Result<PathTemplate, ParseError> {
-        let segments = path.parse(input).map_err(|e| ParseError {
+    pub fn parse(input: &str, bounds: DateBounds) -> std::result::Result<PathTemplate, ParseError> {
+        let segments = path(bounds).parse(input).map_err(|e| ParseError {
             message: e.inner().to_string(),
             span: e.char_span(),
             input: input.to_owned(),
@@ -496,21 +733,78 @@ mod parser {
 }
 
 mod ast {
-    use std::{fmt, str::FromStr};
+    use std::{collections::HashMap, fmt, str::FromStr};
 
     use anyhow::anyhow;
     use chrono::Utc;
 
-    use super::parser::{self, ParseError};
+    use super::{
+        parser::{self, ParseError},
+        DateBounds, DEFAULT_EXTENSION,
+    };
 
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, Clone, PartialEq)]
     pub struct PathTemplate {
         pub(crate) segments: Vec<Segment>,
     }
 
     impl PathTemplate {
         pub fn parse(path: &str) -> Result<Self, ParseError> {
-            parser::parse(path)
+            parser::parse(path, DateBounds::default())
+        }
+
+        /// parses `path`, taking dates and epoch timestamps within `bounds` for its version
+        pub fn parse_within(path: &str, bounds: DateBounds) -> Result<Self, ParseError> {
+            parser::parse(path, bounds)
+        }
+
+        pub fn compile(template: &str) -> Result<Self, ParseError> {
+            parser::compile(template)
+        }
+
+        /// true when resolving needs [TemplateData::branch] or [TemplateData::author]
+        pub fn includes_git(&self) -> bool {
+            self.tokens()
+                .any(|t| matches!(t, Token::Branch | Token::Author))
+        }
+
+        /// the names of the variables resolving needs in [TemplateData::vars] (e.g. `team` for
+        /// `{team}`)
+        pub fn vars(&self) -> impl Iterator<Item = &str> {
+            self.tokens().filter_map(|t| match t {
+                Token::Var(name) => Some(name.as_str()),
+                _ => None,
+            })
+        }
+
+        /// the file extension new migrations are written with (without the `.`)
+        pub fn extension(&self) -> &str {
+            self.tokens()
+                .find_map(|t| match t {
+                    Token::Extension(ext) => Some(ext.as_str()),
+                    _ => None,
+                })
+                .unwrap_or(DEFAULT_EXTENSION)
+        }
+
+        /// writes new migrations with the file extension `ext` (without the `.`)
+        pub fn with_extension(mut self, ext: &str) -> Self {
+            for token in self.segments.iter_mut().flat_map(|s| &mut s.tokens) {
+                if let Token::Extension(current) = token {
+                    *current = ext.to_owned();
+                }
+            }
+            self
+        }
+
+        /// true for Flyway's repeatable migrations (`R__<name>.sql`), which are applied after the
+        /// versioned ones
+        pub fn is_repeatable(&self) -> bool {
+            matches!(self.tokens().next(), Some(Token::Prefix(prefix)) if prefix == "R")
+        }
+
+        pub(crate) fn tokens(&self) -> impl Iterator<Item = &Token> {
+            self.segments.iter().flat_map(|s| &s.tokens)
         }
 
         pub fn includes_up_down(&self) -> bool {
@@ -518,29 +812,137 @@ mod ast {
                 s.tokens
                     .iter()
                     .rev()
-                    .any(|t| matches!(t, Token::UpDown(_) | Token::DoUndo(_)))
+                    .any(|t| matches!(t, Token::UpDown(..) | Token::DoUndo(..)))
             })
         }
 
         pub fn with_up_down(self) -> Self {
             let mut segments = self.segments;
             if let Some(s) = segments.last_mut() {
-                let ext = s.tokens.pop().unwrap_or(Token::Extension);
+                let ext = s
+                    .tokens
+                    .pop()
+                    .unwrap_or_else(|| Token::Extension(DEFAULT_EXTENSION.to_owned()));
                 if !matches!(
                     s.tokens.last(),
-                    Some(Token::UpDown(_)) | Some(Token::DoUndo(_))
+                    Some(Token::UpDown(..)) | Some(Token::DoUndo(..))
                 ) {
                     s.tokens.push(Token::Dot);
-                    s.tokens.push(Token::UpDown(UpDown::Up));
+                    s.tokens.push(Token::UpDown(UpDown::Up, Case::Lower));
                 }
                 s.tokens.push(ext);
             }
             Self { segments }
         }
 
+        /// true when migrations are laid out as a dir holding `up.sql` and `down.sql` (e.g.
+        /// `0001_create_users/up.sql`)
+        pub fn is_dir_layout(&self) -> bool {
+            self.segments.len() > 1
+        }
+
+        /// lays out migrations as a dir holding `up.sql` and `down.sql`, e.g.
+        /// `0001_create_users.up.sql` becomes `0001_create_users/up.sql`
+        pub fn with_dir_layout(self) -> Self {
+            if self.is_dir_layout() {
+                return self;
+            }
+            let mut tokens = self
+                .segments
+                .into_iter()
+                .flat_map(|s| s.tokens)
+                .collect::<Vec<_>>();
+            let ext = match tokens.last() {
+                Some(Token::Extension(_)) => tokens.pop(),
+                _ => None,
+            }
+            .unwrap_or_else(|| Token::Extension(DEFAULT_EXTENSION.to_owned()));
+            let updown = match tokens.last() {
+                Some(Token::UpDown(..) | Token::DoUndo(..)) => tokens.pop(),
+                _ => None,
+            }
+            .unwrap_or(Token::UpDown(UpDown::Up, Case::Lower));
+            if matches!(tokens.last(), Some(Token::Dot)) {
+                tokens.pop();
+            }
+            Self {
+                segments: vec![
+                    Segment {
+                        kind: SegmentKind::Dir,
+                        tokens,
+                    },
+                    Segment {
+                        kind: SegmentKind::File,
+                        tokens: vec![updown, ext],
+                    },
+                ],
+            }
+        }
+
+        /// lays out migrations as a single file each, e.g. `0001_create_users/up.sql` becomes
+        /// `0001_create_users.up.sql`
+        pub fn with_flat_layout(self) -> Self {
+            if !self.is_dir_layout() {
+                return self;
+            }
+            let (dirs, files): (Vec<_>, Vec<_>) = self
+                .segments
+                .into_iter()
+                .partition(|s| s.kind == SegmentKind::Dir);
+            let mut tokens = dirs.into_iter().flat_map(|s| s.tokens).collect::<Vec<_>>();
+            tokens.push(Token::Dot);
+            tokens.extend(files.into_iter().flat_map(|s| s.tokens));
+            Self {
+                segments: vec![Segment {
+                    kind: SegmentKind::File,
+                    tokens,
+                }],
+            }
+        }
+
         pub fn resolve(&self, data: &TemplateData) -> String {
             super::resolver::Resolve::resolve(self, data)
         }
+
+        /// continues the counter of `previous` (e.g. the template of the most recent migration),
+        /// so the next migration is numbered after it instead of starting over at 1
+        pub fn continue_from(mut self, previous: &PathTemplate) -> Self {
+            let Some(last) = previous.tokens().find_map(|t| match t {
+                Token::PaddedNumber(padding) => Some(padding),
+                _ => None,
+            }) else {
+                return self;
+            };
+            for token in self.segments.iter_mut().flat_map(|s| &mut s.tokens) {
+                if let Token::PaddedNumber(padding) = token {
+                    padding.number = last.number;
+                    padding.width = padding.width.max(last.width);
+                }
+            }
+            self
+        }
+
+        /// the data the template was parsed from, i.e. resolving it yields the original path
+        pub fn template_data(&self) -> anyhow::Result<TemplateData> {
+            let mut data = TemplateData {
+                verbatim_name: true,
+                ..Default::default()
+            };
+            for token in self.segments.iter().flat_map(|s| &s.tokens) {
+                match token {
+                    Token::Timestamp(ts) => data.timestamp = ts.clone().try_into()?,
+                    Token::Name(name) => data.name = name.clone(),
+                    Token::PaddedNumber(padding) => data.counter = Some(padding.number),
+                    Token::RandomNumber(rand) => data.random = Some(*rand),
+                    Token::Semver(semver) => data.semver = Some(semver.clone()),
+                    Token::UpDown(updown, _) => data.up_down = Some(updown.clone()),
+                    Token::DoUndo(doundo, _) => data.up_down = Some(doundo.clone().into()),
+                    // the rest of the data is used directly
+                    _ => {}
+                }
+            }
+            Ok(data)
+        }
     }
 
     impl Default for PathTemplate {
@@ -553,21 +955,21 @@ mod ast {
                         Token::Underscore,
                         Token::Name("generated_migration".to_string()),
                         Token::Dot,
-                        Token::UpDown(UpDown::Up),
-                        Token::Extension,
+                        Token::UpDown(UpDown::Up, Case::Lower),
+                        Token::Extension(DEFAULT_EXTENSION.to_owned()),
                     ],
                 }],
             }
         }
     }
 
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, Clone, PartialEq)]
     pub struct Segment {
         pub kind: SegmentKind,
         pub tokens: Vec<Token>,
     }
 
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, Clone, PartialEq)]
     pub enum SegmentKind {
         Dir,
         File,
@@ -581,6 +983,27 @@ mod ast {
         pub counter: Option<usize>,
         pub random: Option<usize>,
         pub semver: Option<Semver>,
+        /// the current git branch
+        pub branch: Option<String>,
+        /// the git user's name
+        pub author: Option<String>,
+        /// the UTC offset dates and times are written in (default is UTC)
+        pub offset: Option<chrono::FixedOffset>,
+        /// write the name as it is instead of slugifying it (e.g. `Add users & teams` becomes
+        /// `add_users_teams`)
+        pub verbatim_name: bool,
+        /// the values of the template's variables (e.g. `team` for `{team}`)
+        pub vars: HashMap<String, String>,
+    }
+
+    impl TemplateData {
+        /// the timestamp in the offset dates and times are written in
+        pub fn offset_timestamp(&self) -> chrono::DateTime<chrono::FixedOffset> {
+            match self.offset {
+                Some(offset) => self.timestamp.with_timezone(&offset),
+                None => self.timestamp.fixed_offset(),
+            }
+        }
     }
 
     #[derive(Debug, Clone, PartialEq)]
@@ -591,24 +1014,30 @@ mod ast {
         PaddedNumber(PaddedNumber),
         /// any sequence of numbers
         RandomNumber(usize),
-        /// e.g. 0.1.0, 11.12.13, etc
+        /// e.g. 0.1.0, 11.12.13, 1.2.3.4, etc
         Semver(Semver),
         /// represents a date/time
         Timestamp(Timestamp),
         /// name of the migration
         Name(String),
-        /// either ".up" or ".down"
-        UpDown(UpDown),
+        /// either ".up" or ".down" (in any case, e.g. ".UP" or ".Down")
+        UpDown(UpDown, Case),
         /// either ".do" or ".undo" (alias for UpDown)
-        DoUndo(DoUndo),
+        DoUndo(DoUndo, Case),
         /// literal underscore ("_")
         Underscore,
         /// literal dot (".")
         Dot,
         /// literal dash ("-")
         Dash,
-        /// file extension (e.g. ".sql")
-        Extension,
+        /// file extension without the `.` (e.g. "sql")
+        Extension(String),
+        /// the current git branch (only from a compiled template)
+        Branch,
+        /// the git user's name (only from a compiled template)
+        Author,
+        /// a variable from [TemplateData::vars] (only from a compiled template)
+        Var(String),
     }
 
     #[derive(Debug, Clone, PartialEq)]
@@ -628,6 +1057,48 @@ mod ast {
         }
     }
 
+    /// how an up/down suffix is written, which new migrations keep (e.g. `.UP.sql` is followed by
+    /// `.DOWN.sql`)
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Case {
+        /// e.g. "up"
+        Lower,
+        /// e.g. "UP"
+        Upper,
+        /// e.g. "Up"
+        Capitalized,
+    }
+
+    impl Case {
+        /// the case `word` is written in (lowercase unless it's all uppercase or capitalized)
+        pub(crate) fn of(word: &str) -> Self {
+            let mut chars = word.chars();
+            let first = chars.next().is_some_and(char::is_uppercase);
+            let rest = chars.as_str();
+            if first && rest.chars().all(char::is_uppercase) {
+                Self::Upper
+            } else if first && rest.chars().all(char::is_lowercase) {
+                Self::Capitalized
+            } else {
+                Self::Lower
+            }
+        }
+
+        /// writes the lowercase `word` in this case
+        pub(crate) fn apply(self, word: &str) -> String {
+            match self {
+                Self::Lower => word.to_owned(),
+                Self::Upper => word.to_uppercase(),
+                Self::Capitalized => {
+                    let mut chars = word.chars();
+                    chars.next().map_or_else(String::new, |first| {
+                        first.to_uppercase().chain(chars).collect()
+                    })
+                }
+            }
+        }
+    }
+
     #[derive(Debug, Clone, PartialEq)]
     pub enum UpDown {
         Up,
@@ -638,7 +1109,7 @@ mod ast {
         type Err = anyhow::Error;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            Ok(match s {
+            Ok(match s.to_ascii_lowercase().as_str() {
                 "up" => Self::Up,
                 "down" => Self::Down,
                 _ => return Err(anyhow!("invalid UP_DOWN token: {:?}", s)),
@@ -665,7 +1136,7 @@ mod ast {
         type Err = anyhow::Error;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            Ok(match s {
+            Ok(match s.to_ascii_lowercase().as_str() {
                 "do" => Self::Do,
                 "undo" => Self::Undo,
                 _ => return Err(anyhow!("invalid DO_UNDO token: {:?}", s)),
@@ -678,7 +1149,9 @@ mod ast {
         major: u32,
         minor: u32,
         patch: u32,
-        widths: (usize, usize, usize),
+        /// the fourth part of a 4-part version (e.g. `1.2.3.4`)
+        build: Option<u32>,
+        widths: (usize, usize, usize, usize),
     }
 
     impl Semver {
@@ -686,6 +1159,7 @@ mod ast {
             Self {
                 minor: self.minor + 1,
                 patch: 0,
+                build: self.build.map(|_| 0),
                 ..self
             }
         }
@@ -693,12 +1167,16 @@ mod ast {
 
     impl fmt::Display for Semver {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            let (w1, w2, w3) = self.widths;
+            let (w1, w2, w3, w4) = self.widths;
             write!(
                 f,
                 "{:0>w1$}.{:0>w2$}.{:0>w3$}",
                 self.major, self.minor, self.patch
-            )
+            )?;
+            if let Some(build) = self.build {
+                write!(f, ".{build:0>w4$}")?;
+            }
+            Ok(())
         }
     }
 
@@ -707,7 +1185,7 @@ mod ast {
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
             let parts = s
-                .splitn(3, '.')
+                .splitn(4, '.')
                 .map(|s| {
                     let width = s.len();
                     let num = s.parse::<u32>()?;
@@ -715,15 +1193,17 @@ mod ast {
                 })
                 .collect::<Result<Vec<_>, _>>()?;
 
-            if parts.len() != 3 {
+            if parts.len() < 3 {
                 return Err(anyhow!("invalid semver: {s}"));
             }
+            let (w4, build) = parts.get(3).map_or((0, None), |(w, n)| (*w, Some(*n)));
 
             Ok(Self {
                 major: parts[0].1,
                 minor: parts[1].1,
                 patch: parts[2].1,
-                widths: (parts[0].0, parts[1].0, parts[2].0),
+                build,
+                widths: (parts[0].0, parts[1].0, parts[2].0, w4),
             })
         }
     }
@@ -734,7 +1214,8 @@ mod ast {
                 major: 0,
                 minor: 1,
                 patch: 0,
-                widths: (6, 6, 2),
+                build: None,
+                widths: (6, 6, 2, 0),
             }
         }
     }
@@ -765,7 +1246,7 @@ mod ast {
                 },
                 Timestamp::DateTime(dt) => {
                     let datetime = chrono::NaiveDateTime::try_from(dt)?;
-                    chrono::DateTime::from_naive_utc_and_offset(datetime, *Utc::now().offset())
+                    chrono::DateTime::from_naive_utc_and_offset(datetime, Utc)
                 }
             })
         }
@@ -808,6 +1289,8 @@ mod ast {
         pub month: u32,
         pub month_sep: Option<String>,
         pub day: u32,
+        /// written with a two-digit year (e.g. `23-01-04`)
+        pub short_year: bool,
     }
 
     impl TryFrom<Date> for chrono::NaiveDate {
@@ -886,7 +1369,8 @@ mod resolver {
             self.segments
                 .iter()
                 .map(|s| Resolve::resolve(s, data))
-                .collect()
+                .collect::<Vec<_>>()
+                .join("/")
         }
     }
 
@@ -900,7 +1384,7 @@ mod resolver {
                     // special case: when there's an UpDown token and we're not rendering it, also don't render the preceding Dot token.
                     if data.up_down.is_none()
                         && matches!(t, Token::Dot)
-                        && matches!(next, Some(Token::UpDown(_)))
+                        && matches!(next, Some(Token::UpDown(..)))
                     {
                         String::new()
                     } else {
@@ -925,15 +1409,88 @@ mod resolver {
                 }
                 Token::Semver(v) => Resolve::resolve(v, data),
                 Token::Timestamp(ts) => Resolve::resolve(ts, data),
-                Token::Name(_) => data.name.clone(),
-                Token::UpDown(updown) => Resolve::resolve(updown, data),
-                Token::DoUndo(updown) => Resolve::resolve(updown, data),
+                Token::Name(_) if data.verbatim_name => data.name.clone(),
+                Token::Name(_) => slugify(&data.name),
+                Token::UpDown(updown, case) => case.apply(&Resolve::resolve(updown, data)),
+                Token::DoUndo(updown, case) => case.apply(&Resolve::resolve(updown, data)),
                 Token::Underscore => "_".to_owned(),
                 Token::Dot => ".".to_owned(),
                 Token::Dash => "-".to_owned(),
-                Token::Extension => ".sql".to_owned(),
+                Token::Extension(ext) => format!(".{ext}"),
+                Token::Branch => slug(data.branch.as_deref().unwrap_or_default()),
+                Token::Author => slug(data.author.as_deref().unwrap_or_default()),
+                Token::Var(name) => slug(data.vars.get(name).map_or("", String::as_str)),
+            }
+        }
+    }
+
+    /// lowercases `s` and replaces anything but letters and digits with dashes (e.g. `feature/x`
+    /// becomes `feature-x`)
+    fn slug(s: &str) -> String {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// lowercases `name`, spells accented letters without their accents, and replaces each run of
+    /// anything else but letters and digits with a single separator (the run's `-` or `_` if it has
+    /// one, e.g. `Add users & teams` becomes `add_users_teams`)
+    fn slugify(name: &str) -> String {
+        let mut slug = String::with_capacity(name.len());
+        let mut sep = None;
+        for c in name.chars().flat_map(char::to_lowercase) {
+            let letters = match c {
+                'a'..='z' | '0'..='9' => None,
+                c => match unaccented(c) {
+                    Some(letters) => Some(letters),
+                    None => {
+                        // any other character only counts when the run has no `-` or `_`
+                        sep = match (sep, c) {
+                            (Some('-' | '_'), _) => sep,
+                            (_, '-' | '_') => Some(c),
+                            _ => Some(' '),
+                        };
+                        continue;
+                    }
+                },
+            };
+            if let Some(sep) = sep.take().filter(|_| !slug.is_empty()) {
+                slug.push(if sep == ' ' { '_' } else { sep });
+            }
+            match letters {
+                Some(letters) => slug.push_str(letters),
+                None => slug.push(c),
             }
         }
+        slug
+    }
+
+    /// the ASCII spelling of a lowercase accented Latin letter
+    fn unaccented(c: char) -> Option<&'static str> {
+        Some(match c {
+            'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+            'æ' => "ae",
+            'ç' | 'ć' | 'č' => "c",
+            'ď' | 'đ' | 'ð' => "d",
+            'è'..='ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+            'ğ' => "g",
+            'ì'..='ï' | 'ī' | 'į' | 'ı' => "i",
+            'ł' => "l",
+            'ñ' | 'ń' | 'ň' => "n",
+            'ò'..='ö' | 'ø' | 'ō' | 'ő' => "o",
+            'œ' => "oe",
+            'ř' => "r",
+            'ś' | 'š' | 'ş' => "s",
+            'ß' => "ss",
+            'ť' | 'ţ' => "t",
+            'þ' => "th",
+            'ù'..='ü' | 'ū' | 'ů' | 'ű' => "u",
+            'ý' | 'ÿ' => "y",
+            'ź' | 'ż' | 'ž' => "z",
+            _ => return None,
+        })
     }
 
     impl Resolve for PaddedNumber {
@@ -991,10 +1548,15 @@ mod resolver {
 
     impl Resolve for Date {
         fn resolve(&self, data: &TemplateData) -> String {
-            let ts = data.timestamp;
+            let ts = data.offset_timestamp();
+            let year = if self.short_year {
+                ts.year() % 100
+            } else {
+                ts.year()
+            };
             format!(
                 "{:02}{}{:02}{}{:02}",
-                ts.year(),
+                year,
                 self.year_sep.clone().unwrap_or_default(),
                 ts.month(),
                 self.month_sep.clone().unwrap_or_default(),
@@ -1005,7 +1567,7 @@ mod resolver {
 
     impl Resolve for Time {
         fn resolve(&self, data: &TemplateData) -> String {
-            let ts = data.timestamp;
+            let ts = data.offset_timestamp();
             format!(
                 "{:02}{}{:02}{}{:02}{}{}",
                 ts.hour(),
@@ -1063,34 +1625,7 @@ mod tests {
     use anyhow::Context;
     use chrono::Utc;
 
-    use super::ast::{PathTemplate, Semver, TemplateData, Token, UpDown};
-
-    fn data(tmpl: &PathTemplate) -> TemplateData {
-        let mut data = TemplateData::default();
-        let mut timestamp = data.timestamp;
-        tmpl.segments
-            .iter()
-            .flat_map(|s| &s.tokens)
-            .for_each(|t| {
-                match t {
-                    Token::Timestamp(ts) => timestamp = ts.clone().try_into().unwrap(),
-                    Token::Name(name) => data.name = name.clone(),
-                    Token::PaddedNumber(padding) => data.counter = Some(padding.number),
-                    Token::RandomNumber(rand) => data.random = Some(*rand),
-                    Token::Semver(semver) => data.semver = Some(semver.clone()),
-                    Token::UpDown(updown) => {
-                        data.up_down = Some(updown.clone());
-                    }
-                    Token::DoUndo(doundo) => {
-                        data.up_down = Some(doundo.clone().into());
-                    }
-                    // the rest of the data is used directly
-                    _ => {}
-                };
-            });
-        data.timestamp = timestamp;
-        data
-    }
+    use super::ast::{Semver, TemplateData, UpDown};
 
     #[test]
     fn test_parse_resolve() {
@@ -1132,31 +1667,49 @@ mod tests {
             "v200211040353200_create_users.up.sql",
             "v20201231190000123456_add_users_full_name.down.sql",
             "v2_create_tags_table.sql",
+            "V1__initial.sql",
+            "V012__add_users_full_name.sql",
+            "V2__desc.sql",
             "v2.2.2_create_tags_table.sql",
             "v11.12.13_create_tags_table.sql",
             "v88.99.00_create_tags_table.sql",
             "11.12.13_create_tags_table.sql",
             "0011.0012.0013_create_tags_table.sql",
+            "1.2.3.4_create_tags_table.sql",
+            "v01.02.03.0004_create_tags_table.up.sql",
+            "23-01-04_add_comments_table.sql",
+            "23-01-04-120000_add_comments_table.down.sql",
+            "230104_add_comments_table.sql",
+            "230104120000_add_comments_table.sql",
             "zv2234234203984209384_oops_we_ran_out_of_digits.sql",
+            "000522_create_users.up.pgsql",
+            "000522_create_users.UP.sql",
+            "000522_create_users.Undo.sql",
+            "20230101_initial_setup.ddl",
             // dirs
             "017_create_logs_table/do.sql",
             "1704067200_add_users_full_name/up.sql",
             "2003-10-02.01-20-000_add_users_full_name/down.sql",
             "v1_create_posts_table/up.sql",
+            "2024-01-01-000000_create_users/up.sql",
+            "2024-01-01-134501_add_users_full_name/down.sql",
             "v20201231190000123456_add_users_full_name/down.sql",
             "v0.1.0_add_users_full_name/down.sql",
             "v11.12.13_add_users_full_name/down.sql",
             "11.12.13_add_users_full_name/down.sql",
             "1011.0012.0013_add_users_full_name/down.sql",
+            "1.2.3.4_add_users_full_name/down.sql",
+            "23-01-04_add_users_full_name/up.sql",
+            "1704067200_add_users_full_name/up.pgsql",
         ]
         .into_iter()
         .enumerate()
         .for_each(|(i, input)| {
             eprintln!("{input:?}");
-            let template = super::parser::parse(input)
+            let template = super::PathTemplate::parse(input)
                 .context(format!("test case {i:02}"))
                 .unwrap_or_else(|_| panic!("{input} should parse"));
-            let data = data(&template);
+            let data = template.template_data().unwrap();
             let template = template.with_up_down();
             let out = template.resolve(&data);
             assert_eq!(
@@ -1199,4 +1752,296 @@ mod tests {
             });
         });
     }
+
+    #[test]
+    fn test_version_shapes() {
+        let parsed = |path: &str| {
+            super::PathTemplate::parse(path)
+                .unwrap_or_else(|err| panic!("{path} should parse: {err}"))
+                .template_data()
+                .unwrap()
+        };
+        assert_eq!(
+            parsed("1.2.3.4_init.sql").semver,
+            Some("1.2.3.4".parse().unwrap())
+        );
+        let template = super::PathTemplate::parse("1.2.3.4_init.sql").unwrap();
+        let data = TemplateData {
+            name: "add_users".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(template.resolve(&data), "1.3.0.0_add_users.sql");
+
+        for path in [
+            "23-01-04_init.sql",
+            "230104_init.sql",
+            "230104120000_init.sql",
+        ] {
+            assert_eq!(
+                parsed(path).timestamp.date_naive(),
+                chrono::NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                "{path}"
+            );
+        }
+        let template = super::PathTemplate::parse("23-01-04_init.sql").unwrap();
+        let data = TemplateData {
+            timestamp: chrono::DateTime::from_timestamp(1704067200, 0).unwrap(),
+            ..data
+        };
+        assert_eq!(template.resolve(&data), "24-01-01_add_users.sql");
+
+        // padded numbers and epoch timestamps aren't mistaken for two-digit-year dates
+        assert_eq!(parsed("000522_init.sql").counter, Some(522));
+        assert_eq!(
+            parsed("1701231200_init.sql").timestamp.timestamp(),
+            1701231200
+        );
+        assert_eq!(
+            parsed("1704067200123_init.sql")
+                .timestamp
+                .timestamp_millis(),
+            1704067200123
+        );
+    }
+
+    #[test]
+    fn test_up_down_case() {
+        for (path, down) in [
+            ("0001_init.UP.sql", "0002_add_users.DOWN.sql"),
+            ("0001_init.Up.sql", "0002_add_users.Down.sql"),
+            ("0001_init.DO.sql", "0002_add_users.UNDO.sql"),
+            ("0001_init/Up.sql", "0002_add_users/Down.sql"),
+        ] {
+            let template = super::PathTemplate::parse(path).unwrap();
+            let data = template.template_data().unwrap();
+            assert_eq!(data.up_down, Some(UpDown::Up), "{path}");
+            assert_eq!(template.resolve(&data), path);
+            let data = TemplateData {
+                name: "add_users".to_owned(),
+                up_down: Some(UpDown::Down),
+                ..Default::default()
+            };
+            assert_eq!(template.resolve(&data), down);
+        }
+    }
+
+    #[test]
+    fn test_date_bounds() {
+        let parsed = |path: &str, bounds| {
+            super::PathTemplate::parse_within(path, bounds)
+                .unwrap()
+                .template_data()
+                .unwrap()
+        };
+        // afternoon times are read on a 24-hour clock
+        let data = parsed("20240101134500_init.sql", Default::default());
+        assert_eq!(
+            data.timestamp.naive_utc(),
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 1)
+                .unwrap()
+                .and_hms_opt(13, 45, 0)
+                .unwrap()
+        );
+        let data = parsed("2024-01-01-2359_init.sql", Default::default());
+        assert_eq!(data.timestamp.format("%H:%M").to_string(), "23:59");
+
+        // a date before 2000 is a padded number unless the bounds include it
+        let data = parsed("19991231_init.sql", Default::default());
+        assert_eq!(data.counter, Some(19991231));
+        let bounds = super::DateBounds::years(1990, 2100).unwrap();
+        let data = parsed("19991231_init.sql", bounds);
+        assert_eq!(data.counter, None);
+        assert_eq!(
+            data.timestamp.date_naive(),
+            chrono::NaiveDate::from_ymd_opt(1999, 12, 31).unwrap()
+        );
+        let data = parsed("946684799_init.sql", bounds);
+        assert_eq!(data.timestamp.timestamp(), 946684799);
+
+        // the last year is in the bounds
+        let bounds = super::DateBounds::years(2000, 2024).unwrap();
+        let data = parsed("20240315_init.sql", bounds);
+        assert_eq!(data.counter, None);
+        assert_eq!(
+            data.timestamp.date_naive(),
+            chrono::NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()
+        );
+        let data = parsed("20250101_init.sql", bounds);
+        assert_eq!(data.counter, Some(20250101));
+    }
+
+    #[test]
+    fn test_repeatable() {
+        let template = super::PathTemplate::parse("R__view_definitions.sql").unwrap();
+        assert!(template.is_repeatable());
+        let data = template.template_data().unwrap();
+        assert_eq!(data.name, "view_definitions");
+        assert_eq!(template.resolve(&data), "R__view_definitions.sql");
+        let template = super::PathTemplate::parse("V2__desc.sql").unwrap();
+        assert!(!template.is_repeatable());
+    }
+
+    #[test]
+    fn test_slugify() {
+        let template = super::PathTemplate::parse("0001_init.sql").unwrap();
+        let data = |name: &str| TemplateData {
+            name: name.to_owned(),
+            ..Default::default()
+        };
+        for (name, path) in [
+            ("Add users & teams", "0002_add_users_teams.sql"),
+            ("  Crème brûlée!  ", "0002_creme_brulee.sql"),
+            ("add-users -- teams", "0002_add-users-teams.sql"),
+            ("add_users", "0002_add_users.sql"),
+        ] {
+            assert_eq!(template.resolve(&data(name)), path);
+        }
+        let data = TemplateData {
+            verbatim_name: true,
+            ..data("Add users & teams")
+        };
+        assert_eq!(template.resolve(&data), "0002_Add users & teams.sql");
+
+        // a generated name is written as it is, keeping the separator between its parts
+        let tree = crate::SyntaxTree::parse(
+            crate::dialect::Generic,
+            "ALTER TABLE users ADD COLUMN name TEXT; CREATE TABLE posts (id INT);",
+        )
+        .unwrap();
+        let name = crate::name_gen::generate_name(&tree).build().unwrap();
+        let data = TemplateData {
+            name,
+            verbatim_name: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            template.resolve(&data),
+            "0002_alter_users_add_name__create_posts.sql"
+        );
+    }
+
+    #[test]
+    fn test_extension() {
+        let template = super::PathTemplate::parse("0001_init.ddl").unwrap();
+        assert_eq!(template.extension(), "ddl");
+        let data = TemplateData {
+            name: "add_users".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(template.resolve(&data), "0002_add_users.ddl");
+        let template = template.with_extension("pgsql");
+        assert_eq!(template.resolve(&data), "0002_add_users.pgsql");
+        assert_eq!(super::PathTemplate::default().extension(), "sql");
+        assert!(super::PathTemplate::parse("0001_init.sql~").is_err());
+    }
+
+    #[test]
+    fn test_compile() {
+        let template =
+            super::PathTemplate::compile("V{timestamp}_{author}_{branch}_{name}.{updown}.sql")
+                .unwrap();
+        assert!(template.includes_git());
+        let data = TemplateData {
+            timestamp: chrono::DateTime::from_timestamp(1704067200, 0).unwrap(),
+            name: "add_users".to_owned(),
+            up_down: Some(UpDown::Down),
+            branch: Some("feature/Sign-Up".to_owned()),
+            author: Some("Jane Doe".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            template.resolve(&data),
+            "V1704067200_jane-doe_feature-sign-up_add_users.down.sql"
+        );
+
+        let template = super::PathTemplate::compile("{timestamp}-{name}/{updown}.sql").unwrap();
+        assert!(!template.includes_git());
+        assert_eq!(template.resolve(&data), "1704067200-add_users/down.sql");
+
+        let template = super::PathTemplate::compile("V{counter}__{name}.sql").unwrap();
+        assert_eq!(template.resolve(&data), "V1__add_users.sql");
+        let previous = super::PathTemplate::parse("V007__add_posts.sql").unwrap();
+        assert_eq!(
+            template.continue_from(&previous).resolve(&data),
+            "V008__add_users.sql"
+        );
+
+        let template = super::PathTemplate::compile("{timestamp}_{name}.{updown}.pgsql").unwrap();
+        assert_eq!(template.extension(), "pgsql");
+        assert_eq!(template.resolve(&data), "1704067200_add_users.down.pgsql");
+
+        assert!(super::PathTemplate::compile("{timestamp}_{name}").is_err());
+        assert!(super::PathTemplate::compile("{timestamp}_{name}.{updown}").is_err());
+        let template = super::PathTemplate::compile("{timestamp}_{team}_{name}.sql").unwrap();
+        assert_eq!(template.vars().collect::<Vec<_>>(), ["team"]);
+        let data = TemplateData {
+            vars: [("team".to_owned(), "Payments Team".to_owned())].into(),
+            ..data
+        };
+        assert_eq!(
+            template.resolve(&data),
+            "1704067200_payments-team_add_users.sql"
+        );
+        assert!(super::PathTemplate::compile("{timestamp}_{a b}_{name}.sql").is_err());
+        assert!(super::PathTemplate::compile("{timestamp}_{}_{name}.sql").is_err());
+        assert!(super::PathTemplate::compile("{timestamp}.sql").is_err());
+    }
+
+    #[test]
+    fn test_resolve_offset() {
+        let template = super::PathTemplate::parse("20240101120000_init.sql").unwrap();
+        let data = TemplateData {
+            timestamp: chrono::DateTime::from_timestamp(1704067200, 0).unwrap(),
+            name: "add_users".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(template.resolve(&data), "20240101000000_add_users.sql");
+
+        let data = TemplateData {
+            offset: chrono::FixedOffset::west_opt(5 * 3600),
+            ..data
+        };
+        assert_eq!(template.resolve(&data), "20231231190000_add_users.sql");
+
+        // epoch timestamps don't depend on the offset
+        let template = super::PathTemplate::parse("1704067200_init.sql").unwrap();
+        assert_eq!(template.resolve(&data), "1704067200_add_users.sql");
+    }
+
+    #[test]
+    fn test_layout() {
+        let data = TemplateData {
+            name: "add_users".to_owned(),
+            up_down: Some(UpDown::Down),
+            ..Default::default()
+        };
+        let template = super::PathTemplate::parse("0001_init.up.pgsql").unwrap();
+        assert!(!template.is_dir_layout());
+        let template = template.with_dir_layout();
+        assert!(template.is_dir_layout());
+        assert_eq!(template.resolve(&data), "0002_add_users/down.pgsql");
+        let template = template.with_flat_layout();
+        assert_eq!(template.resolve(&data), "0002_add_users.down.pgsql");
+
+        let template = super::PathTemplate::parse("0001_init.sql")
+            .unwrap()
+            .with_dir_layout();
+        assert_eq!(template.resolve(&data), "0002_add_users/down.sql");
+        let template = super::PathTemplate::default().with_dir_layout();
+        assert_eq!(template.with_dir_layout().segments.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_dir() {
+        let template = super::PathTemplate::parse("0001_init/up.sql").unwrap();
+        assert_eq!(template.segments.len(), 2, "{template:?}");
+        assert!(template.includes_up_down());
+
+        let data = TemplateData {
+            name: "backfill".to_owned(),
+            up_down: Some(UpDown::Down),
+            ..Default::default()
+        };
+        assert_eq!(template.resolve(&data), "0002_backfill/down.sql");
+    }
 }
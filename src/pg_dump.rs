@@ -0,0 +1,485 @@
+/*!
+Clean up the output of `pg_dump --schema-only` so it can be parsed like a schema file.
+
+pg_dump wraps the schema in session settings (`SET ...`, `SELECT pg_catalog.set_config(...)`),
+ownership (`ALTER ... OWNER TO ...`), extension comments, and psql meta-commands (e.g. `\restrict`),
+none of which describe the schema. It also qualifies every name with its schema and spreads each
+table over several statements: its sequences, defaults, identities, and constraints follow it as
+`ALTER` statements.
+
+[normalize] drops the former and folds the latter back into each `CREATE TABLE`, rendering the
+schema the same way introspecting the database does (e.g. a column owning a sequence becomes
+`serial`). Whatever it doesn't recognize is left as it is for the parser to deal with.
+*/
+
+use std::collections::HashMap;
+
+use sqlparser::{
+    dialect::PostgreSqlDialect,
+    parser::Parser,
+    tokenizer::{Token, Tokenizer},
+};
+
+use crate::ast::{
+    AlterColumnOperation, AlterTableOperation, ColumnDef, ColumnOption, ColumnOptionDef,
+    CreateTable, DataType, GeneratedAs, Ident, ObjectName, Statement,
+};
+
+/// the first line pg_dump writes (after an empty comment)
+const HEADER: &str = "-- PostgreSQL database dump";
+
+/// the schema pg_dump qualifies names with that schema files leave out
+const DEFAULT_SCHEMA: &str = "public";
+
+/// whether `sql` starts with the header pg_dump writes
+pub fn is_dump(sql: &str) -> bool {
+    sql.lines()
+        .take_while(|line| line.starts_with("--") || line.trim().is_empty())
+        .any(|line| line.trim_end() == HEADER)
+}
+
+/// strips the boilerplate out of pg_dump's output and folds each table back together
+pub fn normalize(sql: &str) -> String {
+    // psql meta-commands aren't SQL
+    let sql = sql
+        .lines()
+        .filter(|line| !line.starts_with('\\'))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let Ok(tokens) = Tokenizer::new(&PostgreSqlDialect {}, &sql).tokenize() else {
+        // the parser will say what's wrong with it
+        return sql;
+    };
+    let mut dump = Dump::default();
+    for statement in tokens.split(|token| *token == Token::SemiColon) {
+        dump.push(unqualify(statement));
+    }
+    dump.finish()
+}
+
+#[derive(Debug)]
+enum Piece {
+    Sql(String),
+    Parsed(Box<Statement>),
+    /// `CREATE SEQUENCE`, left out when a column owns it
+    Sequence(String, String),
+}
+
+#[derive(Debug, Default)]
+struct Dump {
+    pieces: Vec<Piece>,
+    /// the column owning each sequence, as `(table, column)`
+    owners: HashMap<String, (String, String)>,
+}
+
+impl Dump {
+    fn push(&mut self, tokens: Vec<Token>) {
+        let start = tokens
+            .iter()
+            .position(|token| !matches!(token, Token::Whitespace(_)))
+            .unwrap_or(tokens.len());
+        let end = tokens
+            .iter()
+            .rposition(|token| !matches!(token, Token::Whitespace(_)))
+            .map_or(start, |end| end + 1);
+        let mut tokens = tokens[start..end].to_vec();
+        let significant = tokens
+            .iter()
+            .filter(|token| !matches!(token, Token::Whitespace(_)))
+            .collect::<Vec<_>>();
+        let words = significant.iter().copied().map(keyword).collect::<Vec<_>>();
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        // names as they're written, from `start` up to `end` significant tokens
+        let name = |start: usize, end: usize| {
+            significant[start..end]
+                .iter()
+                .map(|token| match token {
+                    Token::Word(word) => word.value.clone(),
+                    token => token.to_string(),
+                })
+                .collect::<String>()
+        };
+
+        match words.as_slice() {
+            [] => return,
+            // session settings, privileges, and comments on extensions
+            ["SET", ..]
+            | ["RESET", ..]
+            | ["SELECT", ..]
+            | ["GRANT", ..]
+            | ["REVOKE", ..]
+            | ["COMMENT", "ON", "EXTENSION", ..] => return,
+            ["ALTER", ..] if words.windows(2).any(|w| w == ["OWNER", "TO"]) => return,
+            ["CREATE", "SEQUENCE", rest @ ..] => {
+                let end = 2 + rest
+                    .iter()
+                    .take_while(|w| !matches!(**w, "AS" | "START" | "INCREMENT"))
+                    .count();
+                self.pieces
+                    .push(Piece::Sequence(name(2, end), render(&tokens)));
+                return;
+            }
+            ["ALTER", "SEQUENCE", rest @ ..] => {
+                if let Some(by) = rest.windows(2).position(|w| w == ["OWNED", "BY"]) {
+                    if let [.., _, ".", _] = &rest[by + 2..] {
+                        let len = words.len();
+                        let owner = (name(len - 3, len - 2), name(len - 1, len));
+                        self.owners.insert(name(2, by + 2), owner);
+                        return;
+                    }
+                }
+            }
+            // the options only say how the sequence behind the identity is set up
+            ["ALTER", "TABLE", ..] if words.contains(&"IDENTITY") => {
+                if let Some(identity) = tokens.iter().position(|t| keyword(t) == "IDENTITY") {
+                    tokens.truncate(identity + 1);
+                }
+            }
+            _ => {}
+        }
+
+        let sql = render(&tokens);
+        let statement = match Parser::parse_sql(&PostgreSqlDialect {}, &sql) {
+            Ok(mut statements) if statements.len() == 1 => statements.remove(0),
+            _ => {
+                self.pieces.push(Piece::Sql(sql));
+                return;
+            }
+        };
+        let statement = match statement {
+            Statement::CreateIndex(mut index) => {
+                // btree is the default index method, so it's typically omitted from schema files
+                if index
+                    .using
+                    .as_ref()
+                    .is_some_and(|using| using.to_string() == "BTREE")
+                {
+                    index.using = None;
+                }
+                Statement::CreateIndex(index)
+            }
+            Statement::CreateExtension(mut extension) => {
+                if extension
+                    .schema
+                    .as_ref()
+                    .is_some_and(|schema| schema.value == DEFAULT_SCHEMA)
+                {
+                    extension.schema = None;
+                }
+                Statement::CreateExtension(extension)
+            }
+            statement @ (Statement::CreateTable(_) | Statement::AlterTable(_)) => statement,
+            _ => {
+                self.pieces.push(Piece::Sql(sql));
+                return;
+            }
+        };
+        self.pieces.push(Piece::Parsed(Box::new(statement)));
+    }
+
+    fn finish(self) -> String {
+        let Self { pieces, owners } = self;
+        let mut tables = HashMap::new();
+        let mut folded: Vec<Piece> = Vec::with_capacity(pieces.len());
+        for piece in pieces {
+            let statement = match piece {
+                Piece::Sequence(name, _) if owners.contains_key(&name) => continue,
+                Piece::Parsed(statement) => statement,
+                piece => {
+                    folded.push(piece);
+                    continue;
+                }
+            };
+            match *statement {
+                Statement::CreateTable(table) => {
+                    tables.insert(table.name.to_string(), folded.len());
+                    folded.push(Piece::Parsed(Box::new(Statement::CreateTable(table))));
+                }
+                Statement::AlterTable(alter) => {
+                    let table = tables.get(&alter.name.to_string()).and_then(|&index| {
+                        match &mut folded[index] {
+                            Piece::Parsed(statement) => match statement.as_mut() {
+                                Statement::CreateTable(table) => Some(table),
+                                _ => None,
+                            },
+                            _ => None,
+                        }
+                    });
+                    if let Some(table) = table {
+                        if let Some(with) = fold(table.clone(), &alter.operations, &owners) {
+                            *table = with;
+                            continue;
+                        }
+                    }
+                    folded.push(Piece::Parsed(Box::new(Statement::AlterTable(alter))));
+                }
+                statement => folded.push(Piece::Parsed(Box::new(statement))),
+            }
+        }
+        let mut sql = folded
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Sql(sql) | Piece::Sequence(_, sql) => sql,
+                Piece::Parsed(statement) => statement.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(";\n\n");
+        if !sql.is_empty() {
+            sql.push(';');
+        }
+        sql
+    }
+}
+
+/// applies `operations` to `table`, or nothing when one of them has no place in `CREATE TABLE`
+fn fold(
+    mut table: CreateTable,
+    operations: &[AlterTableOperation],
+    owners: &HashMap<String, (String, String)>,
+) -> Option<CreateTable> {
+    let table_name = table.name.to_string();
+    for operation in operations {
+        match operation {
+            AlterTableOperation::AddConstraint { constraint, .. } => {
+                table.constraints.push(constraint.clone());
+            }
+            AlterTableOperation::AlterColumn { column_name, op } => {
+                let column = table
+                    .columns
+                    .iter_mut()
+                    .find(|c| c.name.value == column_name.value)?;
+                match op {
+                    AlterColumnOperation::SetDefault { value } => {
+                        let sequence = value
+                            .to_string()
+                            .strip_prefix("nextval('")
+                            .and_then(|rest| rest.split_once('\''))
+                            .map(|(sequence, _)| match sequence.split_once('.') {
+                                Some((DEFAULT_SCHEMA, sequence)) => sequence.to_owned(),
+                                _ => sequence.to_owned(),
+                            });
+                        let serial = sequence
+                            .and_then(|sequence| owners.get(&sequence))
+                            .filter(|(t, c)| *t == table_name && *c == column.name.value)
+                            .and_then(|_| serial(&column.data_type));
+                        match serial {
+                            Some(serial) => {
+                                column.data_type = DataType::Custom(
+                                    ObjectName::from(vec![Ident::new(serial)]),
+                                    vec![],
+                                );
+                                column
+                                    .options
+                                    .retain(|o| !matches!(o.option, ColumnOption::NotNull));
+                            }
+                            None => insert_option(column, ColumnOption::Default(value.clone())),
+                        }
+                    }
+                    AlterColumnOperation::AddGenerated { generated_as, .. } => insert_option(
+                        column,
+                        ColumnOption::Generated {
+                            generated_as: generated_as.unwrap_or(GeneratedAs::Always),
+                            sequence_options: None,
+                            generation_expr: None,
+                            generation_expr_mode: None,
+                            generated_keyword: true,
+                        },
+                    ),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(table)
+}
+
+/// adds `option` to `column` ahead of `NOT NULL`, where introspection puts it
+fn insert_option(column: &mut ColumnDef, option: ColumnOption) {
+    let index = column
+        .options
+        .iter()
+        .position(|o| matches!(o.option, ColumnOption::NotNull))
+        .unwrap_or(column.options.len());
+    column
+        .options
+        .insert(index, ColumnOptionDef { name: None, option });
+}
+
+/// the serial type backed by a sequence of `data_type`
+fn serial(data_type: &DataType) -> Option<&'static str> {
+    match data_type {
+        DataType::Int(None) | DataType::Integer(None) | DataType::Int4(None) => Some("serial"),
+        DataType::BigInt(None) | DataType::Int8(None) => Some("bigserial"),
+        DataType::SmallInt(None) | DataType::Int2(None) => Some("smallserial"),
+        _ => None,
+    }
+}
+
+/// leaves out the default schema where it qualifies a name (e.g. `public.users`)
+fn unqualify(tokens: &[Token]) -> Vec<Token> {
+    let mut unqualified = Vec::with_capacity(tokens.len());
+    let mut tokens = tokens.iter().peekable();
+    while let Some(token) = tokens.next() {
+        let qualifier = match token {
+            Token::Word(word) => word.value == DEFAULT_SCHEMA,
+            _ => false,
+        };
+        let after_period = matches!(unqualified.last(), Some(Token::Period));
+        if qualifier && !after_period && tokens.peek() == Some(&&Token::Period) {
+            tokens.next();
+            continue;
+        }
+        unqualified.push(token.clone());
+    }
+    unqualified
+}
+
+/// an unquoted word in uppercase (to match keywords with), or any other token as it's written
+fn keyword(token: &Token) -> String {
+    match token {
+        Token::Word(word) if word.quote_style.is_none() => word.value.to_uppercase(),
+        Token::Word(word) => word.value.clone(),
+        token => token.to_string(),
+    }
+}
+
+fn render(tokens: &[Token]) -> String {
+    tokens.iter().map(Token::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{dialect::PostgreSQL, SyntaxTree};
+
+    const DUMP: &str = r#"--
+-- PostgreSQL database dump
+--
+
+\restrict 8sOU1GbWRNd5k3
+
+-- Dumped from database version 17.6
+-- Dumped by pg_dump version 17.6
+
+SET statement_timeout = 0;
+SET client_encoding = 'UTF8';
+SELECT pg_catalog.set_config('search_path', '', false);
+SET client_min_messages = warning;
+
+CREATE EXTENSION IF NOT EXISTS citext WITH SCHEMA public;
+
+COMMENT ON EXTENSION citext IS 'data type for case-insensitive character strings';
+
+CREATE TYPE public.status AS ENUM (
+    'active',
+    'inactive'
+);
+
+ALTER TYPE public.status OWNER TO postgres;
+
+SET default_table_access_method = heap;
+
+CREATE TABLE public.users (
+    id integer NOT NULL,
+    email public.citext NOT NULL,
+    status public.status DEFAULT 'active'::public.status NOT NULL
+);
+
+ALTER TABLE public.users OWNER TO postgres;
+
+CREATE SEQUENCE public.users_id_seq
+    AS integer
+    START WITH 1
+    INCREMENT BY 1
+    NO MINVALUE
+    NO MAXVALUE
+    CACHE 1;
+
+ALTER SEQUENCE public.users_id_seq OWNER TO postgres;
+
+ALTER SEQUENCE public.users_id_seq OWNED BY public.users.id;
+
+CREATE TABLE public.posts (
+    id bigint NOT NULL,
+    user_id integer NOT NULL,
+    published boolean
+);
+
+ALTER TABLE public.posts ALTER COLUMN id ADD GENERATED ALWAYS AS IDENTITY (
+    SEQUENCE NAME public.posts_id_seq
+    START WITH 1
+    INCREMENT BY 1
+    NO MINVALUE
+    NO MAXVALUE
+    CACHE 1
+);
+
+ALTER TABLE ONLY public.users ALTER COLUMN id SET DEFAULT nextval('public.users_id_seq'::regclass);
+
+ALTER TABLE ONLY public.posts ALTER COLUMN published SET DEFAULT false;
+
+ALTER TABLE ONLY public.users
+    ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.posts
+    ADD CONSTRAINT posts_pkey PRIMARY KEY (id);
+
+CREATE UNIQUE INDEX users_email_idx ON public.users USING btree (email);
+
+ALTER TABLE ONLY public.posts
+    ADD CONSTRAINT posts_user_id_fkey FOREIGN KEY (user_id) REFERENCES public.users(id) ON DELETE CASCADE;
+
+--
+-- PostgreSQL database dump complete
+--
+
+\unrestrict 8sOU1GbWRNd5k3
+"#;
+
+    #[test]
+    fn detect() {
+        assert!(is_dump(DUMP));
+        assert!(!is_dump("CREATE TABLE users (id INT);"));
+        assert!(!is_dump(
+            "CREATE TABLE users (id INT);\n-- PostgreSQL database dump"
+        ));
+    }
+
+    #[test]
+    fn dump() {
+        let tree = SyntaxTree::parse(PostgreSQL, normalize(DUMP).as_str()).unwrap();
+        let schema = SyntaxTree::parse(
+            PostgreSQL,
+            "CREATE EXTENSION IF NOT EXISTS citext;
+            CREATE TYPE status AS ENUM ('active', 'inactive');
+            CREATE TABLE users (
+                id serial,
+                email citext NOT NULL,
+                status status DEFAULT 'active'::status NOT NULL,
+                CONSTRAINT users_pkey PRIMARY KEY (id)
+            );
+            CREATE TABLE posts (
+                id bigint GENERATED ALWAYS AS IDENTITY NOT NULL,
+                user_id integer NOT NULL,
+                published boolean DEFAULT false,
+                CONSTRAINT posts_pkey PRIMARY KEY (id),
+                CONSTRAINT posts_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+            );
+            CREATE UNIQUE INDEX users_email_idx ON users (email);",
+        )
+        .unwrap();
+        assert_eq!(tree.to_string(), schema.to_string());
+        assert!(tree.diff(&schema).unwrap().is_none());
+    }
+
+    #[test]
+    fn passthrough() {
+        let sql = "CREATE VIEW active_users AS SELECT * FROM public.users WHERE active;";
+        assert_eq!(
+            normalize(sql),
+            "CREATE VIEW active_users AS SELECT * FROM users WHERE active;"
+        );
+    }
+}
@@ -0,0 +1,1111 @@
+/*!
+Rewrite a migration into steps that don't hold long locks on tables that are in use, e.g. building
+indexes with `CREATE INDEX CONCURRENTLY` and adding constraints as `NOT VALID` before validating
+them separately (PostgreSQL).
+
+Also describes the locks each statement takes, so the risk of running a migration can be reviewed,
+splits column renames into expand/contract steps for applications deployed with rolling releases,
+and leaves NOT NULL off new columns that existing rows need a value for.
+*/
+
+use std::fmt;
+
+use crate::{
+    ast::{
+        AlterColumnOperation, AlterTable, AlterTableAlgorithm, AlterTableLock, AlterTableOperation,
+        AlterType, AlterTypeOperation, CheckConstraint, ColumnDef, ColumnOption, CreateIndex, Expr,
+        ForeignKeyConstraint, Ident, ObjectName, ObjectNamePart, ObjectType, Statement,
+        TableConstraint,
+    },
+    dialect::{Custom, Generic, MsSQL, MySQL, PostgreSQL, SQLite, Supports},
+    sealed::Sealed,
+    transpile::is_autoincrement_keyword,
+    Parse, ParseError, SyntaxTree,
+};
+
+/// what a dialect supports for changing tables without blocking reads and writes
+pub trait ZeroDowntime: Supports + Sealed {
+    /// indexes can be built without blocking writes (`CREATE INDEX CONCURRENTLY`)
+    fn concurrent_indexes(&self) -> bool {
+        false
+    }
+
+    /// constraints can be added without checking existing rows and validated later (`NOT VALID`
+    /// and `VALIDATE CONSTRAINT`)
+    fn deferred_validation(&self) -> bool {
+        false
+    }
+
+    fn plan_zero_downtime(&self, tree: &[Statement]) -> Vec<Statement> {
+        plan(self, tree)
+    }
+
+    /// the statement can't run inside a transaction, so it has to be kept out of a migration
+    /// that's run in one (see [SyntaxTree::split_transactions])
+    fn outside_transaction(&self, statement: &Statement) -> bool {
+        match statement {
+            Statement::CreateIndex(index) => index.concurrently,
+            Statement::AlterType(AlterType {
+                operation: AlterTypeOperation::AddValue(_),
+                ..
+            }) => !self.supports().add_value_in_transaction,
+            _ => false,
+        }
+    }
+
+    /// the lock a statement takes on an existing table, if the dialect locks tables that way
+    fn lock_impact(&self, _statement: &Statement) -> Option<LockImpact> {
+        None
+    }
+}
+
+/// the lock a statement holds on a table while it runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LockImpact {
+    pub table: String,
+    pub level: LockLevel,
+    /// true when the table (and its indexes) are rewritten, holding the lock for as long as that
+    /// takes
+    pub rewrite: bool,
+}
+
+/// table lock levels, from weakest to strongest: PostgreSQL's lock modes, with MySQL's `LOCK`
+/// levels after the mode they're like
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum LockLevel {
+    /// blocks other schema changes, but not reads or writes
+    ShareUpdateExclusive,
+    /// MySQL's `LOCK=NONE`, which doesn't block reads or writes
+    LockNone,
+    /// blocks writes
+    Share,
+    /// MySQL's `LOCK=SHARED`, which blocks writes
+    LockShared,
+    /// blocks writes and other schema changes
+    ShareRowExclusive,
+    /// blocks reads and writes
+    AccessExclusive,
+    /// MySQL's `LOCK=EXCLUSIVE`, which blocks reads and writes
+    LockExclusive,
+}
+
+impl fmt::Display for LockLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::ShareUpdateExclusive => "SHARE UPDATE EXCLUSIVE",
+            Self::Share => "SHARE",
+            Self::ShareRowExclusive => "SHARE ROW EXCLUSIVE",
+            Self::AccessExclusive => "ACCESS EXCLUSIVE",
+            Self::LockNone => "NONE",
+            Self::LockShared => "SHARED",
+            Self::LockExclusive => "EXCLUSIVE",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl ZeroDowntime for Generic {}
+
+impl ZeroDowntime for Custom {}
+
+impl ZeroDowntime for PostgreSQL {
+    fn concurrent_indexes(&self) -> bool {
+        true
+    }
+
+    fn deferred_validation(&self) -> bool {
+        true
+    }
+
+    fn lock_impact(&self, statement: &Statement) -> Option<LockImpact> {
+        postgres_lock_impact(statement)
+    }
+}
+
+impl ZeroDowntime for SQLite {}
+
+impl ZeroDowntime for MsSQL {}
+
+impl ZeroDowntime for MySQL {
+    /// adds `ALGORITHM` and `LOCK` clauses to the ALTER TABLE statements of tables in use, so MySQL
+    /// fails them instead of blocking writes (those that can only copy the table are left as-is)
+    fn plan_zero_downtime(&self, tree: &[Statement]) -> Vec<Statement> {
+        plan_mysql(tree)
+    }
+
+    fn lock_impact(&self, statement: &Statement) -> Option<LockImpact> {
+        mysql_lock_impact(statement)
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect>
+where
+    Dialect: ZeroDowntime + Clone,
+{
+    /// rewrites the migration into steps that are safe to run against tables in use, as far as
+    /// the dialect supports it
+    ///
+    /// some of the steps can't run inside a transaction (e.g. `CREATE INDEX CONCURRENTLY`), so
+    /// the result has to be split with [SyntaxTree::split_transactions] and those parts run
+    /// without one (see [SyntaxTree::outside_transaction])
+    pub fn plan_zero_downtime(&self) -> Self {
+        Self {
+            dialect: self.dialect.clone(),
+            format: self.format,
+            tree: self.dialect.plan_zero_downtime(&self.tree),
+            provenance: Vec::new(),
+            handlers: self.handlers.clone(),
+        }
+    }
+
+    /// builds the indexes the migration adds to existing tables without blocking writes, when the
+    /// dialect can (the part of [SyntaxTree::plan_zero_downtime] that only changes indexes)
+    ///
+    /// indexes are still dropped with a plain `DROP INDEX`, since sqlparser can't parse
+    /// `DROP INDEX CONCURRENTLY` back from the migration
+    pub fn build_indexes_concurrently(&self) -> Self {
+        let in_use = in_use(&self.tree);
+        let tree = self
+            .tree
+            .iter()
+            .map(|s| match s {
+                Statement::CreateIndex(index)
+                    if self.dialect.concurrent_indexes() && in_use(&index.table_name) =>
+                {
+                    Statement::CreateIndex(CreateIndex {
+                        concurrently: true,
+                        ..index.clone()
+                    })
+                }
+                _ => s.clone(),
+            })
+            .collect();
+        Self {
+            dialect: self.dialect.clone(),
+            format: self.format,
+            tree,
+            provenance: Vec::new(),
+            handlers: self.handlers.clone(),
+        }
+    }
+
+    /// whether the tree has a statement that can't run inside a transaction, or runs transactions
+    /// of its own (e.g. a rebuild, see [SyntaxTree::rebuild_tables]), so it has to be run without
+    /// one (e.g. by marking its migration with [NO_TRANSACTION_MARKER])
+    ///
+    /// [NO_TRANSACTION_MARKER]: crate::migrations::NO_TRANSACTION_MARKER
+    pub fn outside_transaction(&self) -> bool {
+        self.tree.iter().any(|s| {
+            self.dialect.outside_transaction(s) || matches!(s, Statement::StartTransaction { .. })
+        })
+    }
+
+    /// splits the tree around the statements that can't run inside a transaction, giving each of
+    /// them a tree of its own while keeping the statements between them together
+    pub fn split_transactions(&self) -> Vec<Self> {
+        let mut trees: Vec<Vec<Statement>> = Vec::new();
+        let mut outside = false;
+        for s in &self.tree {
+            let was_outside = outside;
+            outside = self.dialect.outside_transaction(s);
+            match trees.last_mut() {
+                Some(tree) if !outside && !was_outside => tree.push(s.clone()),
+                _ => trees.push(vec![s.clone()]),
+            }
+        }
+        trees
+            .into_iter()
+            .map(|tree| Self {
+                dialect: self.dialect.clone(),
+                format: self.format,
+                tree,
+                provenance: Vec::new(),
+                handlers: self.handlers.clone(),
+            })
+            .collect()
+    }
+}
+
+/// a NOT NULL column added to an existing table without a default, added as nullable so its
+/// existing rows can be backfilled first
+///
+/// displays as a commented template for the backfill
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backfill {
+    pub table: ObjectName,
+    pub column: Ident,
+}
+
+impl fmt::Display for Backfill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { table, column } = self;
+        writeln!(f, "-- backfill {table}.{column} before making it NOT NULL:")?;
+        writeln!(
+            f,
+            "-- UPDATE {table} SET {column} = ... WHERE {column} IS NULL;"
+        )?;
+        writeln!(
+            f,
+            "-- ALTER TABLE {table} ALTER COLUMN {column} SET NOT NULL;"
+        )
+    }
+}
+
+impl<Dialect: Clone> SyntaxTree<Dialect> {
+    /// drops NOT NULL from columns added to existing tables without a default (which fails when
+    /// the table has rows), returning the backfills that need to be written by hand
+    pub fn defer_not_null(&self) -> (Self, Vec<Backfill>) {
+        let mut backfills = Vec::new();
+        let tree = self
+            .tree
+            .iter()
+            .map(|s| {
+                let mut s = s.clone();
+                if let Statement::AlterTable(alter) = &mut s {
+                    for op in &mut alter.operations {
+                        let AlterTableOperation::AddColumn { column_def, .. } = op else {
+                            continue;
+                        };
+                        let has_value = column_def.options.iter().any(|def| {
+                            matches!(
+                                def.option,
+                                ColumnOption::Default(_)
+                                    | ColumnOption::Generated { .. }
+                                    | ColumnOption::Identity(_)
+                            )
+                        });
+                        if has_value {
+                            continue;
+                        }
+                        let len = column_def.options.len();
+                        column_def
+                            .options
+                            .retain(|def| !matches!(def.option, ColumnOption::NotNull));
+                        if column_def.options.len() < len {
+                            backfills.push(Backfill {
+                                table: alter.name.clone(),
+                                column: column_def.name.clone(),
+                            });
+                        }
+                    }
+                }
+                s
+            })
+            .collect();
+        let tree = Self {
+            dialect: self.dialect.clone(),
+            format: self.format,
+            tree,
+            provenance: Vec::new(),
+            handlers: self.handlers.clone(),
+        };
+        (tree, backfills)
+    }
+}
+
+/// a migration that renames columns, split into steps that both the old and new versions of an
+/// application can run against
+#[derive(Debug, Clone)]
+pub struct ExpandContract<Dialect> {
+    /// the rest of the migration, plus each new column (run before deploying code that writes to
+    /// both columns)
+    pub expand: SyntaxTree<Dialect>,
+    /// copies existing values into the new columns (run once the code writes to both)
+    pub backfill: SyntaxTree<Dialect>,
+    /// drops the old columns (run once no deployed code reads them)
+    pub contract: SyntaxTree<Dialect>,
+}
+
+impl<Dialect> SyntaxTree<Dialect>
+where
+    Dialect: Parse + Clone,
+{
+    /// splits the column renames in `migration` (to be applied to this tree) into expand/contract
+    /// steps, or `None` when it doesn't rename a column of a table in this tree
+    pub fn expand_contract(
+        &self,
+        migration: &Self,
+    ) -> Result<Option<ExpandContract<Dialect>>, ParseError> {
+        let tree = |tree| Self {
+            dialect: self.dialect.clone(),
+            format: self.format,
+            tree,
+            provenance: Vec::new(),
+            handlers: self.handlers.clone(),
+        };
+        let mut expand = Vec::new();
+        let mut backfill = Vec::new();
+        let mut contract = Vec::new();
+        for s in &migration.tree {
+            let Statement::AlterTable(alter) = s else {
+                expand.push(s.clone());
+                continue;
+            };
+            let mut operations = Vec::new();
+            for op in &alter.operations {
+                let column = match op {
+                    AlterTableOperation::RenameColumn {
+                        old_column_name,
+                        new_column_name,
+                    } => self
+                        .column(&alter.name, old_column_name)
+                        .map(|column| (column, new_column_name)),
+                    _ => None,
+                };
+                let Some((old, new)) = column else {
+                    operations.push(op.clone());
+                    continue;
+                };
+                let alter_table = |op| {
+                    Statement::AlterTable(AlterTable {
+                        operations: vec![op],
+                        ..alter.clone()
+                    })
+                };
+
+                // the new column stays nullable until it's backfilled
+                let mut column_def = ColumnDef {
+                    name: new.clone(),
+                    ..old.clone()
+                };
+                column_def.options.retain(|def| {
+                    !matches!(
+                        def.option,
+                        ColumnOption::NotNull
+                            | ColumnOption::PrimaryKey(_)
+                            | ColumnOption::Unique(_)
+                    )
+                });
+                expand.push(alter_table(AlterTableOperation::AddColumn {
+                    column_keyword: true,
+                    if_not_exists: false,
+                    column_def,
+                    column_position: None,
+                }));
+                let sql = format!("UPDATE {} SET {new} = {}", alter.name, old.name);
+                backfill.extend(self.dialect.parse_sql::<Dialect>(sql.as_str())?);
+                let not_null = old
+                    .options
+                    .iter()
+                    .any(|def| matches!(def.option, ColumnOption::NotNull));
+                if not_null {
+                    contract.push(alter_table(AlterTableOperation::AlterColumn {
+                        column_name: new.clone(),
+                        op: AlterColumnOperation::SetNotNull,
+                    }));
+                }
+                contract.push(alter_table(AlterTableOperation::DropColumn {
+                    has_column_keyword: true,
+                    column_names: vec![old.name.clone()],
+                    if_exists: false,
+                    drop_behavior: None,
+                }));
+            }
+            if !operations.is_empty() {
+                expand.push(Statement::AlterTable(AlterTable {
+                    operations,
+                    ..alter.clone()
+                }));
+            }
+        }
+        if backfill.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(ExpandContract {
+            expand: tree(expand),
+            backfill: tree(backfill),
+            contract: tree(contract),
+        }))
+    }
+
+    /// the definition of `column` in the CREATE TABLE for `table`
+    fn column(&self, table: &ObjectName, column: &Ident) -> Option<&ColumnDef> {
+        self.tree.iter().find_map(|s| match s {
+            Statement::CreateTable(t) if &t.name == table => {
+                t.columns.iter().find(|c| &c.name == column)
+            }
+            _ => None,
+        })
+    }
+}
+
+impl<Dialect: ZeroDowntime> SyntaxTree<Dialect> {
+    /// the lock each statement takes, in the same order as [SyntaxTree::changes]
+    pub fn lock_impacts(&self) -> Vec<Option<LockImpact>> {
+        self.tree
+            .iter()
+            .map(|s| self.dialect.lock_impact(s))
+            .collect()
+    }
+}
+
+/// whether a table is in use, as opposed to created by the migration `tree` (and so empty and not
+/// in use yet)
+fn in_use(tree: &[Statement]) -> impl Fn(&ObjectName) -> bool + '_ {
+    move |name| {
+        !tree.iter().any(|s| {
+            matches!(s, Statement::CreateTable(table) if table_name(&table.name) == table_name(name))
+        })
+    }
+}
+
+/// the name of a table the way the database resolves it: unquoted parts are case-insensitive,
+/// and the `public` schema is the one unqualified names are in (e.g. `public.Posts` is `posts`)
+fn table_name(name: &ObjectName) -> Vec<String> {
+    let mut parts = name
+        .0
+        .iter()
+        .map(|part| match part {
+            ObjectNamePart::Identifier(ident) if ident.quote_style.is_none() => {
+                ident.value.to_lowercase()
+            }
+            ObjectNamePart::Identifier(ident) => ident.value.clone(),
+            part => part.to_string(),
+        })
+        .collect::<Vec<_>>();
+    if parts.len() > 1 && parts[0] == "public" {
+        parts.remove(0);
+    }
+    parts
+}
+
+fn plan<Dialect: ZeroDowntime + ?Sized>(dialect: &Dialect, tree: &[Statement]) -> Vec<Statement> {
+    let in_use = in_use(tree);
+
+    let mut planned = Vec::with_capacity(tree.len());
+    for s in tree {
+        match s {
+            Statement::CreateIndex(index)
+                if dialect.concurrent_indexes() && in_use(&index.table_name) =>
+            {
+                planned.push(Statement::CreateIndex(CreateIndex {
+                    concurrently: true,
+                    ..index.clone()
+                }));
+            }
+            Statement::AlterTable(alter)
+                if dialect.deferred_validation() && in_use(&alter.name) =>
+            {
+                planned.extend(plan_alter_table(alter));
+            }
+            _ => planned.push(s.clone()),
+        }
+    }
+    planned
+}
+
+/// splits foreign keys, checks, and `SET NOT NULL` out of an ALTER TABLE so they're added as
+/// `NOT VALID` and validated in separate statements
+fn plan_alter_table(alter: &AlterTable) -> Vec<Statement> {
+    let table = alter
+        .name
+        .0
+        .last()
+        .and_then(|part| part.as_ident())
+        .map(|ident| ident.value.clone())
+        .unwrap_or_default();
+
+    let mut operations = Vec::new();
+    let mut constraints = Vec::new();
+    let mut not_null = Vec::new();
+    for op in &alter.operations {
+        match op {
+            AlterTableOperation::AddColumn { .. } => {
+                let mut op = op.clone();
+                if let AlterTableOperation::AddColumn { column_def, .. } = &mut op {
+                    let column = column_def.name.clone();
+                    column_def.options.retain(|def| {
+                        let constraint = match &def.option {
+                            ColumnOption::ForeignKey(fk) => {
+                                TableConstraint::ForeignKey(ForeignKeyConstraint {
+                                    name: def.name.clone().or(fk.name.clone()),
+                                    columns: vec![column.clone()],
+                                    ..fk.clone()
+                                })
+                            }
+                            ColumnOption::Check(check) => TableConstraint::Check(CheckConstraint {
+                                name: def.name.clone().or(check.name.clone()).or_else(|| {
+                                    Some(Ident::new(format!("{table}_{}_check", column.value)))
+                                }),
+                                ..check.clone()
+                            }),
+                            _ => return true,
+                        };
+                        constraints.extend(deferrable(&constraint, &table));
+                        false
+                    });
+                }
+                operations.push(op);
+            }
+            AlterTableOperation::AddConstraint {
+                constraint,
+                not_valid: false,
+            } => match deferrable(constraint, &table) {
+                Some(constraint) => constraints.push(constraint),
+                None => operations.push(op.clone()),
+            },
+            AlterTableOperation::AlterColumn {
+                column_name,
+                op: AlterColumnOperation::SetNotNull,
+            } => not_null.push(column_name.clone()),
+            _ => operations.push(op.clone()),
+        }
+    }
+
+    // a validated `IS NOT NULL` check lets SET NOT NULL skip scanning the table
+    let not_null_checks = not_null
+        .iter()
+        .map(|column| {
+            let name = Ident::new(format!("{table}_{}_not_null", column.value));
+            let check = TableConstraint::Check(CheckConstraint {
+                name: Some(name.clone()),
+                expr: Box::new(Expr::IsNotNull(Box::new(Expr::Identifier(column.clone())))),
+                enforced: None,
+            });
+            (check, name)
+        })
+        .collect::<Vec<_>>();
+
+    let alter_table = |operations| {
+        Statement::AlterTable(AlterTable {
+            operations,
+            ..alter.clone()
+        })
+    };
+    let mut planned = Vec::new();
+    if !operations.is_empty() {
+        planned.push(alter_table(operations));
+    }
+    for (constraint, _) in constraints.iter().chain(&not_null_checks) {
+        planned.push(alter_table(vec![AlterTableOperation::AddConstraint {
+            constraint: constraint.clone(),
+            not_valid: true,
+        }]));
+    }
+    for (_, name) in constraints.iter().chain(&not_null_checks) {
+        planned.push(alter_table(vec![AlterTableOperation::ValidateConstraint {
+            name: name.clone(),
+        }]));
+    }
+    for (column, (_, name)) in not_null.into_iter().zip(not_null_checks) {
+        planned.push(alter_table(vec![AlterTableOperation::AlterColumn {
+            column_name: column,
+            op: AlterColumnOperation::SetNotNull,
+        }]));
+        planned.push(alter_table(vec![AlterTableOperation::DropConstraint {
+            if_exists: false,
+            name,
+            drop_behavior: None,
+        }]));
+    }
+    planned
+}
+
+/// a foreign key or check constraint along with its name, named the way PostgreSQL would name it
+/// when it's unnamed (other constraints can't be added as `NOT VALID`)
+fn deferrable(constraint: &TableConstraint, table: &str) -> Option<(TableConstraint, Ident)> {
+    let mut constraint = constraint.clone();
+    let name = match &mut constraint {
+        TableConstraint::ForeignKey(fk) => fk.name.get_or_insert_with(|| {
+            let columns = fk.columns.iter().map(|c| c.value.as_str());
+            Ident::new(format!(
+                "{table}_{}_fkey",
+                columns.collect::<Vec<_>>().join("_")
+            ))
+        }),
+        TableConstraint::Check(check) => check
+            .name
+            .get_or_insert_with(|| Ident::new(format!("{table}_check"))),
+        _ => return None,
+    }
+    .clone();
+    Some((constraint, name))
+}
+
+/// the lock PostgreSQL takes for a statement (statements that create new objects, other than
+/// indexes, don't lock existing tables)
+fn postgres_lock_impact(statement: &Statement) -> Option<LockImpact> {
+    let (table, level, rewrite) = match statement {
+        Statement::CreateIndex(index) => {
+            let level = if index.concurrently {
+                LockLevel::ShareUpdateExclusive
+            } else {
+                LockLevel::Share
+            };
+            (&index.table_name, level, false)
+        }
+        Statement::AlterTable(alter) => {
+            let (level, rewrite) = alter
+                .operations
+                .iter()
+                .map(alter_table_lock)
+                .fold((LockLevel::ShareUpdateExclusive, false), |a, b| {
+                    (a.0.max(b.0), a.1 || b.1)
+                });
+            (&alter.name, level, rewrite)
+        }
+        Statement::Drop {
+            object_type: ObjectType::Table | ObjectType::Index,
+            names,
+            ..
+        } => (names.first()?, LockLevel::AccessExclusive, false),
+        _ => return None,
+    };
+    Some(LockImpact {
+        table: table.to_string(),
+        level,
+        rewrite,
+    })
+}
+
+fn alter_table_lock(op: &AlterTableOperation) -> (LockLevel, bool) {
+    match op {
+        AlterTableOperation::AddColumn { column_def, .. } => {
+            (LockLevel::AccessExclusive, rewrites_on_add(column_def))
+        }
+        // converting existing values rewrites the table (unless the types are binary compatible,
+        // e.g. increasing a VARCHAR's length)
+        AlterTableOperation::AlterColumn {
+            op: AlterColumnOperation::SetDataType { .. },
+            ..
+        } => (LockLevel::AccessExclusive, true),
+        AlterTableOperation::AddConstraint {
+            constraint: TableConstraint::ForeignKey(_),
+            ..
+        } => (LockLevel::ShareRowExclusive, false),
+        AlterTableOperation::ValidateConstraint { .. } => (LockLevel::ShareUpdateExclusive, false),
+        _ => (LockLevel::AccessExclusive, false),
+    }
+}
+
+/// adding a column only rewrites the table when each row needs its own value, i.e. a generated or
+/// serial column, or a default that might be volatile
+fn rewrites_on_add(column_def: &ColumnDef) -> bool {
+    let serial = matches!(
+        column_def.data_type.to_string().to_uppercase().as_str(),
+        "SERIAL" | "BIGSERIAL" | "SMALLSERIAL"
+    );
+    serial
+        || column_def.options.iter().any(|def| match &def.option {
+            ColumnOption::Generated { .. } | ColumnOption::Identity(_) => true,
+            ColumnOption::Default(Expr::Function(f)) => !is_stable_function(&f.name),
+            _ => false,
+        })
+}
+
+/// functions that return the same value for every row in a statement
+fn is_stable_function(name: &ObjectName) -> bool {
+    matches!(
+        name.to_string().to_lowercase().as_str(),
+        "now" | "current_timestamp" | "current_date" | "transaction_timestamp"
+    )
+}
+
+/// how MySQL makes a change to a table, from least to most disruptive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Algorithm {
+    /// only changes the table's metadata
+    Instant,
+    /// changes the table without blocking reads or writes
+    Inplace,
+    /// copies the table, blocking writes until it's done
+    Copy,
+}
+
+fn plan_mysql(tree: &[Statement]) -> Vec<Statement> {
+    let in_use = in_use(tree);
+    tree.iter()
+        .map(|s| match s {
+            // the ones that say how they're made are left as they're written
+            Statement::AlterTable(alter)
+                if in_use(&alter.name)
+                    && !alter.operations.iter().any(|op| {
+                        matches!(
+                            op,
+                            AlterTableOperation::Algorithm { .. }
+                                | AlterTableOperation::Lock { .. }
+                        )
+                    }) =>
+            {
+                let mut alter = alter.clone();
+                let algorithm = |algorithm| AlterTableOperation::Algorithm {
+                    equals: true,
+                    algorithm,
+                };
+                match mysql_algorithm(&alter) {
+                    Algorithm::Instant => alter
+                        .operations
+                        .push(algorithm(AlterTableAlgorithm::Instant)),
+                    Algorithm::Inplace => alter.operations.extend([
+                        algorithm(AlterTableAlgorithm::Inplace),
+                        AlterTableOperation::Lock {
+                            equals: true,
+                            lock: AlterTableLock::None,
+                        },
+                    ]),
+                    Algorithm::Copy => {}
+                }
+                Statement::AlterTable(alter)
+            }
+            _ => s.clone(),
+        })
+        .collect()
+}
+
+/// how MySQL (8.0.29 or later) makes all the changes of an ALTER TABLE, unless it says how
+fn mysql_algorithm(alter: &AlterTable) -> Algorithm {
+    let stated = alter.operations.iter().find_map(|op| match op {
+        AlterTableOperation::Algorithm { algorithm, .. } => match algorithm {
+            AlterTableAlgorithm::Instant => Some(Algorithm::Instant),
+            AlterTableAlgorithm::Inplace => Some(Algorithm::Inplace),
+            AlterTableAlgorithm::Copy => Some(Algorithm::Copy),
+            AlterTableAlgorithm::Default => None,
+        },
+        _ => None,
+    });
+    stated.unwrap_or_else(|| {
+        alter
+            .operations
+            .iter()
+            .map(alter_table_algorithm)
+            .max()
+            .unwrap_or(Algorithm::Instant)
+    })
+}
+
+fn alter_table_algorithm(op: &AlterTableOperation) -> Algorithm {
+    match op {
+        AlterTableOperation::AddColumn { column_def, .. } => {
+            let options = || column_def.options.iter();
+            if options().any(is_autoincrement_keyword) {
+                Algorithm::Copy
+            } else if options().any(|def| {
+                matches!(
+                    def.option,
+                    ColumnOption::PrimaryKey(_) | ColumnOption::Unique(_)
+                )
+            }) {
+                Algorithm::Inplace
+            } else {
+                Algorithm::Instant
+            }
+        }
+        AlterTableOperation::DropColumn { .. }
+        | AlterTableOperation::RenameColumn { .. }
+        | AlterTableOperation::RenameTable { .. }
+        | AlterTableOperation::AlterColumn {
+            op: AlterColumnOperation::SetDefault { .. } | AlterColumnOperation::DropDefault,
+            ..
+        }
+        | AlterTableOperation::Algorithm { .. }
+        | AlterTableOperation::Lock { .. } => Algorithm::Instant,
+        AlterTableOperation::AddConstraint {
+            constraint:
+                TableConstraint::PrimaryKey(_) | TableConstraint::Unique(_) | TableConstraint::Index(_),
+            ..
+        }
+        | AlterTableOperation::DropConstraint { .. }
+        | AlterTableOperation::DropForeignKey { .. }
+        | AlterTableOperation::DropIndex { .. } => Algorithm::Inplace,
+        // e.g. MODIFY COLUMN (which copies the table when it changes the column's type, and that
+        // can't be told from the statement alone), adding a foreign key or check, or dropping the
+        // primary key
+        _ => Algorithm::Copy,
+    }
+}
+
+/// the lock MySQL takes for a statement (an instant or in-place change doesn't block reads or
+/// writes, while copying the table blocks writes)
+fn mysql_lock_impact(statement: &Statement) -> Option<LockImpact> {
+    let (table, level, rewrite) = match statement {
+        Statement::CreateIndex(index) => (&index.table_name, LockLevel::LockNone, false),
+        Statement::AlterTable(alter) => {
+            let (level, rewrite) = match mysql_algorithm(alter) {
+                Algorithm::Copy => (LockLevel::LockShared, true),
+                _ => (LockLevel::LockNone, false),
+            };
+            let stated = alter.operations.iter().find_map(|op| match op {
+                AlterTableOperation::Lock {
+                    lock: AlterTableLock::Shared,
+                    ..
+                } => Some(LockLevel::LockShared),
+                AlterTableOperation::Lock {
+                    lock: AlterTableLock::Exclusive,
+                    ..
+                } => Some(LockLevel::LockExclusive),
+                _ => None,
+            });
+            (
+                &alter.name,
+                stated.map_or(level, |stated| stated.max(level)),
+                rewrite,
+            )
+        }
+        Statement::Drop {
+            object_type: ObjectType::Table,
+            names,
+            ..
+        } => (names.first()?, LockLevel::LockExclusive, false),
+        Statement::Drop {
+            object_type: ObjectType::Index,
+            table: Some(table),
+            ..
+        } => (table, LockLevel::LockNone, false),
+        _ => return None,
+    };
+    Some(LockImpact {
+        table: table.to_string(),
+        level,
+        rewrite,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_zero_downtime() {
+        let sql = "CREATE TABLE tags (id INT); \
+            CREATE INDEX tags_idx ON tags (id); \
+            CREATE INDEX posts_idx ON posts (user_id); \
+            ALTER TABLE posts ADD COLUMN user_id INT REFERENCES users (id), ALTER COLUMN title SET NOT NULL;";
+        let planned = SyntaxTree::parse(PostgreSQL, sql)
+            .unwrap()
+            .plan_zero_downtime();
+        let actual = planned
+            .tree
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actual,
+            [
+                "CREATE TABLE tags (id INT)",
+                "CREATE INDEX tags_idx ON tags(id)",
+                "CREATE INDEX CONCURRENTLY posts_idx ON posts(user_id)",
+                "ALTER TABLE posts ADD COLUMN user_id INT",
+                "ALTER TABLE posts ADD CONSTRAINT posts_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) NOT VALID",
+                "ALTER TABLE posts ADD CONSTRAINT posts_title_not_null CHECK (title IS NOT NULL) NOT VALID",
+                "ALTER TABLE posts VALIDATE CONSTRAINT posts_user_id_fkey",
+                "ALTER TABLE posts VALIDATE CONSTRAINT posts_title_not_null",
+                "ALTER TABLE posts ALTER COLUMN title SET NOT NULL",
+                "ALTER TABLE posts DROP CONSTRAINT posts_title_not_null",
+            ]
+        );
+
+        // without support for either, the migration is left as-is
+        let tree = SyntaxTree::parse(Generic, sql).unwrap();
+        assert_eq!(tree.plan_zero_downtime().to_string(), tree.to_string());
+    }
+
+    #[test]
+    fn split_transactions() {
+        let sql = "CREATE TABLE tags (id INT); \
+            CREATE INDEX tags_idx ON tags (id); \
+            CREATE INDEX posts_idx ON posts (user_id); \
+            CREATE INDEX posts_title_idx ON posts (title); \
+            ALTER TABLE posts ADD COLUMN body TEXT;";
+        let trees = SyntaxTree::parse(PostgreSQL, sql)
+            .unwrap()
+            .build_indexes_concurrently()
+            .split_transactions();
+        let actual = trees
+            .iter()
+            .map(|tree| {
+                tree.tree
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actual,
+            [
+                vec![
+                    "CREATE TABLE tags (id INT)",
+                    "CREATE INDEX tags_idx ON tags(id)",
+                ],
+                vec!["CREATE INDEX CONCURRENTLY posts_idx ON posts(user_id)"],
+                vec!["CREATE INDEX CONCURRENTLY posts_title_idx ON posts(title)"],
+                vec!["ALTER TABLE posts ADD COLUMN body TEXT"],
+            ]
+        );
+        let outside = trees.iter().map(SyntaxTree::outside_transaction);
+        assert_eq!(outside.collect::<Vec<_>>(), [false, true, true, false]);
+
+        // a table created by the migration isn't in use, however its name is written
+        let tree = SyntaxTree::parse(
+            PostgreSQL,
+            "CREATE TABLE public.Tags (id INT); CREATE INDEX tags_idx ON \"tags\" (id);",
+        )
+        .unwrap();
+        assert!(!tree.build_indexes_concurrently().outside_transaction());
+
+        // a new enum label can't be used in the transaction that adds it
+        let tree = SyntaxTree::parse(
+            PostgreSQL,
+            "ALTER TYPE mood ADD VALUE 'happy'; ALTER TABLE users ADD COLUMN mood mood DEFAULT 'happy';",
+        )
+        .unwrap();
+        assert_eq!(tree.split_transactions().len(), 2);
+
+        // everything can run in one transaction without concurrent indexes
+        let tree = SyntaxTree::parse(Generic, sql).unwrap();
+        assert_eq!(
+            tree.build_indexes_concurrently().split_transactions().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn expand_contract() {
+        let tree = SyntaxTree::parse(
+            PostgreSQL,
+            "CREATE TABLE users (id INT, login TEXT NOT NULL);",
+        )
+        .unwrap();
+        let migration = SyntaxTree::parse(
+            PostgreSQL,
+            "CREATE TABLE tags (id INT); \
+            ALTER TABLE users RENAME COLUMN login TO username;",
+        )
+        .unwrap();
+        let steps = tree.expand_contract(&migration).unwrap().unwrap();
+        let sql = |tree: &SyntaxTree<_>| {
+            tree.tree
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sql(&steps.expand),
+            [
+                "CREATE TABLE tags (id INT)",
+                "ALTER TABLE users ADD COLUMN username TEXT",
+            ]
+        );
+        assert_eq!(sql(&steps.backfill), ["UPDATE users SET username = login"]);
+        assert_eq!(
+            sql(&steps.contract),
+            [
+                "ALTER TABLE users ALTER COLUMN username SET NOT NULL",
+                "ALTER TABLE users DROP COLUMN login",
+            ]
+        );
+
+        // nothing to split without a rename
+        let migration = SyntaxTree::parse(PostgreSQL, "CREATE TABLE tags (id INT);").unwrap();
+        assert!(tree.expand_contract(&migration).unwrap().is_none());
+    }
+
+    #[test]
+    fn defer_not_null() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE tags (id INT NOT NULL); \
+            ALTER TABLE users ADD COLUMN email TEXT NOT NULL, ADD COLUMN active BOOL NOT NULL DEFAULT true;",
+        )
+        .unwrap();
+        let (tree, backfills) = tree.defer_not_null();
+        assert_eq!(
+            tree.tree
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "CREATE TABLE tags (id INT NOT NULL)",
+                "ALTER TABLE users ADD COLUMN email TEXT, ADD COLUMN active BOOL NOT NULL DEFAULT true",
+            ]
+        );
+        assert_eq!(
+            backfills
+                .iter()
+                .map(ToString::to_string)
+                .collect::<String>(),
+            "-- backfill users.email before making it NOT NULL:\n\
+            -- UPDATE users SET email = ... WHERE email IS NULL;\n\
+            -- ALTER TABLE users ALTER COLUMN email SET NOT NULL;\n"
+        );
+    }
+
+    #[test]
+    fn lock_impacts() {
+        let sql = "CREATE TABLE tags (id INT); \
+            CREATE INDEX CONCURRENTLY posts_idx ON posts (user_id); \
+            ALTER TABLE posts ADD COLUMN created_at TIMESTAMP DEFAULT now(); \
+            ALTER TABLE posts ADD COLUMN token UUID DEFAULT gen_random_uuid(); \
+            ALTER TABLE posts ALTER COLUMN title TYPE TEXT; \
+            ALTER TABLE posts VALIDATE CONSTRAINT posts_user_id_fkey;";
+        let actual = SyntaxTree::parse(PostgreSQL, sql)
+            .unwrap()
+            .lock_impacts()
+            .into_iter()
+            .map(|lock| lock.map(|lock| (lock.level, lock.rewrite)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actual,
+            [
+                None,
+                Some((LockLevel::ShareUpdateExclusive, false)),
+                Some((LockLevel::AccessExclusive, false)),
+                Some((LockLevel::AccessExclusive, true)),
+                Some((LockLevel::AccessExclusive, true)),
+                Some((LockLevel::ShareUpdateExclusive, false)),
+            ]
+        );
+
+        let tree = SyntaxTree::parse(Generic, sql).unwrap();
+        assert!(tree.lock_impacts().iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn mysql() {
+        let sql = "CREATE TABLE tags (id INT); \
+            ALTER TABLE tags ADD COLUMN name TEXT; \
+            ALTER TABLE posts ADD COLUMN body TEXT, RENAME COLUMN name TO title; \
+            ALTER TABLE posts ADD UNIQUE (title), ALTER COLUMN body SET DEFAULT ''; \
+            ALTER TABLE posts MODIFY COLUMN title VARCHAR(255) NOT NULL; \
+            ALTER TABLE posts ADD COLUMN id INT, ALGORITHM = COPY;";
+        let tree = SyntaxTree::parse(MySQL, sql).unwrap();
+        let planned = tree.plan_zero_downtime();
+        assert_eq!(
+            planned
+                .tree
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "CREATE TABLE tags (id INT)",
+                "ALTER TABLE tags ADD COLUMN name TEXT",
+                "ALTER TABLE posts ADD COLUMN body TEXT, RENAME COLUMN name TO title, ALGORITHM = INSTANT",
+                "ALTER TABLE posts ADD UNIQUE (title), ALTER COLUMN body SET DEFAULT '', ALGORITHM = INPLACE, LOCK = NONE",
+                "ALTER TABLE posts MODIFY COLUMN title VARCHAR(255) NOT NULL",
+                "ALTER TABLE posts ADD COLUMN id INT, ALGORITHM = COPY",
+            ]
+        );
+        let actual = planned
+            .lock_impacts()
+            .into_iter()
+            .map(|lock| lock.map(|lock| (lock.level, lock.rewrite)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actual,
+            [
+                None,
+                Some((LockLevel::LockNone, false)),
+                Some((LockLevel::LockNone, false)),
+                Some((LockLevel::LockNone, false)),
+                Some((LockLevel::LockShared, true)),
+                Some((LockLevel::LockShared, true)),
+            ]
+        );
+    }
+}
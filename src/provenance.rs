@@ -0,0 +1,250 @@
+/*!
+Where the statements of a [SyntaxTree] were parsed from, so errors can point back at the SQL that
+caused them.
+*/
+
+use std::{fmt, ops::Range};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{ast::Statement, changes::ObjectKind, SyntaxTree};
+
+/// where a statement was parsed from
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Provenance {
+    path: Option<Utf8PathBuf>,
+    index: usize,
+    span: Range<usize>,
+    location: (usize, usize),
+}
+
+impl Provenance {
+    pub(crate) fn new(index: usize, span: Range<usize>, location: (usize, usize)) -> Self {
+        Self {
+            path: None,
+            index,
+            span,
+            location,
+        }
+    }
+
+    pub(crate) fn with_path(self, path: &Utf8Path) -> Self {
+        Self {
+            path: Some(path.to_owned()),
+            ..self
+        }
+    }
+
+    /// the file the statement was read from, when it was read from one
+    pub fn path(&self) -> Option<&Utf8Path> {
+        self.path.as_deref()
+    }
+
+    /// the position of the statement among those in the same SQL (counted from 0)
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// the byte range of the statement in the SQL
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// the line and column (both counted from 1) the statement starts at
+    pub fn location(&self) -> (usize, usize) {
+        self.location
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (line, column) = self.location;
+        match &self.path {
+            Some(path) => write!(f, "{path}:{line}:{column}")?,
+            None => write!(f, "line {line}, column {column}")?,
+        }
+        write!(f, ", statement {}", self.index + 1)
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// names the file the SQL was read from in the provenance of each statement
+    pub fn with_path(mut self, path: impl AsRef<Utf8Path>) -> Self {
+        self.provenance = self
+            .provenance
+            .into_iter()
+            .map(|p| Some(p?.with_path(path.as_ref())))
+            .collect();
+        self
+    }
+
+    /// sets where each statement was parsed from (e.g. as read back from
+    /// [SyntaxTree::provenance])
+    pub fn with_provenance(self, provenance: impl IntoIterator<Item = Option<Provenance>>) -> Self {
+        Self {
+            provenance: provenance.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// where the statement at `index` was parsed from, when it's known
+    pub fn provenance(&self, index: usize) -> Option<&Provenance> {
+        self.provenance.get(index)?.as_ref()
+    }
+
+    /// where `statement` was parsed from, when it's in the tree and that's known
+    pub(crate) fn provenance_of(&self, statement: Option<&Statement>) -> Option<Provenance> {
+        let index = self.tree.iter().position(|s| Some(s) == statement)?;
+        self.provenance(index).cloned()
+    }
+
+    /// keeps the statements (and their provenance) matching `f`
+    pub(crate) fn retain_with_provenance(&mut self, mut f: impl FnMut(&Statement) -> bool) {
+        let mut provenance = std::mem::take(&mut self.provenance).into_iter();
+        let mut kept = Vec::new();
+        self.tree.retain(|s| {
+            let p = provenance.next().flatten();
+            let keep = f(s);
+            if keep {
+                kept.push(p);
+            }
+            keep
+        });
+        // leave it empty when none of it was known
+        if kept.iter().any(Option::is_some) {
+            self.provenance = kept;
+        }
+    }
+}
+
+/// the provenance of each statement of `migrated`, the result of applying `migration` to a tree
+/// with `objects` (see [object]) and `provenance`: the statements copied from the migration keep
+/// theirs, and the ones migrated keep the provenance of the statement they were migrated from
+pub(crate) fn carry_over<Dialect>(
+    objects: &[Option<(ObjectKind, String)>],
+    provenance: &[Option<Provenance>],
+    migration: &SyntaxTree<Dialect>,
+    migrated: &[Statement],
+) -> Vec<Option<Provenance>> {
+    if provenance.is_empty() && migration.provenance.is_empty() {
+        return Vec::new();
+    }
+    migrated
+        .iter()
+        .map(|s| {
+            migration
+                .provenance_of(Some(s))
+                .or_else(|| of_object(objects, provenance, s))
+        })
+        .collect()
+}
+
+/// the provenance of the statement that created the same object as `s`, given what each statement
+/// of its tree creates (see [object])
+pub(crate) fn of_object(
+    objects: &[Option<(ObjectKind, String)>],
+    provenance: &[Option<Provenance>],
+    s: &Statement,
+) -> Option<Provenance> {
+    let object = object(s)?;
+    let index = objects.iter().position(|o| o.as_ref() == Some(&object))?;
+    provenance.get(index)?.clone()
+}
+
+/// the kind and name of the object a statement creates
+pub(crate) fn object(s: &Statement) -> Option<(ObjectKind, String)> {
+    let object = match s {
+        Statement::CreateTable(table) => (ObjectKind::Table, table.name.to_string()),
+        Statement::CreateView(view) => (ObjectKind::View, view.name.to_string()),
+        Statement::CreateFunction(function) => (ObjectKind::Function, function.name.to_string()),
+        Statement::CreateIndex(index) => (ObjectKind::Index, index.name.as_ref()?.to_string()),
+        Statement::CreateType { name, .. } => (ObjectKind::Type, name.to_string()),
+        Statement::CreateExtension(extension) => {
+            (ObjectKind::Extension, extension.name.to_string())
+        }
+        Statement::CreateDomain(domain) => (ObjectKind::Domain, domain.name.to_string()),
+        _ => return None,
+    };
+    Some(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Generic;
+
+    #[test]
+    fn parse() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE foo (id INT);\n\n  CREATE INDEX foo_id ON foo (id);",
+        )
+        .unwrap();
+        let provenance = tree.provenance(1).unwrap();
+        assert_eq!(provenance.index(), 1);
+        assert_eq!(provenance.location(), (3, 3));
+        assert_eq!(provenance.span(), 30..61);
+        assert_eq!(provenance.to_string(), "line 3, column 3, statement 2");
+        let tree = tree.with_path("schema.sql");
+        assert_eq!(
+            tree.provenance(0).unwrap().to_string(),
+            "schema.sql:1:1, statement 1"
+        );
+    }
+
+    #[test]
+    fn migrate() {
+        let schema = SyntaxTree::parse(Generic, "CREATE TABLE foo (id INT);")
+            .unwrap()
+            .with_path("0001.sql");
+        let migration = SyntaxTree::parse(
+            Generic,
+            "ALTER TABLE foo ADD COLUMN name TEXT;\nCREATE TABLE bar (id INT);",
+        )
+        .unwrap()
+        .with_path("0002.sql");
+        let schema = schema.migrate(&migration).unwrap();
+        let actual = (0..2)
+            .map(|i| schema.provenance(i).map(ToString::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actual,
+            [
+                Some("0001.sql:1:1, statement 1".to_owned()),
+                Some("0002.sql:2:1, statement 2".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let schema = SyntaxTree::parse(Generic, "CREATE TABLE foo (id INT);")
+            .unwrap()
+            .with_path("0001.sql");
+        let migration = SyntaxTree::parse(Generic, "\nALTER TABLE foo DROP PRIMARY KEY;")
+            .unwrap()
+            .with_path("0002.sql");
+        let err = schema.migrate(&migration).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Oops, we couldn't migrate that: ALTER TABLE operation \"DROP PRIMARY KEY\" not yet supported\n\n\
+            Subject (0001.sql:1:1, statement 1):\nCREATE TABLE foo (id INT)\n\n\
+            Migration (0002.sql:2:1, statement 1):\nALTER TABLE foo DROP PRIMARY KEY"
+        );
+
+        let a = SyntaxTree::parse(Generic, "CREATE TYPE status AS ENUM ('a', 'b');").unwrap();
+        let b = SyntaxTree::parse(Generic, "CREATE TYPE status AS ENUM ('a');")
+            .unwrap()
+            .with_path("schema.sql");
+        let err = a.diff(&b).unwrap_err();
+        assert_eq!(
+            err.provenance_a().map(ToString::to_string).as_deref(),
+            Some("line 1, column 1, statement 1")
+        );
+        assert_eq!(
+            err.provenance_b().and_then(Provenance::path),
+            Some(Utf8Path::new("schema.sql"))
+        );
+    }
+}
@@ -0,0 +1,63 @@
+/*!
+Python bindings for the diff engine, so scripts can call it directly instead of running the binary.
+
+Build the extension module with this feature, e.g. with `maturin build --features python` or
+`cargo rustc --lib --crate-type cdylib --features python`, and `import sql_schema`. Each function
+takes the dialect by name (`generic`, `postgresql`, `sqlite`, `mysql`, or `mssql`) and returns
+SQL.
+*/
+
+use std::error::Error;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::bindings::{self, with_dialect};
+
+/// the schema, formatted
+#[pyfunction]
+fn parse(dialect: &str, sql: &str) -> PyResult<String> {
+    with_dialect!(dialect, |dialect| bindings::parse(dialect, sql)).map_err(py_error)
+}
+
+/// the migration from schema `a` to schema `b`, or `None` when they're the same
+#[pyfunction]
+fn diff(dialect: &str, a: &str, b: &str) -> PyResult<Option<String>> {
+    with_dialect!(dialect, |dialect| bindings::diff(dialect, a, b)).map_err(py_error)
+}
+
+/// the schema after applying `migration` to `schema`
+#[pyfunction]
+fn migrate(dialect: &str, schema: &str, migration: &str) -> PyResult<String> {
+    with_dialect!(dialect, |dialect| bindings::migrate(
+        dialect, schema, migration
+    ))
+    .map_err(py_error)
+}
+
+/// a name describing `migration`, e.g. `create_users`, or `None` when there's nothing to name it
+/// after
+#[pyfunction]
+#[pyo3(signature = (dialect, migration, max_len = None))]
+fn generate_name(
+    dialect: &str,
+    migration: &str,
+    max_len: Option<usize>,
+) -> PyResult<Option<String>> {
+    with_dialect!(dialect, |dialect| bindings::name(
+        dialect, migration, max_len
+    ))
+    .map_err(py_error)
+}
+
+fn py_error(err: Box<dyn Error>) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+#[pymodule]
+fn sql_schema(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(diff, m)?)?;
+    m.add_function(wrap_pyfunction!(migrate, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_name, m)?)?;
+    Ok(())
+}
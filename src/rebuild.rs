@@ -0,0 +1,282 @@
+/*!
+Rebuild tables whose changes the dialect can't make with ALTER TABLE (SQLite), as far as its
+[Capabilities] go: the table is copied into a new one that takes its place.
+*/
+
+use crate::{
+    ast::{AlterTableOperation, CreateTable, ObjectName, ObjectNamePart, ObjectType, Statement},
+    dialect::{Capabilities, Supports},
+    Parse, ParseError, SyntaxTree,
+};
+
+impl<Dialect> SyntaxTree<Dialect>
+where
+    Dialect: Supports + Parse + Clone,
+{
+    /// replaces the changes in `migration` (from this tree to `schema`) that the dialect can't
+    /// make with ALTER TABLE with a rebuild of each table they're made to: the table is created
+    /// under a new name the way it is in `schema`, its rows are copied into it, the old table is
+    /// dropped, and the new one is renamed to take its place before its indexes are recreated
+    ///
+    /// this includes the changes to existing columns the diff can't express with ALTER TABLE
+    /// (e.g. reordering them, or changing their collation)
+    ///
+    /// dropping the old table deletes its rows when foreign keys are enforced, so the rebuilds
+    /// are made with them turned off the way SQLite documents: `PRAGMA foreign_keys = 0`, then
+    /// the migration in a transaction of its own that checks the foreign keys with
+    /// `PRAGMA foreign_key_check` before it commits, and `PRAGMA foreign_keys = 1` again
+    ///
+    /// since the pragma does nothing inside a transaction, the migration has to be run without
+    /// one (see [SyntaxTree::outside_transaction])
+    pub fn rebuild_tables(&self, schema: &Self, migration: &Self) -> Result<Self, ParseError> {
+        let tree = |tree| Self {
+            dialect: self.dialect.clone(),
+            format: self.format,
+            tree,
+            provenance: Vec::new(),
+            handlers: self.handlers.clone(),
+        };
+        let supports = self.dialect.supports();
+        if !supports.rebuilds_tables() {
+            return Ok(tree(migration.tree.clone()));
+        }
+        let rebuilt = schema
+            .tree
+            .iter()
+            .filter_map(|s| match s {
+                Statement::CreateTable(b) => {
+                    let a = self.create_table(&b.name)?;
+                    needs_rebuild(a, b, &migration.tree, supports).then_some((a, b))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // each rebuild takes the place of the first statement it replaces
+        let mut planned = Vec::with_capacity(migration.tree.len());
+        let mut done = Vec::new();
+        for s in &migration.tree {
+            let replaced = rebuilt
+                .iter()
+                .find(|(a, _)| self.replaced_by_rebuild(s, &a.name));
+            match replaced {
+                Some((a, b)) if !done.contains(&&b.name) => {
+                    planned.extend(self.rebuild(a, b, schema)?);
+                    done.push(&b.name);
+                }
+                Some(_) => {}
+                None => planned.push(s.clone()),
+            }
+        }
+        // tables whose only changes were left out of the diff
+        for (a, b) in &rebuilt {
+            if !done.contains(&&b.name) {
+                planned.extend(self.rebuild(a, b, schema)?);
+            }
+        }
+        if rebuilt.is_empty() {
+            return Ok(tree(planned));
+        }
+        let mut rebuilds = self
+            .dialect
+            .parse_sql::<Dialect>("PRAGMA foreign_keys = 0; BEGIN;")?;
+        rebuilds.extend(planned);
+        rebuilds.extend(
+            self.dialect.parse_sql::<Dialect>(
+                "PRAGMA foreign_key_check; COMMIT; PRAGMA foreign_keys = 1;",
+            )?,
+        );
+        Ok(tree(rebuilds))
+    }
+
+    /// the statements that rebuild the table `a` as `b`
+    fn rebuild(
+        &self,
+        a: &CreateTable,
+        b: &CreateTable,
+        schema: &Self,
+    ) -> Result<Vec<Statement>, ParseError> {
+        let name = &b.name;
+        let mut new_name = name.clone();
+        let mut table = String::new();
+        if let Some(ObjectNamePart::Identifier(ident)) = new_name.0.last_mut() {
+            table = ident.to_string();
+            ident.value = format!("new_{}", ident.value);
+        }
+        let mut rebuild = vec![Statement::CreateTable(CreateTable {
+            name: new_name.clone(),
+            ..b.clone()
+        })];
+        let columns = b
+            .columns
+            .iter()
+            .filter(|column| a.columns.iter().any(|c| c.name == column.name))
+            .map(|column| column.name.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !columns.is_empty() {
+            let sql = format!("INSERT INTO {new_name} ({columns}) SELECT {columns} FROM {name}");
+            rebuild.extend(self.dialect.parse_sql::<Dialect>(sql.as_str())?);
+        }
+        rebuild.push(Statement::Drop {
+            object_type: ObjectType::Table,
+            if_exists: false,
+            names: vec![name.clone()],
+            cascade: false,
+            restrict: false,
+            purge: false,
+            temporary: false,
+            table: None,
+        });
+        let sql = format!("ALTER TABLE {new_name} RENAME TO {table}");
+        rebuild.extend(self.dialect.parse_sql::<Dialect>(sql.as_str())?);
+        rebuild.extend(
+            schema
+                .tree
+                .iter()
+                .filter(|s| matches!(s, Statement::CreateIndex(index) if &index.table_name == name))
+                .cloned(),
+        );
+        Ok(rebuild)
+    }
+
+    /// whether rebuilding `table` takes care of `s` (an ALTER TABLE of it, or a change to one of
+    /// its indexes)
+    fn replaced_by_rebuild(&self, s: &Statement, table: &ObjectName) -> bool {
+        match s {
+            Statement::AlterTable(alter) => &alter.name == table,
+            Statement::CreateIndex(index) => &index.table_name == table,
+            Statement::Drop {
+                object_type: ObjectType::Index,
+                names,
+                ..
+            } => names.iter().any(|name| {
+                self.tree.iter().any(|s| {
+                    matches!(s, Statement::CreateIndex(index)
+                        if index.name.as_ref() == Some(name) && &index.table_name == table)
+                })
+            }),
+            _ => false,
+        }
+    }
+
+    fn create_table(&self, name: &ObjectName) -> Option<&CreateTable> {
+        self.tree.iter().find_map(|s| match s {
+            Statement::CreateTable(table) if &table.name == name => Some(table),
+            _ => None,
+        })
+    }
+}
+
+/// whether the table `a` has to be rebuilt to become `b`, either because `migration` alters it in
+/// a way the dialect can't or because the columns they share (or its constraints) differ in a way
+/// it can't alter
+fn needs_rebuild(
+    a: &CreateTable,
+    b: &CreateTable,
+    migration: &[Statement],
+    supports: Capabilities,
+) -> bool {
+    let alters = migration.iter().any(|s| match s {
+        Statement::AlterTable(alter) if alter.name == a.name => {
+            alter.operations.iter().any(|op| match op {
+                AlterTableOperation::AddColumn { .. } | AlterTableOperation::RenameTable { .. } => {
+                    false
+                }
+                AlterTableOperation::DropColumn { .. } => !supports.drop_column,
+                AlterTableOperation::RenameColumn { .. } => !supports.rename_column,
+                AlterTableOperation::AddConstraint { .. }
+                | AlterTableOperation::DropConstraint { .. }
+                | AlterTableOperation::DropPrimaryKey { .. }
+                | AlterTableOperation::DropForeignKey { .. } => !supports.alter_constraints,
+                _ => !supports.alter_column,
+            })
+        }
+        _ => false,
+    });
+    if alters {
+        return true;
+    }
+    // what the diff can't express with ALTER TABLE (e.g. reordered columns, or their collation)
+    let shared = |table: &CreateTable, other: &CreateTable| {
+        table
+            .columns
+            .iter()
+            .filter(|column| other.columns.iter().any(|c| c.name == column.name))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let columns_changed = shared(a, b) != shared(b, a);
+    // the table's constraints, along with the rest of its definition
+    let constraints_changed = CreateTable {
+        columns: Vec::new(),
+        ..a.clone()
+    } != CreateTable {
+        columns: Vec::new(),
+        ..b.clone()
+    };
+    (columns_changed && !supports.alter_column)
+        || (constraints_changed && !supports.alter_constraints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::{Generic, SQLite};
+
+    #[test]
+    fn rebuild_tables() {
+        let tree = SyntaxTree::parse(
+            SQLite,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, age INT); \
+            CREATE INDEX users_name ON users (name); \
+            CREATE TABLE posts (id INTEGER PRIMARY KEY);",
+        )
+        .unwrap();
+        let schema = SyntaxTree::parse(
+            SQLite,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, email TEXT); \
+            CREATE INDEX users_name ON users (name); \
+            CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT); \
+            CREATE TABLE tags (id INT);",
+        )
+        .unwrap();
+        let migration = tree.diff(&schema).unwrap().unwrap();
+        let rebuilt = tree.rebuild_tables(&schema, &migration).unwrap();
+        assert_eq!(
+            rebuilt
+                .tree
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "PRAGMA foreign_keys = 0",
+                "BEGIN",
+                "CREATE TABLE new_users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, email TEXT)",
+                "INSERT INTO new_users (id, name) SELECT id, name FROM users",
+                "DROP TABLE users",
+                "ALTER TABLE new_users RENAME TO users",
+                "CREATE INDEX users_name ON users(name)",
+                "ALTER TABLE posts ADD COLUMN title TEXT",
+                "CREATE TABLE tags (id INT)",
+                "PRAGMA foreign_key_check",
+                "COMMIT",
+                "PRAGMA foreign_keys = 1",
+            ]
+        );
+        assert!(rebuilt.outside_transaction());
+        assert!(!migration.outside_transaction());
+
+        // other dialects can make the changes with ALTER TABLE
+        let tree = SyntaxTree::parse(Generic, "CREATE TABLE users (id INT);").unwrap();
+        let migration =
+            SyntaxTree::parse(Generic, "ALTER TABLE users ALTER COLUMN id SET NOT NULL;").unwrap();
+        let schema = SyntaxTree::parse(Generic, "CREATE TABLE users (id INT NOT NULL);").unwrap();
+        assert_eq!(
+            tree.rebuild_tables(&schema, &migration)
+                .unwrap()
+                .to_string(),
+            migration.to_string()
+        );
+    }
+}
@@ -0,0 +1,307 @@
+/*!
+Render a schema as SQL its dialect accepts.
+
+[SyntaxTree]'s `Display` writes each statement the way sqlparser does, keeping e.g. MySQL-style
+backtick quoting from a schema parsed as generic SQL. [SyntaxTree::to_sql] writes it for the
+tree's dialect instead.
+*/
+
+use sqlparser::{
+    dialect::SQLiteDialect,
+    tokenizer::{Location, Token, Tokenizer},
+};
+
+use crate::{
+    ast::{AlterColumnOperation, AlterTableOperation, DataType, Statement},
+    dialect::{Custom, Generic, MsSQL, MySQL, PostgreSQL, SQLite},
+    parser::byte_offset,
+    sealed::Sealed,
+    transpile::Transpile,
+    SyntaxTree,
+};
+
+/// how a dialect writes SQL
+pub trait Render: Sealed {
+    /// the quote quoted identifiers are written with, or `None` to keep the one each was written
+    /// with
+    fn quote_style(&self) -> Option<char> {
+        None
+    }
+
+    /// how `data_type` is spelled in this dialect, or `None` when it's valid as-is
+    fn data_type(&self, _data_type: &DataType) -> Option<DataType> {
+        None
+    }
+
+    /// the statement has to be in a batch of its own, so it's separated from the statements around
+    /// it with a `GO` line (e.g. `CREATE VIEW` with SQL Server)
+    fn own_batch(&self, _statement: &Statement) -> bool {
+        false
+    }
+}
+
+impl Render for Generic {}
+
+impl Render for Custom {}
+
+impl Render for PostgreSQL {
+    fn quote_style(&self) -> Option<char> {
+        Some('"')
+    }
+
+    fn data_type(&self, data_type: &DataType) -> Option<DataType> {
+        // the types it maps are the ones PostgreSQL doesn't have
+        self.map_data_type(data_type)
+    }
+}
+
+// SQLite accepts every quoting style and any type name
+impl Render for SQLite {}
+
+// types are written as they are, since some of the names mean something else in SQL Server (e.g.
+// TIMESTAMP is a row version), and quoted identifiers keep the quote they were written with (like
+// MySQL's)
+impl Render for MsSQL {
+    /// sqlcmd and SSMS send the statements between `GO` lines to the server as a batch, and these
+    /// have to be the only statement in theirs
+    fn own_batch(&self, statement: &Statement) -> bool {
+        matches!(
+            statement,
+            Statement::CreateView(_)
+                | Statement::AlterView { .. }
+                | Statement::CreateFunction(_)
+                | Statement::CreateProcedure { .. }
+                | Statement::CreateTrigger(_)
+                | Statement::CreateSchema { .. }
+        )
+    }
+}
+
+// quoted identifiers keep the quote they were written with: MySQL reads "double" quotes as a
+// string, and they're compared along with their quote, so requoting a table in a migration would
+// make it a different one from the schema's
+impl Render for MySQL {
+    fn data_type(&self, data_type: &DataType) -> Option<DataType> {
+        self.map_data_type(data_type)
+    }
+}
+
+impl<Dialect: Render> SyntaxTree<Dialect> {
+    /// renders the tree as SQL for its dialect, quoting identifiers the way it does and spelling
+    /// the data types it doesn't have the way it does (e.g. `DATETIME` becomes `TIMESTAMP` in
+    /// PostgreSQL)
+    pub fn to_sql(&self) -> String {
+        let statements = self.formatted(|s| self.statement_sql(s));
+        self.join(statements, |s| self.dialect.own_batch(s))
+    }
+
+    /// renders the tree like [SyntaxTree::to_sql], except for the statements that are the same as
+    /// one of `original` (parsed from `sql`): those are written the way they are in `sql`, along
+    /// with the comments before them, so rewriting a file only changes what changed
+    pub fn to_sql_keeping(&self, original: &Self, sql: &str) -> String {
+        let mut kept = original
+            .tree
+            .iter()
+            .zip(original.source_sql(sql))
+            .filter_map(|(s, source)| Some((s, source?)))
+            .collect::<Vec<_>>();
+        let statements = self
+            .tree
+            .iter()
+            .map(|s| match kept.iter().position(|(o, _)| *o == s) {
+                Some(i) => kept.remove(i).1,
+                None => crate::format_sql(&self.statement_sql(s), &self.format),
+            })
+            .collect();
+        self.join(statements, |s| self.dialect.own_batch(s))
+    }
+
+    /// the statement as SQL for the tree's dialect (see [SyntaxTree::to_sql]), before it's
+    /// formatted
+    fn statement_sql(&self, s: &Statement) -> String {
+        let mut s = s.clone();
+        map_data_types(&mut s, |data_type| self.dialect.data_type(data_type));
+        let sql = format!("{s};");
+        match self.dialect.quote_style() {
+            Some(quote) => requote(&sql, quote),
+            None => sql,
+        }
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// the SQL each statement was parsed from, given the `sql` the whole tree was parsed from,
+    /// along with the comments before it (`None` when where it was parsed from isn't known)
+    pub fn source_sql(&self, sql: &str) -> Vec<Option<String>> {
+        let mut end = 0;
+        (0..self.tree.len())
+            .map(|i| {
+                let span = self.provenance(i)?.span();
+                let before = sql.get(end..span.start)?;
+                let statement = sql.get(span.clone())?;
+                // leave out the end of the statement before, along with a comment on the same line
+                let before = match before.trim_start().strip_prefix(';') {
+                    Some(rest) if end > 0 => {
+                        let rest = rest.trim_start_matches([' ', '\t']);
+                        match rest.strip_prefix("--") {
+                            Some(comment) => comment.split_once('\n').map_or("", |(_, rest)| rest),
+                            None => rest,
+                        }
+                    }
+                    _ => before,
+                };
+                end = span.end;
+                Some(format!("{}{statement};", before.trim_start()))
+            })
+            .collect()
+    }
+}
+
+fn map_data_types(s: &mut Statement, f: impl Fn(&DataType) -> Option<DataType>) {
+    let map = |data_type: &mut DataType| {
+        if let Some(mapped) = f(data_type) {
+            *data_type = mapped;
+        }
+    };
+    match s {
+        Statement::CreateTable(table) => {
+            for column in &mut table.columns {
+                map(&mut column.data_type);
+            }
+        }
+        Statement::AlterTable(alter) => {
+            for op in &mut alter.operations {
+                match op {
+                    AlterTableOperation::AddColumn { column_def, .. } => {
+                        map(&mut column_def.data_type)
+                    }
+                    AlterTableOperation::AlterColumn {
+                        op: AlterColumnOperation::SetDataType { data_type, .. },
+                        ..
+                    } => map(data_type),
+                    AlterTableOperation::ModifyColumn { data_type, .. } => map(data_type),
+                    _ => {}
+                }
+            }
+        }
+        Statement::CreateDomain(domain) => map(&mut domain.data_type),
+        _ => {}
+    }
+}
+
+/// rewrites every quoted identifier in `sql` with `quote` (leaving those that contain it)
+fn requote(sql: &str, quote: char) -> String {
+    // SQLite's tokenizer knows every quoting style: "double", `backtick`, and [bracket]
+    let Ok(tokens) = Tokenizer::new(&SQLiteDialect {}, sql).tokenize_with_location() else {
+        return sql.to_owned();
+    };
+    let offset = |location: Location| {
+        byte_offset(sql, location.line as usize, location.column as usize)
+            .expect("tokens are within the SQL")
+    };
+    let mut out = String::with_capacity(sql.len());
+    let mut last = 0;
+    for token in tokens {
+        let Token::Word(word) = &token.token else {
+            continue;
+        };
+        if word.quote_style.is_none_or(|q| q == quote) || word.value.contains(quote) {
+            continue;
+        }
+        let (start, end) = (offset(token.span.start), offset(token.span.end));
+        out.push_str(&sql[last..start]);
+        out.extend([quote].into_iter().chain(word.value.chars()).chain([quote]));
+        last = end;
+    }
+    out.push_str(&sql[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_sql() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE `users` (id INT, `created at` DATETIME, note TEXT DEFAULT '`it''s`')",
+        )
+        .unwrap();
+        assert_eq!(
+            tree.to_sql(),
+            "CREATE TABLE `users` (\n  id INT,\n  `created at` DATETIME,\n  note TEXT DEFAULT '`it''s`'\n);"
+        );
+        let tree = SyntaxTree::<PostgreSQL>::from(tree.into_statements());
+        assert_eq!(
+            tree.to_sql(),
+            "CREATE TABLE \"users\" (\n  id INT,\n  \"created at\" TIMESTAMP,\n  note TEXT DEFAULT '`it''s`'\n);"
+        );
+    }
+
+    #[test]
+    fn mssql() {
+        let sql = "CREATE TABLE \"order\" (id INT, \"total\" INT);\nGO\nCREATE VIEW totals AS SELECT SUM(total) AS total FROM [order]\ngo\nCREATE INDEX order_idx ON \"order\" (id)";
+        let tree = SyntaxTree::parse(MsSQL, sql).unwrap();
+        assert_eq!(
+            tree.to_sql(),
+            "CREATE TABLE \"order\" (id INT, \"total\" INT);\nGO\n\nCREATE VIEW totals AS\nSELECT\n  SUM(total) AS total\nFROM\n  [order];\nGO\n\nCREATE INDEX order_idx ON \"order\"(id);"
+        );
+        // the batch separators are kept along with the statements after them
+        assert_eq!(
+            tree.to_sql_keeping(&tree, sql),
+            "CREATE TABLE \"order\" (id INT, \"total\" INT);\n\nGO\nCREATE VIEW totals AS SELECT SUM(total) AS total FROM [order];\n\ngo\nCREATE INDEX order_idx ON \"order\" (id);"
+        );
+    }
+
+    #[test]
+    fn quoted_migrations() {
+        fn second_migration<Dialect>(dialect: Dialect, table: &str) -> String
+        where
+            Dialect: Render + crate::Parse + crate::TreeDiffer,
+        {
+            let schema = SyntaxTree::parse(dialect.clone(), table).unwrap();
+            let first = SyntaxTree::empty().diff(&schema).unwrap().unwrap().to_sql();
+            let migrated = SyntaxTree::parse(dialect.clone(), first.as_str()).unwrap();
+            let schema = SyntaxTree::parse(
+                dialect,
+                format!("{table}; CREATE TABLE posts (id INT)").as_str(),
+            )
+            .unwrap();
+            migrated.diff(&schema).unwrap().unwrap().to_sql()
+        }
+
+        assert_eq!(
+            second_migration(MySQL, "CREATE TABLE \"users\" (`id` INT)"),
+            "CREATE TABLE posts (id INT);"
+        );
+        assert_eq!(
+            second_migration(MsSQL, "CREATE TABLE \"users\" ([id] INT)"),
+            "CREATE TABLE posts (id INT);"
+        );
+    }
+
+    #[test]
+    fn to_sql_keeping() {
+        let sql = "-- people\ncreate table users (id int);  -- trailing\n\n/* posts */\ncreate table posts (id int)\n;\ncreate table tags (id int);";
+        let original = SyntaxTree::parse(Generic, sql).unwrap();
+        assert_eq!(
+            original.source_sql(sql),
+            vec![
+                Some("-- people\ncreate table users (id int);".to_owned()),
+                Some("/* posts */\ncreate table posts (id int);".to_owned()),
+                Some("create table tags (id int);".to_owned()),
+            ]
+        );
+        let migration = SyntaxTree::parse(
+            Generic,
+            "ALTER TABLE posts ADD COLUMN title TEXT; CREATE TABLE comments (id INT)",
+        )
+        .unwrap();
+        let schema = original.clone().migrate(&migration).unwrap();
+        assert_eq!(
+            schema.to_sql_keeping(&original, sql),
+            "-- people\ncreate table users (id int);\n\nCREATE TABLE posts (id INT, title TEXT);\n\ncreate table tags (id int);\n\nCREATE TABLE comments (id INT);"
+        );
+    }
+}
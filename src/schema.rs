@@ -0,0 +1,659 @@
+/*!
+A typed model of a schema: its tables, columns, constraints, indexes, types, and so on.
+
+The model only holds what sql-schema understands about each object (names, data types, and
+expressions are kept as SQL text), so code built on it doesn't break when sqlparser's AST changes.
+Build one with [SyntaxTree::schema].
+*/
+
+use std::collections::HashSet;
+
+use crate::{
+    ast::{
+        CheckConstraint, ColumnDef, ColumnOption, CreateIndex, CreateTable, ForeignKeyConstraint,
+        Ident, IndexColumn, ObjectName, ObjectNamePart, Statement, TableConstraint,
+        UserDefinedTypeRepresentation,
+    },
+    SyntaxTree,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Schema {
+    pub tables: Vec<Table>,
+    pub indexes: Vec<Index>,
+    pub types: Vec<Type>,
+    pub domains: Vec<Domain>,
+    pub extensions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+    /// constraints declared on the table along with those declared on a single column (e.g.
+    /// `id INT PRIMARY KEY`), in the order they're written
+    pub constraints: Vec<Constraint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Column {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Constraint {
+    pub name: Option<String>,
+    pub kind: ConstraintKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConstraintKind {
+    PrimaryKey {
+        columns: Vec<String>,
+    },
+    Unique {
+        columns: Vec<String>,
+    },
+    ForeignKey {
+        columns: Vec<String>,
+        foreign_table: String,
+        referred_columns: Vec<String>,
+        /// e.g. `CASCADE` or `SET NULL`
+        on_delete: Option<String>,
+        on_update: Option<String>,
+    },
+    Check {
+        expr: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Index {
+    pub name: Option<String>,
+    pub table: String,
+    /// the indexed columns or expressions
+    pub columns: Vec<String>,
+    pub unique: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Type {
+    pub name: String,
+    pub kind: TypeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TypeKind {
+    Enum {
+        labels: Vec<String>,
+    },
+    /// a composite, range, or other type
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Domain {
+    pub name: String,
+    pub data_type: String,
+}
+
+/// how a table differs between two versions of it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TableDiff {
+    /// columns only in the old version, in its order
+    pub dropped_columns: Vec<String>,
+    /// columns only in the new version, in its order
+    pub added_columns: Vec<String>,
+    /// columns in both versions whose type, nullability, or default changed, in the new version's
+    /// order
+    pub altered_columns: Vec<ColumnDiff>,
+    /// constraints only in the old version (including those of dropped columns), in its order
+    pub dropped_constraints: Vec<Constraint>,
+    /// constraints only in the new version (including those of added columns), in its order
+    pub added_constraints: Vec<Constraint>,
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.dropped_columns.is_empty()
+            && self.added_columns.is_empty()
+            && self.altered_columns.is_empty()
+            && self.dropped_constraints.is_empty()
+            && self.added_constraints.is_empty()
+    }
+}
+
+/// how a column differs between two versions of a table (each field is `None` when that part of
+/// it is the same)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ColumnDiff {
+    pub name: String,
+    /// the new data type
+    pub data_type: Option<String>,
+    /// whether the column is nullable now
+    pub nullable: Option<bool>,
+    /// the new default, `Some(None)` when it was dropped
+    pub default: Option<Option<String>>,
+}
+
+impl ColumnDiff {
+    /// the differences between `a` and `b` (a later version of it), or `None` when there aren't
+    /// any
+    fn new(a: &Column, b: &Column) -> Option<Self> {
+        fn changed<T: PartialEq + Clone>(a: &T, b: &T) -> Option<T> {
+            (a != b).then(|| b.clone())
+        }
+        let diff = Self {
+            name: b.name.clone(),
+            data_type: changed(&a.data_type, &b.data_type),
+            nullable: changed(&a.nullable, &b.nullable),
+            default: changed(&a.default, &b.default),
+        };
+        let same = diff.data_type.is_none() && diff.nullable.is_none() && diff.default.is_none();
+        (!same).then_some(diff)
+    }
+}
+
+/// called for each object when walking a [Schema] (e.g. for a custom lint); each method does
+/// nothing unless it's implemented
+pub trait Visitor {
+    fn visit_table(&mut self, _table: &Table) {}
+
+    fn visit_column(&mut self, _table: &Table, _column: &Column) {}
+
+    fn visit_constraint(&mut self, _table: &Table, _constraint: &Constraint) {}
+
+    fn visit_index(&mut self, _index: &Index) {}
+
+    fn visit_type(&mut self, _ty: &Type) {}
+
+    fn visit_domain(&mut self, _domain: &Domain) {}
+
+    fn visit_extension(&mut self, _name: &str) {}
+}
+
+impl Schema {
+    pub(crate) fn from_statements(statements: &[Statement]) -> Self {
+        let mut schema = Self::default();
+        for s in statements {
+            match s {
+                Statement::CreateTable(table) => schema.tables.push(table.into()),
+                Statement::CreateIndex(index) => schema.indexes.push(index.into()),
+                Statement::CreateType {
+                    name,
+                    representation,
+                } => schema.types.push(Type {
+                    name: object_name(name),
+                    kind: match representation {
+                        Some(UserDefinedTypeRepresentation::Enum { labels }) => TypeKind::Enum {
+                            labels: labels.iter().map(|label| label.value.clone()).collect(),
+                        },
+                        _ => TypeKind::Other,
+                    },
+                }),
+                Statement::CreateDomain(domain) => schema.domains.push(Domain {
+                    name: object_name(&domain.name),
+                    data_type: domain.data_type.to_string(),
+                }),
+                Statement::CreateExtension(extension) => {
+                    schema.extensions.push(extension.name.value.clone())
+                }
+                _ => {}
+            }
+        }
+        schema
+    }
+
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|t| t.name == name)
+    }
+
+    /// visits each table (followed by its columns and constraints), then each index, type,
+    /// domain, and extension
+    pub fn walk(&self, visitor: &mut (impl Visitor + ?Sized)) {
+        for table in &self.tables {
+            visitor.visit_table(table);
+            for column in &table.columns {
+                visitor.visit_column(table, column);
+            }
+            for constraint in &table.constraints {
+                visitor.visit_constraint(table, constraint);
+            }
+        }
+        for index in &self.indexes {
+            visitor.visit_index(index);
+        }
+        for ty in &self.types {
+            visitor.visit_type(ty);
+        }
+        for domain in &self.domains {
+            visitor.visit_domain(domain);
+        }
+        for extension in &self.extensions {
+            visitor.visit_extension(extension);
+        }
+    }
+}
+
+impl Table {
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    /// the columns of the primary key (none when the table doesn't have one)
+    pub fn primary_key(&self) -> &[String] {
+        self.constraints
+            .iter()
+            .find_map(|c| match &c.kind {
+                ConstraintKind::PrimaryKey { columns } => Some(columns.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// compares the columns and constraints of this table to those of `other` (a later version
+    /// of it)
+    pub fn diff(&self, other: &Table) -> TableDiff {
+        let names = |table: &Table| -> HashSet<String> {
+            table.columns.iter().map(|c| c.name.clone()).collect()
+        };
+        let (a, b) = (names(self), names(other));
+        let only = |table: &Table, other: &HashSet<String>| {
+            table
+                .columns
+                .iter()
+                .filter(|c| !other.contains(&c.name))
+                .map(|c| c.name.clone())
+                .collect()
+        };
+        let altered_columns = other
+            .columns
+            .iter()
+            .filter_map(|b| ColumnDiff::new(self.column(&b.name)?, b))
+            .collect();
+        let only_constraints = |table: &Table, other: &Table| {
+            table
+                .constraints
+                .iter()
+                .filter(|c| !other.constraints.contains(c))
+                .cloned()
+                .collect()
+        };
+        TableDiff {
+            dropped_columns: only(self, &b),
+            added_columns: only(other, &a),
+            altered_columns,
+            dropped_constraints: only_constraints(self, other),
+            added_constraints: only_constraints(other, self),
+        }
+    }
+}
+
+impl Constraint {
+    /// the columns of the constraint, or `None` for a check (whose columns are somewhere in its
+    /// expression)
+    pub fn columns(&self) -> Option<&[String]> {
+        match &self.kind {
+            ConstraintKind::PrimaryKey { columns }
+            | ConstraintKind::Unique { columns }
+            | ConstraintKind::ForeignKey { columns, .. } => Some(columns),
+            ConstraintKind::Check { .. } => None,
+        }
+    }
+}
+
+impl From<&CreateTable> for Table {
+    fn from(table: &CreateTable) -> Self {
+        let mut constraints = Vec::new();
+        let mut columns = table
+            .columns
+            .iter()
+            .map(|column| {
+                constraints.extend(column_constraints(column));
+                Column::from(column)
+            })
+            .collect::<Vec<_>>();
+        constraints.extend(table.constraints.iter().filter_map(table_constraint));
+        // the columns of a primary key declared on the table aren't nullable either
+        for constraint in &constraints {
+            if let ConstraintKind::PrimaryKey { columns: key } = &constraint.kind {
+                for column in columns.iter_mut().filter(|c| key.contains(&c.name)) {
+                    column.nullable = false;
+                }
+            }
+        }
+        Self {
+            name: object_name(&table.name),
+            columns,
+            constraints,
+        }
+    }
+}
+
+impl From<&ColumnDef> for Column {
+    fn from(column: &ColumnDef) -> Self {
+        let mut nullable = true;
+        let mut default = None;
+        for def in &column.options {
+            match &def.option {
+                ColumnOption::NotNull | ColumnOption::PrimaryKey(_) => nullable = false,
+                ColumnOption::Default(expr) => default = Some(expr.to_string()),
+                _ => {}
+            }
+        }
+        Self {
+            name: column.name.value.clone(),
+            data_type: column.data_type.to_string(),
+            nullable,
+            default,
+        }
+    }
+}
+
+impl From<&CreateIndex> for Index {
+    fn from(index: &CreateIndex) -> Self {
+        Self {
+            name: index.name.as_ref().map(object_name),
+            table: object_name(&index.table_name),
+            columns: index
+                .columns
+                .iter()
+                .map(|c| c.column.expr.to_string())
+                .collect(),
+            unique: index.unique,
+        }
+    }
+}
+
+/// the constraints declared on a column, as if they were declared on the table
+pub(crate) fn column_constraints(column: &ColumnDef) -> impl Iterator<Item = Constraint> + '_ {
+    let columns = || vec![column.name.value.clone()];
+    column.options.iter().filter_map(move |def| {
+        let kind = match &def.option {
+            ColumnOption::PrimaryKey(_) => ConstraintKind::PrimaryKey { columns: columns() },
+            ColumnOption::Unique(_) => ConstraintKind::Unique { columns: columns() },
+            ColumnOption::ForeignKey(fk) => foreign_key(fk, columns()),
+            ColumnOption::Check(CheckConstraint { expr, .. }) => ConstraintKind::Check {
+                expr: expr.to_string(),
+            },
+            _ => return None,
+        };
+        Some(Constraint {
+            name: def.name.as_ref().map(|name| name.value.clone()),
+            kind,
+        })
+    })
+}
+
+pub(crate) fn table_constraint(constraint: &TableConstraint) -> Option<Constraint> {
+    let index_columns =
+        |columns: &[IndexColumn]| columns.iter().map(|c| c.column.expr.to_string()).collect();
+    let (name, kind) = match constraint {
+        TableConstraint::PrimaryKey(pk) => (
+            &pk.name,
+            ConstraintKind::PrimaryKey {
+                columns: index_columns(&pk.columns),
+            },
+        ),
+        TableConstraint::Unique(unique) => (
+            &unique.name,
+            ConstraintKind::Unique {
+                columns: index_columns(&unique.columns),
+            },
+        ),
+        TableConstraint::ForeignKey(fk) => (&fk.name, foreign_key(fk, idents(&fk.columns))),
+        TableConstraint::Check(check) => (
+            &check.name,
+            ConstraintKind::Check {
+                expr: check.expr.to_string(),
+            },
+        ),
+        _ => return None,
+    };
+    Some(Constraint {
+        name: name.as_ref().map(|name| name.value.clone()),
+        kind,
+    })
+}
+
+/// the foreign key `fk` from `columns` (which are left out when it's declared on a column)
+fn foreign_key(fk: &ForeignKeyConstraint, columns: Vec<String>) -> ConstraintKind {
+    ConstraintKind::ForeignKey {
+        columns,
+        foreign_table: object_name(&fk.foreign_table),
+        referred_columns: idents(&fk.referred_columns),
+        on_delete: fk.on_delete.map(|action| action.to_string()),
+        on_update: fk.on_update.map(|action| action.to_string()),
+    }
+}
+
+fn idents(idents: &[Ident]) -> Vec<String> {
+    idents.iter().map(|i| i.value.clone()).collect()
+}
+
+/// the name without quotes (e.g. `public.users` for `"public"."users"`)
+fn object_name(name: &ObjectName) -> String {
+    name.0
+        .iter()
+        .map(|part| match part {
+            ObjectNamePart::Identifier(ident) => ident.value.clone(),
+            part => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// the typed model of the tables, indexes, types, etc. the tree creates
+    pub fn schema(&self) -> Schema {
+        Schema::from_statements(&self.tree)
+    }
+
+    /// walks the [Schema] the tree creates
+    pub fn walk(&self, visitor: &mut (impl Visitor + ?Sized)) {
+        self.schema().walk(visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Generic;
+
+    #[test]
+    fn build() {
+        let schema = SyntaxTree::parse(
+            Generic,
+            "CREATE TYPE status AS ENUM ('open', 'closed');
+            CREATE TABLE \"users\" (id INT PRIMARY KEY, email TEXT NOT NULL UNIQUE, status status DEFAULT 'open');
+            CREATE TABLE posts (
+                id INT,
+                author_id INT REFERENCES users (id) ON DELETE SET NULL,
+                title VARCHAR(255),
+                CONSTRAINT posts_pk PRIMARY KEY (id),
+                CHECK (LENGTH(title) > 0)
+            );
+            CREATE UNIQUE INDEX posts_title ON posts (title);",
+        )
+        .unwrap()
+        .schema();
+
+        let users = schema.table("users").unwrap();
+        assert_eq!(users.primary_key(), ["id"]);
+        let email = users.column("email").unwrap();
+        assert_eq!((email.data_type.as_str(), email.nullable), ("TEXT", false));
+        assert_eq!(
+            users.column("status").unwrap().default.as_deref(),
+            Some("'open'")
+        );
+
+        let posts = schema.table("posts").unwrap();
+        assert_eq!(
+            posts.constraints,
+            [
+                Constraint {
+                    name: None,
+                    kind: ConstraintKind::ForeignKey {
+                        columns: vec!["author_id".to_owned()],
+                        foreign_table: "users".to_owned(),
+                        referred_columns: vec!["id".to_owned()],
+                        on_delete: Some("SET NULL".to_owned()),
+                        on_update: None,
+                    },
+                },
+                Constraint {
+                    name: Some("posts_pk".to_owned()),
+                    kind: ConstraintKind::PrimaryKey {
+                        columns: vec!["id".to_owned()],
+                    },
+                },
+                Constraint {
+                    name: None,
+                    kind: ConstraintKind::Check {
+                        expr: "LENGTH(title) > 0".to_owned(),
+                    },
+                },
+            ]
+        );
+        assert_eq!(
+            schema.indexes,
+            [Index {
+                name: Some("posts_title".to_owned()),
+                table: "posts".to_owned(),
+                columns: vec!["title".to_owned()],
+                unique: true,
+            }]
+        );
+        assert_eq!(
+            schema.types[0].kind,
+            TypeKind::Enum {
+                labels: vec!["open".to_owned(), "closed".to_owned()]
+            }
+        );
+    }
+
+    #[test]
+    fn table_diff() {
+        let schema = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE a (id INT, name TEXT); CREATE TABLE b (\"id\" INT, email TEXT, age INT);",
+        )
+        .unwrap()
+        .schema();
+        let diff = schema.tables[0].diff(&schema.tables[1]);
+        assert_eq!(diff.dropped_columns, ["name"]);
+        assert_eq!(diff.added_columns, ["email", "age"]);
+        assert!(diff.altered_columns.is_empty());
+
+        let schema = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE a (id INT, email TEXT, age INT DEFAULT 0, CONSTRAINT a_email_key UNIQUE (email));
+            CREATE TABLE b (id BIGINT NOT NULL PRIMARY KEY, email TEXT, age INT, CHECK (age > 0));",
+        )
+        .unwrap()
+        .schema();
+        let diff = schema.tables[0].diff(&schema.tables[1]);
+        assert!(diff.dropped_columns.is_empty() && diff.added_columns.is_empty());
+        assert_eq!(
+            diff.altered_columns,
+            [
+                ColumnDiff {
+                    name: "id".to_owned(),
+                    data_type: Some("BIGINT".to_owned()),
+                    nullable: Some(false),
+                    default: None,
+                },
+                ColumnDiff {
+                    name: "age".to_owned(),
+                    data_type: None,
+                    nullable: None,
+                    default: Some(None),
+                },
+            ]
+        );
+        assert_eq!(
+            diff.dropped_constraints,
+            [Constraint {
+                name: Some("a_email_key".to_owned()),
+                kind: ConstraintKind::Unique {
+                    columns: vec!["email".to_owned()],
+                },
+            }]
+        );
+        assert_eq!(
+            diff.added_constraints,
+            [
+                Constraint {
+                    name: None,
+                    kind: ConstraintKind::PrimaryKey {
+                        columns: vec!["id".to_owned()],
+                    },
+                },
+                Constraint {
+                    name: None,
+                    kind: ConstraintKind::Check {
+                        expr: "age > 0".to_owned(),
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn walk() {
+        /// foreign keys without an index on their columns
+        #[derive(Default)]
+        struct UnindexedForeignKeys {
+            indexed: Vec<(String, Vec<String>)>,
+            foreign_keys: Vec<(String, Vec<String>)>,
+        }
+
+        impl Visitor for UnindexedForeignKeys {
+            fn visit_constraint(&mut self, table: &Table, constraint: &Constraint) {
+                if let ConstraintKind::ForeignKey { columns, .. } = &constraint.kind {
+                    self.foreign_keys
+                        .push((table.name.clone(), columns.clone()));
+                }
+            }
+
+            fn visit_index(&mut self, index: &Index) {
+                self.indexed
+                    .push((index.table.clone(), index.columns.clone()));
+            }
+        }
+
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE users (id INT PRIMARY KEY);
+            CREATE TABLE posts (id INT, author_id INT REFERENCES users (id));
+            CREATE TABLE comments (post_id INT REFERENCES posts (id));
+            CREATE INDEX comments_post_id ON comments (post_id);",
+        )
+        .unwrap();
+        let mut visitor = UnindexedForeignKeys::default();
+        tree.walk(&mut visitor);
+        let unindexed = visitor
+            .foreign_keys
+            .iter()
+            .filter(|fk| !visitor.indexed.contains(fk))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unindexed,
+            [&("posts".to_owned(), vec!["author_id".to_owned()])]
+        );
+    }
+}
@@ -0,0 +1,773 @@
+/*!
+Read which migrations have been applied from the table a migration runner records them in, and
+apply or roll back migrations through a [Runner] that keeps it up to date.
+
+Tables created by other tools are read and written as-is, e.g. `schema_migrations` (golang-migrate,
+dbmate, Rails), `_sqlx_migrations` (sqlx), or `__diesel_schema_migrations` (Diesel), so switching to
+sql-schema doesn't require re-baselining. golang-migrate only records the most recent migration
+(along with a `dirty` flag), so every migration before it counts as applied too.
+*/
+
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use sha2::{Digest, Sha384};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum TrackingError {
+    #[cfg(feature = "postgres")]
+    #[error("Oops, we couldn't query the database: {0}")]
+    Postgres(#[from] ::postgres::Error),
+    #[cfg(feature = "sqlite")]
+    #[error("Oops, we couldn't query the database: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Oops, we don't know which column of {table} holds the version (columns: {})", columns.join(", "))]
+    UnknownLayout { table: String, columns: Vec<String> },
+}
+
+pub type Result<T, E = TrackingError> = std::result::Result<T, E>;
+
+/// default name of the tracking table
+pub const DEFAULT_TABLE: &str = "schema_migrations";
+
+/// the table applied migrations are recorded in, optionally qualified with a schema (e.g.
+/// `public.schema_migrations`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackingTable {
+    pub schema: Option<String>,
+    pub name: String,
+}
+
+impl Default for TrackingTable {
+    fn default() -> Self {
+        Self {
+            schema: None,
+            name: DEFAULT_TABLE.to_owned(),
+        }
+    }
+}
+
+impl FromStr for TrackingTable {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.split_once('.') {
+            Some((schema, name)) => Self {
+                schema: Some(schema.to_owned()),
+                name: name.to_owned(),
+            },
+            None => Self {
+                schema: None,
+                name: s.to_owned(),
+            },
+        })
+    }
+}
+
+impl fmt::Display for TrackingTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.schema {
+            Some(schema) => write!(f, "{schema}.{}", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl TrackingTable {
+    /// the table name quoted for use in a query
+    #[cfg(any(feature = "postgres", feature = "sqlite"))]
+    fn quoted(&self) -> String {
+        match &self.schema {
+            Some(schema) => format!("{}.{}", quote(schema), quote(&self.name)),
+            None => quote(&self.name),
+        }
+    }
+}
+
+/// which columns of a tracking table hold what, detected from the column names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    /// the column holding each migration's version
+    pub version: String,
+    /// the column holding a [checksum] of each migration file, if any
+    pub checksum: Option<String>,
+    /// the column flagging a migration that failed partway, when the table only records the most
+    /// recent migration (golang-migrate's `dirty`)
+    pub dirty: Option<String>,
+    /// the column holding each migration's description (sqlx's `description`), if any
+    pub description: Option<String>,
+    /// the column flagging whether each migration succeeded (sqlx's `success`), if any
+    pub success: Option<String>,
+    /// the column holding how long each migration took in nanoseconds (sqlx's
+    /// `execution_time`), if any
+    pub execution_time: Option<String>,
+}
+
+/// the layout of tables sql-schema creates: each migration's version and [checksum]
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            version: "version".to_owned(),
+            checksum: Some("checksum".to_owned()),
+            dirty: None,
+            description: None,
+            success: None,
+            execution_time: None,
+        }
+    }
+}
+
+impl Layout {
+    /// column names that hold the version, in order of preference
+    const VERSION_COLUMNS: &[&str] = &["version", "version_id", "id", "name", "migration"];
+
+    /// detects the layout from the table's column names, or `None` when no column looks like it
+    /// holds the version
+    pub fn detect(columns: &[impl AsRef<str>]) -> Option<Self> {
+        let find = |names: &[&str]| {
+            names.iter().find_map(|name| {
+                columns
+                    .iter()
+                    .map(AsRef::as_ref)
+                    .find(|column| column.eq_ignore_ascii_case(name))
+                    .map(ToOwned::to_owned)
+            })
+        };
+        Some(Self {
+            version: find(Self::VERSION_COLUMNS)?,
+            checksum: find(&["checksum"]),
+            dirty: find(&["dirty"]),
+            description: find(&["description"]),
+            success: find(&["success"]),
+            execution_time: find(&["execution_time"]),
+        })
+    }
+
+    /// golang-migrate's layout, which only records the most recent migration
+    pub fn golang_migrate() -> Self {
+        Self {
+            version: "version".to_owned(),
+            checksum: None,
+            dirty: Some("dirty".to_owned()),
+            description: None,
+            success: None,
+            execution_time: None,
+        }
+    }
+
+    #[cfg(any(feature = "postgres", feature = "sqlite"))]
+    fn query(&self, table: &TrackingTable) -> String {
+        let version = quote(&self.version);
+        let checksum = self.checksum.as_deref().map_or("NULL".to_owned(), quote);
+        let dirty = self.dirty.as_deref().map_or("NULL".to_owned(), quote);
+        format!(
+            "SELECT CAST({version} AS TEXT), {checksum}, {dirty} FROM {} ORDER BY {version}",
+            table.quoted()
+        )
+    }
+}
+
+/// a migration recorded in the tracking table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedMigration {
+    pub version: String,
+    /// the [checksum] of the file when it was applied (when the table records one)
+    pub checksum: Option<Vec<u8>>,
+    /// whether the migration failed partway, when the table only records the most recent
+    /// migration (`None` for tables that record each one)
+    pub dirty: Option<bool>,
+}
+
+impl AppliedMigration {
+    /// whether this is the migration with the version `version` (e.g. the leading digits of a
+    /// migration's file name), ignoring leading zeros and separators like the dashes in Diesel's
+    /// `2025-01-31-090000` versions
+    pub fn matches(&self, version: &str) -> bool {
+        normalize(&self.version) == normalize(version)
+    }
+
+    /// whether the migration with the version `version` has been applied according to this
+    /// record: it [matches], or the table only records the most recent migration and `version`
+    /// comes before it
+    ///
+    /// [matches]: AppliedMigration::matches
+    pub fn covers(&self, version: &str) -> bool {
+        if self.matches(version) {
+            return true;
+        }
+        let (applied, version) = (normalize(&self.version), normalize(version));
+        let numeric = |v: &str| v.chars().all(|c| c.is_ascii_digit());
+        self.dirty.is_some()
+            && numeric(&applied)
+            && numeric(&version)
+            && (version.len(), &version) < (applied.len(), &applied)
+    }
+
+    /// whether `sql` differs from the migration file that was applied (always false when the
+    /// table doesn't record checksums)
+    pub fn is_modified(&self, sql: impl AsRef<[u8]>) -> bool {
+        self.checksum
+            .as_ref()
+            .is_some_and(|checksum| *checksum != self::checksum(sql))
+    }
+}
+
+/// the SHA-384 digest of a migration file's contents, the same checksum sqlx records
+pub fn checksum(sql: impl AsRef<[u8]>) -> Vec<u8> {
+    Sha384::digest(sql).to_vec()
+}
+
+fn normalize(version: &str) -> String {
+    let digits = version
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect::<String>();
+    if digits.is_empty() {
+        return version.to_owned();
+    }
+    let trimmed = digits.trim_start_matches('0');
+    if trimmed.is_empty() {
+        "0".to_owned()
+    } else {
+        trimmed.to_owned()
+    }
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn quote(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn layout(table: &TrackingTable, columns: Vec<String>) -> Result<Layout> {
+    Layout::detect(&columns).ok_or_else(|| TrackingError::UnknownLayout {
+        table: table.to_string(),
+        columns,
+    })
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// a connection to a database that migrations are run over and recorded in
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub trait Connection {
+    /// the type checksums are stored as in tables sql-schema creates
+    const BLOB: &'static str;
+
+    /// `bytes` as a literal
+    fn blob(bytes: &[u8]) -> String;
+
+    /// the columns of `table` in order (none when it doesn't exist)
+    fn columns(&mut self, table: &TrackingTable) -> Result<Vec<String>>;
+
+    /// the migrations recorded in `table`, which has the columns `layout` describes
+    fn query(&mut self, table: &TrackingTable, layout: &Layout) -> Result<Vec<AppliedMigration>>;
+
+    /// runs `sql`, which may hold any number of statements
+    fn execute(&mut self, sql: &str) -> Result<()>;
+}
+
+#[cfg(feature = "postgres")]
+impl Connection for ::postgres::Client {
+    const BLOB: &'static str = "BYTEA";
+
+    fn blob(bytes: &[u8]) -> String {
+        format!("decode('{}', 'hex')", hex(bytes))
+    }
+
+    fn columns(&mut self, table: &TrackingTable) -> Result<Vec<String>> {
+        Ok(self
+            .query(
+                "SELECT column_name::text
+                FROM information_schema.columns
+                WHERE table_schema = coalesce($1, current_schema()) AND table_name = $2
+                ORDER BY ordinal_position",
+                &[&table.schema, &table.name],
+            )?
+            .into_iter()
+            .map(|row| row.get::<_, String>(0))
+            .collect())
+    }
+
+    fn query(&mut self, table: &TrackingTable, layout: &Layout) -> Result<Vec<AppliedMigration>> {
+        Ok(
+            ::postgres::Client::query(self, layout.query(table).as_str(), &[])?
+                .into_iter()
+                .map(|row| AppliedMigration {
+                    version: row.get(0),
+                    // checksums recorded as anything but bytes aren't ours to verify
+                    checksum: row.try_get(1).ok().flatten(),
+                    // NULL (as text) when the table has no `dirty` column
+                    dirty: row.try_get(2).ok().flatten(),
+                })
+                .collect(),
+        )
+    }
+
+    fn execute(&mut self, sql: &str) -> Result<()> {
+        Ok(self.batch_execute(sql)?)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Connection for rusqlite::Connection {
+    const BLOB: &'static str = "BLOB";
+
+    fn blob(bytes: &[u8]) -> String {
+        format!("X'{}'", hex(bytes))
+    }
+
+    fn columns(&mut self, table: &TrackingTable) -> Result<Vec<String>> {
+        Ok(self
+            .prepare("SELECT name FROM pragma_table_info(?1, ?2)")?
+            .query_map(
+                (&table.name, table.schema.as_deref().unwrap_or("main")),
+                |row| row.get::<_, String>(0),
+            )?
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    fn query(&mut self, table: &TrackingTable, layout: &Layout) -> Result<Vec<AppliedMigration>> {
+        let mut stmt = self.prepare(&layout.query(table))?;
+        let applied = stmt
+            .query_map([], |row| {
+                Ok(AppliedMigration {
+                    version: row.get(0)?,
+                    // checksums recorded as anything but bytes aren't ours to verify
+                    checksum: row.get(1).ok().flatten(),
+                    dirty: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(applied)
+    }
+
+    fn execute(&mut self, sql: &str) -> Result<()> {
+        Ok(self.execute_batch(sql)?)
+    }
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// the migrations recorded in `table` (none when the table doesn't exist yet)
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub fn applied(conn: &mut impl Connection, table: &TrackingTable) -> Result<Vec<AppliedMigration>> {
+    let columns = conn.columns(table)?;
+    if columns.is_empty() {
+        return Ok(Vec::new());
+    }
+    let layout = layout(table, columns)?;
+    conn.query(table, &layout)
+}
+
+/// a migration to record as applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record<'a> {
+    /// the leading version component of the migration name
+    pub version: &'a str,
+    /// recorded in tables with a description column (e.g. the rest of the migration name)
+    pub description: &'a str,
+    /// the [checksum] of the migration file
+    pub checksum: Vec<u8>,
+}
+
+/// runs migrations over a [Connection], recording them in the tracking table
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[derive(Debug)]
+pub struct Runner<'a, C> {
+    conn: &'a mut C,
+    table: TrackingTable,
+    layout: Layout,
+    /// whether `table` doesn't exist yet (it's created along with the first migration)
+    create: bool,
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+impl<'a, C: Connection> Runner<'a, C> {
+    /// detects the layout of `table`, or creates it with `layout` once a migration is applied
+    /// when it doesn't exist yet
+    pub fn new(conn: &'a mut C, table: TrackingTable, layout: Layout) -> Result<Self> {
+        let columns = conn.columns(&table)?;
+        let create = columns.is_empty();
+        let layout = if create {
+            layout
+        } else {
+            self::layout(&table, columns)?
+        };
+        Ok(Self {
+            conn,
+            table,
+            layout,
+            create,
+        })
+    }
+
+    /// the columns of the tracking table
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// the migrations recorded so far
+    pub fn applied(&mut self) -> Result<Vec<AppliedMigration>> {
+        if self.create {
+            return Ok(Vec::new());
+        }
+        self.conn.query(&self.table, &self.layout)
+    }
+
+    /// runs `sql` and records `migration` as applied, together in a transaction unless
+    /// `transaction` is false (e.g. for a migration running `CREATE INDEX CONCURRENTLY`)
+    ///
+    /// tables that only record the most recent migration are marked dirty while it runs, the way
+    /// golang-migrate does
+    pub fn apply(&mut self, migration: &Record, sql: &str, transaction: bool) -> Result<()> {
+        self.run(transaction, |runner| {
+            runner.create_table()?;
+            runner.set_version(Some(migration.version), true)?;
+            let start = std::time::Instant::now();
+            runner.conn.execute(sql)?;
+            runner.record(migration, start.elapsed())
+        })
+    }
+
+    /// runs the down migration `sql` and removes the record of `version` (as it's recorded),
+    /// together in a transaction unless `transaction` is false
+    ///
+    /// tables that only record the most recent migration are left recording `previous` (the
+    /// migration before it, if any)
+    pub fn rollback(
+        &mut self,
+        version: &str,
+        previous: Option<&str>,
+        sql: &str,
+        transaction: bool,
+    ) -> Result<()> {
+        self.run(transaction, |runner| {
+            runner.set_version(previous, true)?;
+            runner.conn.execute(sql)?;
+            if runner.layout.dirty.is_some() {
+                return runner.set_version(previous, false);
+            }
+            let sql = format!(
+                "DELETE FROM {} WHERE {} = {}",
+                runner.table.quoted(),
+                quote(&runner.layout.version),
+                literal(version)
+            );
+            runner.conn.execute(&sql)
+        })
+    }
+
+    fn run(&mut self, transaction: bool, f: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        if !transaction {
+            return f(self);
+        }
+        let create = self.create;
+        self.conn.execute("BEGIN")?;
+        match f(self) {
+            Ok(()) => self.conn.execute("COMMIT"),
+            Err(err) => {
+                // the error running the migration matters more than one rolling it back
+                let _ = self.conn.execute("ROLLBACK");
+                self.create = create;
+                Err(err)
+            }
+        }
+    }
+
+    fn create_table(&mut self) -> Result<()> {
+        if !self.create {
+            return Ok(());
+        }
+        let layout = &self.layout;
+        // golang-migrate's versions are numbers
+        let version = if layout.dirty.is_some() {
+            "BIGINT"
+        } else {
+            "TEXT"
+        };
+        let columns = [
+            Some(format!(
+                "{} {version} NOT NULL PRIMARY KEY",
+                quote(&layout.version)
+            )),
+            layout
+                .checksum
+                .as_deref()
+                .map(|c| format!("{} {}", quote(c), C::BLOB)),
+            layout
+                .dirty
+                .as_deref()
+                .map(|c| format!("{} BOOLEAN NOT NULL", quote(c))),
+            layout
+                .description
+                .as_deref()
+                .map(|c| format!("{} TEXT", quote(c))),
+            layout
+                .success
+                .as_deref()
+                .map(|c| format!("{} BOOLEAN", quote(c))),
+            layout
+                .execution_time
+                .as_deref()
+                .map(|c| format!("{} BIGINT", quote(c))),
+        ];
+        let columns = columns.into_iter().flatten().collect::<Vec<_>>();
+        let sql = format!(
+            "CREATE TABLE {} ({})",
+            self.table.quoted(),
+            columns.join(", ")
+        );
+        self.conn.execute(&sql)?;
+        self.create = false;
+        Ok(())
+    }
+
+    /// replaces the row of a table that only records the most recent migration (leaving it empty
+    /// when `version` is `None`)
+    fn set_version(&mut self, version: Option<&str>, dirty: bool) -> Result<()> {
+        let Some(column) = &self.layout.dirty else {
+            return Ok(());
+        };
+        let table = self.table.quoted();
+        let mut sql = format!("DELETE FROM {table};");
+        if let Some(version) = version {
+            sql += &format!(
+                " INSERT INTO {table} ({}, {}) VALUES ({}, {dirty});",
+                quote(&self.layout.version),
+                quote(column),
+                literal(version)
+            );
+        }
+        self.conn.execute(&sql)
+    }
+
+    fn record(&mut self, migration: &Record, elapsed: std::time::Duration) -> Result<()> {
+        if self.layout.dirty.is_some() {
+            return self.set_version(Some(migration.version), false);
+        }
+        let layout = &self.layout;
+        let values = [
+            Some((&layout.version, literal(migration.version))),
+            layout
+                .checksum
+                .as_ref()
+                .map(|c| (c, C::blob(&migration.checksum))),
+            layout
+                .description
+                .as_ref()
+                .map(|c| (c, literal(migration.description))),
+            layout.success.as_ref().map(|c| (c, "TRUE".to_owned())),
+            layout
+                .execution_time
+                .as_ref()
+                .map(|c| (c, elapsed.as_nanos().to_string())),
+        ];
+        let (columns, values): (Vec<_>, Vec<_>) = values
+            .into_iter()
+            .flatten()
+            .map(|(column, value)| (quote(column), value))
+            .unzip();
+        let table = self.table.quoted();
+        // repeatable migrations are recorded again each time they're applied
+        let sql = format!(
+            "DELETE FROM {table} WHERE {} = {}; INSERT INTO {table} ({}) VALUES ({});",
+            columns[0],
+            values[0],
+            columns.join(", "),
+            values.join(", ")
+        );
+        self.conn.execute(&sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_layout() {
+        let sqlx = [
+            "version",
+            "description",
+            "installed_on",
+            "success",
+            "checksum",
+        ];
+        let layout = Layout::detect(&sqlx).unwrap();
+        assert_eq!(layout.version, "version");
+        assert_eq!(layout.checksum.as_deref(), Some("checksum"));
+        assert_eq!(layout.description.as_deref(), Some("description"));
+        assert_eq!(layout.success.as_deref(), Some("success"));
+        let goose = ["id", "version_id", "is_applied", "tstamp"];
+        assert_eq!(Layout::detect(&goose).unwrap().version, "version_id");
+        let golang_migrate = Layout::detect(&["version", "dirty"]).unwrap();
+        assert_eq!(golang_migrate, Layout::golang_migrate());
+        assert_eq!(Layout::detect(&["applied_at"]), None);
+    }
+
+    #[test]
+    fn matches_version() {
+        let applied = AppliedMigration {
+            version: "1".to_owned(),
+            checksum: None,
+            dirty: None,
+        };
+        assert!(applied.matches("0001"));
+        assert!(!applied.matches("0010"));
+        let applied = AppliedMigration {
+            version: "20250131090000".to_owned(),
+            checksum: None,
+            dirty: None,
+        };
+        assert!(applied.matches("2025-01-31-090000"));
+        assert!(!applied.covers("20250101000000"));
+    }
+
+    #[test]
+    fn covers_earlier_versions() {
+        let applied = AppliedMigration {
+            version: "12".to_owned(),
+            checksum: None,
+            dirty: Some(false),
+        };
+        assert!(applied.covers("000012"));
+        assert!(applied.covers("000002"));
+        assert!(!applied.covers("000013"));
+        assert!(!applied.covers("000100"));
+    }
+
+    #[test]
+    fn is_modified() {
+        let mut applied = AppliedMigration {
+            version: "1".to_owned(),
+            checksum: None,
+            dirty: None,
+        };
+        assert!(!applied.is_modified("CREATE TABLE foo (id INT);"));
+        applied.checksum = Some(checksum("CREATE TABLE foo (id INT);"));
+        assert!(!applied.is_modified("CREATE TABLE foo (id INT);"));
+        assert!(applied.is_modified("CREATE TABLE foo (id BIGINT);"));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite() {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        let table = "_sqlx_migrations".parse().unwrap();
+        assert_eq!(applied(&mut conn, &table).unwrap(), []);
+
+        conn.execute_batch(
+            "CREATE TABLE _sqlx_migrations (version BIGINT PRIMARY KEY, description TEXT, checksum BLOB);
+            INSERT INTO _sqlx_migrations VALUES (10, 'b', X'0102'), (2, 'a', NULL);",
+        )
+        .unwrap();
+        let sqlx = applied(&mut conn, &table)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.version, m.checksum))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sqlx,
+            [("2".to_owned(), None), ("10".to_owned(), Some(vec![1, 2]))]
+        );
+
+        let table = TrackingTable::default();
+        conn.execute_batch(
+            "CREATE TABLE schema_migrations (version BIGINT PRIMARY KEY, dirty BOOLEAN NOT NULL);
+            INSERT INTO schema_migrations VALUES (3, TRUE);",
+        )
+        .unwrap();
+        let golang_migrate = applied(&mut conn, &table).unwrap();
+        assert_eq!(golang_migrate[0].dirty, Some(true));
+        assert!(golang_migrate[0].covers("000002"));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn runner() {
+        let record = |version| Record {
+            version,
+            description: "create_users",
+            checksum: checksum(version),
+        };
+        let versions = |runner: &mut Runner<_>| {
+            let applied = runner.applied().unwrap();
+            applied.into_iter().map(|m| m.version).collect::<Vec<_>>()
+        };
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        let mut runner =
+            Runner::new(&mut conn, TrackingTable::default(), Layout::default()).unwrap();
+        assert_eq!(versions(&mut runner), Vec::<String>::new());
+        let err = runner.apply(&record("0001"), "CREATE TABLE (", true);
+        assert!(err.is_err());
+        runner
+            .apply(&record("0001"), "CREATE TABLE users (id INT);", true)
+            .unwrap();
+        runner
+            .apply(&record("0002"), "CREATE TABLE posts (id INT);", false)
+            .unwrap();
+        assert_eq!(versions(&mut runner), ["0001", "0002"]);
+        let applied = runner.applied().unwrap();
+        assert!(!applied[1].is_modified("0002"));
+        runner
+            .rollback(&applied[1].version, Some("0001"), "DROP TABLE posts;", true)
+            .unwrap();
+        assert_eq!(versions(&mut runner), ["0001"]);
+        // a failed down migration leaves the record
+        let err = runner.rollback(&applied[0].version, None, "DROP TABLE posts;", true);
+        assert!(err.is_err());
+        assert_eq!(versions(&mut runner), ["0001"]);
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        let mut runner = Runner::new(
+            &mut conn,
+            TrackingTable::default(),
+            Layout::golang_migrate(),
+        )
+        .unwrap();
+        runner
+            .apply(&record("1"), "CREATE TABLE users (id INT);", true)
+            .unwrap();
+        runner
+            .apply(&record("2"), "CREATE TABLE posts (id INT);", true)
+            .unwrap();
+        let applied = runner.applied().unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(
+            (applied[0].version.as_str(), applied[0].dirty),
+            ("2", Some(false))
+        );
+        runner
+            .rollback(&applied[0].version, Some("1"), "DROP TABLE posts;", true)
+            .unwrap();
+        assert_eq!(versions(&mut runner), ["1"]);
+        let applied = runner.applied().unwrap();
+        runner
+            .rollback(&applied[0].version, None, "DROP TABLE users;", true)
+            .unwrap();
+        assert_eq!(versions(&mut runner), Vec::<String>::new());
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE _sqlx_migrations (version BIGINT PRIMARY KEY, description TEXT NOT NULL,
+                installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, success BOOLEAN NOT NULL,
+                checksum BLOB NOT NULL, execution_time BIGINT NOT NULL);",
+        )
+        .unwrap();
+        let table = "_sqlx_migrations".parse().unwrap();
+        let mut runner = Runner::new(&mut conn, table, Layout::default()).unwrap();
+        runner
+            .apply(&record("1"), "CREATE TABLE users (id INT);", true)
+            .unwrap();
+        assert_eq!(versions(&mut runner), ["1"]);
+    }
+}
@@ -0,0 +1,503 @@
+/*!
+Convert a schema from one dialect to another (e.g. PostgreSQL to SQLite).
+
+Data types are mapped to their closest equivalent, auto-incrementing columns are rewritten (e.g.
+`SERIAL` becomes `INTEGER PRIMARY KEY AUTOINCREMENT`), and enum types become CHECK constraints in
+dialects without them. Statements with no equivalent are left out and reported.
+*/
+
+use std::collections::HashMap;
+
+use sqlparser::{
+    ast::{BinaryLength, CharacterLength, TimezoneInfo, Value},
+    tokenizer::Token,
+};
+
+use crate::{
+    ast::{
+        AlterTableOperation, CheckConstraint, ColumnDef, ColumnOption, ColumnOptionDef, DataType,
+        Expr, GeneratedAs, Ident, ObjectName, Statement, UserDefinedTypeRepresentation,
+    },
+    changes::{classify, Change},
+    dialect::{Custom, Generic, MsSQL, MySQL, PostgreSQL, SQLite},
+    sealed::Sealed,
+    SyntaxTree,
+};
+
+/// a statement left out of a converted schema
+#[derive(Debug, Clone)]
+pub struct Untranslatable {
+    pub change: Change,
+    /// why the target dialect has no equivalent
+    pub reason: String,
+}
+
+/// what a dialect supports when converting a schema into it
+pub trait Transpile: Sealed {
+    /// the equivalent of `data_type` in this dialect, or `None` to keep it as-is
+    fn map_data_type(&self, _data_type: &DataType) -> Option<DataType> {
+        None
+    }
+
+    /// enum types (`CREATE TYPE ... AS ENUM`), otherwise columns using them become TEXT with a
+    /// CHECK constraint
+    fn enums(&self) -> bool {
+        true
+    }
+
+    /// domains, otherwise columns using them get the domain's base type
+    fn domains(&self) -> bool {
+        true
+    }
+
+    fn extensions(&self) -> bool {
+        true
+    }
+
+    /// rewrites an auto-incrementing column (written in any dialect, e.g. `SERIAL` or
+    /// `AUTOINCREMENT`) the way this dialect writes them
+    fn auto_increment(&self, _column: &mut ColumnDef) {}
+}
+
+impl Transpile for Generic {}
+
+impl Transpile for Custom {}
+
+impl Transpile for PostgreSQL {
+    fn map_data_type(&self, data_type: &DataType) -> Option<DataType> {
+        Some(match data_type {
+            DataType::Blob(_)
+            | DataType::TinyBlob
+            | DataType::MediumBlob
+            | DataType::LongBlob
+            | DataType::Binary(_)
+            | DataType::Varbinary(_) => DataType::Bytea,
+            DataType::Datetime(precision) => DataType::Timestamp(*precision, TimezoneInfo::None),
+            DataType::TinyInt(_) => DataType::SmallInt(None),
+            DataType::Double(_) => DataType::DoublePrecision,
+            DataType::Nvarchar(length) => DataType::Varchar(*length),
+            DataType::TinyText | DataType::MediumText | DataType::LongText => DataType::Text,
+            _ => return None,
+        })
+    }
+
+    fn auto_increment(&self, column: &mut ColumnDef) {
+        column.options.retain(|def| !is_autoincrement_keyword(def));
+        if is_serial(&column.data_type) || column.options.iter().any(is_identity) {
+            return;
+        }
+        let name = match column.data_type {
+            DataType::BigInt(_) | DataType::Int8(_) => "BIGSERIAL",
+            DataType::SmallInt(_) | DataType::Int2(_) | DataType::TinyInt(_) => "SMALLSERIAL",
+            _ => "SERIAL",
+        };
+        column.data_type = DataType::Custom(ObjectName::from(vec![Ident::new(name)]), vec![]);
+    }
+}
+
+impl Transpile for SQLite {
+    fn map_data_type(&self, data_type: &DataType) -> Option<DataType> {
+        Some(match data_type {
+            DataType::Character(_)
+            | DataType::Char(_)
+            | DataType::CharacterVarying(_)
+            | DataType::CharVarying(_)
+            | DataType::Varchar(_)
+            | DataType::Nvarchar(_)
+            | DataType::Uuid
+            | DataType::JSON
+            | DataType::JSONB
+            | DataType::Array(_)
+            | DataType::Date
+            | DataType::Time(..)
+            | DataType::Datetime(_)
+            | DataType::Timestamp(..) => DataType::Text,
+            DataType::Bool | DataType::Boolean => DataType::Integer(None),
+            DataType::Int(_)
+            | DataType::Int2(_)
+            | DataType::Int4(_)
+            | DataType::Int8(_)
+            | DataType::TinyInt(_)
+            | DataType::SmallInt(_)
+            | DataType::MediumInt(_)
+            | DataType::BigInt(_) => DataType::Integer(None),
+            DataType::Float4
+            | DataType::Float8
+            | DataType::Double(_)
+            | DataType::DoublePrecision => DataType::Real,
+            DataType::Bytea | DataType::Binary(_) | DataType::Varbinary(_) => DataType::Blob(None),
+            _ => return None,
+        })
+    }
+
+    fn enums(&self) -> bool {
+        false
+    }
+
+    fn domains(&self) -> bool {
+        false
+    }
+
+    fn extensions(&self) -> bool {
+        false
+    }
+
+    fn auto_increment(&self, column: &mut ColumnDef) {
+        column.options.retain(|def| !is_identity(def));
+        column.data_type = DataType::Integer(None);
+        // AUTOINCREMENT is only allowed on an INTEGER PRIMARY KEY
+        let primary_key = column
+            .options
+            .iter()
+            .position(|def| matches!(def.option, ColumnOption::PrimaryKey(_)));
+        if let Some(i) = primary_key {
+            if !column.options.iter().any(is_autoincrement_keyword) {
+                column.options.insert(
+                    i + 1,
+                    ColumnOptionDef {
+                        name: None,
+                        option: ColumnOption::DialectSpecific(vec![Token::make_keyword(
+                            "AUTOINCREMENT",
+                        )]),
+                    },
+                );
+            }
+        }
+    }
+}
+
+impl Transpile for MySQL {
+    fn map_data_type(&self, data_type: &DataType) -> Option<DataType> {
+        Some(match data_type {
+            DataType::Bytea => DataType::Blob(None),
+            DataType::Uuid => DataType::Char(Some(CharacterLength::IntegerLength {
+                length: 36,
+                unit: None,
+            })),
+            DataType::JSONB | DataType::Array(_) => DataType::JSON,
+            DataType::Timestamp(precision, timezone) if *timezone != TimezoneInfo::None => {
+                DataType::Timestamp(*precision, TimezoneInfo::None)
+            }
+            _ => return None,
+        })
+    }
+
+    fn enums(&self) -> bool {
+        false
+    }
+
+    fn domains(&self) -> bool {
+        false
+    }
+
+    fn extensions(&self) -> bool {
+        false
+    }
+
+    fn auto_increment(&self, column: &mut ColumnDef) {
+        column
+            .options
+            .retain(|def| !is_identity(def) && !is_autoincrement_keyword(def));
+        if is_serial(&column.data_type) {
+            column.data_type = match column.data_type.to_string().to_uppercase().as_str() {
+                "BIGSERIAL" => DataType::BigInt(None),
+                "SMALLSERIAL" => DataType::SmallInt(None),
+                _ => DataType::Int(None),
+            };
+        }
+        column.options.push(ColumnOptionDef {
+            name: None,
+            option: ColumnOption::DialectSpecific(vec![Token::make_keyword("AUTO_INCREMENT")]),
+        });
+    }
+}
+
+impl Transpile for MsSQL {
+    fn map_data_type(&self, data_type: &DataType) -> Option<DataType> {
+        let custom = |name: &str, modifiers: Vec<String>| {
+            DataType::Custom(ObjectName::from(vec![Ident::new(name)]), modifiers)
+        };
+        Some(match data_type {
+            DataType::Bool | DataType::Boolean => DataType::Bit(None),
+            DataType::Bytea | DataType::Blob(_) => DataType::Varbinary(Some(BinaryLength::Max)),
+            DataType::Uuid => custom("UNIQUEIDENTIFIER", vec![]),
+            DataType::JSON | DataType::JSONB | DataType::Array(_) => {
+                DataType::Nvarchar(Some(CharacterLength::Max))
+            }
+            // TIMESTAMP is a row version in SQL Server
+            DataType::Timestamp(precision, timezone) => {
+                let name = match timezone {
+                    TimezoneInfo::WithTimeZone | TimezoneInfo::Tz => "DATETIMEOFFSET",
+                    _ => "DATETIME2",
+                };
+                custom(name, precision.iter().map(u64::to_string).collect())
+            }
+            DataType::Double(_) => DataType::DoublePrecision,
+            _ => return None,
+        })
+    }
+
+    fn enums(&self) -> bool {
+        false
+    }
+
+    fn domains(&self) -> bool {
+        false
+    }
+
+    fn extensions(&self) -> bool {
+        false
+    }
+
+    fn auto_increment(&self, column: &mut ColumnDef) {
+        column
+            .options
+            .retain(|def| !is_identity(def) && !is_autoincrement_keyword(def));
+        if is_serial(&column.data_type) {
+            column.data_type = match column.data_type.to_string().to_uppercase().as_str() {
+                "BIGSERIAL" => DataType::BigInt(None),
+                "SMALLSERIAL" => DataType::SmallInt(None),
+                _ => DataType::Int(None),
+            };
+        }
+        column.options.push(ColumnOptionDef {
+            name: None,
+            option: ColumnOption::DialectSpecific(vec![Token::make_keyword("IDENTITY")]),
+        });
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// converts the schema into the `to` dialect, along with the statements that were left out
+    /// because it has no equivalent for them
+    pub fn transpile<To: Transpile>(&self, to: To) -> (SyntaxTree<To>, Vec<Untranslatable>) {
+        // what columns using an enum or domain need when they're inlined
+        let mut enums = HashMap::new();
+        let mut domains = HashMap::new();
+        for s in &self.tree {
+            match s {
+                Statement::CreateType {
+                    name,
+                    representation: Some(UserDefinedTypeRepresentation::Enum { labels }),
+                } => {
+                    enums.insert(name.clone(), labels.clone());
+                }
+                Statement::CreateDomain(domain) => {
+                    domains.insert(domain.name.clone(), domain.data_type.clone());
+                }
+                _ => {}
+            }
+        }
+        let column = |column: &mut ColumnDef| {
+            if is_auto_increment(column) {
+                to.auto_increment(column);
+            }
+            if let DataType::Custom(name, _) = &column.data_type {
+                if let Some(labels) = enums.get(name).filter(|_| !to.enums()) {
+                    let labels = labels.iter().map(|label| {
+                        Expr::Value(Value::SingleQuotedString(label.value.clone()).into())
+                    });
+                    let check = CheckConstraint {
+                        name: None,
+                        expr: Box::new(Expr::InList {
+                            expr: Box::new(Expr::Identifier(column.name.clone())),
+                            list: labels.collect(),
+                            negated: false,
+                        }),
+                        enforced: None,
+                    };
+                    column.data_type = DataType::Text;
+                    column.options.push(ColumnOptionDef {
+                        name: None,
+                        option: ColumnOption::Check(check),
+                    });
+                } else if let Some(data_type) = domains.get(name).filter(|_| !to.domains()) {
+                    column.data_type = data_type.clone();
+                }
+            }
+            if let Some(data_type) = to.map_data_type(&column.data_type) {
+                column.data_type = data_type;
+            }
+        };
+
+        let mut tree = Vec::new();
+        let mut untranslatable = Vec::new();
+        for s in &self.tree {
+            let reason = match s {
+                Statement::CreateType {
+                    representation: Some(UserDefinedTypeRepresentation::Enum { .. }),
+                    ..
+                } if !to.enums() => continue,
+                Statement::CreateDomain(_) if !to.domains() => {
+                    "domains aren't supported (columns use its base type instead)"
+                }
+                Statement::CreateExtension(_) if !to.extensions() => "extensions aren't supported",
+                Statement::CreateTable(table) => {
+                    let mut table = table.clone();
+                    table.columns.iter_mut().for_each(column);
+                    tree.push(Statement::CreateTable(table));
+                    continue;
+                }
+                Statement::AlterTable(alter) => {
+                    let mut alter = alter.clone();
+                    for op in &mut alter.operations {
+                        if let AlterTableOperation::AddColumn { column_def, .. } = op {
+                            column(column_def);
+                        }
+                    }
+                    tree.push(Statement::AlterTable(alter));
+                    continue;
+                }
+                Statement::CreateDomain(domain) => {
+                    let mut domain = domain.clone();
+                    if let Some(data_type) = to.map_data_type(&domain.data_type) {
+                        domain.data_type = data_type;
+                    }
+                    tree.push(Statement::CreateDomain(domain));
+                    continue;
+                }
+                _ => {
+                    tree.push(s.clone());
+                    continue;
+                }
+            };
+            untranslatable.push(Untranslatable {
+                change: classify(s, &self.format),
+                reason: reason.to_owned(),
+            });
+        }
+        let tree = SyntaxTree {
+            dialect: to,
+            format: self.format,
+            tree,
+            provenance: Vec::new(),
+            handlers: self.handlers.clone(),
+        };
+        (tree, untranslatable)
+    }
+}
+
+/// PostgreSQL's `SERIAL`, `BIGSERIAL`, and `SMALLSERIAL`
+fn is_serial(data_type: &DataType) -> bool {
+    let DataType::Custom(name, _) = data_type else {
+        return false;
+    };
+    matches!(
+        name.to_string().to_uppercase().as_str(),
+        "SERIAL" | "BIGSERIAL" | "SMALLSERIAL"
+    )
+}
+
+fn is_auto_increment(column: &ColumnDef) -> bool {
+    is_serial(&column.data_type)
+        || column
+            .options
+            .iter()
+            .any(|def| is_identity(def) || is_autoincrement_keyword(def))
+}
+
+/// `GENERATED ... AS IDENTITY` (or `IDENTITY`)
+fn is_identity(def: &ColumnOptionDef) -> bool {
+    matches!(
+        &def.option,
+        ColumnOption::Generated {
+            generated_as: GeneratedAs::Always | GeneratedAs::ByDefault,
+            generation_expr: None,
+            ..
+        } | ColumnOption::Identity(_)
+    )
+}
+
+/// SQLite's (and MySQL's) `AUTOINCREMENT`
+pub(crate) fn is_autoincrement_keyword(def: &ColumnOptionDef) -> bool {
+    match &def.option {
+        ColumnOption::DialectSpecific(tokens) => tokens.iter().any(|token| {
+            matches!(token, Token::Word(word)
+                if word.value.eq_ignore_ascii_case("AUTOINCREMENT")
+                    || word.value.eq_ignore_ascii_case("AUTO_INCREMENT"))
+        }),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn postgres_to_sqlite() {
+        let tree = SyntaxTree::parse(
+            PostgreSQL,
+            "CREATE EXTENSION pgcrypto; \
+            CREATE TYPE status AS ENUM ('open', 'closed'); \
+            CREATE TABLE bugs (id SERIAL PRIMARY KEY, title VARCHAR(255) NOT NULL, status status, open BOOLEAN);",
+        )
+        .unwrap();
+        let (tree, untranslatable) = tree.transpile(SQLite);
+        assert_eq!(
+            tree.tree
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "CREATE TABLE bugs (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, status TEXT CHECK (status IN ('open', 'closed')), open INTEGER)"
+            ]
+        );
+        let skipped = untranslatable
+            .into_iter()
+            .map(|u| (u.change.name, u.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            [(
+                "pgcrypto".to_owned(),
+                "extensions aren't supported".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn sqlite_to_postgres() {
+        let tree = SyntaxTree::parse(
+            SQLite,
+            "CREATE TABLE files (id INTEGER PRIMARY KEY AUTOINCREMENT, data BLOB, created DATETIME);",
+        )
+        .unwrap();
+        let (tree, untranslatable) = tree.transpile(PostgreSQL);
+        assert_eq!(
+            tree.tree[0].to_string(),
+            "CREATE TABLE files (id SERIAL PRIMARY KEY, data BYTEA, created TIMESTAMP)"
+        );
+        assert!(untranslatable.is_empty());
+    }
+
+    #[test]
+    fn postgres_to_mysql() {
+        let tree = SyntaxTree::parse(
+            PostgreSQL,
+            "CREATE TABLE files (id BIGSERIAL PRIMARY KEY, token UUID, data BYTEA, meta JSONB);",
+        )
+        .unwrap();
+        let (tree, untranslatable) = tree.transpile(MySQL);
+        assert_eq!(
+            tree.tree[0].to_string(),
+            "CREATE TABLE files (id BIGINT PRIMARY KEY AUTO_INCREMENT, token CHAR(36), data BLOB, meta JSON)"
+        );
+        assert!(untranslatable.is_empty());
+    }
+
+    #[test]
+    fn postgres_to_mssql() {
+        let tree = SyntaxTree::parse(
+            PostgreSQL,
+            "CREATE TABLE files (id SERIAL PRIMARY KEY, token UUID, data BYTEA, created TIMESTAMPTZ(3), open BOOLEAN);",
+        )
+        .unwrap();
+        let (tree, untranslatable) = tree.transpile(MsSQL);
+        assert_eq!(
+            tree.tree[0].to_string(),
+            "CREATE TABLE files (id INT PRIMARY KEY IDENTITY, token UNIQUEIDENTIFIER, data VARBINARY(MAX), created DATETIMEOFFSET(3), open BIT)"
+        );
+        assert!(untranslatable.is_empty());
+    }
+}
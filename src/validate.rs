@@ -0,0 +1,361 @@
+/*!
+Semantic checks of a schema: names that are used more than once and references to tables, columns,
+and enum labels that don't exist.
+
+Names are compared ignoring ASCII case, and an unqualified name matches a qualified one with the
+same last part (e.g. `users` and `public.users`), so the checks only report what's certainly wrong.
+*/
+
+use std::fmt;
+
+use crate::{
+    changes::{classify, ObjectKind, Operation},
+    schema::{ConstraintKind, Schema, Table, TypeKind},
+    Provenance, SyntaxTree,
+};
+
+/// what [SyntaxTree::validate] found wrong with a schema
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, problem) in self.problems.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{problem}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Problem {
+    /// more than one table, index, or type (including domains) has the same name
+    Duplicate {
+        object: ObjectKind,
+        name: String,
+        /// where it was first created, when that's known
+        first: Option<Provenance>,
+        /// where it was created again
+        second: Option<Provenance>,
+    },
+    /// a foreign key references a table that isn't in the schema
+    MissingForeignTable {
+        table: String,
+        foreign_table: String,
+    },
+    /// a foreign key references a column its table doesn't have
+    MissingForeignColumn {
+        table: String,
+        foreign_table: String,
+        column: String,
+    },
+    /// an index is on a table that isn't in the schema
+    MissingIndexTable {
+        index: Option<String>,
+        table: String,
+    },
+    /// an index is on a column its table doesn't have
+    MissingIndexColumn {
+        index: Option<String>,
+        table: String,
+        column: String,
+    },
+    /// a column of an enum type defaults to a label the type doesn't have
+    InvalidEnumDefault {
+        table: String,
+        column: String,
+        ty: String,
+        default: String,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let index = |name: &Option<String>| match name {
+            Some(name) => format!("index {name}"),
+            None => "an unnamed index".to_owned(),
+        };
+        match self {
+            Self::Duplicate {
+                object,
+                name,
+                first,
+                second,
+            } => {
+                write!(f, "{object} {name} is created more than once")?;
+                match (first, second) {
+                    (Some(first), Some(second)) => {
+                        write!(f, " (first at {first}; again at {second})")
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Self::MissingForeignTable {
+                table,
+                foreign_table,
+            } => write!(
+                f,
+                "a foreign key of {table} references table {foreign_table}, which doesn't exist"
+            ),
+            Self::MissingForeignColumn {
+                table,
+                foreign_table,
+                column,
+            } => write!(
+                f,
+                "a foreign key of {table} references {foreign_table}.{column}, which doesn't exist"
+            ),
+            Self::MissingIndexTable { index: name, table } => {
+                write!(
+                    f,
+                    "{} is on table {table}, which doesn't exist",
+                    index(name)
+                )
+            }
+            Self::MissingIndexColumn {
+                index: name,
+                table,
+                column,
+            } => write!(
+                f,
+                "{} is on {table}.{column}, which doesn't exist",
+                index(name)
+            ),
+            Self::InvalidEnumDefault {
+                table,
+                column,
+                ty,
+                default,
+            } => write!(
+                f,
+                "{table}.{column} defaults to {default}, which isn't a label of {ty}"
+            ),
+        }
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// checks that each table, index, and type is only created once, and that the foreign keys,
+    /// indexes, and enum defaults refer to tables, columns, and labels that exist
+    pub fn validate(&self) -> Report {
+        let schema = self.schema();
+        let mut problems = self.duplicates();
+        problems.extend(missing_references(&schema));
+        problems.extend(invalid_enum_defaults(&schema));
+        Report { problems }
+    }
+
+    /// the tables, indexes, and types (including domains) that are created more than once, each
+    /// along with where it was first created
+    pub fn duplicates(&self) -> Vec<Problem> {
+        let mut created = Vec::<(ObjectKind, String, usize)>::new();
+        let mut problems = Vec::new();
+        for (i, s) in self.tree.iter().enumerate() {
+            let change = classify(s, &self.format);
+            let object = match change.object {
+                ObjectKind::Domain => ObjectKind::Type,
+                object @ (ObjectKind::Table | ObjectKind::Index | ObjectKind::Type) => object,
+                _ => continue,
+            };
+            if change.operation != Operation::Create || change.name.is_empty() {
+                continue;
+            }
+            let first = created
+                .iter()
+                .find(|(o, n, _)| *o == object && same_name(n, &change.name));
+            match first {
+                Some((_, _, first)) => problems.push(Problem::Duplicate {
+                    object,
+                    name: change.name,
+                    first: self.provenance(*first).cloned(),
+                    second: self.provenance(i).cloned(),
+                }),
+                None => created.push((object, change.name, i)),
+            }
+        }
+        problems
+    }
+}
+
+fn missing_references(schema: &Schema) -> Vec<Problem> {
+    let table = |name: &str| schema.tables.iter().find(|t| same_name(&t.name, name));
+    let has_column =
+        |table: &Table, column: &str| table.columns.iter().any(|c| same_name(&c.name, column));
+
+    let mut problems = Vec::new();
+    for t in &schema.tables {
+        for constraint in &t.constraints {
+            let ConstraintKind::ForeignKey {
+                foreign_table,
+                referred_columns,
+                ..
+            } = &constraint.kind
+            else {
+                continue;
+            };
+            let Some(foreign) = table(foreign_table) else {
+                problems.push(Problem::MissingForeignTable {
+                    table: t.name.clone(),
+                    foreign_table: foreign_table.clone(),
+                });
+                continue;
+            };
+            for column in referred_columns {
+                if !has_column(foreign, column) {
+                    problems.push(Problem::MissingForeignColumn {
+                        table: t.name.clone(),
+                        foreign_table: foreign_table.clone(),
+                        column: column.clone(),
+                    });
+                }
+            }
+        }
+    }
+    for index in &schema.indexes {
+        let Some(t) = table(&index.table) else {
+            problems.push(Problem::MissingIndexTable {
+                index: index.name.clone(),
+                table: index.table.clone(),
+            });
+            continue;
+        };
+        // expressions (e.g. `lower(email)`) aren't checked
+        for column in index.columns.iter().filter_map(|c| identifier(c)) {
+            if !has_column(t, column) {
+                problems.push(Problem::MissingIndexColumn {
+                    index: index.name.clone(),
+                    table: index.table.clone(),
+                    column: column.to_owned(),
+                });
+            }
+        }
+    }
+    problems
+}
+
+fn invalid_enum_defaults(schema: &Schema) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for table in &schema.tables {
+        for column in &table.columns {
+            let Some(default) = &column.default else {
+                continue;
+            };
+            let Some(TypeKind::Enum { labels }) = schema
+                .types
+                .iter()
+                .find(|ty| same_name(&ty.name, &column.data_type))
+                .map(|ty| &ty.kind)
+            else {
+                continue;
+            };
+            // e.g. `'open'` or `'open'::status`
+            let literal = default
+                .split_once("::")
+                .map_or(default.as_str(), |(literal, _)| literal);
+            let Some(label) = literal
+                .strip_prefix('\'')
+                .and_then(|l| l.strip_suffix('\''))
+            else {
+                continue;
+            };
+            if !labels.iter().any(|l| *l == label.replace("''", "'")) {
+                problems.push(Problem::InvalidEnumDefault {
+                    table: table.name.clone(),
+                    column: column.name.clone(),
+                    ty: column.data_type.clone(),
+                    default: default.clone(),
+                });
+            }
+        }
+    }
+    problems
+}
+
+/// whether two names (each possibly qualified by a schema) can refer to the same object
+pub(crate) fn same_name(a: &str, b: &str) -> bool {
+    if a.eq_ignore_ascii_case(b) {
+        return true;
+    }
+    let last = |name: &str| name.rsplit('.').next().unwrap_or(name).to_owned();
+    (!a.contains('.') || !b.contains('.')) && last(a).eq_ignore_ascii_case(&last(b))
+}
+
+/// the name of the column when `expr` is just a (possibly quoted) column name
+fn identifier(expr: &str) -> Option<&str> {
+    let quoted = [('"', '"'), ('`', '`'), ('[', ']')]
+        .into_iter()
+        .find_map(|(open, close)| expr.strip_prefix(open)?.strip_suffix(close));
+    if quoted.is_some() {
+        return quoted;
+    }
+    let mut chars = expr.chars();
+    let start = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_');
+    (start && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')).then_some(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Generic;
+
+    #[test]
+    fn validate() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TYPE status AS ENUM ('open', 'closed');
+            CREATE TABLE users (id INT PRIMARY KEY, status status DEFAULT 'archived');
+            CREATE TABLE Users (id INT);
+            CREATE TABLE posts (
+                id INT,
+                author_id INT REFERENCES users (uuid),
+                team_id INT REFERENCES public.teams (id),
+                status status DEFAULT 'open'::status
+            );
+            CREATE INDEX posts_title ON posts (title);
+            CREATE INDEX posts_lower ON posts (lower(id));
+            CREATE INDEX ON comments (post_id);
+            CREATE DOMAIN status AS TEXT;",
+        )
+        .unwrap();
+        let report = tree.validate();
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.to_string().lines().collect::<Vec<_>>(),
+            [
+                "table Users is created more than once \
+                (first at line 2, column 13, statement 2; again at line 3, column 13, statement 3)",
+                "type status is created more than once \
+                (first at line 1, column 1, statement 1; again at line 13, column 13, statement 8)",
+                "a foreign key of posts references users.uuid, which doesn't exist",
+                "a foreign key of posts references table public.teams, which doesn't exist",
+                "index posts_title is on posts.title, which doesn't exist",
+                "an unnamed index is on table comments, which doesn't exist",
+                "users.status defaults to 'archived', which isn't a label of status",
+            ]
+        );
+    }
+
+    #[test]
+    fn valid() {
+        let tree = SyntaxTree::parse(
+            Generic,
+            "CREATE TABLE public.users (id INT PRIMARY KEY);
+            CREATE TABLE posts (id INT, author_id INT REFERENCES users (id));
+            CREATE UNIQUE INDEX posts_author ON posts (\"author_id\", id);",
+        )
+        .unwrap();
+        assert_eq!(tree.validate(), Report::default());
+    }
+}
@@ -0,0 +1,191 @@
+/*!
+Check that a schema and the migrations it's built from agree, e.g. in CI.
+
+[verify] diffs the schema the migrations add up to against the schema file both ways, applies each
+diff, and checks that what it lands on matches the other side, so a diff that doesn't do what it
+says is caught along with the schema file being out of date.
+*/
+
+use std::fmt;
+
+use crate::{DiffError, MigrateError, SyntaxTree, TreeDiffer, TreeMigrator};
+
+/// which way a diff goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// from the migrations to the schema
+    Forward,
+    /// from the schema to the migrations
+    Backward,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Forward => write!(f, "from the migrations to the schema"),
+            Self::Backward => write!(f, "from the schema to the migrations"),
+        }
+    }
+}
+
+/// what [verify] found
+#[derive(Debug)]
+pub struct VerificationReport<Dialect> {
+    /// the migration that brings the migrations up to date with the schema (none when they match)
+    pub forward: Option<SyntaxTree<Dialect>>,
+    /// the migration that takes the schema back to the migrations (none when they match)
+    pub backward: Option<SyntaxTree<Dialect>>,
+    pub discrepancies: Vec<Discrepancy<Dialect>>,
+}
+
+impl<Dialect> VerificationReport<Dialect> {
+    /// whether the schema and the migrations are the same
+    pub fn matches(&self) -> bool {
+        self.forward.is_none() && self.backward.is_none() && self.discrepancies.is_empty()
+    }
+
+    /// whether diffing both ways and applying each diff worked, regardless of whether the schema
+    /// and the migrations are the same
+    pub fn converges(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+/// a diff that couldn't be made or applied, or that didn't land on the other side
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Discrepancy<Dialect> {
+    Diff {
+        direction: Direction,
+        error: DiffError,
+    },
+    Migrate {
+        direction: Direction,
+        error: MigrateError,
+    },
+    /// the differences left after applying the diff
+    Diverged {
+        direction: Direction,
+        remaining: SyntaxTree<Dialect>,
+    },
+}
+
+impl<Dialect> fmt::Display for Discrepancy<Dialect> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Diff { direction, error } => write!(f, "diffing {direction}: {error}"),
+            Self::Migrate { direction, error } => {
+                write!(f, "applying the diff {direction}: {error}")
+            }
+            Self::Diverged {
+                direction,
+                remaining,
+            } => write!(
+                f,
+                "applying the diff {direction} leaves these differences:\n{remaining}"
+            ),
+        }
+    }
+}
+
+/// diffs `migrations` (the schema they add up to) and `schema` both ways, and checks that applying
+/// each diff makes one match the other
+pub fn verify<Dialect>(
+    migrations: &SyntaxTree<Dialect>,
+    schema: &SyntaxTree<Dialect>,
+) -> VerificationReport<Dialect>
+where
+    Dialect: TreeDiffer + TreeMigrator,
+{
+    let mut discrepancies = Vec::new();
+    let forward = round_trip(Direction::Forward, migrations, schema, &mut discrepancies);
+    let backward = round_trip(Direction::Backward, schema, migrations, &mut discrepancies);
+    VerificationReport {
+        forward,
+        backward,
+        discrepancies,
+    }
+}
+
+/// the diff from `a` to `b`, noting any discrepancy found making or applying it
+fn round_trip<Dialect>(
+    direction: Direction,
+    a: &SyntaxTree<Dialect>,
+    b: &SyntaxTree<Dialect>,
+    discrepancies: &mut Vec<Discrepancy<Dialect>>,
+) -> Option<SyntaxTree<Dialect>>
+where
+    Dialect: TreeDiffer + TreeMigrator,
+{
+    let diff = match a.diff(b) {
+        Ok(diff) => diff?,
+        Err(error) => {
+            discrepancies.push(Discrepancy::Diff { direction, error });
+            return None;
+        }
+    };
+    let migrated = match a.clone().migrate(&diff) {
+        Ok(migrated) => migrated,
+        Err(error) => {
+            discrepancies.push(Discrepancy::Migrate { direction, error });
+            return Some(diff);
+        }
+    };
+    match migrated.diff(b) {
+        Ok(None) => {}
+        Ok(Some(remaining)) => discrepancies.push(Discrepancy::Diverged {
+            direction,
+            remaining,
+        }),
+        Err(error) => discrepancies.push(Discrepancy::Diff { direction, error }),
+    }
+    Some(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Generic;
+
+    fn parse(sql: &str) -> SyntaxTree<Generic> {
+        SyntaxTree::parse(Generic, sql).unwrap()
+    }
+
+    #[test]
+    fn verify() {
+        let migrations = parse("CREATE TABLE users (id INT);");
+        let report = super::verify(&migrations, &migrations);
+        assert!(report.matches());
+
+        let schema = parse("CREATE TABLE users (id INT, name TEXT);");
+        let report = super::verify(&migrations, &schema);
+        assert!(!report.matches());
+        assert!(report.converges());
+        assert_eq!(
+            report.forward.unwrap().statements()[0].to_string(),
+            "ALTER TABLE users ADD COLUMN name TEXT"
+        );
+        assert_eq!(
+            report.backward.unwrap().statements()[0].to_string(),
+            "ALTER TABLE users DROP COLUMN name"
+        );
+    }
+
+    #[test]
+    fn discrepancies() {
+        let migrations = parse("CREATE TYPE status AS ENUM ('a', 'b');");
+        let schema = parse("CREATE TYPE status AS ENUM ('a');");
+        let report = super::verify(&migrations, &schema);
+        assert!(!report.converges());
+        assert!(report.forward.is_none());
+        assert!(report.backward.is_some());
+        let [Discrepancy::Diff { direction, error }] = report.discrepancies.as_slice() else {
+            panic!("expected a diff error, got {:?}", report.discrepancies);
+        };
+        assert_eq!(*direction, Direction::Forward);
+        assert_eq!(
+            report.discrepancies[0].to_string(),
+            format!("diffing from the migrations to the schema: {error}")
+        );
+    }
+}
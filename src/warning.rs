@@ -0,0 +1,252 @@
+/*!
+What [SyntaxTree::diff] and [SyntaxTree::migrate] glossed over: differences the diff doesn't
+migrate (yet) and statements of a migration that didn't change the schema. None of it stops the
+diff or migration, it's reported to the callbacks registered with [SyntaxTree::on_warning].
+*/
+
+use std::fmt;
+
+use crate::{
+    ast::{ColumnDef, ColumnOption, CreateTable, Statement},
+    changes::{classify, Operation},
+    dialect::Capabilities,
+    diff, parser,
+    provenance::object,
+    schema::{self, Table},
+    FormatOptions, SyntaxTree,
+};
+
+/// something [SyntaxTree::diff] or [SyntaxTree::migrate] left out
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// a column changed in a way the diff doesn't migrate yet (e.g. its collation, or how it's
+    /// generated), rather than its type, nullability, default, or constraints
+    ColumnChanged { table: String, column: String },
+    /// the columns a table has in both schemas are in a different order, which the diff doesn't
+    /// migrate
+    ColumnOrder { table: String },
+    /// the constraints of a table changed in a way the diff doesn't migrate yet: constraints
+    /// other than primary keys, unique constraints, foreign keys, and checks (e.g. MySQL's
+    /// `INDEX`), or any constraint when the dialect can't alter them (it rebuilds the table
+    /// instead, see [SyntaxTree::rebuild_tables])
+    ConstraintsChanged { table: String },
+    /// a statement of a migration that doesn't change the schema (e.g. `GRANT` or `INSERT`), or
+    /// alters or drops something that isn't in it, was skipped
+    Skipped(Box<Statement>),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ColumnChanged { table, column } => {
+                write!(f, "{table}.{column} changed, which isn't migrated yet")
+            }
+            Self::ColumnOrder { table } => {
+                write!(
+                    f,
+                    "the columns of {table} were reordered, which isn't migrated"
+                )
+            }
+            Self::ConstraintsChanged { table } => {
+                write!(
+                    f,
+                    "the constraints of {table} changed, which isn't migrated yet"
+                )
+            }
+            Self::Skipped(statement) => {
+                write!(f, "skipped {}", parser::summary(statement))
+            }
+        }
+    }
+}
+
+impl<Dialect> SyntaxTree<Dialect> {
+    /// calls `f` with each [Warning] about what diffing or migrating this tree glossed over
+    /// (carried over to the result of [SyntaxTree::diff] and [SyntaxTree::migrate])
+    pub fn on_warning(mut self, f: impl Fn(&Warning) + Send + Sync + 'static) -> Self {
+        self.handlers.on_warning(f);
+        self
+    }
+}
+
+/// the differences between the tables of `a` and `b` that the diff doesn't migrate
+pub(crate) fn diff_warnings(
+    a: &[Statement],
+    b: &[Statement],
+    supports: Capabilities,
+) -> Vec<Warning> {
+    let tables = |tree: &[Statement]| {
+        tree.iter()
+            .filter_map(|s| match s {
+                Statement::CreateTable(table) => Some(table.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let b = tables(b);
+    let mut warnings = Vec::new();
+    for create_a in tables(a) {
+        let Some(create_b) = b.iter().find(|tb| tb.name == create_a.name) else {
+            continue;
+        };
+        let (ta, tb) = (Table::from(&create_a), Table::from(create_b));
+        for column in &create_a.columns {
+            let Some(other) = create_b.columns.iter().find(|c| c.name == column.name) else {
+                continue;
+            };
+            if unmigrated(column) != unmigrated(other) {
+                warnings.push(Warning::ColumnChanged {
+                    table: ta.name.clone(),
+                    column: column.name.value.clone(),
+                });
+            }
+        }
+        let common = |table: &Table, other: &Table| {
+            table
+                .columns
+                .iter()
+                .filter(|c| other.columns.iter().any(|o| o.name == c.name))
+                .map(|c| c.name.clone())
+                .collect::<Vec<_>>()
+        };
+        let (ca, cb) = (common(&ta, &tb), common(&tb, &ta));
+        if ca != cb {
+            warnings.push(Warning::ColumnOrder {
+                table: ta.name.clone(),
+            });
+        }
+        // constraints the schema model leaves out
+        let unmodeled = |table: &CreateTable| {
+            table
+                .constraints
+                .iter()
+                .filter(|c| schema::table_constraint(c).is_none())
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        // constraints of columns that were added or dropped go with them
+        let same_columns = ca.len() == ta.columns.len() && cb.len() == tb.columns.len();
+        let unaltered =
+            !supports.alter_constraints && same_columns && ta.constraints != tb.constraints;
+        if unaltered || unmodeled(&create_a) != unmodeled(create_b) {
+            warnings.push(Warning::ConstraintsChanged {
+                table: ta.name.clone(),
+            });
+        }
+    }
+    warnings
+}
+
+/// the parts of a column the diff doesn't migrate
+fn unmigrated(column: &ColumnDef) -> Vec<&ColumnOption> {
+    column
+        .options
+        .iter()
+        .map(|def| &def.option)
+        .filter(|option| {
+            !matches!(
+                option,
+                ColumnOption::Null
+                    | ColumnOption::NotNull
+                    | ColumnOption::Default(_)
+                    | ColumnOption::PrimaryKey(_)
+                    | ColumnOption::Unique(_)
+                    | ColumnOption::ForeignKey(_)
+                    | ColumnOption::Check(_)
+            )
+        })
+        .collect()
+}
+
+/// the statements of `migration` that applying it to `schema` skips
+pub(crate) fn migrate_warnings(schema: &[Statement], migration: &[Statement]) -> Vec<Warning> {
+    let format = FormatOptions::default();
+    let mut objects = schema.iter().filter_map(object).collect::<Vec<_>>();
+    migration
+        .iter()
+        .filter(|s| {
+            if diff::is_supported(s) {
+                // what the migration creates can be altered or dropped later on in it
+                objects.extend(object(s));
+                return false;
+            }
+            let change = classify(s, &format);
+            let applied = matches!(change.operation, Operation::Alter | Operation::Drop)
+                && objects.contains(&(change.object, change.name));
+            !applied
+        })
+        .map(|s| Warning::Skipped(Box::new(s.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::dialect::{Generic, SQLite};
+
+    fn parse(sql: &str, warnings: &Arc<Mutex<Vec<String>>>) -> SyntaxTree<Generic> {
+        let warnings = warnings.clone();
+        SyntaxTree::parse(Generic, sql)
+            .unwrap()
+            .on_warning(move |w| warnings.lock().unwrap().push(w.to_string()))
+    }
+
+    #[test]
+    fn diff() {
+        let warnings = Arc::default();
+        let a = parse(
+            "CREATE TABLE foo (id INT, name TEXT, email TEXT);
+            CREATE TABLE bar (id INT, name TEXT);",
+            &warnings,
+        );
+        let b = parse(
+            "CREATE TABLE foo (id INT, email TEXT, name TEXT NOT NULL, age INT);
+            CREATE TABLE bar (id INT PRIMARY KEY, name TEXT COLLATE \"C\");",
+            &warnings,
+        );
+        assert!(a.diff(&b).unwrap().is_some());
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            [
+                "the columns of foo were reordered, which isn't migrated",
+                "bar.name changed, which isn't migrated yet",
+            ]
+        );
+
+        // SQLite rebuilds tables to change their constraints
+        let a = SyntaxTree::parse(SQLite, "CREATE TABLE bar (id INT);").unwrap();
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let sink = warnings.clone();
+        let a = a.on_warning(move |w| sink.lock().unwrap().push(w.to_string()));
+        let b = SyntaxTree::parse(SQLite, "CREATE TABLE bar (id INT PRIMARY KEY);").unwrap();
+        assert!(a.diff(&b).unwrap().is_some());
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            ["the constraints of bar changed, which isn't migrated yet"]
+        );
+    }
+
+    #[test]
+    fn migrate() {
+        let warnings = Arc::default();
+        let schema = parse("CREATE TABLE foo (id INT);", &warnings);
+        let migration = parse(
+            "ALTER TABLE foo ADD COLUMN name TEXT;
+            ALTER TABLE bar ADD COLUMN name TEXT;
+            GRANT SELECT ON foo TO app;
+            DROP TABLE foo;",
+            &warnings,
+        );
+        assert_eq!(schema.migrate(&migration).unwrap().to_string(), "");
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            [
+                "skipped ALTER TABLE bar ADD COLUMN name TEXT",
+                "skipped GRANT SELECT ON foo TO app",
+            ]
+        );
+    }
+}
@@ -0,0 +1,41 @@
+/*!
+JavaScript bindings for running the diff engine in the browser.
+
+Build for `wasm32-unknown-unknown` with only this feature, e.g.
+`cargo rustc --lib --crate-type cdylib --target wasm32-unknown-unknown --no-default-features --features wasm`,
+and generate the JS glue with `wasm-bindgen`. Each function takes the dialect by name (`generic`,
+`postgresql`, `sqlite`, `mysql`, or `mssql`) and returns SQL.
+*/
+
+use std::error::Error;
+
+use wasm_bindgen::prelude::*;
+
+use crate::bindings::{self, with_dialect};
+
+/// the schema, formatted
+#[wasm_bindgen]
+pub fn parse(dialect: &str, sql: &str) -> Result<String, JsError> {
+    with_dialect!(dialect, |dialect| bindings::parse(dialect, sql)).map_err(js_error)
+}
+
+/// the migration from schema `a` to schema `b` (empty when they're the same)
+#[wasm_bindgen]
+pub fn diff(dialect: &str, a: &str, b: &str) -> Result<String, JsError> {
+    with_dialect!(dialect, |dialect| bindings::diff(dialect, a, b))
+        .map(Option::unwrap_or_default)
+        .map_err(js_error)
+}
+
+/// the schema after applying `migration` to `schema`
+#[wasm_bindgen]
+pub fn migrate(dialect: &str, schema: &str, migration: &str) -> Result<String, JsError> {
+    with_dialect!(dialect, |dialect| bindings::migrate(
+        dialect, schema, migration
+    ))
+    .map_err(js_error)
+}
+
+fn js_error(err: Box<dyn Error>) -> JsError {
+    JsError::new(&err.to_string())
+}